@@ -0,0 +1,70 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use mrf::{
+    matcher::{match_all, Matcher},
+    parser::parse,
+    replacement::{resolve, ResolveOpts},
+    replacer::{ReplaceIndices, Replacer},
+    tokens::{tokenize, tokenize_into, tokenize_with_mode, TokenMode, TokenType},
+};
+
+/// Generate sample items resembling file names.
+fn items(n: usize) -> Vec<String> {
+    (0..n)
+        .map(|i| format!("IMG_2020-08-{:02} holiday photo {}.jpg", i % 31, i))
+        .collect()
+}
+
+fn bench_tokenize(c: &mut Criterion) {
+    let items = items(10_000);
+    let input = items.join("\n");
+    let mut group = c.benchmark_group("tokenize");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("default", |b| b.iter(|| tokenize(black_box(&input))));
+    group.bench_function("code", |b| {
+        b.iter(|| tokenize_with_mode(black_box(&input), TokenMode::Code))
+    });
+    group.bench_function("items_into", |b| {
+        let mut indices: Vec<usize> = vec![];
+        let mut token_types: Vec<TokenType> = vec![];
+        b.iter(|| {
+            for item in &items {
+                tokenize_into(
+                    black_box(item),
+                    TokenMode::Default,
+                    &mut indices,
+                    &mut token_types,
+                );
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_match(c: &mut Criterion) {
+    let items = items(10_000);
+    let matchers = [Matcher::Any, Matcher::Number, Matcher::Any];
+    c.bench_function("match_all", |b| {
+        b.iter(|| {
+            for item in &items {
+                let _ = match_all(black_box(item), &matchers);
+            }
+        })
+    });
+    let replacer = Replacer::new(&parse("{}{n}{=x}{}").unwrap());
+    c.bench_function("replace_into", |b| {
+        let mut out = String::new();
+        let mut indices = ReplaceIndices::default();
+        b.iter(|| {
+            for item in &items {
+                let _ = replacer.replace_into(black_box(item), &mut out, &mut indices);
+            }
+        })
+    });
+    c.bench_function("resolve", |b| {
+        b.iter(|| resolve(black_box(&items), "{}{n}{=x}{}", ResolveOpts::new()))
+    });
+}
+
+criterion_group!(benches, bench_tokenize, bench_match);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use mrf::{parser::parse, replacer::Replacer};
+
+// Parse the first line as a replacer, and replace each of the remaining lines with it.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        let mut lines = s.lines();
+        if let Some(Ok(elems)) = lines.next().map(parse) {
+            let replacer = Replacer::from(elems).with_all_occurrences(true);
+            for line in lines {
+                let _ = replacer.replace(line);
+            }
+        }
+    }
+});
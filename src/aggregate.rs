@@ -0,0 +1,150 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::replacement::Highlight;
+
+/// Aggregate function over the numeric values of a match group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Sum,
+    Min,
+    Max,
+    Count,
+}
+
+/// Aggregate of a match group across items, e.g. `sum:1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aggregate {
+    pub function: Function,
+    /// Index of the match group (1-based).
+    pub group: usize,
+}
+
+impl FromStr for Function {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sum" => Ok(Function::Sum),
+            "min" => Ok(Function::Min),
+            "max" => Ok(Function::Max),
+            "count" => Ok(Function::Count),
+            _ => Err(format!(
+                "invalid aggregate function: {} (expected sum, min, max or count)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Function::Sum => "sum",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::Count => "count",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Aggregate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (function, group) = s
+            .split_once(':')
+            .ok_or_else(|| format!("invalid aggregate: {} (expected e.g. sum:1)", s))?;
+        let group = match usize::from_str(group) {
+            Ok(group) if group > 0 => group,
+            _ => return Err(format!("invalid aggregate group: {}", group)),
+        };
+        Ok(Self {
+            function: function.parse()?,
+            group,
+        })
+    }
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.function, self.group)
+    }
+}
+
+impl Aggregate {
+    /// Compute the aggregate over the matched items.
+    ///
+    /// Match groups which are not numbers are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `highlights` - Highlighted replacements of the matched items.
+    ///
+    /// # Returns
+    ///
+    /// The aggregated value, or `None` for the minimum or maximum of no values.
+    pub fn compute(&self, highlights: &[Highlight]) -> Option<f64> {
+        let values = highlights.iter().filter_map(|h| {
+            let (range, _) = h.left_spans.get(self.group - 1)?;
+            f64::from_str(h.left[range.clone()].trim()).ok()
+        });
+        match self.function {
+            Function::Sum => Some(values.sum()),
+            Function::Min => values.reduce(f64::min),
+            Function::Max => values.reduce(f64::max),
+            Function::Count => Some(values.count() as f64),
+        }
+    }
+}
+
+/// Format aggregates with their values, one per line.
+pub fn format_aggregates(aggregates: &[Aggregate], highlights: &[Highlight]) -> String {
+    aggregates
+        .iter()
+        .map(|a| match a.compute(highlights) {
+            Some(value) => format!("{}: {}", a, value),
+            None => format!("{}: -", a),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replacement::{resolve_highlights, ResolveOpts};
+
+    macro_rules! aggregate_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (aggregate, expected): (&str, Option<f64>) = $value;
+                    let items = ["a-1", "b-20", "c-x", "d-3.5", "y"];
+                    let highlights = resolve_highlights(&items, "{}{=_}{}", ResolveOpts::new())
+                        .unwrap();
+                    let aggregate: Aggregate = aggregate.parse().unwrap();
+                    assert_eq!(aggregate.compute(&highlights), expected);
+                }
+            )*
+        }
+    }
+
+    aggregate_tests!(
+        aggregate_sum: ("sum:3", Some(24.5)),
+        aggregate_min: ("min:3", Some(1.0)),
+        aggregate_max: ("max:3", Some(20.0)),
+        aggregate_count: ("count:3", Some(3.0)),
+        aggregate_no_numbers: ("min:1", None),
+        aggregate_out_of_range: ("sum:9", Some(0.0)),
+    );
+
+    #[test]
+    fn parse_aggregate_invalid() {
+        assert!("sum".parse::<Aggregate>().is_err());
+        assert!("avg:1".parse::<Aggregate>().is_err());
+        assert!("sum:0".parse::<Aggregate>().is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+use std::fs::Metadata;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+
+/// Default format of times without a format, e.g. `{%mtime}`.
+pub const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d";
+
+/// Template deriving a directory from the metadata of a file, e.g. `{%mtime:%Y/%m}`.
+///
+/// Placeholders of the form `{%field:format}` are replaced with a time of the file formatted with
+/// strftime-style `format` in the local time zone, and the rest of the template is kept as-is.
+/// Fields are `mtime` (modified), `ctime` (created) and `atime` (accessed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bucket {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Lit(String),
+    Time(TimeField, String),
+}
+
+/// Time of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeField {
+    Modified,
+    Created,
+    Accessed,
+}
+
+impl FromStr for TimeField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mtime" => Ok(TimeField::Modified),
+            "ctime" => Ok(TimeField::Created),
+            "atime" => Ok(TimeField::Accessed),
+            _ => Err(format!(
+                "invalid time field: {} (expected mtime, ctime or atime)",
+                s
+            )),
+        }
+    }
+}
+
+impl TimeField {
+    /// Get the time from file metadata.
+    pub fn get(self, metadata: &Metadata) -> io::Result<SystemTime> {
+        match self {
+            TimeField::Modified => metadata.modified(),
+            TimeField::Created => metadata.created(),
+            TimeField::Accessed => metadata.accessed(),
+        }
+    }
+}
+
+impl FromStr for Bucket {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = vec![];
+        let mut rest = s;
+        while let Some(start) = rest.find("{%") {
+            if start > 0 {
+                parts.push(Part::Lit(rest[..start].to_owned()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unterminated placeholder in bucket: {}", s))?;
+            let placeholder = &rest[start + 2..start + end];
+            let (field, format) = match placeholder.split_once(':') {
+                Some((field, format)) => (field, format),
+                None => (placeholder, DEFAULT_TIME_FORMAT),
+            };
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(format!("invalid time format: {}", format));
+            }
+            parts.push(Part::Time(field.parse()?, format.to_owned()));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Lit(rest.to_owned()));
+        }
+        Ok(Self { parts })
+    }
+}
+
+impl fmt::Display for TimeField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeField::Modified => write!(f, "mtime"),
+            TimeField::Created => write!(f, "ctime"),
+            TimeField::Accessed => write!(f, "atime"),
+        }
+    }
+}
+
+impl fmt::Display for Bucket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Lit(lit) => write!(f, "{}", lit)?,
+                Part::Time(field, format) => write!(f, "{{%{}:{}}}", field, format)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Bucket {
+    /// Resolve the directory of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the directory. Fails if the metadata cannot be read, or a time
+    /// is not supported by the platform.
+    pub fn resolve(&self, path: &Path) -> io::Result<String> {
+        let metadata = path.metadata()?;
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Lit(lit) => out.push_str(lit),
+                Part::Time(field, format) => {
+                    let time: DateTime<Local> = field.get(&metadata)?.into();
+                    out.push_str(&time.format(format).to_string());
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! parse_bucket_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected): (&str, Option<&str>) = $value;
+                    let bucket = s.parse::<Bucket>().ok().map(|b| b.to_string());
+                    assert_eq!(bucket.as_deref(), expected);
+                }
+            )*
+        }
+    }
+
+    parse_bucket_tests!(
+        parse_bucket_time: ("{%mtime:%Y/%m}", Some("{%mtime:%Y/%m}")),
+        parse_bucket_default_format: ("{%ctime}", Some("{%ctime:%Y-%m-%d}")),
+        parse_bucket_literals: ("by-{%atime:%Y}/x", Some("by-{%atime:%Y}/x")),
+        parse_bucket_invalid_field: ("{%size}", None),
+        parse_bucket_invalid_format: ("{%mtime:%Q}", None),
+        parse_bucket_unterminated: ("{%mtime", None),
+    );
+
+    #[test]
+    fn resolve_bucket() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let bucket: Bucket = "x/{%mtime:%Y}".parse().unwrap();
+        let year = Local::now().format("%Y").to_string();
+        assert_eq!(bucket.resolve(temp.path()).unwrap(), format!("x/{}", year));
+    }
+}
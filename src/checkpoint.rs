@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Interval between syncs of a checkpoint to disk.
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Item recorded as finished, in the format of plan entries.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    left: String,
+    right: String,
+}
+
+/// Writer appending finished items to a checkpoint file as JSON Lines, so that an interrupted run
+/// can be resumed by skipping them.
+pub struct Checkpoint {
+    /// File, and when it was last synced to disk.
+    file: Mutex<(File, Instant)>,
+}
+
+impl Checkpoint {
+    /// Open a checkpoint file for appending, creating it if missing.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        // Terminate a last line truncated by a crash, so that it does not swallow the next record.
+        let len = file.metadata()?.len();
+        if len > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::Start(len - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+        Ok(Self {
+            file: Mutex::new((file, Instant::now())),
+        })
+    }
+
+    /// Record that an item was finished.
+    ///
+    /// Each record is written as soon as the item is finished, so that it survives the process
+    /// being killed, and synced to disk periodically, so that it survives a power loss.
+    pub fn record(&self, left: &str, right: &str) -> io::Result<()> {
+        let record = Record {
+            left: left.to_owned(),
+            right: right.to_owned(),
+        };
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        // Write the line at once so that a record is never interleaved or split by another.
+        file.0.write_all(line.as_bytes())?;
+        if file.1.elapsed() >= SYNC_INTERVAL {
+            file.0.sync_data()?;
+            file.1 = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Sync the records to disk.
+    pub fn sync(&self) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        file.0.sync_data()?;
+        file.1 = Instant::now();
+        Ok(())
+    }
+}
+
+/// Read the items recorded as finished in a checkpoint file.
+///
+/// # Returns
+///
+/// An `io::Result` containing the items and their replaced strings. A missing checkpoint file is
+/// empty. Invalid lines, e.g. a last line truncated by a crash, are skipped.
+pub fn load(path: &Path) -> io::Result<HashSet<(String, String)>> {
+    let s = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(s.lines()
+        .filter_map(|line| serde_json::from_str::<Record>(line).ok())
+        .map(|r| (r.left, r.right))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn record_and_load() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let path = temp.child("checkpoint.jsonl");
+        let checkpoint = Checkpoint::open(path.path()).unwrap();
+        checkpoint.record("a-1", "a_1").unwrap();
+        checkpoint.record("b\n", "b").unwrap();
+        checkpoint.sync().unwrap();
+        // A record truncated by a crash.
+        fs::OpenOptions::new()
+            .append(true)
+            .open(path.path())
+            .unwrap()
+            .write_all(b"{\"left\":\"c")
+            .unwrap();
+        Checkpoint::open(path.path())
+            .unwrap()
+            .record("d", "e")
+            .unwrap();
+        let finished = load(path.path()).unwrap();
+        assert_eq!(finished.len(), 3);
+        assert!(finished.contains(&("d".to_owned(), "e".to_owned())));
+        assert!(finished.contains(&("a-1".to_owned(), "a_1".to_owned())));
+        assert!(finished.contains(&("b\n".to_owned(), "b".to_owned())));
+        assert!(load(temp.child("missing").path()).unwrap().is_empty());
+    }
+}
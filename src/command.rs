@@ -1,12 +1,16 @@
 use std::fmt;
+use std::io;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::process::Command;
 
 use nom::{
     branch::alt,
-    bytes::complete::{escaped, is_a, is_not},
-    character::complete::{char, one_of},
-    combinator::{all_consuming, map, opt},
-    multi::{many1, separated_list},
-    sequence::delimited,
+    bytes::complete::{is_a, is_not, tag, take_while1},
+    character::complete::{anychar, char, one_of},
+    combinator::{all_consuming, map, not, opt, peek, verify},
+    multi::{many0, many1, separated_list},
+    sequence::{delimited, preceded, terminated},
     IResult,
 };
 
@@ -21,49 +25,216 @@ impl fmt::Display for Error {
     }
 }
 
+#[derive(Default)]
+pub struct ParseOpts {
+    /// Expand environment variables (`$VAR`, `${VAR}`) and a leading tilde (`~`).
+    pub expand_env: bool,
+}
+
 /// Parse command.
-pub fn parse(s: &str) -> Result<Vec<String>, Error> {
-    match all_args(s) {
+///
+/// Quoting follows POSIX shell rules:
+/// * Outside quotes, a backslash (`\`) escapes any character.
+/// * Inside single quotes, every character is literal.
+/// * Inside double quotes, a backslash only escapes `$`, `` ` ``, `"` and `\`, and is preserved
+///   otherwise.
+///
+/// If `opts.expand_env` is set, environment variables are expanded outside single quotes, and a
+/// tilde at the start of an unquoted argument is expanded to the home directory. Unset variables
+/// are expanded to an empty string.
+///
+/// # Arguments
+///
+/// * `s` - String slice to parse.
+/// * `opts` - Options.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of arguments.
+pub fn parse(s: &str, opts: &ParseOpts) -> Result<Vec<String>, Error> {
+    match all_args(s, opts.expand_env) {
         Ok((_, a)) => Ok(a),
         Err(_) => Err(Error {}),
     }
 }
 
+/// Substitute `{left}` and `{right}` in parsed arguments with an item and its replacement.
+///
+/// Substitution happens after parsing, so items containing spaces or quotes remain single
+/// arguments.
+pub fn substitute(args: &[String], left: &str, right: &str) -> Vec<String> {
+    args.iter()
+        .map(|a| a.replace("{left}", left).replace("{right}", right))
+        .collect()
+}
+
+/// Commands built into `cmd.exe`, which cannot be spawned directly on Windows.
+const CMD_BUILTINS: [&str; 14] = [
+    "assoc", "copy", "del", "dir", "echo", "erase", "md", "mkdir", "move", "rd", "ren", "rename",
+    "rmdir", "type",
+];
+
+/// Build a `Command` from parsed arguments, followed by extra arguments.
+///
+/// On Windows, batch files (`.bat`/`.cmd`) and `cmd.exe` built-ins are run through
+/// `cmd /V:OFF /S /C` with a command line quoted according to `cmd.exe`'s rules, since the default
+/// quoting used by `Command::args` only applies to programs parsing their arguments with the MSVC
+/// runtime. Delayed expansion is disabled so that `!` is not expanded.
+///
+/// # Arguments
+///
+/// * `args` - Parsed command arguments.
+/// * `extra` - Extra arguments appended after `args`.
+///
+/// # Returns
+///
+/// An `io::Result` containing a `Command`. Fails if `args` is empty.
+pub fn build(args: &[String], extra: &[&str]) -> io::Result<Command> {
+    if args.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "command is empty",
+        ));
+    }
+    #[cfg(windows)]
+    {
+        if needs_cmd(&args[0]) {
+            let mut cmd = Command::new("cmd");
+            cmd.args(&["/V:OFF", "/S", "/C"]);
+            // cmd.exe does not recognize quoted built-ins, whose names need no quoting.
+            let program = if CMD_BUILTINS.contains(&args[0].to_ascii_lowercase().as_str()) {
+                args[0].clone()
+            } else {
+                quote_cmd_arg(&args[0])
+            };
+            cmd.raw_arg(format!(
+                "\"{}\"",
+                std::iter::once(program)
+                    .chain(
+                        args[1..]
+                            .iter()
+                            .map(|a| a.as_str())
+                            .chain(extra.iter().copied())
+                            .map(quote_cmd_arg)
+                    )
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ));
+            return Ok(cmd);
+        }
+    }
+    let mut cmd = Command::new(&args[0]);
+    cmd.args(&args[1..]);
+    cmd.args(extra);
+    Ok(cmd)
+}
+
+/// Check whether program must be run through `cmd.exe`, i.e. it is a batch file or a built-in.
+pub fn needs_cmd(program: &str) -> bool {
+    let lower = program.to_ascii_lowercase();
+    lower.ends_with(".bat") || lower.ends_with(".cmd") || CMD_BUILTINS.contains(&lower.as_str())
+}
+
+/// Quote an argument for `cmd.exe`.
+///
+/// The argument is surrounded by double quotes, with embedded double quotes doubled. `cmd.exe`
+/// expands `%VAR%` even inside double quotes and has no escape for `%` there, so each `%` is
+/// followed by `%cd:~,%`, an empty substring of the current directory, which prevents a variable
+/// name from following it.
+pub fn quote_cmd_arg(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('"', "\"\"").replace('%', "%%cd:~,%"))
+}
+
 /// Parse all arguments.
-fn all_args(s: &str) -> IResult<&str, Vec<String>> {
-    all_consuming(separated_list(is_a(" "), arg))(s.trim())
+fn all_args(s: &str, expand: bool) -> IResult<&str, Vec<String>> {
+    all_consuming(separated_list(is_a(" "), |s| arg(s, expand)))(s.trim())
 }
 
 /// Parse one argument.
-fn arg(s: &str) -> IResult<&str, String> {
+fn arg(s: &str, expand: bool) -> IResult<&str, String> {
+    let (s, home) = if expand { opt(tilde)(s)? } else { (s, None) };
+    let part = |s| {
+        alt((
+            |s| unquoted(s, expand),
+            |s| double_quoted(s, expand),
+            single_quoted,
+        ))(s)
+    };
+    let (s, parts) = match home {
+        Some(_) => many0(part)(s)?,
+        None => many1(part)(s)?,
+    };
+    Ok((s, [home.unwrap_or_default(), parts.join("")].concat()))
+}
+
+/// Parse a leading tilde, followed by a slash or the end of the argument.
+fn tilde(s: &str) -> IResult<&str, String> {
+    map(terminated(char('~'), peek(not(is_not("/ ")))), |_| {
+        dirs::home_dir()
+            .map(|home| home.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    })(s)
+}
+
+/// Parse an unquoted part of an argument.
+fn unquoted(s: &str, expand: bool) -> IResult<&str, String> {
     map(
-        many1(alt((is_not(" \"\'"), double_quoted, single_quoted))),
+        many1(alt((
+            map(preceded(char('\\'), anychar), |c| c.to_string()),
+            |s| variable(s, expand),
+            map(is_not(" \"'\\$"), str::to_owned),
+            map(char('$'), |c| c.to_string()),
+        ))),
         |a| a.join(""),
     )(s)
 }
 
 /// Parse a double quoted string.
-fn double_quoted(s: &str) -> IResult<&str, &str> {
+fn double_quoted(s: &str, expand: bool) -> IResult<&str, String> {
     delimited(
         char('"'),
-        map(opt(escaped(is_not("\\\""), '\\', one_of(r#""\"#))), |a| {
-            a.unwrap_or("")
-        }),
+        map(
+            many0(alt((
+                map(preceded(char('\\'), one_of("$`\"\\")), |c| c.to_string()),
+                map(char('\\'), |c| c.to_string()),
+                |s| variable(s, expand),
+                map(is_not("\"\\$"), str::to_owned),
+                map(char('$'), |c| c.to_string()),
+            ))),
+            |a| a.join(""),
+        ),
         char('"'),
     )(s)
 }
 
 /// Parse a single quoted string.
-fn single_quoted(s: &str) -> IResult<&str, &str> {
+fn single_quoted(s: &str) -> IResult<&str, String> {
     delimited(
         char('\''),
-        map(opt(escaped(is_not("\\'"), '\\', one_of(r#"'\"#))), |a| {
-            a.unwrap_or("")
+        map(opt(is_not("'")), |a: Option<&str>| {
+            a.unwrap_or("").to_owned()
         }),
         char('\''),
     )(s)
 }
 
+/// Parse an environment variable (`$VAR` or `${VAR}`) and expand it. Fails if `expand` is false.
+fn variable(s: &str, expand: bool) -> IResult<&str, String> {
+    let name = |s| take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_')(s);
+    map(
+        preceded(
+            verify(tag("$"), |_: &str| expand),
+            alt((delimited(char('{'), name, char('}')), name)),
+        ),
+        env_var,
+    )(s)
+}
+
+/// Get the value of an environment variable, or an empty string if it is not set.
+fn env_var(name: &str) -> String {
+    std::env::var(name).unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,7 +246,7 @@ mod tests {
                 fn $name() {
                     let (s, expected) = $value;
                     assert_eq!(
-                        all_args(s).unwrap(),
+                        all_args(s, false).unwrap(),
                         ("", expected.iter().map(|a| a.to_string()).collect())
                     );
                 }
@@ -84,6 +255,7 @@ mod tests {
     }
 
     all_args_tests!(
+        all_args_grep: (r#"grep -e "a\"b""#, vec!["grep", "-e", r#"a"b"#]),
         all_args_empty: ("", Vec::<&str>::new()),
         all_args_simple: ("echo", vec!["echo"]),
         all_args_multi: (r#"echo "Hello, World!""#, vec!["echo", "Hello, World!"]),
@@ -92,13 +264,107 @@ mod tests {
         all_args_end_spaces: (r#"echo "Hello, World!" "#, vec!["echo", "Hello, World!"]),
     );
 
+    #[test]
+    fn substitute_items() {
+        let args = parse("touch -r {left} '{right}.bak'", &ParseOpts::default()).unwrap();
+        assert_eq!(
+            substitute(&args, "a b", "c"),
+            vec!["touch", "-r", "a b", "c.bak"]
+        );
+    }
+
+    #[test]
+    fn build_empty() {
+        assert!(build(&[], &["a"]).is_err());
+    }
+
+    #[test]
+    fn all_args_expand_env() {
+        std::env::set_var("MRF_COMMAND_TEST", "a b");
+        assert_eq!(
+            all_args(
+                r#"echo $MRF_COMMAND_TEST "${MRF_COMMAND_TEST}c" '$MRF_COMMAND_TEST' \$x"#,
+                true
+            )
+            .unwrap(),
+            (
+                "",
+                vec![
+                    "echo".to_owned(),
+                    "a b".to_owned(),
+                    "a bc".to_owned(),
+                    "$MRF_COMMAND_TEST".to_owned(),
+                    "$x".to_owned(),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn all_args_expand_tilde() {
+        let home = dirs::home_dir()
+            .map(|home| home.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        assert_eq!(
+            all_args("ls ~ ~/a a~", true).unwrap(),
+            (
+                "",
+                vec![
+                    "ls".to_owned(),
+                    home.clone(),
+                    format!("{}/a", home),
+                    "a~".to_owned(),
+                ]
+            )
+        );
+    }
+
+    macro_rules! needs_cmd_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected) = $value;
+                    assert_eq!(needs_cmd(s), expected);
+                }
+            )*
+        }
+    }
+
+    needs_cmd_tests!(
+        needs_cmd_exe: ("convert.exe", false),
+        needs_cmd_bat: ("convert.bat", true),
+        needs_cmd_cmd_uppercase: ("CONVERT.CMD", true),
+        needs_cmd_builtin: ("copy", true),
+        needs_cmd_no_extension: ("cp", false),
+    );
+
+    macro_rules! quote_cmd_arg_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected) = $value;
+                    assert_eq!(quote_cmd_arg(s), expected);
+                }
+            )*
+        }
+    }
+
+    quote_cmd_arg_tests!(
+        quote_cmd_arg_simple: ("a", r#""a""#),
+        quote_cmd_arg_spaces: ("a b", r#""a b""#),
+        quote_cmd_arg_quotes: (r#"a "b""#, r#""a ""b""""#),
+        quote_cmd_arg_percent: ("100%USERNAME%", r#""100%%cd:~,%USERNAME%%cd:~,%""#),
+    );
+
     macro_rules! arg_tests {
         ($($name:ident: $value:expr,)*) => {
             $(
                 #[test]
                 fn $name() {
                     let (s, expected) = $value;
-                    assert_eq!(arg(s).unwrap(), (expected.0, expected.1.to_owned()));
+                    assert_eq!(arg(s, false).unwrap(), (expected.0, expected.1.to_owned()));
                 }
             )*
         }
@@ -110,6 +376,9 @@ mod tests {
         arg_double_quoted: (r#""a b""#, ("", r"a b")),
         arg_single_quoted: ("'a b'", ("", r"a b")),
         arg_joined_quoted: (r#"a"b c""#, ("", r"ab c")),
+        arg_escaped_space: (r"a\ b", ("", "a b")),
+        arg_escaped_quote: (r#"a\"b"#, ("", r#"a"b"#)),
+        arg_dollar_not_expanded: ("$HOME", ("", "$HOME")),
     );
 
     macro_rules! double_quoted_tests {
@@ -118,7 +387,8 @@ mod tests {
                 #[test]
                 fn $name() {
                     let (s, expected) = $value;
-                    assert_eq!(double_quoted(s).unwrap(), expected);
+                    let (rest, parsed) = double_quoted(s, false).unwrap();
+                    assert_eq!((rest, parsed.as_str()), expected);
                 }
             )*
         }
@@ -127,9 +397,11 @@ mod tests {
     double_quoted_tests!(
         double_quoted_empty: (r#""""#, ("", "")),
         double_quoted_simple: (r#""abc""#, ("", "abc")),
-        double_quoted_escaped: (r#""abc \"def\"""#, ("", r#"abc \"def\""#)),
+        double_quoted_escaped: (r#""abc \"def\"""#, ("", r#"abc "def""#)),
         double_quoted_not_escaped: (r#""abc "def""#, (r#"def""#, "abc ")),
-        double_quoted_escaped_backslash: (r#""abc \\"def\""#, (r#"def\""#, r#"abc \\"#)),
+        double_quoted_escaped_backslash: (r#""abc \\"def\""#, (r#"def\""#, r#"abc \"#)),
+        double_quoted_preserve_backslash: (r#""a\b""#, ("", r#"a\b"#)),
+        double_quoted_dollar: (r#""$HOME""#, ("", "$HOME")),
         double_quoted_single_quotes: (r#""a'b'c""#, ("", "a'b'c")),
     );
 
@@ -139,7 +411,8 @@ mod tests {
                 #[test]
                 fn $name() {
                     let (s, expected) = $value;
-                    assert_eq!(single_quoted(s).unwrap(), expected);
+                    let (rest, parsed) = single_quoted(s).unwrap();
+                    assert_eq!((rest, parsed.as_str()), expected);
                 }
             )*
         }
@@ -148,9 +421,8 @@ mod tests {
     single_quoted_tests!(
         single_quoted_empty: ("''", ("", "")),
         single_quoted_simple: ("'abc'", ("", "abc")),
-        single_quoted_escaped: (r#"'abc \'def\''"#, ("", r#"abc \'def\'"#)),
+        single_quoted_backslash: (r#"'abc \'def'"#, ("def'", r#"abc \"#)),
         single_quoted_not_escaped: (r#"'abc 'def'"#, ("def'", "abc ")),
-        single_quoted_escaped_backslash: (r#"'abc \\'def\'"#, (r#"def\'"#, r#"abc \\"#)),
         single_quoted_double_quotes: (r#"'a"b"c'"#, ("", r#"a"b"c"#)),
     );
 }
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{App, ArgSettings};
+use serde::Deserialize;
+
+/// Name of project configuration files.
+pub const CONFIG_FILE: &str = ".mrf.toml";
+
+/// Flags which may be given defaults. A configuration file may come from any parent directory, so
+/// flags which skip confirmation, run commands, write files elsewhere or change how positional
+/// arguments are parsed (such as `--last`) are left out.
+const DEFAULT_FLAGS: &[&str] = &[
+    "all-occurrences",
+    "ascii-digits",
+    "bwlimit",
+    "case-insensitive-fs",
+    "collapse-spaces",
+    "concurrency",
+    "json",
+    "keep-rest",
+    "max-depth",
+    "normalize",
+    "null",
+    "number-locale",
+    "preview-all",
+    "progress",
+    "reflink",
+    "report-errors",
+    "safe",
+    "sort",
+    "stats",
+    "syntax",
+    "token-mode",
+    "truncate-to-fit",
+    "unique",
+    "uniquify",
+    "uniquify-suffix",
+    "unordered",
+    "verify",
+];
+
+/// Project configuration, read from `.mrf.toml`.
+///
+/// ```toml
+/// stop_words = ["a", "an", "of", "the"]
+/// reserved = ["-*", ".", "..", "*.tmp"]
+///
+/// [replacers]
+/// episode = "{}{=S}{n:02}{=E}{n:02}{}"
+///
+/// [defaults]
+/// mv = ["--verify", "--syntax=angle"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Named replacers, used with `--named`.
+    #[serde(default)]
+    pub replacers: HashMap<String, String>,
+    /// Default flags of each subcommand, each flag in long form with its value as a single
+    /// argument. Only the flags in `DEFAULT_FLAGS` are allowed.
+    #[serde(default)]
+    pub defaults: HashMap<String, Vec<String>>,
+    /// Words kept in lowercase by `{:titlecase}` unless first, replacing the default ones.
+    pub stop_words: Option<Vec<String>>,
+    /// Patterns of names which replaced strings must not have, with `*` and `?` wildcards, e.g.
+    /// `-*` for names looking like flags.
+    #[serde(default)]
+    pub reserved: Vec<String>,
+}
+
+impl Config {
+    /// Find the configuration of a directory, looking in it and then in each of its parents.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the path and contents of the nearest configuration file, or `None` if
+    /// there is none. Fails if the file cannot be read or parsed.
+    pub fn find(dir: &Path) -> Result<Option<(PathBuf, Self)>, Box<dyn Error>> {
+        for dir in dir.ancestors() {
+            let path = dir.join(CONFIG_FILE);
+            if path.is_file() {
+                let config = toml::from_str(&fs::read_to_string(&path)?)
+                    .map_err(|e| format!("invalid {}: {}", path.display(), e))?;
+                return Ok(Some((path, config)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Insert the default flags of the subcommand into command line arguments.
+    ///
+    /// The defaults are inserted after the subcommand, so that they apply to it. Defaults of flags
+    /// which are also given in `args`, in long or short form, are skipped, so that the command
+    /// line takes precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Command line arguments, starting with the program name.
+    /// * `app` - The application, used to find the long names of short flags.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the arguments with the defaults inserted. Fails if a default is not
+    /// one of the allowed flags.
+    pub fn with_defaults(
+        &self,
+        mut args: Vec<OsString>,
+        app: &App,
+    ) -> Result<Vec<OsString>, Box<dyn Error>> {
+        let subcommand = match args.get(1).and_then(|s| s.to_str()) {
+            Some(subcommand) => subcommand,
+            None => return Ok(args),
+        };
+        let defaults = match self.defaults.get(subcommand) {
+            Some(defaults) => defaults,
+            None => return Ok(args),
+        };
+        for default in defaults {
+            let name = flag_name(default);
+            if !name.starts_with("--") || !DEFAULT_FLAGS.contains(&&name[2..]) {
+                return Err(format!(
+                    "default {} of {} is not allowed, allowed flags are:\n    --{}",
+                    default,
+                    subcommand,
+                    DEFAULT_FLAGS.join(", --")
+                )
+                .into());
+            }
+        }
+        let shorts: Vec<(char, &str, bool)> = app
+            .get_subcommands()
+            .iter()
+            .filter(|app| app.get_name() == subcommand)
+            .flat_map(|app| app.get_arguments())
+            .filter_map(|arg| {
+                Some((
+                    arg.get_short()?,
+                    arg.get_long()?,
+                    arg.is_set(ArgSettings::TakesValue),
+                ))
+            })
+            .collect();
+        let given: Vec<String> = args[2..]
+            .iter()
+            .filter_map(|a| a.to_str())
+            .take_while(|a| *a != "--")
+            .flat_map(|a| given_flags(a, &shorts))
+            .collect();
+        let defaults: Vec<OsString> = defaults
+            .iter()
+            .filter(|d| !given.iter().any(|g| g == flag_name(d)))
+            .map(OsString::from)
+            .collect();
+        args.splice(2..2, defaults);
+        Ok(args)
+    }
+}
+
+/// Get the name of a flag, without its value given with `=`.
+fn flag_name(arg: &str) -> &str {
+    arg.split('=').next().unwrap_or(arg)
+}
+
+/// Get the long names of the flags given in an argument, which may be a long flag or a cluster of
+/// short flags, e.g. `-yc3`.
+///
+/// # Arguments
+///
+/// * `arg` - Command line argument.
+/// * `shorts` - Short name, long name and whether it takes a value of each flag.
+fn given_flags(arg: &str, shorts: &[(char, &str, bool)]) -> Vec<String> {
+    if arg.starts_with("--") {
+        return vec![flag_name(arg).to_owned()];
+    }
+    let mut flags = vec![];
+    if let Some(cluster) = arg.strip_prefix('-') {
+        for c in cluster.chars() {
+            match shorts.iter().find(|(short, _, _)| *short == c) {
+                Some((_, long, takes_value)) => {
+                    flags.push(format!("--{}", long));
+                    // The rest of the cluster is the value.
+                    if *takes_value {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use clap::Arg;
+
+    #[test]
+    fn find_config_in_parent() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child(CONFIG_FILE)
+            .write_str("[replacers]\nep = \"{n}\"\n")
+            .unwrap();
+        temp.child("a/b").create_dir_all().unwrap();
+        let (path, config) = Config::find(temp.child("a/b").path()).unwrap().unwrap();
+        assert_eq!(path, temp.child(CONFIG_FILE).path());
+        assert_eq!(config.replacers["ep"], "{n}");
+    }
+
+    #[test]
+    fn parse_config_example() {
+        let config: Config = toml::from_str(concat!(
+            "stop_words = [\"a\", \"an\", \"of\", \"the\"]\n",
+            "reserved = [\"-*\", \".\", \"..\", \"*.tmp\"]\n",
+            "\n",
+            "[replacers]\n",
+            "episode = \"{}{=S}{n:02}{=E}{n:02}{}\"\n",
+            "\n",
+            "[defaults]\n",
+            "mv = [\"--verify\", \"--syntax=angle\"]\n",
+        ))
+        .unwrap();
+        assert_eq!(config.stop_words.unwrap(), ["a", "an", "of", "the"]);
+        assert_eq!(config.replacers.len(), 1);
+    }
+
+    #[test]
+    fn find_config_invalid() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child(CONFIG_FILE).write_str("[unknown]\n").unwrap();
+        assert!(Config::find(temp.path()).is_err());
+    }
+
+    macro_rules! with_defaults_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (args, expected): (&[&str], &[&str]) = $value;
+                    let config: Config =
+                        toml::from_str("[defaults]\nmv = [\"--verify\", \"--syntax=angle\"]\n")
+                            .unwrap();
+                    let app = App::new("mrf").subcommand(
+                        App::new("mv")
+                            .arg(Arg::new("assume-yes").short('y').long("assume-yes"))
+                            .arg(Arg::new("syntax").short('s').long("syntax").takes_value(true)),
+                    );
+                    let args = args.iter().map(OsString::from).collect();
+                    let expected: Vec<OsString> = expected.iter().map(OsString::from).collect();
+                    assert_eq!(config.with_defaults(args, &app).unwrap(), expected);
+                }
+            )*
+        }
+    }
+
+    with_defaults_tests!(
+        with_defaults_inserted: (
+            &["mrf", "mv", "a", "{}"],
+            &["mrf", "mv", "--verify", "--syntax=angle", "a", "{}"]
+        ),
+        with_defaults_overridden: (
+            &["mrf", "mv", "--syntax=percent", "a", "{}"],
+            &["mrf", "mv", "--verify", "--syntax=percent", "a", "{}"]
+        ),
+        with_defaults_overridden_short: (
+            &["mrf", "mv", "-ys", "percent", "a", "{}"],
+            &["mrf", "mv", "--verify", "-ys", "percent", "a", "{}"]
+        ),
+        with_defaults_other_subcommand: (&["mrf", "map", "a", "{}"], &["mrf", "map", "a", "{}"]),
+        with_defaults_no_subcommand: (&["mrf"], &["mrf"]),
+    );
+
+    #[test]
+    fn with_defaults_not_allowed() {
+        let app = App::new("mrf");
+        for flag in &[
+            "--assume-yes",
+            "-y",
+            "--pre-cmd=rm -rf ~",
+            "--emit-script=sh",
+            "--last",
+        ] {
+            let config: Config =
+                toml::from_str(&format!("[defaults]\nmv = [{:?}]\n", flag)).unwrap();
+            let args = vec![OsString::from("mrf"), OsString::from("mv")];
+            assert!(config.with_defaults(args, &app).is_err());
+        }
+    }
+}
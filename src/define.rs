@@ -0,0 +1,143 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::parser::Syntax;
+
+/// Maximum depth of fragments referring to other fragments, to detect cycles.
+const MAX_DEPTH: usize = 16;
+
+/// Reusable replacer fragment, e.g. `date={n}-{n}-{n}`, referred to as `{@date}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Define {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for Define {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid definition: {} (expected e.g. date={{n}}-{{n}})", s))?;
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(format!("invalid definition name: {}", name));
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Define {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
+/// Expand references to fragments in a replacer.
+///
+/// References are written like specifiers of the syntax with the name preceded by `@`, e.g.
+/// `{@date}`, `<@date>` or `%{@date}`. Fragments may refer to other fragments. Escaped
+/// references (e.g. `\{@date}`) are kept as-is.
+///
+/// # Arguments
+///
+/// * `s` - Replacer.
+/// * `defines` - Fragments. Later definitions of a name take precedence.
+/// * `syntax` - Syntax of the replacer.
+///
+/// # Returns
+///
+/// A `Result` containing the expanded replacer. Fails if a fragment is not defined, or fragments
+/// refer to each other in a cycle.
+pub fn expand(s: &str, defines: &[Define], syntax: Syntax) -> Result<String, String> {
+    expand_depth(s, defines, syntax, 0)
+}
+
+fn expand_depth(
+    s: &str,
+    defines: &[Define],
+    syntax: Syntax,
+    depth: usize,
+) -> Result<String, String> {
+    let (open, close) = match syntax {
+        Syntax::Braces => ("{@", '}'),
+        Syntax::Angle => ("<@", '>'),
+        Syntax::Percent => ("%{@", '}'),
+    };
+    let mut out = String::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            let len = escaped.chars().next().map_or(0, char::len_utf8);
+            out.push('\\');
+            out.push_str(&escaped[..len]);
+            rest = &escaped[len..];
+            continue;
+        }
+        let reference = rest
+            .strip_prefix(open)
+            .and_then(|r| r.find(close).map(|end| (&r[..end], &r[end + 1..])));
+        match reference {
+            Some((name, after)) => {
+                if depth >= MAX_DEPTH {
+                    return Err(format!("definition of {} refers to itself", name));
+                }
+                let define = defines
+                    .iter()
+                    .rev()
+                    .find(|d| d.name == name)
+                    .ok_or_else(|| format!("undefined fragment: {}", name))?;
+                out.push_str(&expand_depth(&define.value, defines, syntax, depth + 1)?);
+                rest = after;
+            }
+            None => {
+                let len = rest.chars().next().map_or(0, char::len_utf8);
+                out.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! expand_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, syntax, expected): (&str, Syntax, Option<&str>) = $value;
+                    let defines: Vec<Define> = ["date={n}-{n}-{n}", "stamp={@date}T{n}", "loop={@loop}"]
+                        .iter()
+                        .map(|d| d.parse().unwrap())
+                        .collect();
+                    assert_eq!(expand(s, &defines, syntax).ok().as_deref(), expected);
+                }
+            )*
+        }
+    }
+
+    expand_tests!(
+        expand_simple: ("{}_{@date}.{}", Syntax::Braces, Some("{}_{n}-{n}-{n}.{}")),
+        expand_nested: ("{@stamp}", Syntax::Braces, Some("{n}-{n}-{n}T{n}")),
+        expand_escaped: (r"\{@date}", Syntax::Braces, Some(r"\{@date}")),
+        expand_angle: ("<@date>", Syntax::Angle, Some("{n}-{n}-{n}")),
+        expand_percent: ("%{@date}", Syntax::Percent, Some("{n}-{n}-{n}")),
+        expand_none: ("{n}é", Syntax::Braces, Some("{n}é")),
+        expand_undefined: ("{@time}", Syntax::Braces, None),
+        expand_cycle: ("{@loop}", Syntax::Braces, None),
+    );
+
+    #[test]
+    fn parse_define_invalid() {
+        assert!("date".parse::<Define>().is_err());
+        assert!("=x".parse::<Define>().is_err());
+        assert!("a b=x".parse::<Define>().is_err());
+    }
+}
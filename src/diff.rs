@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::{indices::SplitAtIndices, tokens::tokenize};
+
+/// A change between two strings, spanning whole tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<'a> {
+    /// Tokens present in both strings.
+    Equal(&'a str),
+    /// Tokens of the first string replaced by tokens of the second. Either may be empty.
+    Replace(&'a str, &'a str),
+}
+
+/// Compute the token-level differences between two strings.
+///
+/// Strings are tokenized, and the longest common subsequence of tokens is kept as `Equal`
+/// changes. The tokens between are grouped into `Replace` changes.
+///
+/// # Arguments
+///
+/// * `left` - Original string.
+/// * `right` - Changed string.
+///
+/// # Returns
+///
+/// A `Vec` of changes, which concatenate to `left` on the left, and `right` on the right.
+pub fn diff<'a>(left: &'a str, right: &'a str) -> Vec<Change<'a>> {
+    let (left_indices, _) = tokenize(left);
+    let (right_indices, _) = tokenize(right);
+    let a = left.split_at_indices(&left_indices);
+    let b = right.split_at_indices(&right_indices);
+    // Byte index of the start of the nth token, or the end of the string.
+    let at = |indices: &[usize], s: &str, n: usize| indices.get(n).copied().unwrap_or(s.len());
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..].
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    // Start of the pending replaced tokens in each string.
+    let (mut del, mut ins) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            if del < i || ins < j {
+                changes.push(Change::Replace(
+                    &left[at(&left_indices, left, del)..at(&left_indices, left, i)],
+                    &right[at(&right_indices, right, ins)..at(&right_indices, right, j)],
+                ));
+            }
+            let start = i;
+            while i < a.len() && j < b.len() && a[i] == b[j] && lcs[i][j] == lcs[i + 1][j + 1] + 1 {
+                i += 1;
+                j += 1;
+            }
+            changes.push(Change::Equal(
+                &left[at(&left_indices, left, start)..at(&left_indices, left, i)],
+            ));
+            del = i;
+            ins = j;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+    if del < a.len() || ins < b.len() {
+        changes.push(Change::Replace(
+            &left[at(&left_indices, left, del)..],
+            &right[at(&right_indices, right, ins)..],
+        ));
+    }
+    changes
+}
+
+/// Changes formatted in word diff style, e.g. "a[---]{+_+}1".
+pub struct WordDiff<'a, 'b>(pub &'b [Change<'a>]);
+
+impl fmt::Display for WordDiff<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in self.0 {
+            match change {
+                Change::Equal(s) => write!(f, "{}", s)?,
+                Change::Replace(del, ins) => {
+                    if !del.is_empty() {
+                        write!(f, "[-{}-]", del)?;
+                    }
+                    if !ins.is_empty() {
+                        write!(f, "{{+{}+}}", ins)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build a replacer string reproducing the changes.
+///
+/// Equal tokens are kept with `{}`, replaced tokens are replaced with `{=...}`, and inserted
+/// tokens are output as literals. The replacer is not guaranteed to reproduce the changes, since
+/// the groups are matched independently of the original positions.
+pub fn infer_replacer(changes: &[Change]) -> String {
+    changes
+        .iter()
+        .map(|change| match change {
+            Change::Equal(_) => "{}".to_owned(),
+            Change::Replace("", ins) => escape(ins, &['{', '}', '\\']),
+            Change::Replace(_, ins) => format!("{{={}}}", escape(ins, &['{', '}', ':', '\\'])),
+        })
+        .collect()
+}
+
+/// Escape characters with a backslash.
+fn escape(s: &str, chars: &[char]) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if chars.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! diff_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (left, right, expected, replacer): (&str, &str, &str, &str) = $value;
+                    let changes = diff(left, right);
+                    assert_eq!(WordDiff(&changes).to_string(), expected);
+                    assert_eq!(infer_replacer(&changes), replacer);
+                }
+            )*
+        }
+    }
+
+    diff_tests!(
+        diff_equal: ("a-1", "a-1", "a-1", "{}"),
+        diff_replace: ("a-1", "a_1", "a[---]{+_+}1", "{}{=_}{}"),
+        diff_prefix: ("IMG_001.jpg", "photo_001.jpg", "[-IMG-]{+photo+}_001.jpg", "{=photo}{}"),
+        diff_delete: ("a-1.jpg", "a.jpg", "a[--1-].jpg", "{}{=}{}"),
+        diff_insert: ("a.jpg", "a-1.jpg", "a{+-1+}.jpg", "{}-1{}"),
+        diff_escape: ("a", "{b}", "[-a-]{+{b}+}", "{=\\{b\\}}"),
+        diff_empty: ("", "a", "{+a+}", "a"),
+    );
+}
@@ -0,0 +1,230 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Read the title embedded in the metadata of a document.
+///
+/// PDF files are read from their document information dictionary, as long as it is not in a
+/// compressed object stream. Office Open XML (`.docx`, `.xlsx`, `.pptx`) and OpenDocument (`.odt`,
+/// `.ods`, `.odp`) files are read from their core properties.
+///
+/// # Returns
+///
+/// An `io::Result` containing the title, or `None` if the document has none or its format is not
+/// supported. Fails if the file cannot be read.
+pub fn title(path: &Path) -> io::Result<Option<String>> {
+    let mut magic = [0; 4];
+    let n = File::open(path)?.read(&mut magic)?;
+    let title = match &magic[..n] {
+        b"%PDF" => pdf_title(&fs::read(path)?),
+        b"PK\x03\x04" => office_title(path)?,
+        _ => None,
+    };
+    Ok(title.map(|t| t.trim().to_owned()).filter(|t| !t.is_empty()))
+}
+
+/// Read the title of a PDF from the document information dictionary referred to by the trailer.
+fn pdf_title(data: &[u8]) -> Option<String> {
+    let info = rfind(data, b"/Info")?;
+    let mut refs = data[info + 5..]
+        .split(|c| c.is_ascii_whitespace())
+        .filter(|s| !s.is_empty());
+    let (num, gen) = (refs.next()?, refs.next()?);
+    let header = [num, b" ", gen, b" obj"].concat();
+    let start = find_obj(data, &header)? + header.len();
+    let end = find(&data[start..], b"endobj").map_or(data.len(), |e| start + e);
+    let obj = &data[start..end];
+    let title = find(obj, b"/Title")? + 6;
+    let value = &obj[title..];
+    let value = &value[value.iter().position(|c| !c.is_ascii_whitespace())?..];
+    let bytes = match value.first()? {
+        b'(' => pdf_literal_string(&value[1..])?,
+        b'<' => pdf_hex_string(&value[1..])?,
+        _ => return None,
+    };
+    Some(pdf_text_string(&bytes))
+}
+
+/// Find the start of an indirect object definition, e.g. `12 0 obj`, at the start of a line.
+fn find_obj(data: &[u8], header: &[u8]) -> Option<usize> {
+    let mut from = 0;
+    while let Some(i) = find(&data[from..], header) {
+        let i = from + i;
+        if i == 0 || data[i - 1].is_ascii_whitespace() {
+            return Some(i);
+        }
+        from = i + 1;
+    }
+    None
+}
+
+/// Decode a PDF literal string, e.g. `(Title \(draft\))`, starting after the opening parenthesis.
+fn pdf_literal_string(s: &[u8]) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    let mut depth = 0;
+    let mut bytes = s.iter().copied();
+    while let Some(c) = bytes.next() {
+        match c {
+            b'\\' => match bytes.next()? {
+                b'n' => out.push(b'\n'),
+                b'r' => out.push(b'\r'),
+                b't' => out.push(b'\t'),
+                b'b' => out.push(8),
+                b'f' => out.push(12),
+                c @ b'0'..=b'7' => {
+                    // Octal escapes have up to 3 digits.
+                    let mut value = u32::from(c - b'0');
+                    let rest: Vec<u8> = bytes.clone().take(2).collect();
+                    for d in rest.into_iter().take_while(|d| (b'0'..=b'7').contains(d)) {
+                        value = value * 8 + u32::from(d - b'0');
+                        bytes.next();
+                    }
+                    out.push(value as u8);
+                }
+                b'\r' | b'\n' => {}
+                c => out.push(c),
+            },
+            b'(' => {
+                depth += 1;
+                out.push(c);
+            }
+            b')' if depth == 0 => return Some(out),
+            b')' => {
+                depth -= 1;
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+/// Decode a PDF hexadecimal string, e.g. `<FEFF0041>`, starting after the opening angle bracket.
+fn pdf_hex_string(s: &[u8]) -> Option<Vec<u8>> {
+    let end = s.iter().position(|&c| c == b'>')?;
+    let mut digits: Vec<u8> = s[..end]
+        .iter()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|&c| (c as char).to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()?;
+    if digits.len() % 2 == 1 {
+        digits.push(0);
+    }
+    Some(digits.chunks(2).map(|d| d[0] << 4 | d[1]).collect())
+}
+
+/// Decode a PDF text string, either UTF-16BE with a byte order mark, or PDFDocEncoding (treated as
+/// Latin-1).
+fn pdf_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => bytes.iter().map(|&c| c as char).collect(),
+    }
+}
+
+/// Read the title of an Office Open XML or OpenDocument file from its core properties.
+fn office_title(path: &Path) -> io::Result<Option<String>> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    for name in &["docProps/core.xml", "meta.xml"] {
+        let mut xml = String::new();
+        match archive.by_name(name) {
+            Ok(mut file) => file.read_to_string(&mut xml)?,
+            Err(_) => continue,
+        };
+        return Ok(xml_element_text(&xml, "dc:title"));
+    }
+    Ok(None)
+}
+
+/// Get the text of the first element with the given name, unescaping entities.
+fn xml_element_text(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}", name);
+    let start = xml.find(&open)? + open.len();
+    let rest = &xml[start..];
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == ':') {
+        return None;
+    }
+    let tag_end = rest.find('>')?;
+    if rest[..tag_end].ends_with('/') {
+        return None;
+    }
+    let content = &rest[tag_end + 1..];
+    let text = &content[..content.find(&format!("</{}>", name))?];
+    Some(
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use std::io::Write;
+
+    macro_rules! pdf_title_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (info, expected): (&str, Option<&str>) = $value;
+                    let pdf = format!(
+                        "%PDF-1.4\n1 0 obj\n<< /Title (Outline) >>\nendobj\n11 0 obj\n{}\nendobj\ntrailer\n<< /Root 2 0 R /Info 11 0 R >>\n%%EOF\n",
+                        info
+                    );
+                    assert_eq!(pdf_title(pdf.as_bytes()).as_deref(), expected);
+                }
+            )*
+        }
+    }
+
+    pdf_title_tests!(
+        pdf_title_literal: ("<< /Author (Me) /Title (Tax return 2020) >>", Some("Tax return 2020")),
+        pdf_title_escapes: (r"<</Title(A \(draft\) \101)>>", Some("A (draft) A")),
+        pdf_title_nested: ("<< /Title (A (b) c) >>", Some("A (b) c")),
+        pdf_title_hex_utf16: ("<< /Title <FEFF00C90074C3A9> >>", Some("\u{c9}t\u{c3a9}")),
+        pdf_title_hex: ("<< /Title <48 69> >>", Some("Hi")),
+        pdf_title_missing: ("<< /Author (Me) >>", None),
+    );
+
+    #[test]
+    fn office_title_docx() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let path = temp.child("a.docx");
+        let mut zip = zip::ZipWriter::new(File::create(path.path()).unwrap());
+        zip.start_file("docProps/core.xml", Default::default())
+            .unwrap();
+        zip.write_all(
+            b"<cp:coreProperties><dc:title>Q&amp;A notes</dc:title><dc:creator>Me</dc:creator></cp:coreProperties>",
+        )
+        .unwrap();
+        zip.finish().unwrap();
+        assert_eq!(title(path.path()).unwrap().as_deref(), Some("Q&A notes"));
+    }
+
+    #[test]
+    fn title_unsupported() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let path = temp.child("a.txt");
+        path.write_str("Title").unwrap();
+        assert_eq!(title(path.path()).unwrap(), None);
+    }
+}
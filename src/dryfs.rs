@@ -0,0 +1,600 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::fileops::{FileOps, Kind, Local};
+use crate::fsops::MoveOpts;
+
+/// Entry of an in-memory filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Node {
+    pub kind: Kind,
+    /// Size of a file, in bytes.
+    pub len: u64,
+    /// Identifier of the filesystem the entry is on. Entries cannot be renamed across
+    /// filesystems.
+    pub dev: u64,
+    pub readonly: bool,
+}
+
+impl Node {
+    /// Create a file of `len` bytes on filesystem 0.
+    pub fn file(len: u64) -> Self {
+        Self {
+            kind: Kind::File,
+            len,
+            dev: 0,
+            readonly: false,
+        }
+    }
+
+    /// Create a directory on filesystem 0.
+    pub fn dir() -> Self {
+        Self {
+            kind: Kind::Dir,
+            len: 0,
+            dev: 0,
+            readonly: false,
+        }
+    }
+
+    /// Move the entry to another filesystem.
+    pub fn on(self, dev: u64) -> Self {
+        Self { dev, ..self }
+    }
+}
+
+/// In-memory filesystem, to simulate moves without touching any file.
+///
+/// Paths are compared by components, ignoring `.` components. The current and root directories
+/// always exist, on filesystem 0 unless added with another one.
+#[derive(Default)]
+pub struct DryFs {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+}
+
+impl DryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a filesystem with the entries of the local filesystem at the given paths.
+    ///
+    /// The existing ancestors of each path are added, and the path itself with all the entries
+    /// under it if it exists.
+    pub fn load<'a, I>(paths: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = &'a Path>,
+    {
+        let dry = Self::new();
+        for path in paths {
+            let path = key(path);
+            let mut ancestors: Vec<&Path> = path.ancestors().collect();
+            ancestors.reverse();
+            for dir in ancestors {
+                match load_node(dir) {
+                    Ok(node) => dry.insert(dir, node),
+                    Err(e) if e.kind() == ErrorKind::NotFound => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            if let Ok(Kind::Dir) = Local.kind(&path) {
+                dry.load_tree(&path)?;
+            }
+        }
+        Ok(dry)
+    }
+
+    /// Add the entries under a directory of the local filesystem, recursively.
+    fn load_tree(&self, dir: &Path) -> io::Result<()> {
+        for entry in Local.read_dir(dir)? {
+            let node = load_node(&entry)?;
+            self.insert(&entry, node);
+            if node.kind == Kind::Dir {
+                self.load_tree(&entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add an entry, replacing any entry at its path. Missing parents are added as directories
+    /// on the same filesystem.
+    pub fn insert(&self, path: &Path, node: Node) {
+        let path = key(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        for dir in path.ancestors().skip(1) {
+            if has_parent(dir) && !nodes.contains_key(dir) {
+                nodes.insert(dir.to_owned(), Node::dir().on(node.dev));
+            }
+        }
+        nodes.insert(path, node);
+    }
+
+    /// Get the entry at a path.
+    pub fn get(&self, path: &Path) -> Option<Node> {
+        get(&self.nodes.lock().unwrap(), &key(path))
+    }
+
+    /// Get all entries, by path.
+    pub fn entries(&self) -> BTreeMap<PathBuf, Node> {
+        self.nodes.lock().unwrap().clone()
+    }
+}
+
+impl FileOps for DryFs {
+    fn kind(&self, path: &Path) -> io::Result<Kind> {
+        self.get(path)
+            .map(|node| node.kind)
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let (from, to) = (key(from), key(to));
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = get(&nodes, &from).ok_or_else(|| not_found(&from))?;
+        if from == to {
+            return Ok(());
+        }
+        let parent = parent_dir(&nodes, &to)?;
+        if node.dev != parent.dev {
+            return Err(error(ErrorKind::CrossesDevices, "cross-device rename", &to));
+        }
+        match (node.kind, get(&nodes, &to).map(|n| n.kind)) {
+            (Kind::Dir, _) if to.starts_with(&from) => {
+                return Err(error(
+                    ErrorKind::InvalidInput,
+                    "cannot move a directory into itself",
+                    &to,
+                ));
+            }
+            (Kind::Dir, Some(Kind::Dir)) if children(&nodes, &to).next().is_some() => {
+                return Err(error(
+                    ErrorKind::DirectoryNotEmpty,
+                    "directory not empty",
+                    &to,
+                ));
+            }
+            (Kind::Dir, Some(Kind::File)) | (Kind::Dir, Some(Kind::Symlink)) => {
+                return Err(error(ErrorKind::NotADirectory, "not a directory", &to));
+            }
+            (Kind::File, Some(Kind::Dir)) | (Kind::Symlink, Some(Kind::Dir)) => {
+                return Err(error(ErrorKind::IsADirectory, "is a directory", &to));
+            }
+            _ => {}
+        }
+        let moved: Vec<PathBuf> = nodes
+            .range(from.clone()..)
+            .map(|(path, _)| path)
+            .take_while(|path| path.starts_with(&from))
+            .cloned()
+            .collect();
+        nodes.remove(&to);
+        for path in moved {
+            if let (Some(node), Ok(rest)) = (nodes.remove(&path), path.strip_prefix(&from)) {
+                // Joining an empty path would append a separator.
+                let path = if rest.as_os_str().is_empty() {
+                    to.clone()
+                } else {
+                    to.join(rest)
+                };
+                nodes.insert(path, node);
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_file(&self, from: &Path, to: &Path, _opts: &MoveOpts) -> io::Result<()> {
+        let (from, to) = (key(from), key(to));
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = get(&nodes, &from).ok_or_else(|| not_found(&from))?;
+        if node.kind == Kind::Dir {
+            return Err(error(ErrorKind::IsADirectory, "is a directory", &from));
+        }
+        let parent = parent_dir(&nodes, &to)?;
+        if get(&nodes, &to).is_some() {
+            return Err(error(ErrorKind::AlreadyExists, "already exists", &to));
+        }
+        nodes.insert(
+            to,
+            Node {
+                kind: Kind::File,
+                dev: parent.dev,
+                ..node
+            },
+        );
+        Ok(())
+    }
+
+    fn copy_symlink(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let (from, to) = (key(from), key(to));
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = get(&nodes, &from).ok_or_else(|| not_found(&from))?;
+        if node.kind != Kind::Symlink {
+            return Err(error(ErrorKind::InvalidInput, "not a symlink", &from));
+        }
+        let parent = parent_dir(&nodes, &to)?;
+        if get(&nodes, &to).is_some() {
+            return Err(error(ErrorKind::AlreadyExists, "already exists", &to));
+        }
+        nodes.insert(
+            to,
+            Node {
+                dev: parent.dev,
+                ..node
+            },
+        );
+        Ok(())
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        let path = key(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        if get(&nodes, &path).is_some() {
+            return Err(error(ErrorKind::AlreadyExists, "already exists", &path));
+        }
+        let parent = parent_dir(&nodes, &path)?;
+        nodes.insert(path, Node::dir().on(parent.dev));
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let path = key(path);
+        let mut ancestors: Vec<&Path> = path.ancestors().filter(|p| has_parent(p)).collect();
+        ancestors.reverse();
+        for dir in ancestors {
+            match self.create_dir(dir) {
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if self.kind(dir)? != Kind::Dir {
+                        return Err(error(ErrorKind::NotADirectory, "not a directory", dir));
+                    }
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let path = key(path);
+        let nodes = self.nodes.lock().unwrap();
+        match get(&nodes, &path) {
+            Some(node) if node.kind == Kind::Dir => Ok(children(&nodes, &path).collect()),
+            Some(_) => Err(error(ErrorKind::NotADirectory, "not a directory", &path)),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let path = key(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        match get(&nodes, &path) {
+            Some(node) if node.kind == Kind::Dir => {
+                Err(error(ErrorKind::IsADirectory, "is a directory", &path))
+            }
+            Some(_) => {
+                nodes.remove(&path);
+                Ok(())
+            }
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        let path = key(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        match get(&nodes, &path) {
+            Some(node) if node.kind == Kind::Dir => {
+                nodes.retain(|p, _| !p.starts_with(&path));
+                Ok(())
+            }
+            Some(_) => Err(error(ErrorKind::NotADirectory, "not a directory", &path)),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn hard_link(&self, target: &Path, to: &Path) -> io::Result<()> {
+        let (target, to) = (key(target), key(to));
+        let mut nodes = self.nodes.lock().unwrap();
+        let node = get(&nodes, &target).ok_or_else(|| not_found(&target))?;
+        if node.kind == Kind::Dir {
+            return Err(error(ErrorKind::IsADirectory, "is a directory", &target));
+        }
+        if node.dev != parent_dir(&nodes, &to)?.dev {
+            return Err(error(ErrorKind::CrossesDevices, "cross-device link", &to));
+        }
+        if get(&nodes, &to).is_some() {
+            return Err(error(ErrorKind::AlreadyExists, "already exists", &to));
+        }
+        nodes.insert(to, node);
+        Ok(())
+    }
+
+    fn readonly(&self, path: &Path) -> io::Result<bool> {
+        self.get(path)
+            .map(|node| node.readonly)
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn set_readonly(&self, path: &Path, readonly: bool) -> io::Result<()> {
+        let path = key(path);
+        let mut nodes = self.nodes.lock().unwrap();
+        match nodes.get_mut(&path) {
+            Some(node) => {
+                node.readonly = readonly;
+                Ok(())
+            }
+            None => Err(not_found(&path)),
+        }
+    }
+}
+
+/// Normalize a path into a key of the entries, dropping `.` components.
+fn key(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .collect()
+}
+
+/// Check whether a normalized path has a parent, i.e. is neither the current nor the root
+/// directory.
+fn has_parent(path: &Path) -> bool {
+    path.parent().is_some()
+}
+
+/// Get the entry at a normalized path. The current and root directories always exist.
+fn get(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> Option<Node> {
+    match nodes.get(path) {
+        Some(node) => Some(*node),
+        None if !has_parent(path) => Some(Node::dir()),
+        None => None,
+    }
+}
+
+/// Get the parent directory of a normalized path, failing if it is missing or not a directory.
+fn parent_dir(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> io::Result<Node> {
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    match get(nodes, parent) {
+        Some(node) if node.kind == Kind::Dir => Ok(node),
+        Some(_) => Err(error(ErrorKind::NotADirectory, "not a directory", parent)),
+        None => Err(not_found(parent)),
+    }
+}
+
+/// Get the paths of the entries directly under a normalized directory path.
+fn children<'a>(
+    nodes: &'a BTreeMap<PathBuf, Node>,
+    dir: &'a Path,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    nodes
+        .keys()
+        .filter(move |path| path.parent() == Some(dir))
+        .cloned()
+}
+
+/// Get the entry at a path of the local filesystem.
+fn load_node(path: &Path) -> io::Result<Node> {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+    let metadata = fs::symlink_metadata(path)?;
+    Ok(Node {
+        kind: Local.kind(path)?,
+        len: metadata.len(),
+        dev: dev(&metadata),
+        readonly: metadata.permissions().readonly(),
+    })
+}
+
+/// Get the identifier of the filesystem of a file.
+#[cfg(unix)]
+fn dev(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.dev()
+}
+
+/// Get the identifier of the filesystem of a file. Without identifiers, all files are assumed
+/// to be on the same filesystem.
+#[cfg(not(unix))]
+fn dev(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+fn not_found(path: &Path) -> io::Error {
+    error(ErrorKind::NotFound, "no such file or directory", path)
+}
+
+fn error(kind: ErrorKind, msg: &str, path: &Path) -> io::Error {
+    io::Error::new(kind, format!("{}: {}", msg, path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsops::{move_path, relink_path};
+    use std::sync::Arc;
+
+    /// Create options moving files on an in-memory filesystem with the given entries.
+    fn dry_opts(entries: &[(&str, Node)]) -> (Arc<DryFs>, MoveOpts) {
+        let dry = Arc::new(DryFs::new());
+        for (path, node) in entries {
+            dry.insert(Path::new(path), *node);
+        }
+        let opts = MoveOpts {
+            fs: dry.clone(),
+            ..MoveOpts::new()
+        };
+        (dry, opts)
+    }
+
+    fn paths(dry: &DryFs) -> Vec<String> {
+        dry.entries()
+            .keys()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn move_renames() {
+        let (dry, opts) = dry_opts(&[("a/1.txt", Node::file(3)), ("b", Node::dir())]);
+        move_path(Path::new("./a"), Path::new("b/c"), &opts).unwrap();
+        assert_eq!(paths(&dry), ["b", "b/c", "b/c/1.txt"]);
+        assert_eq!(dry.get(Path::new("b/c/1.txt")), Some(Node::file(3)));
+    }
+
+    #[test]
+    fn move_across_filesystems() {
+        let (dry, opts) = dry_opts(&[
+            ("a/1.txt", Node::file(3)),
+            ("a/d/2.txt", Node::file(4)),
+            ("mnt", Node::dir().on(1)),
+        ]);
+        move_path(Path::new("a"), Path::new("mnt/a"), &opts).unwrap();
+        assert_eq!(
+            paths(&dry),
+            ["mnt", "mnt/a", "mnt/a/1.txt", "mnt/a/d", "mnt/a/d/2.txt"]
+        );
+        assert_eq!(
+            dry.get(Path::new("mnt/a/d/2.txt")),
+            Some(Node::file(4).on(1))
+        );
+    }
+
+    #[test]
+    fn move_across_filesystems_symlink() {
+        let symlink = Node {
+            kind: Kind::Symlink,
+            ..Node::file(1)
+        };
+        let (dry, opts) = dry_opts(&[("a/l", symlink), ("mnt", Node::dir().on(1))]);
+        move_path(Path::new("a"), Path::new("mnt/a"), &opts).unwrap();
+        assert_eq!(paths(&dry), ["mnt", "mnt/a", "mnt/a/l"]);
+        assert_eq!(dry.get(Path::new("mnt/a/l")), Some(symlink.on(1)));
+    }
+
+    /// Filesystem failing to copy files named `fail`.
+    struct FailingCopy(DryFs);
+
+    impl FileOps for FailingCopy {
+        fn kind(&self, path: &Path) -> io::Result<Kind> {
+            self.0.kind(path)
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            self.0.rename(from, to)
+        }
+
+        fn copy_file(&self, from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()> {
+            if from.ends_with("fail") {
+                return Err(error(ErrorKind::Other, "failed", from));
+            }
+            self.0.copy_file(from, to, opts)
+        }
+
+        fn copy_symlink(&self, from: &Path, to: &Path) -> io::Result<()> {
+            self.0.copy_symlink(from, to)
+        }
+
+        fn create_dir(&self, path: &Path) -> io::Result<()> {
+            self.0.create_dir(path)
+        }
+
+        fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+            self.0.create_dir_all(path)
+        }
+
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            self.0.read_dir(path)
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.0.remove_file(path)
+        }
+
+        fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+            self.0.remove_dir_all(path)
+        }
+
+        fn hard_link(&self, target: &Path, to: &Path) -> io::Result<()> {
+            self.0.hard_link(target, to)
+        }
+
+        fn readonly(&self, path: &Path) -> io::Result<bool> {
+            self.0.readonly(path)
+        }
+
+        fn set_readonly(&self, path: &Path, readonly: bool) -> io::Result<()> {
+            self.0.set_readonly(path, readonly)
+        }
+    }
+
+    #[test]
+    fn move_across_filesystems_failure() {
+        let dry = Arc::new(FailingCopy(DryFs::new()));
+        for (path, node) in &[
+            ("a/1.txt", Node::file(3)),
+            ("a/b/fail", Node::file(3)),
+            ("mnt", Node::dir().on(1)),
+            ("mnt/c", Node::file(3).on(1)),
+        ] {
+            dry.0.insert(Path::new(path), *node);
+        }
+        let opts = MoveOpts {
+            fs: dry.clone(),
+            ..MoveOpts::new()
+        };
+        // The partial copy is removed, and the source is kept.
+        assert!(move_path(Path::new("a"), Path::new("mnt/a"), &opts).is_err());
+        assert_eq!(
+            paths(&dry.0),
+            ["a", "a/1.txt", "a/b", "a/b/fail", "mnt", "mnt/c"]
+        );
+        // An existing destination is neither replaced nor removed.
+        assert_eq!(
+            move_path(Path::new("a/1.txt"), Path::new("mnt/c"), &opts)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::AlreadyExists
+        );
+        assert_eq!(dry.0.get(Path::new("mnt/c")), Some(Node::file(3).on(1)));
+    }
+
+    #[test]
+    fn move_keep_source() {
+        let (dry, opts) = dry_opts(&[("a/1.txt", Node::file(3))]);
+        let opts = MoveOpts {
+            keep_source: true,
+            create_parents: true,
+            ..opts
+        };
+        move_path(Path::new("a"), Path::new("b/c"), &opts).unwrap();
+        assert_eq!(paths(&dry), ["a", "a/1.txt", "b", "b/c", "b/c/1.txt"]);
+    }
+
+    #[test]
+    fn relink() {
+        let (dry, opts) = dry_opts(&[("a", Node::file(3)), ("b", Node::file(3))]);
+        move_path(Path::new("a"), Path::new("c"), &opts).unwrap();
+        relink_path(Path::new("c"), Path::new("b"), Path::new("d"), &opts).unwrap();
+        assert_eq!(paths(&dry), ["c", "d"]);
+    }
+
+    #[test]
+    fn move_errors() {
+        let (dry, opts) = dry_opts(&[("a/1.txt", Node::file(3)), ("b/2.txt", Node::file(3))]);
+        let kind = |from: &str, to: &str| {
+            move_path(Path::new(from), Path::new(to), &opts)
+                .unwrap_err()
+                .kind()
+        };
+        assert_eq!(kind("c", "d"), ErrorKind::NotFound);
+        assert_eq!(kind("a/1.txt", "c/1.txt"), ErrorKind::NotFound);
+        assert_eq!(kind("a", "a/b"), ErrorKind::InvalidInput);
+        assert_eq!(kind("a", "b"), ErrorKind::DirectoryNotEmpty);
+        assert_eq!(kind("a/1.txt", "b"), ErrorKind::IsADirectory);
+        assert_eq!(paths(&dry), ["a", "a/1.txt", "b", "b/2.txt"]);
+    }
+}
@@ -1,10 +1,14 @@
-use crate::spec::Spec;
+use crate::{formatter::Formatter, meta::MetaField, spec::Spec};
 
-/// Element, either a literal or a specifier.
+/// Element, either a literal, a specifier, a counter, or a metadata field.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Elem {
     /// Literal.
     Lit(String),
     /// Specifier.
     Spec(Spec),
+    /// Counter, numbering the matched items starting from 1.
+    Counter(Option<Formatter>),
+    /// Metadata field of the item, e.g. its modification time.
+    Meta(MetaField),
 }
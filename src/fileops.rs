@@ -0,0 +1,190 @@
+use std::fs::{self, Permissions};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::fsops::{contents_equal, copy_file, reflink, MoveOpts, Reflink};
+
+/// Kind of a filesystem entry, not following symlinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Dir,
+    Symlink,
+}
+
+/// Operations on a filesystem, through which files are moved.
+///
+/// Files are moved on the local filesystem with `Local`, and on the in-memory filesystem of
+/// `dryfs` to simulate moves. Other backends, e.g. remote filesystems, implement it to move files
+/// with the same code.
+pub trait FileOps: Send + Sync {
+    /// Get the kind of an entry, without following symlinks.
+    fn kind(&self, path: &Path) -> io::Result<Kind>;
+    /// Rename an entry, replacing the destination if it is a file or an empty directory. Fails
+    /// with `ErrorKind::CrossesDevices` if they are on different filesystems.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Copy a file, following symlinks, with the reflink, throttle and verify options of `opts`.
+    fn copy_file(&self, from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()>;
+    /// Create a symlink at `to` with the same target as the symlink at `from`, which may be
+    /// dangling.
+    fn copy_symlink(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn create_dir(&self, path: &Path) -> io::Result<()>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// List the paths of the entries of a directory.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// Create a hard link at `to` to the file at `target`.
+    fn hard_link(&self, target: &Path, to: &Path) -> io::Result<()>;
+    /// Check whether an entry is read-only.
+    fn readonly(&self, path: &Path) -> io::Result<bool>;
+    /// Make an entry read-only, or writable by its owner.
+    fn set_readonly(&self, path: &Path, readonly: bool) -> io::Result<()>;
+}
+
+/// Operations on the local filesystem.
+pub struct Local;
+
+impl FileOps for Local {
+    fn kind(&self, path: &Path) -> io::Result<Kind> {
+        let file_type = fs::symlink_metadata(path)?.file_type();
+        Ok(if file_type.is_dir() {
+            Kind::Dir
+        } else if file_type.is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File
+        })
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn copy_file(&self, from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()> {
+        match opts.reflink {
+            Reflink::Never => copy_file(from, to, opts.throttle.as_deref())?,
+            Reflink::Always => reflink(from, to)?,
+            Reflink::Auto => {
+                if reflink(from, to).is_err() {
+                    copy_file(from, to, opts.throttle.as_deref())?;
+                }
+            }
+        }
+        if opts.verify && !contents_equal(from, to)? {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "verification failed: {} differs from {}",
+                    to.display(),
+                    from.display()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn copy_symlink(&self, from: &Path, to: &Path) -> io::Result<()> {
+        symlink(&fs::read_link(from)?, from, to)
+    }
+
+    fn create_dir(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect()
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    fn hard_link(&self, target: &Path, to: &Path) -> io::Result<()> {
+        fs::hard_link(target, to)
+    }
+
+    fn readonly(&self, path: &Path) -> io::Result<bool> {
+        Ok(fs::symlink_metadata(path)?.permissions().readonly())
+    }
+
+    fn set_readonly(&self, path: &Path, readonly: bool) -> io::Result<()> {
+        let mut permissions = fs::symlink_metadata(path)?.permissions();
+        if readonly {
+            permissions.set_readonly(true);
+        } else {
+            permissions = writable(&permissions);
+        }
+        fs::set_permissions(path, permissions)
+    }
+}
+
+/// Create a symlink at `to` to `target`, copied from the symlink at `from`.
+#[cfg(unix)]
+fn symlink(target: &Path, _from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, to)
+}
+
+/// Create a symlink at `to` to `target`, copied from the symlink at `from`.
+///
+/// Symlinks to directories and to files are different on Windows, so the kind of symlink is that
+/// of the entry `from` points to, or a file symlink if it is dangling.
+#[cfg(windows)]
+fn symlink(target: &Path, from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+    if fs::metadata(from).map_or(false, |m| m.is_dir()) {
+        symlink_dir(target, to)
+    } else {
+        symlink_file(target, to)
+    }
+}
+
+/// Create a symlink at `to` to `target`, copied from the symlink at `from`.
+#[cfg(not(any(unix, windows)))]
+fn symlink(_target: &Path, _from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        ErrorKind::Unsupported,
+        "symlinks are not supported on this platform",
+    ))
+}
+
+/// Get permissions allowing the owner to write.
+#[cfg(unix)]
+fn writable(permissions: &Permissions) -> Permissions {
+    use std::os::unix::fs::PermissionsExt;
+    Permissions::from_mode(permissions.mode() | 0o200)
+}
+
+/// Get permissions allowing the owner to write.
+#[cfg(not(unix))]
+fn writable(permissions: &Permissions) -> Permissions {
+    let mut permissions = permissions.clone();
+    // Only Unix has write permissions for others, which clearing read-only would grant.
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    permissions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn writable_read_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let permissions = writable(&Permissions::from_mode(0o100444));
+        assert_eq!(permissions.mode(), 0o100644);
+        assert!(!permissions.readonly());
+    }
+}
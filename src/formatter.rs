@@ -1,9 +1,196 @@
-use std::fmt::Debug;
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+use std::iter;
+use std::str::FromStr;
+
+use crate::tokens::digit_value;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Formatter {
     fill: char,
     width: usize,
+    /// Number of decimal places of numbers.
+    precision: Option<usize>,
+    /// Group the integer digits of numbers in thousands.
+    grouping: bool,
+    /// Conversion of numbers or case, replacing the separators and precision.
+    conversion: Option<Conversion>,
+}
+
+/// Conversion of matches to another notation or case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    /// Append the English ordinal suffix to integers (e.g. `1st`, `2nd`).
+    Ordinal,
+    /// Scale numbers with an SI prefix (e.g. `1400000000` to `1.4G`).
+    Si,
+    /// Expand numbers with an SI or binary prefix (e.g. `1.4G` or `2Ki`) to plain numbers.
+    Bytes,
+    /// Capitalize each word, except stop words (e.g. `of`, `the`) after the first word.
+    Titlecase,
+}
+
+impl FromStr for Conversion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ordinal" => Ok(Conversion::Ordinal),
+            "si" => Ok(Conversion::Si),
+            "bytes" => Ok(Conversion::Bytes),
+            "titlecase" => Ok(Conversion::Titlecase),
+            _ => Err(format!("invalid conversion: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conversion::Ordinal => write!(f, "ordinal"),
+            Conversion::Si => write!(f, "si"),
+            Conversion::Bytes => write!(f, "bytes"),
+            Conversion::Titlecase => write!(f, "titlecase"),
+        }
+    }
+}
+
+/// Words kept in lowercase by `titlecase` unless first, by default.
+pub const DEFAULT_STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+    "or", "the", "to", "vs", "with",
+];
+
+/// Options applying to all format specifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOpts {
+    /// Separators used when formatting numbers with `{:,}` or `{:.2}`.
+    pub number_locale: NumberLocale,
+    /// Words kept in lowercase by `{:titlecase}` unless first, in lowercase.
+    pub stop_words: Vec<String>,
+    /// Transliterate the digits of other scripts in matches to ASCII digits, before formatting.
+    pub ascii_digits: bool,
+}
+
+impl FormatOpts {
+    pub fn new() -> Self {
+        Self {
+            number_locale: NumberLocale::new(),
+            stop_words: DEFAULT_STOP_WORDS.iter().map(|&w| w.to_owned()).collect(),
+            ascii_digits: false,
+        }
+    }
+}
+
+impl Default for FormatOpts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// SI prefixes, each 1000 times the previous one, starting from 1000.
+const SI_PREFIXES: [char; 6] = ['k', 'M', 'G', 'T', 'P', 'E'];
+
+/// Separators used when formatting numbers with decimal places or thousands separators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberLocale {
+    pub thousands: char,
+    pub decimal: char,
+}
+
+impl NumberLocale {
+    pub fn new() -> Self {
+        Self {
+            thousands: ',',
+            decimal: '.',
+        }
+    }
+}
+
+impl Default for NumberLocale {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromStr for NumberLocale {
+    type Err = String;
+
+    /// Parse a locale name (`en`, `de`, `fr` or `ch`), or the thousands separator followed by the
+    /// decimal separator (e.g. `.,`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (thousands, decimal) = match s {
+            "en" => (',', '.'),
+            "de" => ('.', ','),
+            "fr" => (' ', ','),
+            "ch" => ('\'', '.'),
+            _ => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next(), chars.next()) {
+                    (Some(thousands), Some(decimal), None) if thousands != decimal => {
+                        (thousands, decimal)
+                    }
+                    _ => {
+                        return Err(format!(
+                            "invalid number locale: {} (expected en, de, fr, ch, or the thousands \
+                             and decimal separators, e.g. \".,\")",
+                            s
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(Self { thousands, decimal })
+    }
+}
+
+/// Parse a decimal number, optionally signed, with a fraction, or with thousands separators:
+/// those of `locale`, or `,` with `.` as the decimal separator. `_` may separate thousands too.
+/// Digits may be of other scripts than ASCII (see `digit_value`).
+///
+/// # Returns
+///
+/// The number with ASCII digits, no thousands separators and `.` as the decimal separator (e.g.
+/// `-1234.5`), or `None` if `s` is not a number.
+pub fn plain_number(s: &str, locale: NumberLocale) -> Option<String> {
+    let (sign, unsigned) = match s.strip_prefix(|c| c == '-' || c == '+') {
+        Some(unsigned) => (&s[..1], unsigned),
+        None => ("", s),
+    };
+    let plain = plain_unsigned(unsigned, &[locale.thousands, '_'], locale.decimal)
+        .or_else(|| plain_unsigned(unsigned, &[',', '_'], '.'))?;
+    Some([sign, &plain].concat())
+}
+
+/// Parse an unsigned decimal number with the given separators, as with `plain_number`.
+fn plain_unsigned(s: &str, thousands: &[char], decimal: char) -> Option<String> {
+    let (int, frac) = match s.split_once(decimal) {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (s, None),
+    };
+    let digits = |g: &str| -> Option<String> {
+        if g.is_empty() {
+            return None;
+        }
+        g.chars()
+            .map(|c| digit_value(c).and_then(|d| char::from_digit(d, 10)))
+            .collect()
+    };
+    let mut plain = match thousands.iter().find(|&&sep| int.contains(sep)) {
+        Some(&sep) => {
+            let groups: Vec<&str> = int.split(sep).collect();
+            if groups[0].chars().count() > 3 || groups[1..].iter().any(|g| g.chars().count() != 3) {
+                return None;
+            }
+            groups.into_iter().map(digits).collect::<Option<String>>()?
+        }
+        None => digits(int)?,
+    };
+    if let Some(frac) = frac {
+        plain.push('.');
+        plain.push_str(&digits(frac)?);
+    }
+    Some(plain)
 }
 
 pub enum InputType {
@@ -23,6 +210,9 @@ impl Formatter {
         Self {
             width: 0,
             fill: ' ',
+            precision: None,
+            grouping: false,
+            conversion: None,
         }
     }
 
@@ -37,7 +227,29 @@ impl Formatter {
     ///
     /// A `Formatter` with the specified width and fill.
     pub fn with_width(width: usize, fill: char) -> Self {
-        Self { width, fill }
+        Self {
+            width,
+            fill,
+            ..Self::new()
+        }
+    }
+
+    /// Format numbers with a fixed number of decimal places, rounding if necessary.
+    pub fn with_precision(mut self, precision: Option<usize>) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Group the integer digits of numbers in thousands with a separator.
+    pub fn with_grouping(mut self, grouping: bool) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    /// Convert numbers to another notation, e.g. ordinals (`1st`, `2nd`) or SI prefixes (`1.4G`).
+    pub fn with_conversion(mut self, conversion: Option<Conversion>) -> Self {
+        self.conversion = conversion;
+        self
     }
 
     /// Format string.
@@ -51,18 +263,328 @@ impl Formatter {
     ///
     /// The formatted string.
     pub fn format(&self, input_type: InputType, s: &str) -> String {
+        self.format_with_opts(input_type, s, &FormatOpts::new())
+    }
+
+    /// Format string with options, e.g. the separators of numbers.
+    ///
+    /// With a precision or grouping, strings which are numbers (with `.` as the decimal separator)
+    /// are formatted as such, and other strings are kept as-is.
+    pub fn format_with_opts(&self, input_type: InputType, s: &str, opts: &FormatOpts) -> String {
         let s = match input_type {
             InputType::String => s,
-            InputType::Number => s.trim_start_matches('0'),
+            InputType::Number => trim_zeros(s),
         };
-        if s.len() >= self.width {
-            return s.to_owned();
+        let s = if self.precision.is_some() || self.grouping {
+            self.format_number(s, opts.number_locale)
+                .unwrap_or_else(|| s.to_owned())
+        } else if let Some(conversion) = self.conversion {
+            match conversion {
+                Conversion::Ordinal => format_ordinal(s),
+                Conversion::Si => format_si(s),
+                Conversion::Bytes => format_bytes(s),
+                Conversion::Titlecase => Some(format_titlecase(s, &opts.stop_words)),
+            }
+            .unwrap_or_else(|| s.to_owned())
+        } else {
+            s.to_owned()
+        };
+        let len = s.chars().count();
+        if len >= self.width {
+            return s;
+        }
+        // Pad numbers of other scripts with their own zero, e.g. `٠١٢` rather than `0١٢`.
+        let fill = match s.chars().next() {
+            Some(c) if self.fill == '0' && !c.is_ascii_digit() => digit_value(c)
+                .and_then(|d| char::from_u32(c as u32 - d))
+                .unwrap_or(self.fill),
+            _ => self.fill,
+        };
+        [fill.to_string().repeat(self.width - len), s].concat()
+    }
+
+    /// Format a number with the precision and grouping.
+    ///
+    /// # Returns
+    ///
+    /// The formatted number, or `None` if `s` is not a number.
+    fn format_number(&self, s: &str, locale: NumberLocale) -> Option<String> {
+        let plain = plain_number(s, locale);
+        let s = plain.as_deref().unwrap_or(s);
+        let (sign, unsigned) = match s.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", s),
+        };
+        let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        let (int, frac) = match unsigned.split_once('.') {
+            Some((int, frac)) if is_digits(frac) => (int, Some(frac)),
+            Some(_) => return None,
+            None => (unsigned, None),
+        };
+        if !is_digits(int) || (int.is_empty() && frac.unwrap_or("").is_empty()) {
+            return None;
+        }
+        let rounded;
+        let (int, frac) = match self.precision {
+            Some(precision) => {
+                rounded = round_decimal(int, frac.unwrap_or(""), precision);
+                let (int, frac) = rounded.split_at(rounded.len() - precision);
+                (int, Some(frac).filter(|_| precision > 0))
+            }
+            None => (if int.is_empty() { "0" } else { int }, frac),
+        };
+        let mut out = sign.to_owned();
+        for (i, c) in int.chars().enumerate() {
+            if self.grouping && i > 0 && (int.len() - i).is_multiple_of(3) {
+                out.push(locale.thousands);
+            }
+            out.push(c);
+        }
+        if let Some(frac) = frac {
+            out.push(locale.decimal);
+            out.push_str(frac);
+        }
+        Some(out)
+    }
+}
+
+/// Round a decimal number to `precision` fractional digits, rounding halves away from zero.
+///
+/// The digits are rounded as a string, so that numbers of any length are rounded exactly.
+///
+/// # Arguments
+///
+/// * `int` - ASCII digits of the integer part, possibly empty.
+/// * `frac` - ASCII digits of the fractional part, possibly empty.
+/// * `precision` - Number of fractional digits.
+///
+/// # Returns
+///
+/// The digits of the rounded number without a decimal separator, of which the last `precision`
+/// are fractional. The integer part has no leading zeros, except for a single `0`.
+fn round_decimal(int: &str, frac: &str, precision: usize) -> String {
+    let mut digits: Vec<u8> = int
+        .trim_start_matches('0')
+        .bytes()
+        .chain(frac.bytes().chain(iter::repeat(b'0')).take(precision))
+        .collect();
+    if frac.as_bytes().get(precision).is_some_and(|&d| d >= b'5') {
+        match digits.iter().rposition(|&d| d != b'9') {
+            Some(i) => {
+                digits[i] += 1;
+                digits[i + 1..].fill(b'0');
+            }
+            None => {
+                digits.fill(b'0');
+                digits.insert(0, b'1');
+            }
+        }
+    }
+    if digits.len() == precision {
+        digits.insert(0, b'0');
+    }
+    String::from_utf8(digits).unwrap()
+}
+
+/// Trim the leading zeros of a number, keeping one before the end or a decimal separator, e.g.
+/// `0` and `0.5`.
+fn trim_zeros(s: &str) -> &str {
+    let trimmed = s.trim_start_matches(|c| digit_value(c) == Some(0));
+    if trimmed.len() == s.len() || trimmed.starts_with(|c| digit_value(c).is_some()) {
+        return trimmed;
+    }
+    let zero = s[..s.len() - trimmed.len()]
+        .chars()
+        .next_back()
+        .map_or(0, char::len_utf8);
+    &s[s.len() - trimmed.len() - zero..]
+}
+
+/// Append the English ordinal suffix to an integer.
+///
+/// # Returns
+///
+/// The ordinal (e.g. `1st`, `12th`, `23rd`), or `None` if `s` is not an integer.
+fn format_ordinal(s: &str) -> Option<String> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let tens = s.len().checked_sub(2).map_or(s, |i| &s[i..]);
+    let suffix = match tens.parse::<u8>().unwrap_or(0) {
+        11..=13 => "th",
+        n if n % 10 == 1 => "st",
+        n if n % 10 == 2 => "nd",
+        n if n % 10 == 3 => "rd",
+        _ => "th",
+    };
+    Some([s, suffix].concat())
+}
+
+/// Capitalize the first letter of each word and lowercase the rest, keeping stop words in
+/// lowercase unless first.
+///
+/// Words are runs of alphanumeric characters and apostrophes, so that e.g. `don't` is one word.
+fn format_titlecase(s: &str, stop_words: &[String]) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    let mut first = true;
+    while !rest.is_empty() {
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '\'';
+        let start = rest.find(is_word_char).unwrap_or(rest.len());
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+        if end == 0 {
+            break;
+        }
+        let word = rest[..end].to_lowercase();
+        if !first && stop_words.contains(&word) {
+            out.push_str(&word);
+        } else {
+            let mut chars = word.chars();
+            if let Some(c) = chars.next() {
+                out.extend(c.to_uppercase());
+                out.push_str(chars.as_str());
+            }
         }
-        [
-            self.fill.to_string().repeat(self.width - s.len()),
-            s.to_owned(),
-        ]
-        .concat()
+        first = false;
+        rest = &rest[end..];
+    }
+    out
+}
+
+/// Scale a number with the largest SI prefix keeping it at least 1, with one decimal place at most.
+///
+/// # Returns
+///
+/// The scaled number (e.g. `1.4G` for `1400000000`, `999` for `999`), or `None` if `s` is not a
+/// non-negative number.
+fn format_si(s: &str) -> Option<String> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    let mut value: f64 = s.parse().ok()?;
+    let mut prefix = None;
+    for p in SI_PREFIXES.iter() {
+        // Compare rounded, so that e.g. 999999 is scaled to 1M rather than 1000k.
+        if (value * 10.0).round() / 10.0 < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        prefix = Some(*p);
+    }
+    let mut out = format!("{:.1}", value);
+    if out.ends_with(".0") {
+        out.truncate(out.len() - 2);
+    }
+    out.extend(prefix);
+    Some(out)
+}
+
+/// Expand a number with an SI prefix (`k`, `M`, `G`, ...) or binary prefix (`Ki`, `Mi`, `Gi`,
+/// ...), optionally followed by `B`, to a plain integer, rounded to the nearest.
+///
+/// The number is expanded with integer arithmetic, so that large and unprefixed integers are
+/// exact.
+///
+/// # Returns
+///
+/// The expanded number (e.g. `1400000000` for `1.4G`, `2048` for `2KiB`), or `None` if `s` is
+/// not such a number or the result is too large.
+fn format_bytes(s: &str) -> Option<String> {
+    let s = s.strip_suffix('B').unwrap_or(s);
+    let (s, base) = match s.strip_suffix('i') {
+        Some(s) => (s, 1024u128),
+        None => (s, 1000),
+    };
+    let (number, exponent) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let c = if c == 'K' { 'k' } else { c };
+            let p = SI_PREFIXES.iter().position(|&p| p == c)?;
+            (&s[..i], p as u32 + 1)
+        }
+        _ if base == 1000 => (s, 0),
+        _ => return None,
+    };
+    let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if int.len() + frac.len() == 0 || !is_digits(int) || !is_digits(frac) {
+        return None;
+    }
+    // `1.4G` is 14 * 1000^3 / 10.
+    let mantissa: u128 = [int, frac].concat().parse().ok()?;
+    let denominator = 10u128.checked_pow(frac.len() as u32)?;
+    let value = mantissa.checked_mul(base.checked_pow(exponent)?)?;
+    Some(((value + denominator / 2) / denominator).to_string())
+}
+
+/// Shorten a file name to a maximum length, preserving its extension.
+///
+/// The end of the stem is cut before the word it falls in, if that word is in the second half of
+/// the stem, or else at a character boundary. Whitespace and separators (`-`, `_`, `.`, `,`) left
+/// at the end are cut too, e.g. `The Title - Part 1.mkv` may become `The Title.mkv`. Extensions
+/// longer than half of the maximum length are cut with the stem.
+///
+/// # Arguments
+///
+/// * `name` - File name.
+/// * `max_len` - Maximum length, measured with `len`.
+/// * `len` - Function measuring the length of names, e.g. in bytes.
+///
+/// # Returns
+///
+/// The shortened name, borrowing from `name` if it is short enough already.
+pub fn truncate_name<'a>(name: &'a str, max_len: usize, len: fn(&str) -> usize) -> Cow<'a, str> {
+    if len(name) <= max_len {
+        return Cow::Borrowed(name);
+    }
+    let ext = match name.rfind('.') {
+        Some(i) if i > 0 && len(&name[i..]) <= max_len / 2 => &name[i..],
+        _ => "",
+    };
+    let full_stem = &name[..name.len() - ext.len()];
+    let mut stem = full_stem;
+    let max_stem = max_len - len(ext);
+    while len(stem) > max_stem {
+        let last = stem.chars().next_back().map_or(0, char::len_utf8);
+        stem = &stem[..stem.len() - last];
+    }
+    let is_separator = |c: char| c.is_whitespace() || "-_.,".contains(c);
+    // Cut before a word rather than in the middle of it, unless it is most of the stem.
+    let in_word = |s: &str| s.chars().next().is_some_and(char::is_alphanumeric);
+    if in_word(&full_stem[stem.len()..]) {
+        if let Some(i) = stem.rfind(is_separator).filter(|&i| i >= stem.len() / 2) {
+            stem = &stem[..i];
+        }
+    }
+    let trimmed = stem.trim_end_matches(is_separator);
+    if !trimmed.is_empty() {
+        stem = trimmed;
+    }
+    Cow::Owned([stem, ext].concat())
+}
+
+impl fmt::Display for Formatter {
+    /// Format as a format specifier (e.g. `03`).
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.fill == '0' {
+            write!(f, "0")?;
+        }
+        if self.width > 0
+            || (self.precision.is_none() && !self.grouping && self.conversion.is_none())
+        {
+            write!(f, "{}", self.width)?;
+        }
+        if self.grouping {
+            write!(f, ",")?;
+        }
+        if let Some(conversion) = self.conversion {
+            write!(f, "{}", conversion)?;
+        }
+        if let Some(precision) = self.precision {
+            write!(f, ".{}", precision)?;
+        }
+        Ok(())
     }
 }
 
@@ -97,5 +619,237 @@ mod tests {
         format_number_truncate_zeros: (2, '0', InputType::Number, "0001", "01"),
         format_number_string_no_truncate_zeros: (2, '0', InputType::String, "0001", "0001"),
         format_number_no_truncate_non_zeros: (2, '0', InputType::Number, "1234", "1234"),
+        format_number_arabic_indic: (4, '0', InputType::Number, "٠١٢", "٠٠١٢"),
+        format_number_devanagari_fill: (3, ' ', InputType::Number, "३", "  ३"),
+    );
+
+    macro_rules! format_number_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (precision, grouping, locale, s, expected): (_, _, &str, _, _) = $value;
+                    let formatter = Formatter::new()
+                        .with_precision(precision)
+                        .with_grouping(grouping);
+                    let opts = FormatOpts {
+                        number_locale: locale.parse().unwrap(),
+                        ..FormatOpts::new()
+                    };
+                    assert_eq!(formatter.format_with_opts(InputType::String, s, &opts), expected);
+                }
+            )*
+        }
+    }
+
+    format_number_tests!(
+        format_number_precision: (Some(2), false, "en", "3.14159", "3.14"),
+        format_number_precision_round: (Some(1), false, "en", "2.96", "3.0"),
+        format_number_precision_integer: (Some(2), false, "en", "42", "42.00"),
+        format_number_precision_zero: (Some(0), false, "en", "2.5", "3"),
+        format_number_precision_half_up: (Some(2), false, "en", "2.675", "2.68"),
+        format_number_precision_half_up_small: (Some(2), false, "en", "1.005", "1.01"),
+        format_number_precision_carry: (Some(1), true, "en", "999.96", "1,000.0"),
+        format_number_precision_fraction: (Some(1), false, "en", ".96", "1.0"),
+        format_number_precision_below_half: (Some(2), false, "en", "0.004", "0.00"),
+        format_number_precision_negative: (Some(1), false, "en", "-0.25", "-0.3"),
+        format_number_precision_large: (Some(2), false, "en", "12345678901234567.5", "12345678901234567.50"),
+        format_number_grouping: (None, true, "en", "1234567", "1,234,567"),
+        format_number_grouping_short: (None, true, "en", "123", "123"),
+        format_number_grouping_decimal: (None, true, "en", "-1234.5", "-1,234.5"),
+        format_number_locale_de: (Some(2), true, "de", "1234.5", "1.234,50"),
+        format_number_locale_custom: (None, true, "_.", "1234", "1_234"),
+        format_number_not_number: (Some(2), true, "en", "abc", "abc"),
+        format_number_not_number_dots: (Some(2), false, "en", "1.2.3", "1.2.3"),
+        format_number_grouped_input: (Some(0), false, "en", "1,234.5", "1235"),
+        format_number_grouped_input_de: (None, true, "de", "1234,5", "1.234,5"),
+        format_number_grouped_input_mixed: (Some(1), true, "de", "1,234.56", "1.234,6"),
+        format_number_non_ascii_digits: (Some(1), false, "en", "١٢٫٥", "١٢٫٥"),
+        format_number_devanagari: (None, true, "en", "१२३४", "1,234"),
     );
+
+    #[test]
+    fn format_number_precision_long() {
+        let s = ["9".repeat(401), ".5".to_owned()].concat();
+        let formatter = Formatter::new().with_precision(Some(0));
+        assert_eq!(
+            formatter.format(InputType::String, &s),
+            ["1".to_owned(), "0".repeat(401)].concat()
+        );
+    }
+
+    macro_rules! truncate_name_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (name, max_len, expected) = $value;
+                    assert_eq!(truncate_name(name, max_len, str::len), expected);
+                }
+            )*
+        }
+    }
+
+    truncate_name_tests!(
+        truncate_name_short: ("abc.txt", 7, "abc.txt"),
+        truncate_name_extension: ("abcdef.txt", 8, "abcd.txt"),
+        truncate_name_separators: ("The Title - Part 1.mkv", 19, "The Title.mkv"),
+        truncate_name_no_extension: ("abcdef", 4, "abcd"),
+        truncate_name_long_extension: ("a.bcdefgh", 6, "a.bcde"),
+        truncate_name_char_boundary: ("ééé.txt", 9, "éé.txt"),
+        truncate_name_only_separators: ("a-----.txt", 8, "a.txt"),
+        truncate_name_dotfile: (".abcdef", 4, ".abc"),
+        truncate_name_first_half: ("ab cdefgh.txt", 10, "ab cde.txt"),
+    );
+
+    #[test]
+    fn plain_numbers() {
+        let en = NumberLocale::new();
+        let de = "de".parse().unwrap();
+        assert_eq!(plain_number("-1,234.5", en).as_deref(), Some("-1234.5"));
+        assert_eq!(plain_number("1_000", en).as_deref(), Some("1000"));
+        assert_eq!(plain_number("1.234,5", de).as_deref(), Some("1234.5"));
+        assert_eq!(plain_number("1,5", de).as_deref(), Some("1.5"));
+        assert_eq!(plain_number("+١٢٣", en).as_deref(), Some("+123"));
+        assert_eq!(plain_number("12,34", en), None);
+        assert_eq!(plain_number("1.", en), None);
+        assert_eq!(plain_number("a1", en), None);
+    }
+
+    #[test]
+    fn format_number_trimmed_zeros() {
+        let formatter = Formatter::with_width(6, '0').with_grouping(true);
+        assert_eq!(formatter.format(InputType::Number, "0001234"), "01,234");
+    }
+
+    #[test]
+    fn format_number_zero() {
+        let format = |formatter: Formatter, s| formatter.format(InputType::Number, s);
+        assert_eq!(format(Formatter::new(), "000"), "0");
+        assert_eq!(format(Formatter::new(), "٠٠"), "٠");
+        assert_eq!(format(Formatter::new(), "00.5"), "0.5");
+        assert_eq!(
+            format(Formatter::new().with_precision(Some(2)), "0"),
+            "0.00"
+        );
+        assert_eq!(format(Formatter::new().with_grouping(true), "0"), "0");
+        let ordinal = Formatter::new().with_conversion(Some(Conversion::Ordinal));
+        assert_eq!(format(ordinal, "0"), "0th");
+        let si = Formatter::new().with_conversion(Some(Conversion::Si));
+        assert_eq!(format(si, "00"), "0");
+    }
+
+    macro_rules! format_ordinal_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected) = $value;
+                    let formatter = Formatter::new().with_conversion(Some(Conversion::Ordinal));
+                    assert_eq!(formatter.format(InputType::Number, s), expected);
+                }
+            )*
+        }
+    }
+
+    format_ordinal_tests!(
+        format_ordinal_first: ("1", "1st"),
+        format_ordinal_second: ("02", "2nd"),
+        format_ordinal_third: ("3", "3rd"),
+        format_ordinal_fourth: ("4", "4th"),
+        format_ordinal_teens: ("12", "12th"),
+        format_ordinal_hundreds: ("113", "113th"),
+        format_ordinal_twenties: ("21", "21st"),
+        format_ordinal_large: ("1002", "1002nd"),
+        format_ordinal_not_number: ("a1", "a1"),
+    );
+
+    macro_rules! format_si_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (conversion, s, expected) = $value;
+                    let formatter = Formatter::new().with_conversion(Some(conversion));
+                    assert_eq!(formatter.format(InputType::String, s), expected);
+                }
+            )*
+        }
+    }
+
+    format_si_tests!(
+        format_si_small: (Conversion::Si, "999", "999"),
+        format_si_kilo: (Conversion::Si, "1500", "1.5k"),
+        format_si_giga: (Conversion::Si, "1400000000", "1.4G"),
+        format_si_whole: (Conversion::Si, "2000000", "2M"),
+        format_si_rounded_up: (Conversion::Si, "999999", "1M"),
+        format_si_decimal: (Conversion::Si, "1234.5", "1.2k"),
+        format_si_not_number: (Conversion::Si, "1.4G", "1.4G"),
+        format_bytes_giga: (Conversion::Bytes, "1.4G", "1400000000"),
+        format_bytes_kilo_upper: (Conversion::Bytes, "2KB", "2000"),
+        format_bytes_binary: (Conversion::Bytes, "2KiB", "2048"),
+        format_bytes_plain: (Conversion::Bytes, "512", "512"),
+        format_bytes_plain_large: (Conversion::Bytes, "18446744073709551617", "18446744073709551617"),
+        format_bytes_exact: (Conversion::Bytes, "9.007199254740993P", "9007199254740993"),
+        format_bytes_rounded: (Conversion::Bytes, "1.0005k", "1001"),
+        format_bytes_decimal_only: (Conversion::Bytes, "1.2.3k", "1.2.3k"),
+        format_bytes_invalid_prefix: (Conversion::Bytes, "2X", "2X"),
+        format_bytes_binary_no_prefix: (Conversion::Bytes, "2i", "2i"),
+        format_bytes_no_number: (Conversion::Bytes, "G", "G"),
+    );
+
+    macro_rules! format_titlecase_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected) = $value;
+                    let formatter = Formatter::new().with_conversion(Some(Conversion::Titlecase));
+                    assert_eq!(formatter.format(InputType::String, s), expected);
+                }
+            )*
+        }
+    }
+
+    format_titlecase_tests!(
+        format_titlecase_stop_words: ("the dark side of the moon", "The Dark Side of the Moon"),
+        format_titlecase_uppercase: ("A NIGHT AT THE OPERA", "A Night at the Opera"),
+        format_titlecase_separators: ("01_back-in-black", "01_Back-in-Black"),
+        format_titlecase_apostrophe: ("don't stop me now", "Don't Stop Me Now"),
+        format_titlecase_unicode: ("élan vital", "Élan Vital"),
+        format_titlecase_empty: ("", ""),
+    );
+
+    #[test]
+    fn format_titlecase_custom_stop_words() {
+        let formatter = Formatter::new().with_conversion(Some(Conversion::Titlecase));
+        let opts = FormatOpts {
+            stop_words: vec!["and".to_owned()],
+            ..FormatOpts::new()
+        };
+        assert_eq!(
+            formatter.format_with_opts(InputType::String, "rock and roll of the year", &opts),
+            "Rock and Roll Of The Year"
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Formatter::with_width(3, '0').to_string(), "03");
+        assert_eq!(Formatter::with_width(3, ' ').to_string(), "3");
+        assert_eq!(Formatter::new().to_string(), "0");
+        assert_eq!(
+            Formatter::new()
+                .with_precision(Some(2))
+                .with_grouping(true)
+                .to_string(),
+            ",.2"
+        );
+        assert_eq!(
+            Formatter::new()
+                .with_conversion(Some(Conversion::Ordinal))
+                .to_string(),
+            "ordinal"
+        );
+    }
 }
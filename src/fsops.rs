@@ -0,0 +1,1065 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::{self, File, FileTimes, OpenOptions, Permissions};
+use std::io::{self, ErrorKind, Read};
+use std::path::{Component, Path};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::fileops::{FileOps, Kind, Local};
+use crate::throttle::Throttle;
+
+/// Size of the buffers used to compare file contents.
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Maximum length of file names assumed when a filesystem does not tell it, e.g. `NAME_MAX` on
+/// Linux, or 255 UTF-16 code units on Windows.
+pub const DEFAULT_NAME_MAX: usize = 255;
+
+#[derive(Clone)]
+pub struct MoveOpts {
+    /// Verify copied contents before removing the source, when falling back to copying.
+    pub verify: bool,
+    /// Create the parent directories of the destination if missing.
+    pub create_parents: bool,
+    /// Copy instead of moving, keeping the source.
+    pub keep_source: bool,
+    /// When permission is denied on a read-only source, make it writable and retry, restoring its
+    /// permissions afterwards.
+    pub force: bool,
+    /// Whether to clone files instead of copying their contents.
+    pub reflink: Reflink,
+    /// Limit on the bandwidth of copies, shared by all moves with these options.
+    pub throttle: Option<Arc<Throttle>>,
+    /// Filesystem to move files on.
+    pub fs: Arc<dyn FileOps>,
+}
+
+impl MoveOpts {
+    pub fn new() -> Self {
+        Self {
+            verify: false,
+            create_parents: false,
+            keep_source: false,
+            force: false,
+            reflink: Reflink::Never,
+            throttle: None,
+            fs: Arc::new(Local),
+        }
+    }
+}
+
+impl Default for MoveOpts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether to clone files, sharing their data blocks until modified, instead of copying their
+/// contents. Cloning is supported on btrfs and XFS on Linux, and APFS on macOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reflink {
+    /// Clone files, falling back to copying where cloning is not supported.
+    Auto,
+    /// Clone files, failing where cloning is not supported.
+    Always,
+    /// Always copy contents.
+    #[default]
+    Never,
+}
+
+impl FromStr for Reflink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Reflink::Auto),
+            "always" => Ok(Reflink::Always),
+            "never" => Ok(Reflink::Never),
+            _ => Err(format!(
+                "invalid reflink mode: {} (expected auto, always or never)",
+                s
+            )),
+        }
+    }
+}
+
+/// Cause of a failed move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// The file is locked or in use by another process (sharing violation on Windows).
+    Locked,
+    /// Permission is denied, e.g. on a read-only file or directory.
+    PermissionDenied,
+    Other,
+}
+
+/// Windows error codes of sharing and lock violations.
+const ERROR_SHARING_VIOLATION: i32 = 32;
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+impl Failure {
+    /// Classify an error.
+    pub fn of(e: &io::Error) -> Self {
+        match e.raw_os_error() {
+            Some(ERROR_SHARING_VIOLATION) | Some(ERROR_LOCK_VIOLATION) if cfg!(windows) => {
+                return Failure::Locked;
+            }
+            _ => {}
+        }
+        match e.kind() {
+            ErrorKind::PermissionDenied => Failure::PermissionDenied,
+            _ => Failure::Other,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Locked => write!(f, "locked"),
+            Failure::PermissionDenied => write!(f, "permission denied"),
+            Failure::Other => write!(f, "other error"),
+        }
+    }
+}
+
+/// Move file or directory.
+///
+/// Separators of both paths are first converted with `native_separators`. Renames `from` to `to`.
+/// If they are on different filesystems, `from` is copied to `to` and then removed. With
+/// `keep_source`, `from` is always copied instead.
+///
+/// # Arguments
+///
+/// * `from` - Source path.
+/// * `to` - Destination path.
+/// * `opts` - Options.
+///
+/// # Returns
+///
+/// An `io::Result`. If verification fails, the source is kept and an `InvalidData` error is
+/// returned.
+pub fn move_path(from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()> {
+    let (from, to) = (native_path(from), native_path(to));
+    let (from, to) = (from.as_ref(), to.as_ref());
+    if opts.create_parents {
+        if let Some(parent) = to.parent().filter(|p| has_dirs(p)) {
+            opts.fs.create_dir_all(parent)?;
+        }
+    }
+    match move_once(from, to, opts) {
+        Err(e) if opts.force && Failure::of(&e) == Failure::PermissionDenied => {
+            force_move(from, to, opts, e)
+        }
+        result => result,
+    }
+}
+
+/// Move file or directory, without converting separators or retrying.
+fn move_once(from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()> {
+    if opts.keep_source {
+        return copy_path(from, to, opts);
+    }
+    match opts.fs.rename(from, to) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => copy_and_remove(from, to, opts),
+        result => result,
+    }
+}
+
+/// Retry a move which failed with `error`, with the source made writable by its owner if it is
+/// read-only.
+///
+/// The source is made read-only again once moved, or if the move fails. On Unix, the permissions
+/// of a file do not affect moving it, so only directories (which must be writable to move them to
+/// another directory) are retried.
+fn force_move(from: &Path, to: &Path, opts: &MoveOpts, error: io::Error) -> io::Result<()> {
+    if cfg!(unix) && opts.fs.kind(from)? != Kind::Dir {
+        return Err(error);
+    }
+    if !opts.fs.readonly(from)? {
+        return Err(error);
+    }
+    opts.fs.set_readonly(from, false)?;
+    let result = move_once(from, to, opts);
+    let target = if result.is_ok() { to } else { from };
+    opts.fs.set_readonly(target, true)?;
+    result
+}
+
+/// Permission bits of a file, written in octal, e.g. `755`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mode(pub u32);
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !(3..=4).contains(&s.len()) {
+            return Err(format!("invalid mode: {} (expected e.g. 755)", s));
+        }
+        u32::from_str_radix(s, 8)
+            .map(Mode)
+            .map_err(|_| format!("invalid mode: {} (expected e.g. 755)", s))
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:03o}", self.0)
+    }
+}
+
+/// Set the permission bits of a file.
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: Mode) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, Permissions::from_mode(mode.0))
+}
+
+/// Set the permission bits of a file. Only the owner's write permission applies, making the file
+/// read-only if not set.
+#[cfg(not(unix))]
+pub fn set_mode(path: &Path, mode: Mode) -> io::Result<()> {
+    let mut permissions = path.metadata()?.permissions();
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(mode.0 & 0o200 == 0);
+    fs::set_permissions(path, permissions)
+}
+
+/// Set the modification time of a file, and its access time if given.
+pub fn set_times(
+    path: &Path,
+    modified: SystemTime,
+    accessed: Option<SystemTime>,
+) -> io::Result<()> {
+    let mut times = FileTimes::new().set_modified(modified);
+    if let Some(accessed) = accessed {
+        times = times.set_accessed(accessed);
+    }
+    open_for_attributes(path)?.set_times(times)
+}
+
+/// Open a file to change its attributes, even if it is read-only.
+#[cfg(windows)]
+fn open_for_attributes(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    /// Access right to change the attributes and times of a file.
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+    fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .open(path)
+}
+
+/// Open a file to change its attributes, even if it is read-only.
+#[cfg(not(windows))]
+fn open_for_attributes(path: &Path) -> io::Result<File> {
+    File::open(path)
+}
+
+/// Check whether moving `from` to `to` is likely to be permitted, before attempting it.
+///
+/// The source must exist, and its directory (unless copying with `keep_source`) and the nearest
+/// existing ancestor of the destination must be writable by the current user.
+///
+/// # Returns
+///
+/// A description of the problem, if any.
+pub fn check_move(from: &Path, to: &Path, opts: &MoveOpts) -> Option<String> {
+    if fs::symlink_metadata(from).is_err() {
+        return Some("source does not exist".to_owned());
+    }
+    let mut dirs = vec![parent_or_current(to)
+        .ancestors()
+        .find(|dir| dir.as_os_str().is_empty() || dir.exists())
+        .unwrap_or_else(|| Path::new(""))];
+    if !opts.keep_source {
+        dirs.push(parent_or_current(from));
+    }
+    for dir in dirs {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        if !writable_dir(dir) {
+            return Some(format!("no write permission on {}", dir.display()));
+        }
+    }
+    None
+}
+
+/// Get the parent of a path, or an empty path for the current directory.
+fn parent_or_current(path: &Path) -> &Path {
+    path.parent().unwrap_or_else(|| Path::new(""))
+}
+
+/// Check if the current user may create and remove entries in a directory.
+#[cfg(unix)]
+fn writable_dir(dir: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    match CString::new(dir.as_os_str().as_bytes()) {
+        // SAFETY: `path` is a valid NUL-terminated string.
+        Ok(path) => unsafe { libc::access(path.as_ptr(), libc::W_OK | libc::X_OK) == 0 },
+        Err(_) => false,
+    }
+}
+
+/// Check if the current user may create and remove entries in a directory.
+#[cfg(not(unix))]
+fn writable_dir(dir: &Path) -> bool {
+    fs::metadata(dir).map_or(false, |m| !m.permissions().readonly())
+}
+
+/// Get the length of a file name as limited by filesystems: bytes on Unix, UTF-16 code units on
+/// Windows.
+#[cfg(windows)]
+pub fn name_len(name: &str) -> usize {
+    name.encode_utf16().count()
+}
+
+/// Get the length of a file name as limited by filesystems: bytes on Unix, UTF-16 code units on
+/// Windows.
+#[cfg(not(windows))]
+pub fn name_len(name: &str) -> usize {
+    name.len()
+}
+
+/// Get the maximum length of file names in a directory, as measured by `name_len`.
+///
+/// The limit of a missing directory is taken from its closest existing ancestor. If the
+/// filesystem does not tell, it is assumed to be `DEFAULT_NAME_MAX`.
+#[cfg(unix)]
+pub fn name_max(dir: &Path) -> usize {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let dir = dir
+        .ancestors()
+        .map(|d| {
+            if d.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                d
+            }
+        })
+        .find(|d| d.is_dir())
+        .unwrap_or_else(|| Path::new("."));
+    let max = match CString::new(dir.as_os_str().as_bytes()) {
+        // SAFETY: `path` is a valid NUL-terminated string.
+        Ok(path) => unsafe { libc::pathconf(path.as_ptr(), libc::_PC_NAME_MAX) },
+        Err(_) => -1,
+    };
+    if max > 0 {
+        max as usize
+    } else {
+        DEFAULT_NAME_MAX
+    }
+}
+
+/// Get the maximum length of file names in a directory, as measured by `name_len`.
+#[cfg(not(unix))]
+pub fn name_max(_dir: &Path) -> usize {
+    DEFAULT_NAME_MAX
+}
+
+/// Check whether the name (the last component) of a path is too long for the filesystem of its
+/// directory.
+///
+/// # Returns
+///
+/// The length of the name and the maximum length if it is too long, or `None`.
+pub fn check_name_len(path: &str) -> Option<(usize, usize)> {
+    let (dir, name) = split_name(path);
+    let len = name_len(name);
+    let max = name_max(Path::new(dir));
+    if len > max {
+        Some((len, max))
+    } else {
+        None
+    }
+}
+
+/// Split a path into its directory, including the trailing separator, and its name.
+pub fn split_name(path: &str) -> (&str, &str) {
+    match path.rfind(std::path::is_separator) {
+        Some(i) => path.split_at(i + 1),
+        None => ("", path),
+    }
+}
+
+/// Check whether the filesystem of a directory is case-insensitive.
+///
+/// An entry of the directory with a cased name is looked up with its case swapped. If there is no
+/// such entry (or the directory cannot be read), the platform default is assumed, which is
+/// case-insensitive on Windows and macOS.
+pub fn is_case_insensitive(dir: &Path) -> bool {
+    let default = cfg!(windows) || cfg!(target_os = "macos");
+    let entries = match fs::read_dir(if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    }) {
+        Ok(entries) => entries,
+        Err(_) => return default,
+    };
+    for entry in entries.flatten() {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        let swapped: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_lowercase() {
+                    c.to_ascii_uppercase()
+                } else {
+                    c.to_ascii_lowercase()
+                }
+            })
+            .collect();
+        if swapped == name {
+            continue;
+        }
+        let path = entry.path();
+        return match (
+            fs::symlink_metadata(&path),
+            fs::symlink_metadata(path.with_file_name(&swapped)),
+        ) {
+            (Ok(a), Ok(b)) => same_file(&a, &b),
+            _ => false,
+        };
+    }
+    default
+}
+
+/// Check if metadata belong to the same file.
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// Check if metadata belong to the same file.
+#[cfg(not(unix))]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    // Without file identifiers, assume that a swapped-case name resolving to a file of the same
+    // kind and size is the same file.
+    a.file_type() == b.file_type() && a.len() == b.len()
+}
+
+/// Get the identity of a file with multiple hard links, as its device and inode numbers.
+///
+/// Only supported on Unix, returning `None` elsewhere, and for files with a single link.
+#[cfg(unix)]
+pub fn hard_link_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if metadata.is_dir() || metadata.nlink() < 2 {
+        return None;
+    }
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Get the identity of a file with multiple hard links, as its device and inode numbers.
+///
+/// Only supported on Unix, returning `None` elsewhere, and for files with a single link.
+#[cfg(not(unix))]
+pub fn hard_link_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Replace the hard link `from` with a hard link at `to` to the file at `target`.
+///
+/// Used to move the other links of a file after moving one of them, so that the file is not
+/// copied again when moving between filesystems. With `keep_source`, `from` is kept.
+pub fn relink_path(target: &Path, from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()> {
+    let (target, from, to) = (native_path(target), native_path(from), native_path(to));
+    if opts.create_parents {
+        if let Some(parent) = to.parent().filter(|p| has_dirs(p)) {
+            opts.fs.create_dir_all(parent)?;
+        }
+    }
+    opts.fs.hard_link(&target, &to)?;
+    if !opts.keep_source {
+        opts.fs.remove_file(&from)?;
+    }
+    Ok(())
+}
+
+/// Convert path separators to the native separator of the platform.
+///
+/// On Windows, both `/` and `\` are separators, and are converted to `\`, so that mixed
+/// separators do not end up in paths passed to the system. Elsewhere, backslashes are valid in file
+/// names, so paths are kept as-is.
+pub fn native_separators(path: &str) -> Cow<'_, str> {
+    if cfg!(windows) {
+        to_separator(path, '\\')
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Convert the separators of a path with `native_separators`, if it is valid UTF-8.
+fn native_path(path: &Path) -> Cow<'_, Path> {
+    match path.to_str().map(native_separators) {
+        Some(Cow::Owned(s)) => Cow::Owned(s.into()),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// Check if a path names any directory, rather than being empty, root or only a prefix (e.g. `D:`
+/// or `\\server\share` on Windows), which need not be created.
+fn has_dirs(path: &Path) -> bool {
+    path.components()
+        .any(|c| matches!(c, Component::Normal(_) | Component::ParentDir))
+}
+
+/// Replace both `/` and `\` with `separator`.
+fn to_separator(path: &str, separator: char) -> Cow<'_, str> {
+    if path
+        .chars()
+        .all(|c| c == separator || (c != '/' && c != '\\'))
+    {
+        return Cow::Borrowed(path);
+    }
+    Cow::Owned(
+        path.chars()
+            .map(|c| if c == '/' || c == '\\' { separator } else { c })
+            .collect(),
+    )
+}
+
+/// Copy file or directory, then remove the source.
+pub fn copy_and_remove(from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()> {
+    copy_path(from, to, opts)?;
+    remove_path(from, opts)
+}
+
+/// Copy file or directory recursively, recreating symlinks rather than following them.
+///
+/// If copying fails, whatever was copied to `to` is removed. A destination which exists already
+/// is left as-is, and copying fails.
+fn copy_path(from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()> {
+    if opts.fs.kind(to).is_ok() {
+        return Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    let result = copy_tree(from, to, opts);
+    if result.is_err() {
+        // The error of the copy is more useful than that of the cleanup.
+        let _ = remove_path(to, opts);
+    }
+    result
+}
+
+/// Copy file or directory recursively, recreating symlinks rather than following them.
+fn copy_tree(from: &Path, to: &Path, opts: &MoveOpts) -> io::Result<()> {
+    match opts.fs.kind(from)? {
+        Kind::File => return opts.fs.copy_file(from, to, opts),
+        Kind::Symlink => return opts.fs.copy_symlink(from, to),
+        Kind::Dir => {}
+    }
+    opts.fs.create_dir(to)?;
+    for entry in opts.fs.read_dir(from)? {
+        if let Some(name) = entry.file_name() {
+            copy_tree(&entry, &to.join(name), opts)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove file or directory recursively.
+fn remove_path(path: &Path, opts: &MoveOpts) -> io::Result<()> {
+    if opts.fs.kind(path)? == Kind::Dir {
+        opts.fs.remove_dir_all(path)
+    } else {
+        opts.fs.remove_file(path)
+    }
+}
+
+/// Clone a file, sharing its data blocks with the copy until either is modified.
+///
+/// # Returns
+///
+/// An `io::Result`. Fails if the destination exists, or if the filesystem or platform does not
+/// support cloning, or the files are on different filesystems, in which case no destination is
+/// left behind.
+#[cfg(target_os = "linux")]
+pub fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    /// `_IOW(0x94, 9, int)`, from `linux/fs.h`.
+    #[cfg(any(
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "sparc64"
+    ))]
+    const FICLONE: u32 = 0x8004_9409;
+    #[cfg(not(any(
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "mips",
+        target_arch = "mips64",
+        target_arch = "sparc64"
+    )))]
+    const FICLONE: u32 = 0x4004_9409;
+
+    let src = File::open(from)?;
+    write_new(to, |dst| {
+        // SAFETY: both file descriptors are open, owned by `src` and `dst` for the duration of the
+        // call.
+        if unsafe { libc::ioctl(dst.as_raw_fd(), FICLONE as _, src.as_raw_fd()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        dst.set_permissions(src.metadata()?.permissions())
+    })
+}
+
+/// Clone a file, sharing its data blocks with the copy until either is modified.
+///
+/// # Returns
+///
+/// An `io::Result`. Fails if the destination exists, or if the filesystem or platform does not
+/// support cloning, or the files are on different filesystems, in which case no destination is
+/// left behind.
+#[cfg(target_os = "macos")]
+pub fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    extern "C" {
+        fn clonefile(src: *const libc::c_char, dst: *const libc::c_char, flags: u32)
+            -> libc::c_int;
+    }
+
+    let from = CString::new(from.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: both paths are valid NUL-terminated strings.
+    if unsafe { clonefile(from.as_ptr(), to.as_ptr(), 0) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Clone a file, sharing its data blocks with the copy until either is modified.
+///
+/// # Returns
+///
+/// An `io::Result`. Cloning is not supported on this platform, so this always fails.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn reflink(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        ErrorKind::Other,
+        "cloning files is not supported on this platform",
+    ))
+}
+
+/// Copy a file, preserving holes of sparse files where supported.
+///
+/// Sparse files are copied segment by segment, skipping holes, on Linux, Android and FreeBSD. Other
+/// files are copied with `io::copy`, which uses the fastest copy method of the platform, unless
+/// `throttle` is given, in which case they are copied in paced chunks.
+///
+/// # Returns
+///
+/// An `io::Result`. Fails without touching the destination if it exists, and removes the
+/// partial copy if copying fails.
+pub fn copy_file(from: &Path, to: &Path, throttle: Option<&Throttle>) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    {
+        if is_sparse(&fs::metadata(from)?) && copy_sparse(from, to, throttle)? {
+            return Ok(());
+        }
+    }
+    let mut src = File::open(from)?;
+    write_new(to, |dst| {
+        match throttle {
+            Some(throttle) => throttle.copy(&mut src, dst)?,
+            None => io::copy(&mut src, dst)?,
+        };
+        dst.set_permissions(src.metadata()?.permissions())
+    })
+}
+
+/// Create a file which must not exist yet, and write it with `write`.
+///
+/// # Returns
+///
+/// An `io::Result`. If `write` fails, the created file is removed.
+fn write_new<F>(to: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    let mut dst = OpenOptions::new().write(true).create_new(true).open(to)?;
+    let result = write(&mut dst);
+    drop(dst);
+    if result.is_err() {
+        // The error of the write is more useful than that of the cleanup.
+        let _ = fs::remove_file(to);
+    }
+    result
+}
+
+/// Check if a file has fewer blocks allocated than its size requires.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn is_sparse(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // `blocks` is in units of 512 bytes regardless of the filesystem block size.
+    metadata.is_file() && metadata.blocks() * 512 < metadata.len()
+}
+
+/// Copy the data segments of a sparse file, leaving holes in the copy.
+///
+/// # Returns
+///
+/// An `io::Result` containing `false` if the filesystem does not support finding holes, in which
+/// case nothing is copied.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn copy_sparse(from: &Path, to: &Path, throttle: Option<&Throttle>) -> io::Result<bool> {
+    use std::io::{Seek, SeekFrom};
+    use std::os::unix::io::AsRawFd;
+
+    let mut src = File::open(from)?;
+    let metadata = src.metadata()?;
+    let fd = src.as_raw_fd();
+    // SAFETY: `fd` is an open file descriptor, owned by `src` for the duration of the calls.
+    let seek = |offset: u64, whence| unsafe { libc::lseek(fd, offset as libc::off_t, whence) };
+    if seek(0, libc::SEEK_DATA) < 0 {
+        return match io::Error::last_os_error().raw_os_error() {
+            // No data at all, the file is a single hole.
+            Some(libc::ENXIO) => {
+                write_new(to, |dst| {
+                    dst.set_len(metadata.len())?;
+                    dst.set_permissions(metadata.permissions())
+                })?;
+                Ok(true)
+            }
+            Some(libc::EINVAL) => Ok(false),
+            _ => Err(io::Error::last_os_error()),
+        };
+    }
+    write_new(to, |dst| {
+        let mut pos = 0;
+        while pos < metadata.len() {
+            let data = seek(pos, libc::SEEK_DATA);
+            if data < 0 {
+                match io::Error::last_os_error().raw_os_error() {
+                    Some(libc::ENXIO) => break,
+                    _ => return Err(io::Error::last_os_error()),
+                }
+            }
+            let hole = seek(data as u64, libc::SEEK_HOLE);
+            if hole < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let (data, hole) = (data as u64, hole as u64);
+            src.seek(SeekFrom::Start(data))?;
+            dst.seek(SeekFrom::Start(data))?;
+            let mut segment = (&mut src).take(hole - data);
+            match throttle {
+                Some(throttle) => throttle.copy(&mut segment, dst)?,
+                None => io::copy(&mut segment, dst)?,
+            };
+            pos = hole;
+        }
+        dst.set_len(metadata.len())?;
+        dst.set_permissions(metadata.permissions())
+    })?;
+    Ok(true)
+}
+
+/// Check whether two files have identical contents.
+pub fn contents_equal(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+    let mut buf_a = vec![0; BUF_SIZE];
+    let mut buf_b = vec![0; BUF_SIZE];
+    loop {
+        let n = read_full(&mut a, &mut buf_a)?;
+        if n != read_full(&mut b, &mut buf_b)? || buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Read until the buffer is full or the end of the reader is reached.
+fn read_full(r: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    macro_rules! to_separator_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (path, separator, expected) = $value;
+                    assert_eq!(to_separator(path, separator), expected);
+                }
+            )*
+        }
+    }
+
+    #[test]
+    fn failure_of_errors() {
+        let denied = io::Error::from(ErrorKind::PermissionDenied);
+        assert_eq!(Failure::of(&denied), Failure::PermissionDenied);
+        assert_eq!(
+            Failure::of(&io::Error::from(ErrorKind::NotFound)),
+            Failure::Other
+        );
+        #[cfg(windows)]
+        assert_eq!(
+            Failure::of(&io::Error::from_raw_os_error(ERROR_SHARING_VIOLATION)),
+            Failure::Locked
+        );
+    }
+
+    #[test]
+    fn name_lengths() {
+        assert_eq!(split_name("a/b/c.txt"), ("a/b/", "c.txt"));
+        assert_eq!(split_name("c.txt"), ("", "c.txt"));
+        let temp = assert_fs::TempDir::new().unwrap();
+        let dir = temp.path().join("missing").to_string_lossy().into_owned();
+        let max_len = name_max(temp.path());
+        assert_eq!(name_max(Path::new(&dir)), max_len);
+        let name = "a".repeat(max_len);
+        assert_eq!(check_name_len(&format!("{}/{}", dir, name)), None);
+        assert_eq!(
+            check_name_len(&format!("{}/{}b", dir, name)),
+            Some((max_len + 1, max_len))
+        );
+    }
+
+    #[test]
+    fn parse_mode() {
+        assert_eq!("755".parse(), Ok(Mode(0o755)));
+        assert_eq!("0644".parse(), Ok(Mode(0o644)));
+        assert_eq!(Mode(0o644).to_string(), "644");
+        assert!("75".parse::<Mode>().is_err());
+        assert!("758".parse::<Mode>().is_err());
+        assert!("+x".parse::<Mode>().is_err());
+        assert!("07555".parse::<Mode>().is_err());
+    }
+
+    #[test]
+    fn check_move_missing_source() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        assert_eq!(
+            check_move(
+                &temp.path().join("a"),
+                &temp.path().join("b"),
+                &MoveOpts::new()
+            ),
+            Some("source does not exist".to_owned())
+        );
+        temp.child("a").touch().unwrap();
+        assert_eq!(
+            check_move(
+                &temp.path().join("a"),
+                &temp.path().join("c/d/b"),
+                &MoveOpts::new()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn is_case_insensitive_probe() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a").touch().unwrap();
+        // Assumes the temporary directory is on the platform's default filesystem.
+        assert_eq!(
+            is_case_insensitive(temp.path()),
+            cfg!(windows) || cfg!(target_os = "macos")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn relink_hard_links() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a").write_str("abc").unwrap();
+        fs::hard_link(temp.child("a").path(), temp.child("b").path()).unwrap();
+        let id = hard_link_id(temp.child("a").path());
+        assert!(id.is_some());
+        assert_eq!(hard_link_id(temp.child("b").path()), id);
+        move_path(
+            temp.child("a").path(),
+            temp.child("x").path(),
+            &MoveOpts::new(),
+        )
+        .unwrap();
+        relink_path(
+            temp.child("x").path(),
+            temp.child("b").path(),
+            temp.child("y").path(),
+            &MoveOpts::new(),
+        )
+        .unwrap();
+        temp.child("b").assert(predicates::path::missing());
+        assert_eq!(hard_link_id(temp.child("y").path()), id);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn copy_file_sparse() {
+        use std::io::{Seek, SeekFrom, Write};
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = assert_fs::TempDir::new().unwrap();
+        let len = 64 * 1024 * 1024;
+        let mut file = File::create(temp.child("a").path()).unwrap();
+        file.write_all(b"head").unwrap();
+        file.seek(SeekFrom::Start(len - 4)).unwrap();
+        file.write_all(b"tail").unwrap();
+        drop(file);
+        let source = fs::metadata(temp.child("a").path()).unwrap();
+        if !is_sparse(&source) {
+            // The filesystem does not support sparse files.
+            return;
+        }
+        copy_file(temp.child("a").path(), temp.child("b").path(), None).unwrap();
+        let copy = fs::metadata(temp.child("b").path()).unwrap();
+        assert_eq!(copy.len(), len);
+        assert!(copy.blocks() <= source.blocks());
+        assert!(contents_equal(temp.child("a").path(), temp.child("b").path()).unwrap());
+    }
+
+    #[test]
+    fn reflink_auto_falls_back() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a").write_str("contents").unwrap();
+        let opts = MoveOpts {
+            keep_source: true,
+            reflink: Reflink::Auto,
+            ..MoveOpts::new()
+        };
+        move_path(temp.child("a").path(), temp.child("b").path(), &opts).unwrap();
+        temp.child("a").assert("contents");
+        temp.child("b").assert("contents");
+    }
+
+    #[test]
+    fn reflink_always_leaves_nothing_on_failure() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a").write_str("contents").unwrap();
+        match reflink(temp.child("a").path(), temp.child("b").path()) {
+            Ok(()) => temp.child("b").assert("contents"),
+            Err(_) => temp.child("b").assert(predicates::path::missing()),
+        };
+    }
+
+    #[test]
+    fn copies_keep_existing_destination() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a").write_str("contents").unwrap();
+        temp.child("b").write_str("existing").unwrap();
+        assert!(reflink(temp.child("a").path(), temp.child("b").path()).is_err());
+        temp.child("b").assert("existing");
+        let e = copy_file(temp.child("a").path(), temp.child("b").path(), None).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::AlreadyExists);
+        temp.child("b").assert("existing");
+    }
+
+    to_separator_tests!(
+        to_separator_none: ("a.txt", '\\', "a.txt"),
+        to_separator_mixed: ("a/b\\c", '\\', "a\\b\\c"),
+        to_separator_forward: ("a\\b", '/', "a/b"),
+    );
+
+    #[test]
+    fn has_dirs_of_paths() {
+        assert!(!has_dirs(Path::new("")));
+        assert!(!has_dirs(Path::new("/")));
+        assert!(has_dirs(Path::new("a/b")));
+        assert!(has_dirs(Path::new("..")));
+        #[cfg(windows)]
+        {
+            assert!(!has_dirs(Path::new("D:")));
+            assert!(!has_dirs(Path::new(r"\\server\share\")));
+            assert!(has_dirs(Path::new(r"\\server\share\a")));
+        }
+    }
+
+    #[test]
+    fn contents_equal_same() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a").write_str("abc").unwrap();
+        temp.child("b").write_str("abc").unwrap();
+        assert!(contents_equal(temp.child("a").path(), temp.child("b").path()).unwrap());
+    }
+
+    #[test]
+    fn contents_equal_different() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a").write_str("abc").unwrap();
+        temp.child("b").write_str("abd").unwrap();
+        assert!(!contents_equal(temp.child("a").path(), temp.child("b").path()).unwrap());
+    }
+
+    #[test]
+    fn copy_and_remove_dir() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a/b/c").write_str("abc").unwrap();
+        copy_and_remove(
+            temp.child("a").path(),
+            temp.child("d").path(),
+            &MoveOpts {
+                verify: true,
+                ..MoveOpts::new()
+            },
+        )
+        .unwrap();
+        temp.child("a").assert(predicates::path::missing());
+        temp.child("d/b/c").assert("abc");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn copy_and_remove_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a/b/c").write_str("abc").unwrap();
+        symlink("b", temp.child("a/dir").path()).unwrap();
+        symlink("missing", temp.child("a/dangling").path()).unwrap();
+        copy_and_remove(
+            temp.child("a").path(),
+            temp.child("d").path(),
+            &MoveOpts::new(),
+        )
+        .unwrap();
+        temp.child("a").assert(predicates::path::missing());
+        temp.child("d/b/c").assert("abc");
+        for (link, target) in &[("d/dir", "b"), ("d/dangling", "missing")] {
+            let path = temp.child(link);
+            assert_eq!(Local.kind(path.path()).unwrap(), Kind::Symlink);
+            assert_eq!(fs::read_link(path.path()).unwrap(), Path::new(target));
+        }
+    }
+}
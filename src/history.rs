@@ -0,0 +1,149 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Environment variable overriding the path of the history file. If empty, history is disabled.
+pub const HISTORY_ENV: &str = "MRF_HISTORY";
+
+/// Maximum number of entries kept in the history. The oldest entries are dropped beyond it.
+pub const MAX_ENTRIES: usize = 1000;
+
+/// Replacer used in a directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Directory the replacer was used in.
+    pub dir: PathBuf,
+    pub replacer: String,
+}
+
+/// Get the path of the history file.
+///
+/// # Returns
+///
+/// The value of `MRF_HISTORY` if set, or `mrf/history.jsonl` in the user's data directory.
+/// `None` if history is disabled or there is no data directory.
+pub fn path() -> Option<PathBuf> {
+    match env::var_os(HISTORY_ENV) {
+        Some(path) if path.is_empty() => None,
+        Some(path) => Some(path.into()),
+        None => Some(dirs::data_dir()?.join("mrf").join("history.jsonl")),
+    }
+}
+
+/// Read the history, oldest first.
+///
+/// # Returns
+///
+/// An `io::Result` containing the entries. A missing history file is empty. Invalid lines are
+/// skipped.
+pub fn load(path: &Path) -> io::Result<Vec<Entry>> {
+    let s = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    Ok(s.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Append an entry to the history, unless it is the last entry of its directory already.
+///
+/// Once the history has `MAX_ENTRIES` entries, it is rewritten without the oldest ones.
+pub fn record(path: &Path, entry: &Entry) -> io::Result<()> {
+    let entries = load(path)?;
+    if last(&entries, &entry.dir) == Some(&entry.replacer) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    if entries.len() < MAX_ENTRIES {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        return writeln!(file, "{}", serde_json::to_string(entry)?);
+    }
+    let mut s = String::new();
+    for entry in entries[entries.len() + 1 - MAX_ENTRIES..]
+        .iter()
+        .chain(Some(entry))
+    {
+        s.push_str(&serde_json::to_string(entry)?);
+        s.push('\n');
+    }
+    // Replace the file at once, so that an interrupted write does not lose the history.
+    let temp = path.with_extension("jsonl.tmp");
+    fs::write(&temp, s)?;
+    fs::rename(&temp, path)
+}
+
+/// Get the replacers used in a directory, oldest first, keeping only the latest use of each.
+pub fn replacers<'a>(entries: &'a [Entry], dir: &Path) -> Vec<&'a String> {
+    let mut replacers: Vec<&String> = vec![];
+    for entry in entries.iter().filter(|e| e.dir == dir) {
+        replacers.retain(|r| *r != &entry.replacer);
+        replacers.push(&entry.replacer);
+    }
+    replacers
+}
+
+/// Get the replacer used last in a directory.
+pub fn last<'a>(entries: &'a [Entry], dir: &Path) -> Option<&'a String> {
+    entries
+        .iter()
+        .rev()
+        .find(|e| e.dir == dir)
+        .map(|e| &e.replacer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn record_and_load() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let path = temp.child("mrf/history.jsonl");
+        let entry = |dir: &str, replacer: &str| Entry {
+            dir: dir.into(),
+            replacer: replacer.to_owned(),
+        };
+        for (dir, replacer) in &[
+            ("a", "{}"),
+            ("a", "{}"),
+            ("b", "{n}"),
+            ("a", "{=x}"),
+            ("a", "{}"),
+        ] {
+            record(path.path(), &entry(dir, replacer)).unwrap();
+        }
+        let entries = load(path.path()).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(replacers(&entries, Path::new("a")), vec!["{=x}", "{}"]);
+        assert_eq!(last(&entries, Path::new("b")).unwrap(), "{n}");
+        assert_eq!(last(&entries, Path::new("c")), None);
+    }
+
+    #[test]
+    fn record_drops_oldest() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let path = temp.child("history.jsonl");
+        for i in 0..MAX_ENTRIES + 2 {
+            let entry = Entry {
+                dir: "a".into(),
+                replacer: i.to_string(),
+            };
+            record(path.path(), &entry).unwrap();
+        }
+        let entries = load(path.path()).unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0].replacer, "2");
+        assert_eq!(
+            last(&entries, Path::new("a")).unwrap(),
+            &(MAX_ENTRIES + 1).to_string()
+        );
+    }
+}
@@ -8,7 +8,7 @@ pub trait SplitAtIndices {
         Self: std::marker::Sized;
 }
 
-impl<'a> SplitAtIndices for &'a str {
+impl SplitAtIndices for &str {
     fn split_at_indices(self, indices: &[usize]) -> Vec<Self> {
         [indices, &[self.len()]]
             .concat()
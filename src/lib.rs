@@ -1,11 +1,39 @@
+pub mod aggregate;
+pub mod bucket;
+pub mod checkpoint;
 pub mod command;
+pub mod config;
+pub mod define;
+pub mod diff;
+#[cfg(feature = "doc")]
+pub mod document;
+pub mod dryfs;
 pub mod elem;
+pub mod fileops;
 pub mod formatter;
+pub mod fsops;
+pub mod history;
 pub mod indices;
+pub mod lint;
 pub mod matcher;
+pub mod meta;
+pub mod mmap;
+pub mod normalize;
+pub mod palette;
 pub mod parser;
+pub mod plan;
+pub mod predicate;
 pub mod replacement;
 pub mod replacer;
+pub mod script;
+pub mod selftest;
+pub mod sink;
+pub mod snapshot;
 pub mod spec;
+pub mod sqlite;
+pub mod stats;
 pub mod subcommands;
+pub mod throttle;
 pub mod tokens;
+pub mod walk;
+pub mod xattr;
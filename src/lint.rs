@@ -0,0 +1,218 @@
+use std::fmt;
+
+use crate::{elem::Elem, matcher::Matcher};
+
+/// A possible mistake in a replacer.
+///
+/// Element positions are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The replacer has no specifiers, so every item is replaced with the same string.
+    NoSpecifiers,
+    /// The matcher of the specifier at this position is ignored, since it refers to the entire
+    /// input (index 0).
+    MatcherOnEntire(usize),
+    /// The matcher of the specifier at this position is ignored, since another matcher was
+    /// already given for the group.
+    MatcherOverridden(usize, usize),
+    /// The specifier at this position outputs the first group but its matcher applies to the
+    /// second, because it follows an explicit index.
+    ImplicitIndexMismatch(usize, usize, usize),
+    /// The group is matched but never output.
+    UnusedGroup(usize),
+    /// The specifier at this position formats its replacement string rather than the match.
+    FormatterOnReplacement(usize),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::NoSpecifiers => write!(
+                f,
+                "the replacer has no specifiers, every item is replaced with the same string"
+            ),
+            Warning::MatcherOnEntire(pos) => write!(
+                f,
+                "element {}: the matcher is ignored, index 0 refers to the entire input",
+                pos
+            ),
+            Warning::MatcherOverridden(pos, group) => write!(
+                f,
+                "element {}: the matcher is ignored, group {} already has a matcher",
+                pos, group
+            ),
+            Warning::ImplicitIndexMismatch(pos, output, matched) => write!(
+                f,
+                "element {}: outputs group {} but its matcher applies to group {}, specify the \
+                 index explicitly",
+                pos, output, matched
+            ),
+            Warning::UnusedGroup(group) => write!(
+                f,
+                "group {} is matched but never output, use {{=}} to remove it explicitly",
+                group
+            ),
+            Warning::FormatterOnReplacement(pos) => write!(
+                f,
+                "element {}: the formatter applies to the replacement string, not the match",
+                pos
+            ),
+        }
+    }
+}
+
+/// Interpreted structure of an element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElemInfo {
+    /// Group output by the element (0 for the entire input), if it is a specifier.
+    pub group: Option<usize>,
+    /// Matcher applying to the group, if it is a specifier.
+    pub matcher: Option<Matcher>,
+}
+
+/// Interpret elements, resolving the group output by each specifier and its matcher.
+///
+/// # Arguments
+///
+/// * `elems` - Elements.
+///
+/// # Returns
+///
+/// The interpreted structure of each element, and the warnings found.
+pub fn lint(elems: &[Elem]) -> (Vec<ElemInfo>, Vec<Warning>) {
+    let mut warnings = vec![];
+    // Matcher of each group, and the position of the element defining it.
+    let mut groups: Vec<(Matcher, Option<usize>)> = vec![];
+    let mut matched_groups = vec![None; elems.len()];
+    for (i, e) in elems.iter().enumerate() {
+        let spec = match e {
+            Elem::Spec(spec) => spec,
+            _ => continue,
+        };
+        let pos = i + 1;
+        match spec.index {
+            Some(0) => {
+                if spec.matcher != Matcher::Any {
+                    warnings.push(Warning::MatcherOnEntire(pos));
+                }
+            }
+            Some(idx) => {
+                if idx > groups.len() {
+                    groups.resize(idx, (Matcher::Any, None));
+                }
+                let group = &mut groups[idx - 1];
+                if group.0 == Matcher::Any {
+                    *group = (spec.matcher.clone(), Some(pos));
+                } else if spec.matcher != Matcher::Any && spec.matcher != group.0 {
+                    warnings.push(Warning::MatcherOverridden(pos, idx));
+                }
+                matched_groups[i] = Some(idx);
+            }
+            None => {
+                groups.push((spec.matcher.clone(), Some(pos)));
+                matched_groups[i] = Some(groups.len());
+            }
+        }
+    }
+    if !elems.iter().any(|e| matches!(e, Elem::Spec(_))) {
+        warnings.push(Warning::NoSpecifiers);
+    }
+    let mut cursor = 1;
+    let mut output_groups = vec![];
+    let infos = elems
+        .iter()
+        .enumerate()
+        .map(|(i, e)| match e {
+            Elem::Spec(spec) => {
+                let idx = spec.index.unwrap_or(cursor);
+                cursor = idx + 1;
+                if let (None, Some(matched)) = (spec.index, matched_groups[i]) {
+                    if matched != idx {
+                        warnings.push(Warning::ImplicitIndexMismatch(i + 1, idx, matched));
+                    }
+                }
+                if spec.replace.is_some() && spec.formatter.is_some() {
+                    warnings.push(Warning::FormatterOnReplacement(i + 1));
+                }
+                output_groups.push(idx);
+                ElemInfo {
+                    group: Some(idx),
+                    matcher: Some(match idx {
+                        0 => Matcher::Any,
+                        _ => groups
+                            .get(idx - 1)
+                            .map(|g| g.0.clone())
+                            .unwrap_or(Matcher::Any),
+                    }),
+                }
+            }
+            _ => ElemInfo {
+                group: None,
+                matcher: None,
+            },
+        })
+        .collect();
+    if !output_groups.contains(&0) {
+        for group in 1..=groups.len() {
+            if !output_groups.contains(&group) {
+                warnings.push(Warning::UnusedGroup(group));
+            }
+        }
+    }
+    (infos, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    macro_rules! lint_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected): (&str, &[Warning]) = $value;
+                    assert_eq!(lint(&parse(s).unwrap()).1, expected);
+                }
+            )*
+        }
+    }
+
+    lint_tests!(
+        lint_ok: ("{}{=_}{n:03}", &[]),
+        lint_ok_explicit: ("{3}{2}{1}", &[]),
+        lint_ok_entire: ("{0}.bak", &[]),
+        lint_no_specifiers: ("abc", &[Warning::NoSpecifiers]),
+        lint_matcher_on_entire: ("{n0}", &[Warning::MatcherOnEntire(1)]),
+        lint_matcher_overridden: ("{n1}{x1}", &[Warning::MatcherOverridden(2, 1)]),
+        lint_implicit_index_mismatch: ("{2}{1}{}", &[
+            Warning::ImplicitIndexMismatch(3, 2, 3),
+            Warning::UnusedGroup(3),
+        ]),
+        lint_unused_group: ("{2}", &[Warning::UnusedGroup(1)]),
+        lint_formatter_on_replacement: ("{=1:03}", &[Warning::FormatterOnReplacement(1)]),
+    );
+
+    #[test]
+    fn lint_infos() {
+        let (infos, _) = lint(&parse("a{n2}{1}").unwrap());
+        assert_eq!(
+            infos,
+            vec![
+                ElemInfo {
+                    group: None,
+                    matcher: None,
+                },
+                ElemInfo {
+                    group: Some(2),
+                    matcher: Some(Matcher::Number),
+                },
+                ElemInfo {
+                    group: Some(1),
+                    matcher: Some(Matcher::Any),
+                },
+            ]
+        );
+    }
+}
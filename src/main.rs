@@ -1,7 +1,9 @@
+use std::env;
 use std::error::Error;
 
-use clap::{AppSettings, Clap};
+use clap::{AppSettings, Clap, IntoApp};
 
+use mrf::config::Config;
 use mrf::subcommands;
 
 /// {[m]atch=[r]eplace:[f]ormat} (Run `mrf --help` for more information.)
@@ -25,6 +27,20 @@ enum Subcommand {
     Map(subcommands::map::Opts),
     Exec(subcommands::exec::Opts),
     Mv(subcommands::mv::Opts),
+    Cp(subcommands::cp::Opts),
+    Chmod(subcommands::chmod::Opts),
+    Touch(subcommands::touch::Opts),
+    Tag(subcommands::tag::Opts),
+    Plan(subcommands::plan::Opts),
+    Apply(subcommands::apply::Opts),
+    Refactor(subcommands::refactor::Opts),
+    Check(subcommands::check::Opts),
+    Diff(subcommands::diff::Opts),
+    Try(subcommands::playground::Opts),
+    History(subcommands::history::Opts),
+    Serve(subcommands::serve::Opts),
+    #[clap(setting = AppSettings::Hidden)]
+    Selftest(subcommands::selftest::Opts),
 }
 
 fn main() {
@@ -44,10 +60,38 @@ fn run_app() -> Result<(), Box<dyn Error>> {
         std::process::exit(1);
     })?;
 
-    let opts = Opts::parse();
+    let args: Vec<_> = env::args_os().collect();
+    let app = Opts::into_app();
+    // A broken configuration file in a parent directory should not prevent running mrf at all.
+    let args = match Config::find(&env::current_dir()?).and_then(|config| {
+        config
+            .map(|(_, c)| c.with_defaults(args.clone(), &app))
+            .transpose()
+    }) {
+        Ok(Some(args)) => args,
+        Ok(None) => args,
+        Err(e) => {
+            eprintln!("Warning: ignoring defaults of the configuration: {}", e);
+            args
+        }
+    };
+    let opts = Opts::parse_from(args);
     match opts.subcmd {
         Subcommand::Map(sub_opts) => subcommands::map::run(sub_opts),
         Subcommand::Exec(sub_opts) => subcommands::exec::run(sub_opts),
         Subcommand::Mv(sub_opts) => subcommands::mv::run(sub_opts),
+        Subcommand::Cp(sub_opts) => subcommands::cp::run(sub_opts),
+        Subcommand::Chmod(sub_opts) => subcommands::chmod::run(sub_opts),
+        Subcommand::Touch(sub_opts) => subcommands::touch::run(sub_opts),
+        Subcommand::Tag(sub_opts) => subcommands::tag::run(sub_opts),
+        Subcommand::Plan(sub_opts) => subcommands::plan::run(sub_opts),
+        Subcommand::Apply(sub_opts) => subcommands::apply::run(sub_opts),
+        Subcommand::Refactor(sub_opts) => subcommands::refactor::run(sub_opts),
+        Subcommand::Check(sub_opts) => subcommands::check::run(sub_opts),
+        Subcommand::Diff(sub_opts) => subcommands::diff::run(sub_opts),
+        Subcommand::Try(sub_opts) => subcommands::playground::run(sub_opts),
+        Subcommand::History(sub_opts) => subcommands::history::run(sub_opts),
+        Subcommand::Serve(sub_opts) => subcommands::serve::run(sub_opts),
+        Subcommand::Selftest(sub_opts) => subcommands::selftest::run(sub_opts),
     }
 }
@@ -1,6 +1,9 @@
 use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::tokens::{tokenize, TokenType};
+use crate::formatter::{plain_number, NumberLocale};
+use crate::tokens::{tokenize_with_mode, TokenMode, TokenType};
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -10,17 +13,67 @@ pub enum Matcher {
     Any,
     /// Match numbers only.
     Number,
+    /// Match text only.
+    Text,
+    /// Match punctuations only.
+    Punct,
+    /// Match hexadecimal numbers, optionally prefixed with `0x` (e.g. `0x1A`, `deadbeef`).
+    Hex,
+    /// Match decimal numbers, optionally signed, with a fraction, or with thousands separators
+    /// (e.g. `-1.5`, `1,234`, `1_000`).
+    Decimal,
+    /// Match IPv4 or IPv6 addresses (e.g. `192.168.0.1`, `::1`).
+    Ip,
+    /// Match DNS-style hostnames (e.g. `www.example.com`).
+    Host,
+    /// Match UUIDs in the canonical 8-4-4-4-12 hexadecimal form.
+    Uuid,
+    /// Match checksums, i.e. hexadecimal numbers of exactly this many digits (e.g. 8 for CRC32).
+    Checksum(usize),
+    /// Match a number of consecutive matches of the inner matcher, between a minimum and an
+    /// optional maximum (inclusive), as a single group.
+    Repeat(Box<Matcher>, usize, Option<usize>),
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Matcher::Any => write!(f, "Any"),
+            Matcher::Number => write!(f, "Number"),
+            Matcher::Text => write!(f, "Text"),
+            Matcher::Punct => write!(f, "Punctuation"),
+            Matcher::Hex => write!(f, "Hex"),
+            Matcher::Decimal => write!(f, "Decimal"),
+            Matcher::Ip => write!(f, "Ip"),
+            Matcher::Host => write!(f, "Host"),
+            Matcher::Uuid => write!(f, "Uuid"),
+            Matcher::Checksum(len) => write!(f, "Checksum({})", len),
+            Matcher::Repeat(inner, min, Some(max)) if min == max => {
+                write!(f, "{}{{{}}}", inner, min)
+            }
+            Matcher::Repeat(inner, min, max) => write!(
+                f,
+                "{}{{{},{}}}",
+                inner,
+                min,
+                max.map(|m| m.to_string()).unwrap_or_default()
+            ),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Error {
     MatchError,
+    /// Matching ran out of its budget of steps.
+    TooComplex,
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Error::MatchError => write!(f, "unable to match specifiers with input"),
+            Error::TooComplex => write!(f, "input is too complex to match with specifiers"),
         }
     }
 }
@@ -29,6 +82,10 @@ impl std::error::Error for Error {}
 
 /// Match string with matchers.
 ///
+/// Each matcher matches one or more whole tokens. `Any` matches the minimum number of tokens
+/// required, while `Hex` and `Decimal` match the maximum number of tokens forming a valid number.
+/// `Repeat` matches as many repetitions as possible, and may match zero tokens.
+///
 /// # Arguments
 ///
 /// * `s` - String slice to match.
@@ -38,75 +95,360 @@ impl std::error::Error for Error {}
 ///
 /// A `Result` containing a `Vec` of indices pointing to the start of each match.
 pub fn match_all(s: &str, matchers: &[Matcher]) -> Result<Vec<usize>> {
-    let (token_indices, token_types) = tokenize(s);
-    let indices = match_token(s, &token_indices, &token_types, matchers);
-    if indices.len() != matchers.len() {
-        return Err(Error::MatchError);
+    match_all_with_mode(s, matchers, TokenMode::Default)
+}
+
+/// Match string with matchers, tokenizing the string with the given mode.
+///
+/// # Arguments
+///
+/// * `s` - String slice to match.
+/// * `matchers` - Slice of matchers to match with.
+/// * `mode` - Tokenization mode.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of indices pointing to the start of each match.
+pub fn match_all_with_mode(s: &str, matchers: &[Matcher], mode: TokenMode) -> Result<Vec<usize>> {
+    match_all_with_locale(s, matchers, mode, NumberLocale::new())
+}
+
+/// Match string with matchers, tokenizing the string with the given mode, and matching decimal
+/// numbers with the separators of `locale` as well as the default ones.
+///
+/// # Arguments
+///
+/// * `s` - String slice to match.
+/// * `matchers` - Slice of matchers to match with.
+/// * `mode` - Tokenization mode.
+/// * `locale` - Separators of decimal numbers.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of indices pointing to the start of each match.
+pub fn match_all_with_locale(
+    s: &str,
+    matchers: &[Matcher],
+    mode: TokenMode,
+    locale: NumberLocale,
+) -> Result<Vec<usize>> {
+    match_all_with_budget(s, matchers, mode, locale, None)
+}
+
+/// Match string with matchers like `match_all_with_locale`, taking the steps spent matching from
+/// `budget`.
+///
+/// Matching may backtrack exponentially, so this bounds the work done on untrusted replacers and
+/// input. A step is roughly a token or a byte examined, and the budget can be shared by many
+/// matches.
+///
+/// # Arguments
+///
+/// * `s` - String slice to match.
+/// * `matchers` - Slice of matchers to match with.
+/// * `mode` - Tokenization mode.
+/// * `locale` - Separators of decimal numbers.
+/// * `budget` - Steps left, or `None` for no limit.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of indices pointing to the start of each match. Fails with
+/// `Error::TooComplex` once the budget is spent.
+pub fn match_all_with_budget(
+    s: &str,
+    matchers: &[Matcher],
+    mode: TokenMode,
+    locale: NumberLocale,
+    budget: Option<&AtomicUsize>,
+) -> Result<Vec<usize>> {
+    if matchers.is_empty() {
+        return Ok(vec![]);
+    }
+    let (token_indices, token_types) = tokenize_with_mode(s, mode);
+    let tokens = Tokens {
+        s,
+        indices: &token_indices,
+        types: &token_types,
+        locale,
+        budget,
+    };
+    match_from(&tokens, 0, matchers)?.ok_or(Error::MatchError)
+}
+
+/// Tokenized string being matched.
+struct Tokens<'a> {
+    s: &'a str,
+    indices: &'a [usize],
+    types: &'a [TokenType],
+    /// Separators of decimal numbers, besides the default ones.
+    locale: NumberLocale,
+    /// Steps left for matching, if limited.
+    budget: Option<&'a AtomicUsize>,
+}
+
+impl Tokens<'_> {
+    /// Take `steps` from the budget. If not enough steps are left, the budget is emptied so that
+    /// later matches sharing it fail right away.
+    ///
+    /// # Returns
+    ///
+    /// A `Result`, failing with `Error::TooComplex` if not enough steps are left.
+    fn spend(&self, steps: usize) -> Result<()> {
+        let budget = match self.budget {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+        let left = budget.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+            Some(left.saturating_sub(steps))
+        });
+        match left {
+            Ok(left) if left >= steps && left > 0 => Ok(()),
+            _ => Err(Error::TooComplex),
+        }
+    }
+}
+
+/// Match tokens starting from token at `pos` with matchers, consuming all remaining tokens.
+fn match_from(tokens: &Tokens, pos: usize, matchers: &[Matcher]) -> Result<Option<Vec<usize>>> {
+    tokens.spend(1)?;
+    if matchers.is_empty() {
+        return Ok(if pos == tokens.indices.len() {
+            Some(vec![])
+        } else {
+            None
+        });
+    }
+    for end in candidate_ends(tokens, pos, &matchers[0])? {
+        if let Some(rest) = match_from(tokens, end, &matchers[1..])? {
+            let start = tokens.indices.get(pos).copied().unwrap_or(tokens.s.len());
+            return Ok(Some([vec![start], rest].concat()));
+        }
     }
-    Ok(indices)
+    Ok(None)
 }
 
-/// Check if token type matches matcher.
-fn is_match(token_type: TokenType, matcher: &Matcher) -> bool {
+/// Get the possible end token positions of a match starting at token `pos`, in order of
+/// preference.
+fn candidate_ends(tokens: &Tokens, pos: usize, matcher: &Matcher) -> Result<Vec<usize>> {
+    let n = tokens.indices.len();
+    if let Matcher::Repeat(inner, min, max) = matcher {
+        return repeat_ends(tokens, pos, inner, *min, *max);
+    }
+    if pos >= n {
+        return Ok(vec![]);
+    }
+    let substr = |end: usize| {
+        let end_idx = tokens.indices.get(end).copied().unwrap_or(tokens.s.len());
+        &tokens.s[tokens.indices[pos]..end_idx]
+    };
     match matcher {
-        Matcher::Any => true,
-        Matcher::Number => token_type == TokenType::Number,
+        Matcher::Any => {
+            tokens.spend(n - pos)?;
+            Ok((pos + 1..=n).collect())
+        }
+        Matcher::Number | Matcher::Text | Matcher::Punct => {
+            tokens.spend(1)?;
+            if Some(tokens.types[pos]) == matcher_token_type(matcher) {
+                Ok(vec![pos + 1])
+            } else {
+                Ok(vec![])
+            }
+        }
+        _ => {
+            let mut ends = vec![];
+            for end in (pos + 1..=n).rev() {
+                let s = substr(end);
+                tokens.spend(s.len())?;
+                if is_match_str(matcher, s, tokens.locale) {
+                    ends.push(end);
+                }
+            }
+            Ok(ends)
+        }
     }
 }
 
-/// Match token.
-fn match_token(
-    s: &str,
-    token_indices: &[usize],
-    token_types: &[TokenType],
-    matchers: &[Matcher],
-) -> Vec<usize> {
-    if token_types.is_empty()
-        || matchers.is_empty()
-        || !is_match(token_types[0], &matchers[0])
-        || (matchers.len() == 1 && token_indices.len() != 1 && matchers[0] != Matcher::Any)
-    {
-        return vec![];
-    }
-    let mut indices = vec![token_indices[0]];
-    if matchers.len() > 1 {
-        let next = match matchers[0] {
-            Matcher::Any => match_any(s, &token_indices[1..], &token_types[1..], &matchers[1..]),
-            _ => match_token(s, &token_indices[1..], &token_types[1..], &matchers[1..]),
+/// Get the possible end token positions of `min` to `max` consecutive matches of `inner` starting
+/// at token `pos`, preferring more repetitions, then the order `inner` prefers its matches in.
+fn repeat_ends(
+    tokens: &Tokens,
+    pos: usize,
+    inner: &Matcher,
+    min: usize,
+    max: Option<usize>,
+) -> Result<Vec<usize>> {
+    let n = tokens.indices.len();
+    // Each repetition consumes at least one token, so there are at most `n - pos` of them.
+    let mut ends = match max {
+        Some(max) if max < n - pos => bounded_repeat_counts(tokens, pos, inner, max)?,
+        _ => repeat_counts(tokens, pos, inner)?,
+    };
+    ends.retain(|&(_, count)| count >= min);
+    // Stable, so that ends reached with as many repetitions stay in the order they were found.
+    ends.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    Ok(ends.into_iter().map(|(end, _)| end).collect())
+}
+
+/// Get the end positions of consecutive matches of `inner` starting at token `pos`, with the most
+/// matches ending at each.
+///
+/// Each position is expanded once, in order, rather than again for each number of matches it is
+/// reached with.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of end positions and numbers of matches, in the order the
+/// positions are first reached.
+fn repeat_counts(tokens: &Tokens, pos: usize, inner: &Matcher) -> Result<Vec<(usize, usize)>> {
+    let n = tokens.indices.len();
+    let mut counts = vec![None; n + 1 - pos];
+    counts[0] = Some(0);
+    let mut found = vec![pos];
+    for start in pos..=n {
+        let count = match counts[start - pos] {
+            Some(count) => count + 1,
+            None => continue,
         };
-        if next.is_empty() {
-            return vec![];
+        for end in candidate_ends(tokens, start, inner)? {
+            // Zero-width repetitions would never terminate.
+            if end <= start {
+                continue;
+            }
+            match &mut counts[end - pos] {
+                Some(most) => *most = count.max(*most),
+                reached => {
+                    *reached = Some(count);
+                    found.push(end);
+                }
+            }
         }
-        indices.extend(next);
     }
-    indices
+    Ok(found
+        .into_iter()
+        .map(|end| (end, counts[end - pos].unwrap()))
+        .collect())
 }
 
-/// Match any matcher.
-fn match_any(
-    s: &str,
-    token_indices: &[usize],
-    token_types: &[TokenType],
-    matchers: &[Matcher],
-) -> Vec<usize> {
-    if token_indices.is_empty() || matchers.is_empty() {
-        return vec![];
-    }
-    let mut next = vec![];
-    let mut next_token_idx = 0;
-    while next.is_empty() {
-        if next_token_idx == token_indices.len() {
-            return vec![];
-        }
-        next = match_token(
-            s,
-            &token_indices[next_token_idx..],
-            &token_types[next_token_idx..],
-            &matchers,
-        );
-        next_token_idx += 1;
+/// Get the end positions of consecutive matches of `inner` starting at token `pos`, with the most
+/// matches up to `max` ending at each, like `repeat_counts`.
+///
+/// A position may be reached with more than `max` matches and also with fewer, so every number of
+/// matches reaching each position is kept, as a bitset.
+fn bounded_repeat_counts(
+    tokens: &Tokens,
+    pos: usize,
+    inner: &Matcher,
+    max: usize,
+) -> Result<Vec<(usize, usize)>> {
+    let n = tokens.indices.len();
+    let words = max / 64 + 1;
+    let mut counts = vec![vec![0u64; words]; n + 1 - pos];
+    counts[0][0] = 1;
+    let mut found = vec![pos];
+    for start in pos..=n {
+        // Numbers of matches with one more match, dropping those above `max`.
+        let mut next = vec![0u64; words];
+        let mut carry = 0;
+        for (word, &reached) in next.iter_mut().zip(&counts[start - pos]) {
+            *word = (reached << 1) | carry;
+            carry = reached >> 63;
+        }
+        if max % 64 < 63 {
+            next[words - 1] &= (1 << (max % 64 + 1)) - 1;
+        }
+        if next.iter().all(|&word| word == 0) {
+            continue;
+        }
+        for end in candidate_ends(tokens, start, inner)? {
+            // Zero-width repetitions would never terminate.
+            if end <= start {
+                continue;
+            }
+            let reached = &mut counts[end - pos];
+            if reached.iter().all(|&word| word == 0) {
+                found.push(end);
+            }
+            for (word, &added) in reached.iter_mut().zip(&next) {
+                *word |= added;
+            }
+        }
+    }
+    Ok(found
+        .into_iter()
+        .map(|end| {
+            let (i, word) = counts[end - pos]
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(_, &word)| word != 0)
+                .unwrap();
+            (end, i * 64 + 63 - word.leading_zeros() as usize)
+        })
+        .collect())
+}
+
+/// Get the token type matched by a single token matcher.
+fn matcher_token_type(matcher: &Matcher) -> Option<TokenType> {
+    match matcher {
+        Matcher::Number => Some(TokenType::Number),
+        Matcher::Text => Some(TokenType::Text),
+        Matcher::Punct => Some(TokenType::Punctuation),
+        _ => None,
+    }
+}
+
+/// Check if the whole string is matched by a matcher spanning multiple tokens.
+fn is_match_str(matcher: &Matcher, s: &str, locale: NumberLocale) -> bool {
+    match matcher {
+        Matcher::Any | Matcher::Repeat(..) => true,
+        Matcher::Number | Matcher::Text | Matcher::Punct => {
+            let (_, token_types) = tokenize_with_mode(s, TokenMode::Default);
+            token_types.len() == 1 && Some(token_types[0]) == matcher_token_type(matcher)
+        }
+        Matcher::Hex => is_hex(s),
+        Matcher::Decimal => is_decimal(s, locale),
+        Matcher::Ip => s.parse::<IpAddr>().is_ok(),
+        Matcher::Host => is_hostname(s),
+        Matcher::Uuid => is_uuid(s),
+        Matcher::Checksum(len) => s.len() == *len && s.chars().all(|c| c.is_ascii_hexdigit()),
     }
-    next
+}
+
+/// Check if string is a hexadecimal number, optionally prefixed with `0x`.
+fn is_hex(s: &str) -> bool {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Check if string is a decimal number, as parsed by `plain_number`.
+fn is_decimal(s: &str, locale: NumberLocale) -> bool {
+    plain_number(s, locale).is_some()
+}
+
+/// Check if string is a DNS-style hostname: dot-separated labels of ASCII letters, digits and
+/// hyphens, each at most 63 characters and neither starting nor ending with a hyphen.
+fn is_hostname(s: &str) -> bool {
+    s.len() <= 253
+        && s.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Check if string is a UUID in the canonical 8-4-4-4-12 form.
+fn is_uuid(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip(&[8, 4, 4, 4, 12])
+            .all(|(g, &len)| g.len() == len && g.chars().all(|c| c.is_ascii_hexdigit()))
 }
 
 #[cfg(test)]
@@ -158,7 +500,159 @@ mod tests {
         }
     }
 
+    match_tests!(
+        match_hex_prefixed: ("id-0x1A", &[Matcher::Any, Matcher::Hex], &[0, 3]),
+        match_hex_text: ("id-deadbeef", &[Matcher::Any, Matcher::Hex], &[0, 3]),
+        match_hex_then_any: ("0x1A.bin", &[Matcher::Hex, Matcher::Any], &[0, 4]),
+        match_decimal_fraction: ("v1.5", &[Matcher::Any, Matcher::Decimal], &[0, 1]),
+        match_decimal_signed: ("t-1.5", &[Matcher::Any, Matcher::Decimal], &[0, 1]),
+        match_decimal_separators: ("n1,234,567", &[Matcher::Any, Matcher::Decimal], &[0, 1]),
+        match_decimal_underscores: ("n1_000", &[Matcher::Any, Matcher::Decimal], &[0, 1]),
+        match_decimal_then_any: ("1.5.txt", &[Matcher::Decimal, Matcher::Any], &[0, 3]),
+        match_ipv4: ("host-192.168.0.1.log", &[Matcher::Any, Matcher::Ip, Matcher::Any], &[0, 5, 16]),
+        match_ipv6: ("fe80::1-capture", &[Matcher::Ip, Matcher::Any], &[0, 7]),
+        match_host: ("www.example.com.log", &[Matcher::Host, Matcher::Any], &[0, 15]),
+        match_host_hyphen: ("my-host.lan_01", &[Matcher::Host, Matcher::Any], &[0, 11]),
+        match_uuid: (
+            "build-123e4567-e89b-12d3-a456-426614174000.tar",
+            &[Matcher::Any, Matcher::Uuid, Matcher::Any],
+            &[0, 6, 42],
+        ),
+        match_checksum: (
+            "[Group] Show - 01 [ABCD1234].mkv",
+            &[Matcher::Any, Matcher::Checksum(8), Matcher::Any],
+            &[0, 19, 27],
+        ),
+        match_checksum_lowercase: ("a_0123456789abcdef", &[Matcher::Any, Matcher::Checksum(16)], &[0, 2]),
+        match_repeat_text: (
+            "a-b-c-001",
+            &[
+                Matcher::Repeat(Box::new(Matcher::Any), 1, None),
+                Matcher::Number,
+            ],
+            &[0, 6],
+        ),
+        match_repeat_zero: (
+            "001",
+            &[Matcher::Repeat(Box::new(Matcher::Text), 0, None), Matcher::Number],
+            &[0, 0],
+        ),
+        match_repeat_range: (
+            "a-b-c-001",
+            &[
+                Matcher::Repeat(Box::new(Matcher::Any), 2, Some(3)),
+                Matcher::Any,
+            ],
+            &[0, 3],
+        ),
+        match_repeat_trailing_zero: (
+            "a",
+            &[Matcher::Text, Matcher::Repeat(Box::new(Matcher::Number), 0, Some(1))],
+            &[0, 1],
+        ),
+        match_text_punct: ("a-1", &[Matcher::Text, Matcher::Punct, Matcher::Number], &[0, 1, 2]),
+        match_empty_matchers: ("abc", &[] as &[Matcher], &[] as &[usize]),
+        match_unicode: ("é1", &[Matcher::Any, Matcher::Number], &[0, 2]),
+        match_arabic_indic: ("ص-١٢", &[Matcher::Any, Matcher::Number], &[0, 3]),
+    );
+
+    #[test]
+    fn match_locale_decimal() {
+        let matchers = [Matcher::Any, Matcher::Decimal, Matcher::Any];
+        let de = "de".parse().unwrap();
+        assert_eq!(
+            match_all_with_locale("v1.234,5x", &matchers, TokenMode::Default, de).unwrap(),
+            vec![0, 1, 8]
+        );
+        assert_eq!(match_all("v1.234,5x", &matchers).unwrap(), vec![0, 1, 6]);
+    }
+
+    #[test]
+    fn match_repeat_long() {
+        let s = format!("{}1", "a-".repeat(2000));
+        assert_eq!(
+            match_all(
+                &s,
+                &[
+                    Matcher::Repeat(Box::new(Matcher::Any), 0, None),
+                    Matcher::Number
+                ]
+            )
+            .unwrap(),
+            vec![0, 4000]
+        );
+        assert_eq!(
+            match_all(
+                &s,
+                &[
+                    Matcher::Repeat(Box::new(Matcher::Any), 0, Some(100)),
+                    Matcher::Any
+                ]
+            )
+            .unwrap(),
+            vec![0, 100]
+        );
+    }
+
+    #[test]
+    fn match_budget() {
+        let match_with = |s: &str, matchers: &[Matcher], steps| {
+            let budget = AtomicUsize::new(steps);
+            let locale = NumberLocale::new();
+            match_all_with_budget(s, matchers, TokenMode::Default, locale, Some(&budget))
+        };
+        assert_eq!(
+            match_with("abc123", &[Matcher::Any, Matcher::Any], 100).unwrap(),
+            vec![0, 3]
+        );
+        // Backtracks through every split of the 41 tokens into 14 groups before failing.
+        let mut matchers = vec![Matcher::Any; 14];
+        matchers.push(Matcher::Number);
+        let s = vec!["a"; 21].join("-");
+        assert!(matches!(
+            match_with(&s, &matchers, 100_000),
+            Err(Error::TooComplex)
+        ));
+    }
+
+    macro_rules! is_decimal_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, locale, expected): (&str, &str, bool) = $value;
+                    assert_eq!(is_decimal(s, locale.parse().unwrap()), expected);
+                }
+            )*
+        }
+    }
+
+    is_decimal_tests!(
+        is_decimal_int: ("123", "en", true),
+        is_decimal_signed: ("-1", "en", true),
+        is_decimal_fraction: ("1.5", "en", true),
+        is_decimal_separators: ("1,234.5", "en", true),
+        is_decimal_bad_group: ("1,23", "en", false),
+        is_decimal_long_first_group: ("1234,567", "en", false),
+        is_decimal_trailing_dot: ("1.", "en", false),
+        is_decimal_sign_only: ("-", "en", false),
+        is_decimal_locale: ("1.234,5", "de", true),
+        is_decimal_locale_default: ("1,234.5", "de", true),
+        is_decimal_locale_other: ("1.234,5", "en", false),
+        is_decimal_locale_apostrophe: ("1'234.5", "ch", true),
+        is_decimal_arabic_indic: ("١٬٢٣٤", "\u{66c}\u{66b}", true),
+        is_decimal_devanagari: ("१,२३४.५", "en", true),
+    );
+
     match_fail_tests!(
+        match_repeat_too_few: ("a-1", &[Matcher::Repeat(Box::new(Matcher::Text), 2, None)]),
+        match_uuid_invalid: ("123e4567-e89b-12d3-a456", &[Matcher::Uuid]),
+        match_ip_invalid: ("1.2.3.400", &[Matcher::Ip]),
+        match_host_invalid: ("-a.com", &[Matcher::Host]),
+        match_hex_invalid: ("0xZZ", &[Matcher::Hex]),
+        match_checksum_too_short: ("a-ABCD123", &[Matcher::Any, Matcher::Checksum(8)]),
+        match_checksum_not_hex: ("a-ABCD123G", &[Matcher::Any, Matcher::Checksum(8)]),
+        match_empty_input: ("", &[Matcher::Any]),
         match_no_match: (
             "abc123def456",
             &[Matcher::Any, Matcher::Number, Matcher::Number],
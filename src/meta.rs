@@ -0,0 +1,556 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::fs::Metadata;
+use std::path::{Component, Path};
+use std::process::Command;
+use std::sync::OnceLock;
+#[cfg(unix)]
+use std::{mem, ptr};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::bucket::{TimeField, DEFAULT_TIME_FORMAT};
+use crate::replacer::ReplaceContext;
+use crate::walk;
+
+/// Field of the metadata of a file, referred to as `{%field}` in replacers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetaField {
+    /// Time of the file, formatted with a strftime-style format in the local time zone.
+    Time(TimeField, String),
+    /// Name of the nth directory containing the file, 1 being its parent.
+    Parent(usize),
+    /// Directory of the file relative to its traversal root at an index, counted from the end if
+    /// negative (e.g. `-1` for the parent).
+    PathComponent(isize),
+    /// Number of directories between the traversal root and the file.
+    Depth,
+    /// Whether the file is hidden (`true` or `false`), by its attribute on Windows or a leading
+    /// dot elsewhere.
+    AttrHidden,
+    /// Whether the file has the system attribute (`true` or `false`). Always `false` outside of
+    /// Windows.
+    AttrSystem,
+    /// Name of the user owning the file, or its ID if it has no name. Unix only.
+    Owner,
+    /// Name of the group owning the file, or its ID if it has no name. Unix only.
+    Group,
+    /// Width of the first video stream, in pixels.
+    VideoWidth,
+    /// Height of the first video stream, in pixels.
+    VideoHeight,
+    /// Duration of the media, e.g. `1h32m05s`.
+    VideoDuration,
+    /// Title embedded in a document, e.g. a PDF, with path separators replaced with underscores.
+    DocTitle,
+    /// MIME type detected from the contents, e.g. `image/png`.
+    MimeType,
+    /// Extension of the type detected from the contents, e.g. `png`.
+    MimeExt,
+}
+
+impl MetaField {
+    /// Parse a field from its name and optional argument, given after a colon.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the field. Fails if the name is unknown, or the argument is invalid.
+    pub fn parse(name: &str, arg: Option<&str>) -> Result<Self, String> {
+        let no_arg = |field: Self| match arg {
+            Some(arg) => Err(format!("unexpected argument for %{}: {}", name, arg)),
+            None => Ok(field),
+        };
+        match name {
+            "mtime" | "ctime" | "atime" => {
+                let format = arg.unwrap_or(DEFAULT_TIME_FORMAT);
+                if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                    return Err(format!("invalid time format: {}", format));
+                }
+                Ok(MetaField::Time(name.parse()?, format.to_owned()))
+            }
+            "parent" => no_arg(MetaField::Parent(1)),
+            _ if name.starts_with("parent") => match name["parent".len()..].parse() {
+                Ok(n) if n > 0 => no_arg(MetaField::Parent(n)),
+                _ => Err(format!("invalid metadata field: {}", name)),
+            },
+            "depth" => no_arg(MetaField::Depth),
+            _ if name.starts_with("path.") => match name["path.".len()..].parse() {
+                Ok(i) => no_arg(MetaField::PathComponent(i)),
+                _ => Err(format!("invalid metadata field: {}", name)),
+            },
+            "attr.hidden" => no_arg(MetaField::AttrHidden),
+            "attr.system" => no_arg(MetaField::AttrSystem),
+            "owner" => no_arg(MetaField::Owner),
+            "group" => no_arg(MetaField::Group),
+            "video.width" => no_arg(MetaField::VideoWidth),
+            "video.height" => no_arg(MetaField::VideoHeight),
+            "video.duration" => no_arg(MetaField::VideoDuration),
+            "doc.title" => no_arg(MetaField::DocTitle),
+            "mime.type" => no_arg(MetaField::MimeType),
+            "mime.ext" => no_arg(MetaField::MimeExt),
+            _ => Err(format!("invalid metadata field: {}", name)),
+        }
+    }
+
+    /// Check that the field is supported by this build.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which fails if the field requires a feature which is not enabled.
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            MetaField::VideoWidth | MetaField::VideoHeight | MetaField::VideoDuration
+                if !cfg!(feature = "media") =>
+            {
+                Err(format!(
+                    "{{{}}} requires mrf to be built with the \"media\" feature",
+                    self
+                ))
+            }
+            MetaField::DocTitle if !cfg!(feature = "doc") => Err(format!(
+                "{{{}}} requires mrf to be built with the \"doc\" feature",
+                self
+            )),
+            MetaField::Owner | MetaField::Group if !cfg!(unix) => {
+                Err(format!("{{{}}} is only available on Unix", self))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Get the value of the field for the item of a context.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the value. Fails if the context has no path, or the metadata cannot
+    /// be read.
+    pub fn resolve(&self, ctx: &ReplaceContext) -> Result<String, Box<dyn Error>> {
+        let path = ctx
+            .path
+            .as_deref()
+            .ok_or("metadata fields are only available for files")?;
+        match self {
+            MetaField::Time(field, format) => {
+                let metadata = match &ctx.metadata {
+                    Some(metadata) => metadata.clone(),
+                    None => path.metadata()?,
+                };
+                let time: DateTime<Local> = field.get(&metadata)?.into();
+                Ok(time.format(format).to_string())
+            }
+            MetaField::Parent(n) => parent_name(path, *n),
+            MetaField::PathComponent(i) => {
+                let dirs = relative_dirs(path, ctx.root.as_deref());
+                let index = if *i < 0 {
+                    dirs.len().checked_sub(i.unsigned_abs())
+                } else {
+                    Some(*i as usize)
+                };
+                index
+                    .and_then(|i| dirs.get(i))
+                    .map(|d| d.to_string())
+                    .ok_or_else(|| {
+                        format!("{} has no directory at index {}", path.display(), i).into()
+                    })
+            }
+            MetaField::Depth => Ok(relative_dirs(path, ctx.root.as_deref()).len().to_string()),
+            MetaField::AttrHidden => Ok(walk::is_hidden(path).to_string()),
+            MetaField::AttrSystem => Ok(walk::is_system(path).to_string()),
+            MetaField::Owner | MetaField::Group => {
+                let metadata = match &ctx.metadata {
+                    Some(metadata) => metadata.clone(),
+                    None => path.metadata()?,
+                };
+                owner_name(&metadata, *self == MetaField::Group)
+            }
+            MetaField::VideoWidth => Ok(ctx.cache.media(path)?.width.to_string()),
+            MetaField::VideoHeight => Ok(ctx.cache.media(path)?.height.to_string()),
+            MetaField::VideoDuration => Ok(format_duration(ctx.cache.media(path)?.duration)),
+            MetaField::DocTitle => doc_title(path),
+            MetaField::MimeType => Ok(ctx.cache.mime(path)?.0.to_owned()),
+            MetaField::MimeExt => Ok(ctx.cache.mime(path)?.1.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for MetaField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetaField::Time(field, format) => write!(f, "%{}:{}", field, format),
+            MetaField::Parent(1) => write!(f, "%parent"),
+            MetaField::Parent(n) => write!(f, "%parent{}", n),
+            MetaField::PathComponent(i) => write!(f, "%path.{}", i),
+            MetaField::Depth => write!(f, "%depth"),
+            MetaField::AttrHidden => write!(f, "%attr.hidden"),
+            MetaField::AttrSystem => write!(f, "%attr.system"),
+            MetaField::Owner => write!(f, "%owner"),
+            MetaField::Group => write!(f, "%group"),
+            MetaField::VideoWidth => write!(f, "%video.width"),
+            MetaField::VideoHeight => write!(f, "%video.height"),
+            MetaField::VideoDuration => write!(f, "%video.duration"),
+            MetaField::DocTitle => write!(f, "%doc.title"),
+            MetaField::MimeType => write!(f, "%mime.type"),
+            MetaField::MimeExt => write!(f, "%mime.ext"),
+        }
+    }
+}
+
+/// Metadata of an item read so far, shared by the fields referring to it.
+#[derive(Debug, Clone, Default)]
+pub struct MetaCache {
+    media: OnceLock<Result<MediaInfo, String>>,
+    /// MIME type and extension.
+    mime: OnceLock<Result<(&'static str, &'static str), String>>,
+}
+
+impl MetaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the media information of a file, probing it on first use.
+    fn media(&self, path: &Path) -> Result<&MediaInfo, String> {
+        self.media
+            .get_or_init(|| probe_media(path))
+            .as_ref()
+            .map_err(|e| e.clone())
+    }
+
+    /// Get the MIME type and extension of a file, sniffing its contents on first use.
+    fn mime(&self, path: &Path) -> Result<(&'static str, &'static str), String> {
+        self.mime.get_or_init(|| sniff_type(path)).clone()
+    }
+}
+
+/// Detect the type of a file from the magic bytes at its start.
+///
+/// # Returns
+///
+/// A `Result` containing the MIME type and extension. Fails if the file cannot be read, or its
+/// type is unknown.
+pub fn sniff_type(path: &Path) -> Result<(&'static str, &'static str), String> {
+    match infer::get_from_path(path) {
+        Ok(Some(t)) => Ok((t.mime_type(), t.extension())),
+        Ok(None) => Err(format!("unknown file type of {}", path.display())),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Information about a media file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaInfo {
+    pub width: u64,
+    pub height: u64,
+    /// Duration in seconds.
+    pub duration: f64,
+}
+
+#[derive(Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    width: Option<u64>,
+    height: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+/// Probe the first video stream of a file with `ffprobe`.
+///
+/// # Returns
+///
+/// A `Result` containing the media information. Fails if `ffprobe` cannot be run, or the file has
+/// no video stream.
+pub fn probe_media(path: &Path) -> Result<MediaInfo, String> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height:format=duration"])
+        .args(["-of", "json"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("failed to run ffprobe: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffprobe failed on {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse_probe(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| format!("no video stream in {}", path.display()))
+}
+
+/// Parse the JSON output of `ffprobe`.
+fn parse_probe(json: &str) -> Option<MediaInfo> {
+    let probe: Probe = serde_json::from_str(json).ok()?;
+    let stream = probe.streams.first()?;
+    Some(MediaInfo {
+        width: stream.width?,
+        height: stream.height?,
+        duration: probe
+            .format
+            .and_then(|f| f.duration)
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(0.0),
+    })
+}
+
+/// Get the name of the nth directory containing a file, 1 being its parent.
+///
+/// The directories are resolved from the absolute path of the parent, so that the parents of
+/// relative paths such as `a.txt` or `../b/a.txt` are named too.
+fn parent_name(path: &Path, n: usize) -> Result<String, Box<dyn Error>> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let dir = parent.canonicalize()?;
+    let name = dir
+        .ancestors()
+        .nth(n - 1)
+        .and_then(|d| d.file_name())
+        .ok_or_else(|| format!("{} has no directory at level {}", path.display(), n))?;
+    Ok(name.to_string_lossy().into_owned())
+}
+
+/// Get the names of the directories of a file relative to its traversal root, or to the
+/// beginning of its path if there is none.
+fn relative_dirs<'a>(path: &'a Path, root: Option<&Path>) -> Vec<Cow<'a, str>> {
+    let relative = root
+        .and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path);
+    relative
+        .parent()
+        .map(|dirs| {
+            dirs.components()
+                .filter_map(|c| match c {
+                    Component::Normal(name) => Some(name.to_string_lossy()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Get the name of the user or group owning a file, or its ID if it has no name.
+#[cfg(unix)]
+fn owner_name(metadata: &Metadata, group: bool) -> Result<String, Box<dyn Error>> {
+    use std::ffi::CStr;
+    use std::os::unix::fs::MetadataExt;
+
+    let id = if group {
+        metadata.gid()
+    } else {
+        metadata.uid()
+    };
+    let mut buf = vec![0; 1024];
+    loop {
+        // SAFETY: the entries are zero-initialized C structs, and `buf` outlives the names they
+        // point to, which are copied before it is dropped or resized.
+        let (ret, name) = unsafe {
+            if group {
+                let mut entry: libc::group = mem::zeroed();
+                let mut result = ptr::null_mut();
+                let ret =
+                    libc::getgrgid_r(id, &mut entry, buf.as_mut_ptr(), buf.len(), &mut result);
+                (
+                    ret,
+                    (!result.is_null()).then(|| CStr::from_ptr(entry.gr_name).to_owned()),
+                )
+            } else {
+                let mut entry: libc::passwd = mem::zeroed();
+                let mut result = ptr::null_mut();
+                let ret =
+                    libc::getpwuid_r(id, &mut entry, buf.as_mut_ptr(), buf.len(), &mut result);
+                (
+                    ret,
+                    (!result.is_null()).then(|| CStr::from_ptr(entry.pw_name).to_owned()),
+                )
+            }
+        };
+        match ret {
+            libc::ERANGE => buf.resize(buf.len() * 2, 0),
+            0 => {
+                return Ok(name
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| id.to_string()))
+            }
+            errno => return Err(std::io::Error::from_raw_os_error(errno).into()),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn owner_name(_metadata: &Metadata, _group: bool) -> Result<String, Box<dyn Error>> {
+    Err("owner fields are only available on Unix".into())
+}
+
+/// Read the title of a document, replacing path separators so that it is a single component.
+#[cfg(feature = "doc")]
+fn doc_title(path: &Path) -> Result<String, Box<dyn Error>> {
+    let title =
+        crate::document::title(path)?.ok_or_else(|| format!("no title in {}", path.display()))?;
+    Ok(title.replace(&['/', '\\'][..], "_"))
+}
+
+#[cfg(not(feature = "doc"))]
+fn doc_title(_path: &Path) -> Result<String, Box<dyn Error>> {
+    Err("document fields require the \"doc\" feature".into())
+}
+
+/// Format a duration in seconds as hours, minutes and seconds, e.g. `1h32m05s`, or `4m05s` if
+/// shorter than an hour.
+fn format_duration(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{}h{:02}m{:02}s", h, m, s)
+    } else {
+        format!("{}m{:02}s", m, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! parse_meta_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (name, arg, expected): (&str, Option<&str>, Option<&str>) = $value;
+                    let field = MetaField::parse(name, arg).ok().map(|f| f.to_string());
+                    assert_eq!(field.as_deref(), expected);
+                }
+            )*
+        }
+    }
+
+    parse_meta_tests!(
+        parse_meta_time: ("mtime", Some("%Y"), Some("%mtime:%Y")),
+        parse_meta_time_default: ("ctime", None, Some("%ctime:%Y-%m-%d")),
+        parse_meta_time_invalid_format: ("mtime", Some("%Q"), None),
+        parse_meta_video: ("video.height", None, Some("%video.height")),
+        parse_meta_video_arg: ("video.height", Some("x"), None),
+        parse_meta_doc: ("doc.title", None, Some("%doc.title")),
+        parse_meta_mime: ("mime.ext", None, Some("%mime.ext")),
+        parse_meta_parent: ("parent", None, Some("%parent")),
+        parse_meta_parent1: ("parent1", None, Some("%parent")),
+        parse_meta_parent2: ("parent2", None, Some("%parent2")),
+        parse_meta_parent0: ("parent0", None, None),
+        parse_meta_parent_invalid: ("parentx", None, None),
+        parse_meta_path: ("path.0", None, Some("%path.0")),
+        parse_meta_path_negative: ("path.-1", None, Some("%path.-1")),
+        parse_meta_path_invalid: ("path.x", None, None),
+        parse_meta_depth: ("depth", None, Some("%depth")),
+        parse_meta_attr_hidden: ("attr.hidden", None, Some("%attr.hidden")),
+        parse_meta_owner: ("owner", None, Some("%owner")),
+        parse_meta_group_arg: ("group", Some("x"), None),
+        parse_meta_unknown: ("size", None, None),
+    );
+
+    #[test]
+    fn parse_probe_output() {
+        let json =
+            r#"{"streams": [{"width": 1920, "height": 1080}], "format": {"duration": "5525.4"}}"#;
+        assert_eq!(
+            parse_probe(json),
+            Some(MediaInfo {
+                width: 1920,
+                height: 1080,
+                duration: 5525.4,
+            })
+        );
+        assert_eq!(parse_probe(r#"{"streams": [], "format": {}}"#), None);
+    }
+
+    #[test]
+    fn sniff_types() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let png = temp.path().join("image.jpg");
+        std::fs::write(&png, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        assert_eq!(sniff_type(&png), Ok(("image/png", "png")));
+        let text = temp.path().join("notes");
+        std::fs::write(&text, "notes").unwrap();
+        assert!(sniff_type(&text).is_err());
+    }
+
+    #[test]
+    fn resolve_parent() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let dir = temp.path().join("Artist").join("Album");
+        std::fs::create_dir_all(&dir).unwrap();
+        let ctx = ReplaceContext::new().with_path(Some(dir.join("01.flac")));
+        let parent = |n| MetaField::Parent(n).resolve(&ctx).unwrap();
+        assert_eq!(parent(1), "Album");
+        assert_eq!(parent(2), "Artist");
+        assert!(MetaField::Parent(1000).resolve(&ctx).is_err());
+    }
+
+    #[test]
+    fn resolve_path_components() {
+        let ctx = ReplaceContext::new()
+            .with_path(Some("photos/2020/trip/a.jpg".into()))
+            .with_root(Some("photos".into()));
+        let resolve = |field: MetaField| field.resolve(&ctx).ok();
+        assert_eq!(
+            resolve(MetaField::PathComponent(0)).as_deref(),
+            Some("2020")
+        );
+        assert_eq!(
+            resolve(MetaField::PathComponent(-1)).as_deref(),
+            Some("trip")
+        );
+        assert_eq!(resolve(MetaField::PathComponent(2)), None);
+        assert_eq!(resolve(MetaField::PathComponent(-3)), None);
+        assert_eq!(resolve(MetaField::Depth).as_deref(), Some("2"));
+        let ctx = ReplaceContext::new().with_path(Some("a.jpg".into()));
+        assert_eq!(MetaField::Depth.resolve(&ctx).unwrap(), "0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_owner() {
+        use std::os::unix::fs::MetadataExt;
+
+        let ctx = ReplaceContext::new().with_path(Some("Cargo.toml".into()));
+        let uid = Path::new("Cargo.toml").metadata().unwrap().uid();
+        let owner = MetaField::Owner.resolve(&ctx).unwrap();
+        assert!(!owner.is_empty());
+        if uid == 0 {
+            assert_eq!(owner, "root");
+        }
+        assert!(!MetaField::Group.resolve(&ctx).unwrap().is_empty());
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!(format_duration(5525.4), "1h32m05s");
+        assert_eq!(format_duration(245.0), "4m05s");
+        assert_eq!(format_duration(0.0), "0m00s");
+    }
+
+    #[test]
+    fn resolve_time() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let ctx = ReplaceContext::new().with_path(Some(temp.path().to_owned()));
+        let field = MetaField::parse("mtime", Some("%Y")).unwrap();
+        assert_eq!(
+            field.resolve(&ctx).unwrap(),
+            Local::now().format("%Y").to_string()
+        );
+        assert!(field.resolve(&ReplaceContext::new()).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+/// Contents of a file mapped into memory read-only, so that they are paged in as accessed instead
+/// of being read at once. On platforms other than Unix, the file is read into memory instead.
+pub struct Mmap {
+    #[cfg(unix)]
+    ptr: *mut libc::c_void,
+    #[cfg(unix)]
+    len: usize,
+    #[cfg(not(unix))]
+    data: Vec<u8>,
+}
+
+// SAFETY: the mapping is read-only and owned by `Mmap` until dropped, and `open` requires the file
+// not to change meanwhile, so the contents may be shared across threads like any `&[u8]`.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Map a file into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated by this or any other process until the `Mmap`
+    /// is dropped. The contents are handed out as `&[u8]`, which must not change while borrowed,
+    /// and accessing truncated pages raises `SIGBUS`.
+    #[cfg(unix)]
+    pub unsafe fn open(path: &Path) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            // Empty mappings are invalid.
+            return Ok(Self {
+                ptr: std::ptr::null_mut(),
+                len,
+            });
+        }
+        // SAFETY: `file` is open for reading, and the mapping is private and read-only.
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    /// Map a file into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file is read at once on this platform, so this is always safe; the function is unsafe
+    /// for the sake of the Unix version.
+    #[cfg(not(unix))]
+    pub unsafe fn open(path: &Path) -> io::Result<Self> {
+        use std::io::Read;
+        let mut data = vec![];
+        File::open(path)?.read_to_end(&mut data)?;
+        Ok(Self { data })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    #[cfg(unix)]
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: `ptr` is a readable mapping of `len` bytes until dropped.
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    #[cfg(not(unix))]
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if self.len > 0 {
+            // SAFETY: `ptr` is a mapping of `len` bytes, no longer borrowed.
+            unsafe { libc::munmap(self.ptr, self.len) };
+        }
+    }
+}
+
+/// Split the contents of a file into records, each terminated by `separator` except possibly the
+/// last. A carriage return before a newline separator is stripped.
+///
+/// # Returns
+///
+/// An iterator over the records, each a `Result` which fails if the record is not valid UTF-8.
+pub fn records(data: &[u8], separator: u8) -> impl Iterator<Item = Result<&str, String>> {
+    let data = data.strip_suffix(&[separator]).unwrap_or(data);
+    data.split(move |&b| b == separator)
+        .filter(move |_| !data.is_empty())
+        .enumerate()
+        .map(move |(i, record)| {
+            let record = match separator {
+                b'\n' => record.strip_suffix(b"\r").unwrap_or(record),
+                _ => record,
+            };
+            std::str::from_utf8(record).map_err(|_| format!("item {} is not valid UTF-8", i + 1))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn mmap_file() {
+        let temp = assert_fs::NamedTempFile::new("items").unwrap();
+        temp.write_str("a\nb\n").unwrap();
+        // SAFETY: the file is not modified while mapped.
+        assert_eq!(&*unsafe { Mmap::open(temp.path()) }.unwrap(), b"a\nb\n");
+        temp.write_str("").unwrap();
+        assert_eq!(&*unsafe { Mmap::open(temp.path()) }.unwrap(), b"");
+    }
+
+    macro_rules! records_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (data, separator, expected): (&[u8], u8, &[Result<&str, String>]) = $value;
+                    assert_eq!(records(data, separator).collect::<Vec<_>>(), expected);
+                }
+            )*
+        }
+    }
+
+    records_tests!(
+        records_lines: (b"a\r\nb\n", b'\n', &[Ok("a"), Ok("b")]),
+        records_no_trailing: (b"a\nb", b'\n', &[Ok("a"), Ok("b")]),
+        records_empty_lines: (b"a\n\nb", b'\n', &[Ok("a"), Ok(""), Ok("b")]),
+        records_nul: (b"a\nb\0c\0", b'\0', &[Ok("a\nb"), Ok("c")]),
+        records_empty: (b"", b'\n', &[]),
+        records_invalid: (b"a\n\xff", b'\n', &[Ok("a"), Err("item 2 is not valid UTF-8".to_owned())]),
+    );
+}
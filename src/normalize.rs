@@ -0,0 +1,118 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use unicode_normalization::{is_nfc, is_nfd, UnicodeNormalization};
+
+use crate::formatter::truncate_name;
+use crate::fsops::{check_name_len, name_len, split_name};
+
+/// Unicode normalization form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Canonical composition, used by most Linux software.
+    Nfc,
+    /// Canonical decomposition, used by macOS filesystems.
+    Nfd,
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nfc" => Ok(Normalization::Nfc),
+            "nfd" => Ok(Normalization::Nfd),
+            _ => Err(format!("invalid normalization form: {}", s)),
+        }
+    }
+}
+
+/// Post-processing applied to replaced strings, after the replacer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PostProcess {
+    /// Normalize to this Unicode normalization form.
+    pub normalize: Option<Normalization>,
+    /// Collapse runs of whitespace into a single space, and trim leading and trailing whitespace.
+    pub collapse_spaces: bool,
+    /// Shorten names (the last path component) too long for the filesystem of their directory,
+    /// preserving the extension.
+    pub truncate_to_fit: bool,
+}
+
+impl PostProcess {
+    pub fn new() -> Self {
+        Self {
+            normalize: None,
+            collapse_spaces: false,
+            truncate_to_fit: false,
+        }
+    }
+
+    /// Check if post-processing leaves every string as-is.
+    pub fn is_noop(&self) -> bool {
+        self.normalize.is_none() && !self.collapse_spaces && !self.truncate_to_fit
+    }
+
+    /// Post-process string.
+    ///
+    /// # Returns
+    ///
+    /// The post-processed string, borrowing from `s` if left as-is.
+    pub fn apply<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let s = match self.normalize {
+            Some(Normalization::Nfc) if !is_nfc(s) => Cow::Owned(s.nfc().collect()),
+            Some(Normalization::Nfd) if !is_nfd(s) => Cow::Owned(s.nfd().collect()),
+            _ => Cow::Borrowed(s),
+        };
+        let s = if self.collapse_spaces {
+            let collapsed = s.split_whitespace().collect::<Vec<_>>().join(" ");
+            if collapsed == s {
+                s
+            } else {
+                Cow::Owned(collapsed)
+            }
+        } else {
+            s
+        };
+        if !self.truncate_to_fit {
+            return s;
+        }
+        match check_name_len(&s) {
+            Some((_, max_len)) => {
+                let (dir, name) = split_name(&s);
+                Cow::Owned([dir, &truncate_name(name, max_len, name_len)].concat())
+            }
+            None => s,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! post_process_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (normalize, collapse_spaces, s, expected) = $value;
+                    let post_process = PostProcess {
+                        normalize,
+                        collapse_spaces,
+                        ..PostProcess::new()
+                    };
+                    assert_eq!(post_process.apply(s), expected);
+                }
+            )*
+        }
+    }
+
+    post_process_tests!(
+        post_process_none: (None, false, "a  b\u{301}", "a  b\u{301}"),
+        post_process_nfc: (Some(Normalization::Nfc), false, "e\u{301}.txt", "\u{e9}.txt"),
+        post_process_nfd: (Some(Normalization::Nfd), false, "\u{e9}.txt", "e\u{301}.txt"),
+        post_process_collapse: (None, true, " a \t b  .txt ", "a b .txt"),
+        post_process_both: (Some(Normalization::Nfc), true, "e\u{301}  a", "\u{e9} a"),
+    );
+}
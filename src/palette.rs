@@ -0,0 +1,299 @@
+use std::env;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use colored::{control::SHOULD_COLORIZE, Color, Colorize};
+
+/// Environment variable overriding the palette colors, e.g. `cyan,green,#ff8800,208`.
+pub const PALETTE_ENV: &str = "MRF_PALETTE";
+
+/// Number of colors supported by the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// The 16 basic ANSI colors.
+    Basic,
+    /// The 256-color palette.
+    Ansi256,
+    /// 24-bit colors.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Detect the color depth of the terminal from the `COLORTERM` and `TERM` environment
+    /// variables.
+    pub fn detect() -> Self {
+        Self::from_vars(
+            env::var("COLORTERM").ok().as_deref(),
+            env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    fn from_vars(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+            ColorDepth::TrueColor
+        } else if term.is_some_and(|t| t.contains("256color")) {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Basic
+        }
+    }
+}
+
+/// A palette color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteColor {
+    /// A basic ANSI color.
+    Named(Color),
+    /// A color of the 256-color palette.
+    Ansi256(u8),
+    /// A 24-bit color.
+    Rgb(u8, u8, u8),
+}
+
+impl FromStr for PaletteColor {
+    type Err = String;
+
+    /// Parse a color name (e.g. `cyan`, `bright blue`), a 256-color palette index (e.g. `208`),
+    /// or a hex RGB color (e.g. `#ff8800`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            let rgb = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .ok_or_else(|| format!("invalid color: {}", s))?;
+            return Ok(PaletteColor::Rgb(
+                (rgb >> 16) as u8,
+                (rgb >> 8) as u8,
+                rgb as u8,
+            ));
+        }
+        if let Ok(index) = s.parse::<u8>() {
+            return Ok(PaletteColor::Ansi256(index));
+        }
+        Color::from_str(s)
+            .map(PaletteColor::Named)
+            .map_err(|_| format!("invalid color: {}", s))
+    }
+}
+
+/// Colors used to highlight the groups of a match.
+///
+/// Groups beyond the palette colors are given generated colors, distinct as far as the color depth
+/// of the terminal allows, rather than cycling through the palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    colors: Vec<PaletteColor>,
+    depth: ColorDepth,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(Self::default_colors(), ColorDepth::Basic)
+    }
+}
+
+impl Palette {
+    /// Create a `Palette`.
+    ///
+    /// # Arguments
+    ///
+    /// * `colors` - Colors of the first groups.
+    /// * `depth` - Color depth used to generate colors for the remaining groups.
+    ///
+    /// # Returns
+    ///
+    /// A `Palette`.
+    pub fn new(colors: Vec<PaletteColor>, depth: ColorDepth) -> Self {
+        Self { colors, depth }
+    }
+
+    /// Default palette colors.
+    pub fn default_colors() -> Vec<PaletteColor> {
+        [
+            Color::Cyan,
+            Color::Green,
+            Color::Yellow,
+            Color::Red,
+            Color::Magenta,
+        ]
+        .iter()
+        .map(|&c| PaletteColor::Named(c))
+        .collect()
+    }
+
+    /// Create a `Palette` from the `MRF_PALETTE` environment variable (a comma-separated list of
+    /// colors), falling back to the default colors, with the detected color depth.
+    pub fn from_env() -> Result<Self, String> {
+        let colors = match env::var(PALETTE_ENV) {
+            Ok(v) => v
+                .split(',')
+                .map(PaletteColor::from_str)
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => Self::default_colors(),
+        };
+        Ok(Self::new(colors, ColorDepth::detect()))
+    }
+
+    /// Get the color of the group at index `i`.
+    pub fn color(&self, i: usize) -> PaletteColor {
+        if let Some(&c) = self.colors.get(i) {
+            return c;
+        }
+        let n = i - self.colors.len();
+        match self.depth {
+            ColorDepth::Basic => {
+                const EXTRA: [Color; 7] = [
+                    Color::BrightCyan,
+                    Color::BrightGreen,
+                    Color::BrightYellow,
+                    Color::BrightRed,
+                    Color::BrightMagenta,
+                    Color::BrightBlue,
+                    Color::Blue,
+                ];
+                let total = self.colors.len() + EXTRA.len();
+                match EXTRA.get(n) {
+                    Some(&c) => PaletteColor::Named(c),
+                    // Out of basic colors, cycle.
+                    None => self.color(i % total),
+                }
+            }
+            ColorDepth::Ansi256 => {
+                let (r, g, b) = generated_rgb(n);
+                PaletteColor::Ansi256(rgb_to_ansi256(r, g, b))
+            }
+            ColorDepth::TrueColor => {
+                let (r, g, b) = generated_rgb(n);
+                PaletteColor::Rgb(r, g, b)
+            }
+        }
+    }
+
+    /// Color string with the color of the group at index `i`.
+    pub fn paint(&self, s: &str, i: usize) -> String {
+        match self.color(i) {
+            PaletteColor::Named(c) => s.color(c).to_string(),
+            PaletteColor::Rgb(r, g, b) => s.truecolor(r, g, b).to_string(),
+            PaletteColor::Ansi256(index) => {
+                if SHOULD_COLORIZE.should_colorize() && !s.is_empty() {
+                    format!("\x1b[38;5;{}m{}\x1b[0m", index, s)
+                } else {
+                    s.to_owned()
+                }
+            }
+        }
+    }
+}
+
+/// Get the palette used for highlighting, read from the environment once.
+///
+/// An invalid `MRF_PALETTE` falls back to the default palette.
+pub fn palette() -> &'static Palette {
+    static PALETTE: OnceLock<Palette> = OnceLock::new();
+    PALETTE.get_or_init(|| {
+        Palette::from_env()
+            .unwrap_or_else(|_| Palette::new(Palette::default_colors(), ColorDepth::detect()))
+    })
+}
+
+/// Generate the `n`th color, spacing hues by the golden angle so that consecutive colors are
+/// distinct.
+fn generated_rgb(n: usize) -> (u8, u8, u8) {
+    let hue = (n as f64 * 137.508 + 200.0) % 360.0;
+    hsv_to_rgb(hue, 0.6, 0.95)
+}
+
+/// Convert HSV (hue in degrees, saturation and value in 0-1) to RGB.
+fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (u8, u8, u8) {
+    let c = v * s;
+    let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+    let m = v - c;
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let to_u8 = |f: f64| ((f + m) * 255.0).round() as u8;
+    (to_u8(r), to_u8(g), to_u8(b))
+}
+
+/// Convert RGB to the nearest color of the 6x6x6 cube of the 256-color palette.
+fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let to_6 = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * to_6(r) + 6 * to_6(g) + to_6(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! parse_color_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected) = $value;
+                    assert_eq!(PaletteColor::from_str(s), expected);
+                }
+            )*
+        }
+    }
+
+    parse_color_tests!(
+        parse_color_named: ("cyan", Ok(PaletteColor::Named(Color::Cyan))),
+        parse_color_bright: ("bright blue", Ok(PaletteColor::Named(Color::BrightBlue))),
+        parse_color_index: ("208", Ok(PaletteColor::Ansi256(208))),
+        parse_color_hex: ("#ff8800", Ok(PaletteColor::Rgb(255, 136, 0))),
+        parse_color_bad_hex: ("#ff88", Err("invalid color: #ff88".to_owned())),
+        parse_color_bad_name: ("nope", Err("invalid color: nope".to_owned())),
+    );
+
+    macro_rules! detect_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (colorterm, term, expected) = $value;
+                    assert_eq!(ColorDepth::from_vars(colorterm, term), expected);
+                }
+            )*
+        }
+    }
+
+    detect_tests!(
+        detect_truecolor: (Some("truecolor"), Some("xterm"), ColorDepth::TrueColor),
+        detect_256: (None, Some("xterm-256color"), ColorDepth::Ansi256),
+        detect_basic: (None, Some("xterm"), ColorDepth::Basic),
+        detect_none: (None, None, ColorDepth::Basic),
+    );
+
+    #[test]
+    fn color_palette() {
+        let palette = Palette::default();
+        assert_eq!(palette.color(0), PaletteColor::Named(Color::Cyan));
+        assert_eq!(palette.color(5), PaletteColor::Named(Color::BrightCyan));
+        assert_eq!(palette.color(12), palette.color(0));
+    }
+
+    #[test]
+    fn color_generated_distinct() {
+        for depth in &[ColorDepth::Ansi256, ColorDepth::TrueColor] {
+            let palette = Palette::new(vec![], *depth);
+            let colors: Vec<PaletteColor> = (0..12).map(|i| palette.color(i)).collect();
+            for (i, c) in colors.iter().enumerate() {
+                assert!(!colors[..i].contains(c), "{:?} repeats at {}", c, i);
+            }
+        }
+    }
+
+    #[test]
+    fn rgb_to_ansi256_corners() {
+        assert_eq!(rgb_to_ansi256(0, 0, 0), 16);
+        assert_eq!(rgb_to_ansi256(255, 255, 255), 231);
+        assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
+    }
+}
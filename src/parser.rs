@@ -3,16 +3,31 @@ use std::str::FromStr;
 
 use nom::{
     branch::alt,
-    bytes::complete::{escaped, is_not},
-    character::complete::{char, digit1, one_of},
-    combinator::{all_consuming, map, map_res, opt, peek, verify},
-    error::{convert_error, ParseError, VerboseError},
+    bytes::complete::{escaped, is_not, tag, take_while1},
+    character::complete::{char, digit1, one_of, space0},
+    combinator::{all_consuming, cut, map, map_res, opt, peek, verify},
+    error::{context, convert_error, ParseError, VerboseError},
     multi::many0,
-    sequence::{delimited, preceded},
+    sequence::{delimited, pair, preceded},
     Err, IResult,
 };
 
-use crate::{elem::Elem, formatter::Formatter, matcher::Matcher, spec::Spec};
+use crate::{
+    elem::Elem,
+    formatter::{Conversion, Formatter},
+    matcher::Matcher,
+    meta::MetaField,
+    spec::Spec,
+};
+
+/// Maximum specifier index.
+pub const MAX_INDEX: usize = 1024;
+
+/// Maximum format specifier width.
+pub const MAX_WIDTH: usize = 1024;
+
+/// Maximum number of decimal places in format specifiers.
+pub const MAX_PRECISION: usize = 64;
 
 #[derive(Debug, Clone)]
 pub struct Error {
@@ -51,6 +66,172 @@ pub fn parse<'a>(s: &'a str) -> Result<Vec<Elem>, Error> {
     }
 }
 
+/// Replacer syntax, determining how specifiers are delimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// Specifiers are surrounded by curly braces (`{n}`).
+    #[default]
+    Braces,
+    /// Specifiers are surrounded by angle brackets (`<n>`).
+    Angle,
+    /// Specifiers are surrounded by curly braces preceded by a percent sign (`%{n}`).
+    Percent,
+}
+
+impl FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "braces" => Ok(Syntax::Braces),
+            "angle" => Ok(Syntax::Angle),
+            "percent" => Ok(Syntax::Percent),
+            _ => Err(format!("invalid syntax: {}", s)),
+        }
+    }
+}
+
+/// Parse elements written in the given syntax.
+///
+/// # Arguments
+///
+/// * `s` - String slice to parse.
+/// * `syntax` - Syntax of the string.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of elements parsed from the string.
+pub fn parse_with_syntax(s: &str, syntax: Syntax) -> Result<Vec<Elem>, Error> {
+    match syntax {
+        Syntax::Braces => parse(s),
+        _ => parse(&to_braces(s, syntax)),
+    }
+}
+
+/// Parse elements of a raw replacer, in which only counters (`{#}`) and metadata fields
+/// (`{%mtime}`) are specifiers. Everything else is a literal, including other specifiers and
+/// backslashes.
+///
+/// # Arguments
+///
+/// * `s` - String slice to parse.
+/// * `syntax` - Syntax of the counters and metadata fields.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of elements parsed from the string.
+pub fn parse_raw(s: &str, syntax: Syntax) -> Result<Vec<Elem>, Error> {
+    let (open, close) = match syntax {
+        Syntax::Braces => ("{", '}'),
+        Syntax::Angle => ("<", '>'),
+        Syntax::Percent => ("%{", '}'),
+    };
+    let is_raw_spec = |rest: &str| {
+        rest.strip_prefix(open)
+            .map(|inner| inner.trim_start_matches(' '))
+            .is_some_and(|inner| inner.starts_with('#') || inner.starts_with('%'))
+    };
+    let mut elems = vec![];
+    let mut lit = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find(open) {
+        if !is_raw_spec(&rest[start..]) {
+            lit.push_str(&rest[..start + open.len()]);
+            rest = &rest[start + open.len()..];
+            continue;
+        }
+        let end = match rest[start..].find(close) {
+            Some(end) => start + end + close.len_utf8(),
+            None => {
+                return Err(Error {
+                    msg: format!("unterminated specifier: {}", &rest[start..]),
+                })
+            }
+        };
+        let spec = match parse_with_syntax(&rest[start..end], syntax)?.pop() {
+            Some(elem @ Elem::Counter(_)) | Some(elem @ Elem::Meta(_)) => elem,
+            _ => {
+                return Err(Error {
+                    msg: format!("invalid specifier: {}", &rest[start..end]),
+                })
+            }
+        };
+        lit.push_str(&rest[..start]);
+        if !lit.is_empty() {
+            elems.push(Elem::Lit(std::mem::take(&mut lit)));
+        }
+        elems.push(spec);
+        rest = &rest[end..];
+    }
+    lit.push_str(rest);
+    if !lit.is_empty() {
+        elems.push(Elem::Lit(lit));
+    }
+    Ok(elems)
+}
+
+/// Translate a string written in an alternative syntax to the curly braces syntax.
+///
+/// Outside specifiers, curly braces are literals, and a backslash may be used to escape the
+/// opening delimiter and itself. Inside specifiers, the contents are kept as-is, except that in
+/// the angle syntax, closing curly braces other than those of quantifiers are escaped, and `\>`
+/// is unescaped.
+fn to_braces(s: &str, syntax: Syntax) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    let mut in_spec = false;
+    // Depth of curly braces inside the current specifier.
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match (in_spec, c) {
+            (false, '\\') => match chars.next() {
+                Some('\\') => out.push_str("\\\\"),
+                Some(c) => out.push(c),
+                None => out.push_str("\\\\"),
+            },
+            (false, '{') => out.push_str("\\{"),
+            (false, '}') => out.push_str("\\}"),
+            (false, '<') if syntax == Syntax::Angle => {
+                out.push('{');
+                in_spec = true;
+            }
+            (false, '%') if syntax == Syntax::Percent && chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+                in_spec = true;
+            }
+            (false, c) => out.push(c),
+            (true, '\\') => match chars.next() {
+                Some('>') if syntax == Syntax::Angle => out.push('>'),
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => out.push('\\'),
+            },
+            (true, '{') => {
+                depth += 1;
+                out.push('{');
+            }
+            (true, '}') if depth > 0 => {
+                depth -= 1;
+                out.push('}');
+            }
+            (true, '}') if syntax == Syntax::Angle => out.push_str("\\}"),
+            (true, '>') if syntax == Syntax::Angle => {
+                out.push('}');
+                in_spec = false;
+            }
+            (true, '}') => {
+                out.push('}');
+                in_spec = false;
+            }
+            (true, c) => out.push(c),
+        }
+    }
+    out
+}
+
 /// Parse root.
 ///
 /// Begin parsing from here.
@@ -60,9 +241,10 @@ fn root<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Vec<Elem>, E
 
 /// Parse an element.
 ///
-/// An element could be a "literal" (`Elem::Lit`) or a "specifier" (`Elem::Spec`).
+/// An element could be a "literal" (`Elem::Lit`), a "counter" (`Elem::Counter`), a "metadata
+/// field" (`Elem::Meta`) or a "specifier" (`Elem::Spec`).
 fn elem<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Elem, E> {
-    alt((elem_lit, elem_spec))(s)
+    alt((elem_lit, elem_counter, elem_meta, elem_spec))(s)
 }
 
 /// Parse a literal element.
@@ -91,6 +273,45 @@ fn unescape_lit(s: &str) -> String {
         .replace("\\\\", "\\")
 }
 
+/// Parse a counter element.
+///
+/// A counter is a hash sign (`#`) surrounded by curly braces, optionally followed by a format
+/// specifier preceded by a colon (`{#}`, `{#:03}`).
+fn elem_counter<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Elem, E> {
+    map(
+        delimited(
+            pair(char('{'), delimited(space0, char('#'), space0)),
+            opt(preceded(char(':'), spec_formatter)),
+            char('}'),
+        ),
+        Elem::Counter,
+    )(s)
+}
+
+/// Parse a metadata element.
+///
+/// A metadata element is a percent sign (`%`) followed by a field name, optionally followed by an
+/// argument preceded by a colon, surrounded by curly braces (`{%mtime}`, `{%mtime:%Y}`).
+fn elem_meta<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Elem, E> {
+    map(
+        delimited(
+            pair(char('{'), pair(space0, char('%'))),
+            cut(context(
+                "metadata field",
+                map_res(
+                    pair(
+                        take_while1(|c: char| c.is_alphanumeric() || "._-".contains(c)),
+                        opt(preceded(char(':'), is_not("}"))),
+                    ),
+                    |(name, arg)| MetaField::parse(name, arg),
+                ),
+            )),
+            pair(space0, char('}')),
+        ),
+        Elem::Meta,
+    )(s)
+}
+
 /// Parse a specifier element.
 ///
 /// A specifier is surrounded by curly braces (`{<specifier>}`).
@@ -102,12 +323,14 @@ fn elem_spec<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Elem, E
 ///
 /// A specifier consists of 4 optional parts:
 /// 1. A matcher.
-/// 2. An index.
+/// 2. An index, at most `MAX_INDEX`.
 /// 3. A replacement string, preceded by an equal sign (`=`).
 /// 4. A format specifier, preceded by a colon (`:`).
 fn spec<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Spec, E> {
     let (s, matcher) = spec_matcher(s)?;
-    let (s, index) = opt(map_res(digit1, usize::from_str))(s)?;
+    let (s, index) = opt(verify(map_res(digit1, usize::from_str), |&i| {
+        i <= MAX_INDEX
+    }))(s)?;
     let (s, replace) = opt(preceded(char('='), spec_replace))(s)?;
     let (s, formatter) = opt(preceded(char(':'), spec_formatter))(s)?;
     Ok((
@@ -125,19 +348,104 @@ fn spec<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Spec, E> {
 ///
 /// A matcher is specified at the beginning of a specifier, until a digit (which indicates the
 /// beginning of the index), an equal sign (which indicates the beginning of the replace string),
-/// a colon (which indicates the beginning of the format string), or a closing curly brace (which
-/// indicates the end of the specifier) is met.
+/// a colon (which indicates the beginning of the format string), a quantifier, or a closing curly
+/// brace (which indicates the end of the specifier) is met.
 ///
 /// One of the following is accepted:
 /// * `"n"` - A `Number` matcher.
+/// * `"t"` - A `Text` matcher.
+/// * `"p"` - A `Punct` matcher.
+/// * `"x"` - A `Hex` matcher.
+/// * `"d"` - A `Decimal` matcher.
+/// * `"ip"` - An `Ip` matcher.
+/// * `"host"` - A `Host` matcher.
+/// * `"uuid"` - A `Uuid` matcher.
+/// * `"crc32"`, `"md5"`, `"sha1"` or `"sha256"` - A `Checksum` matcher of the length of the
+///   algorithm's digest, optionally followed by a dot (`.`) and a shorter length (see
+///   `spec_checksum`).
 /// * `""` (Blank) - An `Any` matcher.
+///
+/// The matcher may be followed by a quantifier (see `spec_quantifier`).
 fn spec_matcher<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Matcher, E> {
-    map(opt(is_not("0123456789=:}")), |m: Option<&str>| {
-        match &m.unwrap_or("").trim()[..] {
+    let (s, matcher) = alt((spec_checksum, spec_matcher_name))(s)?;
+    let (s, quantifier) = opt(spec_quantifier)(s)?;
+    let (s, _) = space0(s)?;
+    Ok((
+        s,
+        match quantifier {
+            Some((min, max)) => Matcher::Repeat(Box::new(matcher), min, max),
+            None => matcher,
+        },
+    ))
+}
+
+/// Parse a checksum matcher, e.g. `crc32` (8 hexadecimal digits) or `sha1.8` (the first 8 of 40).
+fn spec_checksum<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Matcher, E> {
+    let (s, _) = space0(s)?;
+    let (s, full) = alt((
+        map(tag("crc32"), |_| 8),
+        map(tag("md5"), |_| 32),
+        map(tag("sha1"), |_| 40),
+        map(tag("sha256"), |_| 64),
+    ))(s)?;
+    let (s, len) = opt(preceded(
+        char('.'),
+        verify(map_res(digit1, usize::from_str), |&len| {
+            len > 0 && len <= full
+        }),
+    ))(s)?;
+    Ok((s, Matcher::Checksum(len.unwrap_or(full))))
+}
+
+/// Parse a matcher name.
+fn spec_matcher_name<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Matcher, E> {
+    map(
+        opt(is_not("0123456789=:}*+?{")),
+        |m: Option<&str>| match m.unwrap_or("").trim() {
             "n" => Matcher::Number,
+            "t" => Matcher::Text,
+            "p" => Matcher::Punct,
+            "x" => Matcher::Hex,
+            "d" => Matcher::Decimal,
+            "ip" => Matcher::Ip,
+            "host" => Matcher::Host,
+            "uuid" => Matcher::Uuid,
             _ => Matcher::Any,
-        }
-    })(s)
+        },
+    )(s)
+}
+
+/// Parse a quantifier, returning the minimum and optional maximum number of repetitions.
+///
+/// One of the following is accepted:
+/// * `"*"` - Zero or more.
+/// * `"+"` - One or more.
+/// * `"?"` - Zero or one.
+/// * `"{m}"` - Exactly `m`.
+/// * `"{m,}"` - At least `m`.
+/// * `"{m,n}"` - Between `m` and `n` (inclusive).
+fn spec_quantifier<'a, E: ParseError<&'a str>>(
+    s: &'a str,
+) -> IResult<&'a str, (usize, Option<usize>), E> {
+    alt((
+        map(char('*'), |_| (0, None)),
+        map(char('+'), |_| (1, None)),
+        map(char('?'), |_| (0, Some(1))),
+        map(
+            delimited(
+                char('{'),
+                pair(
+                    map_res(digit1, usize::from_str),
+                    opt(preceded(char(','), opt(map_res(digit1, usize::from_str)))),
+                ),
+                char('}'),
+            ),
+            |(min, max)| match max {
+                Some(max) => (min, max),
+                None => (min, Some(min)),
+            },
+        ),
+    ))(s)
 }
 
 /// Parse a replacement string.
@@ -165,17 +473,43 @@ fn unescape_replace(s: &str) -> String {
 
 /// Parse a format specifier.
 ///
+/// A format specifier consists of 4 optional parts:
+/// 1. A zero (`0`), to pad with zeros instead of spaces.
+/// 2. A width, at most `MAX_WIDTH`.
+/// 3. A comma (`,`), to separate thousands of numbers.
+/// 4. A precision, i.e. the number of decimal places of numbers, at most `MAX_PRECISION`,
+///    preceded by a dot (`.`).
+///
+/// Instead of the separators and precision, a conversion may be specified: `ordinal` formats
+/// integers as ordinals (`1st`), `si` scales numbers with SI prefixes (`1.4G`), and `bytes`
+/// expands such numbers (`1400000000`), and `titlecase` capitalizes words except stop words.
+///
 /// A format specifier ends when a closing curly brace is met.
 fn spec_formatter<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Formatter, E> {
     let (s, fill) = opt(char('0'))(s)?;
-    let (s, width) = opt(digit1)(s)?;
+    let (s, width) = opt(verify(map_res(digit1, usize::from_str), |&w| {
+        w <= MAX_WIDTH
+    }))(s)?;
+    let formatter = Formatter::with_width(width.unwrap_or(0), fill.unwrap_or(' '));
+    let (s, conversion) = opt(map_res(
+        alt((tag("ordinal"), tag("si"), tag("bytes"), tag("titlecase"))),
+        Conversion::from_str,
+    ))(s)?;
+    if conversion.is_some() {
+        let (s, _) = peek(char('}'))(s)?;
+        return Ok((s, formatter.with_conversion(conversion)));
+    }
+    let (s, grouping) = opt(char(','))(s)?;
+    let (s, precision) = opt(preceded(
+        char('.'),
+        verify(map_res(digit1, usize::from_str), |&p| p <= MAX_PRECISION),
+    ))(s)?;
     let (s, _) = peek(char('}'))(s)?;
     Ok((
         s,
-        Formatter::with_width(
-            width.map(|w| w.parse::<usize>().unwrap()).unwrap_or(0),
-            fill.unwrap_or(' '),
-        ),
+        formatter
+            .with_grouping(grouping.is_some())
+            .with_precision(precision),
     ))
 }
 
@@ -202,6 +536,34 @@ mod tests {
         parse_literal: ("abc", &[Elem::Lit("abc".to_owned())]),
         parse_any: ("{}", &[Elem::Spec(Spec::new(Matcher::Any))]),
         parse_number: ("{n}", &[Elem::Spec(Spec::new(Matcher::Number))]),
+        parse_hex: ("{x}", &[Elem::Spec(Spec::new(Matcher::Hex))]),
+        parse_decimal: ("{d}", &[Elem::Spec(Spec::new(Matcher::Decimal))]),
+        parse_ip: ("{ip}", &[Elem::Spec(Spec::new(Matcher::Ip))]),
+        parse_host: ("{ host }", &[Elem::Spec(Spec::new(Matcher::Host))]),
+        parse_uuid: ("{uuid}", &[Elem::Spec(Spec::new(Matcher::Uuid))]),
+        parse_text: ("{t}", &[Elem::Spec(Spec::new(Matcher::Text))]),
+        parse_punct: ("{p}", &[Elem::Spec(Spec::new(Matcher::Punct))]),
+        parse_star: ("{t*}", &[Elem::Spec(Spec::new(
+            Matcher::Repeat(Box::new(Matcher::Text), 0, None),
+        ))]),
+        parse_plus: ("{ n+ }", &[Elem::Spec(Spec::new(
+            Matcher::Repeat(Box::new(Matcher::Number), 1, None),
+        ))]),
+        parse_optional: ("{?}", &[Elem::Spec(Spec::new(
+            Matcher::Repeat(Box::new(Matcher::Any), 0, Some(1)),
+        ))]),
+        parse_exact: ("{p{2}}", &[Elem::Spec(Spec::new(
+            Matcher::Repeat(Box::new(Matcher::Punct), 2, Some(2)),
+        ))]),
+        parse_at_least: ("{p{2,}}", &[Elem::Spec(Spec::new(
+            Matcher::Repeat(Box::new(Matcher::Punct), 2, None),
+        ))]),
+        parse_range: ("{p{2,3}1=a}", &[Elem::Spec(Spec {
+            matcher: Matcher::Repeat(Box::new(Matcher::Punct), 2, Some(3)),
+            index: Some(1),
+            replace: Some("a".to_owned()),
+            formatter: None,
+        })]),
         parse_ignore_ws: ("{ }", &[Elem::Spec(Spec::new(Matcher::Any))]),
         parse_ignore_ws_number: ("{ n }", &[Elem::Spec(Spec::new(Matcher::Number))]),
         parse_prefix_any: ("abc-{}", &[
@@ -338,6 +700,69 @@ mod tests {
             ],
         ),
 
+        parse_format_precision: (
+            "{d:8,.2}",
+            &[
+                Elem::Spec(Spec {
+                    matcher: Matcher::Decimal,
+                    index: None,
+                    replace: None,
+                    formatter: Some(
+                        Formatter::with_width(8, ' ')
+                            .with_grouping(true)
+                            .with_precision(Some(2))
+                    ),
+                }),
+            ],
+        ),
+
+        parse_format_ordinal: (
+            "{n:4ordinal}",
+            &[
+                Elem::Spec(Spec {
+                    matcher: Matcher::Number,
+                    index: None,
+                    replace: None,
+                    formatter: Some(
+                        Formatter::with_width(4, ' ').with_conversion(Some(Conversion::Ordinal))
+                    ),
+                }),
+            ],
+        ),
+
+        parse_format_si: (
+            "{:si}",
+            &[
+                Elem::Spec(Spec {
+                    matcher: Matcher::Any,
+                    index: None,
+                    replace: None,
+                    formatter: Some(Formatter::new().with_conversion(Some(Conversion::Si))),
+                }),
+            ],
+        ),
+
+        parse_checksum: (
+            "{crc32}{ sha1.8 }{md5{2}}",
+            &[
+                Elem::Spec(Spec::new(Matcher::Checksum(8))),
+                Elem::Spec(Spec::new(Matcher::Checksum(8))),
+                Elem::Spec(Spec::new(Matcher::Repeat(Box::new(Matcher::Checksum(32)), 2, Some(2)))),
+            ],
+        ),
+
+        parse_checksum_index: (
+            "{sha2561}",
+            &[
+                Elem::Spec(Spec {
+                    matcher: Matcher::Checksum(64),
+                    index: Some(1),
+                    replace: None,
+                    formatter: None,
+                }),
+            ],
+        ),
+
         parse_index_replace_format: (
             "{n1=1:04}",
             &[
@@ -351,8 +776,104 @@ mod tests {
         ),
     );
 
+    parse_tests!(
+        parse_counter: ("{#}", &[Elem::Counter(None)]),
+        parse_counter_ws: ("{ # }", &[Elem::Counter(None)]),
+        parse_counter_format: ("{#:03}", &[Elem::Counter(Some(Formatter::with_width(3, '0')))]),
+        parse_counter_format_grouping: (
+            "{#:,}",
+            &[Elem::Counter(Some(Formatter::new().with_grouping(true)))]
+        ),
+        parse_meta: (
+            "{%mtime:%Y}-{ %video.height }",
+            &[
+                Elem::Meta(MetaField::parse("mtime", Some("%Y")).unwrap()),
+                Elem::Lit("-".to_owned()),
+                Elem::Meta(MetaField::VideoHeight),
+            ]
+        ),
+        parse_counter_prefix: ("a{#}", &[
+            Elem::Lit("a".to_owned()),
+            Elem::Counter(None),
+        ]),
+    );
+
     #[test]
     fn parse_incomplete() {
         assert!(parse("{").is_err());
     }
+
+    macro_rules! to_braces_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, syntax, expected) = $value;
+                    assert_eq!(to_braces(s, syntax), expected);
+                }
+            )*
+        }
+    }
+
+    to_braces_tests!(
+        to_braces_angle: ("<>_<n:03>", Syntax::Angle, "{}_{n:03}"),
+        to_braces_angle_literal_braces: ("{a}<>", Syntax::Angle, r"\{a\}{}"),
+        to_braces_angle_escape: (r"\<<=\>>\\", Syntax::Angle, r"<{=>}\\"),
+        to_braces_angle_quantifier: ("<p{2,3}=}>", Syntax::Angle, r"{p{2,3}=\}}"),
+        to_braces_angle_counter: ("<#:03>", Syntax::Angle, "{#:03}"),
+        to_braces_percent: ("%{}-%{n{2}}%", Syntax::Percent, "{}-{n{2}}%"),
+        to_braces_percent_literal_braces: ("{%{}}", Syntax::Percent, r"\{{}\}"),
+    );
+
+    #[test]
+    fn parse_out_of_bounds() {
+        for s in &[
+            "{1025}",
+            "{99999999999999999999999}",
+            "{:1025}",
+            "{#:099999999999999999999999}",
+            "{:.65}",
+            "{:.}",
+            "{:ordinal,}",
+            "{:si.2}",
+            "{%size}",
+            "{%mtime:%Q}",
+        ] {
+            assert!(parse(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_raw_literals() {
+        assert_eq!(
+            parse_raw(r"a{}-\{n}{#}", Syntax::Braces).unwrap(),
+            vec![Elem::Lit(r"a{}-\{n}".to_owned()), Elem::Counter(None),]
+        );
+        assert_eq!(
+            parse_raw("{ #:03}.bin{%parent}", Syntax::Braces).unwrap(),
+            parse("{#:03}.bin{%parent}").unwrap()
+        );
+        assert_eq!(
+            parse_raw("{}<#>%", Syntax::Angle).unwrap(),
+            vec![
+                Elem::Lit("{}".to_owned()),
+                Elem::Counter(None),
+                Elem::Lit("%".to_owned())
+            ]
+        );
+        assert_eq!(
+            parse_raw("{#}%{#}", Syntax::Percent).unwrap(),
+            vec![Elem::Lit("{#}".to_owned()), Elem::Counter(None)]
+        );
+        assert!(parse_raw("a{#", Syntax::Braces).is_err());
+        assert!(parse_raw("{%size}", Syntax::Braces).is_err());
+    }
+
+    #[test]
+    fn parse_with_syntax_angle() {
+        assert_eq!(
+            parse_with_syntax("a<n>", Syntax::Angle).unwrap(),
+            parse("a{n}").unwrap()
+        );
+    }
 }
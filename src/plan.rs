@@ -0,0 +1,238 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::replacement::Replacement;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid JSON plan.
+    Json(serde_json::Error),
+    /// Invalid CSV plan.
+    Csv(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Json(e) => write!(f, "invalid plan: {}", e),
+            Error::Csv(msg) => write!(f, "invalid plan: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Plan file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A JSON array of objects with `left` and `right` fields.
+    Json,
+    /// CSV with a `left,right` header.
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("invalid plan format: {}", s)),
+        }
+    }
+}
+
+impl Format {
+    /// Infer format from path extension, defaulting to JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::Json,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<'a> {
+    left: Cow<'a, str>,
+    right: Cow<'a, str>,
+}
+
+/// Serialize replacements into a plan.
+///
+/// # Arguments
+///
+/// * `replacements` - Replacements.
+/// * `format` - Plan format.
+///
+/// # Returns
+///
+/// The plan string.
+pub fn to_string(replacements: &[Replacement], format: Format) -> String {
+    match format {
+        Format::Json => {
+            let entries: Vec<Entry> = replacements
+                .iter()
+                .map(|(left, right)| Entry {
+                    left: Cow::Borrowed(left),
+                    right: Cow::Borrowed(right),
+                })
+                .collect();
+            // Serializing strings cannot fail.
+            serde_json::to_string_pretty(&entries).unwrap() + "\n"
+        }
+        Format::Csv => {
+            let mut s = "left,right\n".to_owned();
+            for (left, right) in replacements {
+                s.push_str(&format!("{},{}\n", quote_csv(left), quote_csv(right)));
+            }
+            s
+        }
+    }
+}
+
+/// Deserialize replacements from a plan.
+///
+/// # Arguments
+///
+/// * `s` - Plan string.
+/// * `format` - Plan format.
+///
+/// # Returns
+///
+/// A `Result` containing the replacements.
+pub fn from_str(s: &str, format: Format) -> Result<Vec<Replacement<'static>>, Error> {
+    match format {
+        Format::Json => {
+            let entries: Vec<Entry> = serde_json::from_str(s).map_err(Error::Json)?;
+            Ok(entries
+                .into_iter()
+                .map(|e| (Cow::Owned(e.left.into_owned()), e.right.into_owned()))
+                .collect())
+        }
+        Format::Csv => {
+            let mut records = parse_csv(s)?.into_iter();
+            match records.next() {
+                Some(header) if header == ["left", "right"] => {}
+                _ => return Err(Error::Csv("expected header \"left,right\"".to_owned())),
+            }
+            records
+                .enumerate()
+                .map(|(i, record)| match <[String; 2]>::try_from(record) {
+                    Ok([left, right]) => Ok((Cow::Owned(left), right)),
+                    Err(_) => Err(Error::Csv(format!("expected 2 fields on row {}", i + 2))),
+                })
+                .collect()
+        }
+    }
+}
+
+/// Quote CSV field if it contains special characters.
+pub(crate) fn quote_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Parse CSV records. Empty lines are skipped.
+pub(crate) fn parse_csv(s: &str) -> Result<Vec<Vec<String>>, Error> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                if !record.is_empty() || !field.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(Error::Csv("unterminated quoted field".to_owned()));
+    }
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! round_trip_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (format, replacements): (Format, &[(&str, &str)]) = $value;
+                    let replacements: Vec<Replacement> = replacements
+                        .iter()
+                        .map(|(l, r)| (Cow::Borrowed(*l), r.to_string()))
+                        .collect();
+                    assert_eq!(
+                        from_str(&to_string(&replacements, format), format).unwrap(),
+                        replacements
+                    );
+                }
+            )*
+        }
+    }
+
+    round_trip_tests!(
+        round_trip_json: (Format::Json, &[("a-1", "a_1"), ("b \"2\"", "b\n2")]),
+        round_trip_json_empty: (Format::Json, &[]),
+        round_trip_csv: (Format::Csv, &[("a-1", "a_1"), ("b,\"2\"", "b\r\n2")]),
+        round_trip_csv_empty: (Format::Csv, &[]),
+    );
+
+    #[test]
+    fn to_string_csv() {
+        assert_eq!(
+            to_string(&[(Cow::Borrowed("a,1"), "a_1".to_owned())], Format::Csv),
+            "left,right\n\"a,1\",a_1\n"
+        );
+    }
+
+    macro_rules! from_str_fail_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, format) = $value;
+                    assert!(from_str(s, format).is_err());
+                }
+            )*
+        }
+    }
+
+    from_str_fail_tests!(
+        from_str_fail_json: ("[{\"left\": \"a\"}]", Format::Json),
+        from_str_fail_csv_header: ("a,b\n", Format::Csv),
+        from_str_fail_csv_fields: ("left,right\na\n", Format::Csv),
+        from_str_fail_csv_unterminated: ("left,right\n\"a,b\n", Format::Csv),
+    );
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+use std::str::FromStr;
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, digit1, multispace0},
+    combinator::{all_consuming, map, map_res},
+    error::{convert_error, ParseError, VerboseError},
+    multi::separated_nonempty_list,
+    sequence::{delimited, preceded, tuple},
+    Err, IResult,
+};
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    msg: String,
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+/// Predicate over matched groups, e.g. `n1 >= 100 && n1 < 200`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predicate {
+    /// Both predicates hold.
+    And(Box<Predicate>, Box<Predicate>),
+    /// Either predicate holds.
+    Or(Box<Predicate>, Box<Predicate>),
+    /// Predicate does not hold.
+    Not(Box<Predicate>),
+    /// Comparison between two operands.
+    Cmp(Operand, CmpOp, Operand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// Numeric value of the match at index (1-based), written `n<index>`.
+    Group(usize),
+    /// Number literal.
+    Number(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Predicate {
+    /// Evaluate predicate against matched groups.
+    ///
+    /// # Arguments
+    ///
+    /// * `groups` - Matched groups, the first group being referred to as `n1`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the predicate holds. A comparison involving a group which does not exist or is
+    /// not a number does not hold.
+    pub fn eval(&self, groups: &[&str]) -> bool {
+        match self {
+            Predicate::And(a, b) => a.eval(groups) && b.eval(groups),
+            Predicate::Or(a, b) => a.eval(groups) || b.eval(groups),
+            Predicate::Not(p) => !p.eval(groups),
+            Predicate::Cmp(a, op, b) => match (a.value(groups), b.value(groups)) {
+                (Some(a), Some(b)) => match op {
+                    CmpOp::Eq => a == b,
+                    CmpOp::Ne => a != b,
+                    CmpOp::Lt => a < b,
+                    CmpOp::Le => a <= b,
+                    CmpOp::Gt => a > b,
+                    CmpOp::Ge => a >= b,
+                },
+                _ => false,
+            },
+        }
+    }
+}
+
+impl Operand {
+    /// Get value of operand given matched groups.
+    fn value(&self, groups: &[&str]) -> Option<i64> {
+        match self {
+            Operand::Group(i) => groups.get(i.checked_sub(1)?)?.parse().ok(),
+            Operand::Number(n) => Some(*n),
+        }
+    }
+}
+
+/// Parse predicate.
+///
+/// # Arguments
+///
+/// * `s` - String slice to parse.
+///
+/// # Returns
+///
+/// A `Result` containing the parsed predicate.
+pub fn parse<'a>(s: &'a str) -> Result<Predicate, Error> {
+    match all_consuming(delimited(
+        multispace0,
+        or_expr::<VerboseError<&'a str>>,
+        multispace0,
+    ))(s)
+    {
+        Ok((_, p)) => Ok(p),
+        Err(Err::Error(e)) | Err(Err::Failure(e)) => Err(Error {
+            msg: convert_error(s, e),
+        }),
+        Err(Err::Incomplete(_)) => Err(Error {
+            msg: "incomplete input".to_owned(),
+        }),
+    }
+}
+
+/// Parse predicates separated by `||`.
+fn or_expr<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Predicate, E> {
+    map(separated_nonempty_list(ws(tag("||")), and_expr), |ps| {
+        fold(ps, Predicate::Or)
+    })(s)
+}
+
+/// Parse predicates separated by `&&`.
+fn and_expr<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Predicate, E> {
+    map(separated_nonempty_list(ws(tag("&&")), unary), |ps| {
+        fold(ps, Predicate::And)
+    })(s)
+}
+
+/// Parse a negated predicate, a parenthesized predicate, or a comparison.
+fn unary<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Predicate, E> {
+    alt((
+        map(preceded(ws(char('!')), unary), |p| {
+            Predicate::Not(Box::new(p))
+        }),
+        delimited(ws(char('(')), or_expr, ws(char(')'))),
+        comparison,
+    ))(s)
+}
+
+/// Parse a comparison, e.g. `n1 >= 100`.
+fn comparison<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Predicate, E> {
+    map(tuple((operand, ws(cmp_op), operand)), |(a, op, b)| {
+        Predicate::Cmp(a, op, b)
+    })(s)
+}
+
+/// Parse a comparison operator.
+fn cmp_op<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, CmpOp, E> {
+    alt((
+        map(tag("=="), |_| CmpOp::Eq),
+        map(tag("!="), |_| CmpOp::Ne),
+        map(tag("<="), |_| CmpOp::Le),
+        map(tag(">="), |_| CmpOp::Ge),
+        map(tag("<"), |_| CmpOp::Lt),
+        map(tag(">"), |_| CmpOp::Gt),
+    ))(s)
+}
+
+/// Parse an operand, either a group (`n<index>`) or a number literal.
+fn operand<'a, E: ParseError<&'a str>>(s: &'a str) -> IResult<&'a str, Operand, E> {
+    alt((
+        map(
+            preceded(char('n'), map_res(digit1, usize::from_str)),
+            Operand::Group,
+        ),
+        map(map_res(digit1, i64::from_str), Operand::Number),
+    ))(s)
+}
+
+/// Surround parser with optional whitespace.
+fn ws<'a, O, E: ParseError<&'a str>, F>(f: F) -> impl Fn(&'a str) -> IResult<&'a str, O, E>
+where
+    F: Fn(&'a str) -> IResult<&'a str, O, E>,
+{
+    delimited(multispace0, f, multispace0)
+}
+
+/// Combine predicates with operator, from left to right.
+fn fold(ps: Vec<Predicate>, op: fn(Box<Predicate>, Box<Predicate>) -> Predicate) -> Predicate {
+    let mut iter = ps.into_iter();
+    let first = iter.next().unwrap();
+    iter.fold(first, |a, b| op(Box::new(a), Box::new(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! eval_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, groups, expected): (&str, &[&str], bool) = $value;
+                    assert_eq!(parse(s).unwrap().eval(groups), expected);
+                }
+            )*
+        }
+    }
+
+    eval_tests!(
+        eval_eq: ("n1 == 1", &["1"], true),
+        eval_leading_zeros: ("n1 == 1", &["001"], true),
+        eval_range_in: ("n1 >= 100 && n1 < 200", &["150"], true),
+        eval_range_out: ("n1 >= 100 && n1 < 200", &["200"], false),
+        eval_or: ("n1 < 10 || n2 < 10", &["20", "5"], true),
+        eval_not: ("!(n1 == 1)", &["1"], false),
+        eval_precedence: ("n1 == 1 || n1 == 2 && n2 == 3", &["1", "4"], true),
+        eval_parens: ("(n1 == 1 || n1 == 2) && n2 == 3", &["1", "4"], false),
+        eval_compare_groups: ("n1 < n2", &["1", "2"], true),
+        eval_not_number: ("n1 == 1", &["a"], false),
+        eval_missing_group: ("n3 == 1", &["1"], false),
+        eval_group_zero: ("n0 == 1", &["1"], false),
+    );
+
+    macro_rules! parse_fail_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    assert!(parse($value).is_err());
+                }
+            )*
+        }
+    }
+
+    parse_fail_tests!(
+        parse_fail_empty: "",
+        parse_fail_no_op: "n1",
+        parse_fail_trailing: "n1 == 1 &&",
+        parse_fail_unbalanced: "(n1 == 1",
+    );
+}
@@ -1,33 +1,149 @@
 use colored::*;
+use console::{measure_text_width, truncate_str, Term};
 use std::borrow::Cow;
 use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::fmt;
+use std::mem;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::{json, Value};
 
 use crate::{
-    indices::SplitAtIndices,
-    parser::parse,
-    replacer::{ReplaceSource, Replacer},
+    elem::Elem,
+    formatter::{truncate_name, FormatOpts},
+    fsops::{check_name_len, name_len, native_separators, split_name},
+    normalize::PostProcess,
+    palette::palette,
+    parser::{parse_raw, parse_with_syntax, Syntax},
+    replacer::{ReplaceContext, ReplaceSource, Replacer},
+    tokens::TokenMode,
 };
 
-const DEFAULT_MAX_PREVIEWS: usize = 5;
+pub const DEFAULT_MAX_PREVIEWS: usize = 5;
+
+/// Maximum width of items taken into account when aligning previews.
+const MAX_ALIGN_WIDTH: usize = 60;
+
+/// Indentation of preview lines.
+const PREVIEW_INDENT: &str = "    ";
 
-const COLOR_MAP: [&str; 5] = ["cyan", "green", "yellow", "red", "magenta"];
+/// Separator of the sides of preview lines.
+const PREVIEW_ARROW: &str = " -> ";
 
 pub type Replacement<'a> = (Cow<'a, str>, String);
 
-#[derive(Default)]
+/// A span of a string, and the source it originates from.
+pub type Span = (Range<usize>, ReplaceSource);
+
+/// A replacement with the spans of each match in the input string, and the source of each part of
+/// the replaced string, for frontends doing their own highlighting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight<'a> {
+    pub left: &'a str,
+    pub right: String,
+    /// Spans of the input string, each originating from the match at its index.
+    pub left_spans: Vec<Span>,
+    /// Spans of the replaced string.
+    pub right_spans: Vec<Span>,
+}
+
+impl Highlight<'_> {
+    /// Convert to JSON, as output by `mrf map --json` and `mrf serve`.
+    ///
+    /// Spans are given as byte offsets, with their source: `group` (with the 1-based index of the
+    /// match), `entire`, `literal`, `counter` or `metadata`.
+    ///
+    /// ```json
+    /// {"left": "a-1", "right": "a_1", "left_spans": [{"start": 0, "end": 1, "source": "group",
+    /// "group": 1}, ...], "right_spans": [...]}
+    /// ```
+    pub fn to_json(&self) -> Value {
+        json!({
+            "left": self.left,
+            "right": self.right,
+            "left_spans": spans_json(&self.left_spans),
+            "right_spans": spans_json(&self.right_spans),
+        })
+    }
+}
+
+fn spans_json(spans: &[Span]) -> Vec<Value> {
+    spans
+        .iter()
+        .map(|(range, source)| {
+            let mut span = json!({"start": range.start, "end": range.end});
+            let source = match source {
+                ReplaceSource::Index(i) => {
+                    span["group"] = json!(i + 1);
+                    "group"
+                }
+                ReplaceSource::Entire => "entire",
+                ReplaceSource::Literal => "literal",
+                ReplaceSource::Counter => "counter",
+                ReplaceSource::Metadata => "metadata",
+            };
+            span["source"] = json!(source);
+            span
+        })
+        .collect()
+}
+
+#[derive(Clone, Default)]
 pub struct ResolveOpts {
     pub highlight: bool,
+    /// Restart counters for each distinct value of the match at this index (1-based).
+    pub group_by: Option<usize>,
+    pub token_mode: TokenMode,
+    /// Apply the replacer repeatedly along each item.
+    pub all_occurrences: bool,
+    /// Syntax of the replacer string.
+    pub syntax: Syntax,
+    /// Pattern to match items with, making the replacer string an output template.
+    pub pattern: Option<String>,
+    /// Take the replacer string as a literal output, in which only counters and metadata fields
+    /// are specifiers.
+    pub raw: bool,
+    /// Append the unmatched rest of each item to the output.
+    pub keep_rest: bool,
+    /// Make colliding replaced strings unique with this suffix template (e.g. "-{#}").
+    pub uniquify: Option<String>,
+    /// Options applying to all format specifiers.
+    pub format_opts: FormatOpts,
+    /// Post-processing applied to replaced strings, before making them unique.
+    pub post_process: PostProcess,
+    /// Directory relative items are in, for metadata fields.
+    pub base_dir: Option<PathBuf>,
+    /// Directories walked to find the items, for path components (`{%path.0}`).
+    pub roots: Vec<PathBuf>,
 }
 
 impl ResolveOpts {
     pub fn new() -> Self {
-        Self { highlight: false }
+        Self {
+            highlight: false,
+            group_by: None,
+            token_mode: TokenMode::Default,
+            all_occurrences: false,
+            syntax: Syntax::Braces,
+            pattern: None,
+            raw: false,
+            keep_rest: false,
+            uniquify: None,
+            format_opts: FormatOpts::new(),
+            post_process: PostProcess::new(),
+            base_dir: None,
+            roots: vec![],
+        }
     }
 
     pub fn with_highlight() -> Self {
-        Self { highlight: true }
+        Self {
+            highlight: true,
+            ..Self::new()
+        }
     }
 }
 
@@ -50,25 +166,274 @@ pub fn resolve<'a, T>(
 where
     T: AsRef<str> + cmp::PartialEq + std::clone::Clone,
 {
-    let elems = parse(replacer_str)?;
-    let replacer = Replacer::new(&elems);
-    Ok(if opts.highlight {
-        replace_items_highlight(&replacer, items)
+    let replacer = build_replacer(replacer_str, &opts)?;
+    let contexts = contexts(&replacer, items, &opts);
+    let suffix = match &opts.uniquify {
+        Some(suffix) => Some(build_suffix(suffix, opts.syntax)?),
+        None if opts.post_process.is_noop() && opts.highlight => {
+            return Ok(replace_items_highlight(&replacer, items, &contexts))
+        }
+        None if opts.post_process.is_noop() => {
+            return Ok(replace_items(&replacer, items, &contexts))
+        }
+        None => None,
+    };
+    let mut replacements = replace_items(&replacer, items, &contexts);
+    let mut changed = post_process(&mut replacements, &opts.post_process);
+    if let Some(suffix) = suffix {
+        changed.extend(uniquify(
+            &mut replacements,
+            &suffix,
+            opts.post_process.truncate_to_fit,
+        )?);
+    }
+    if !opts.highlight {
+        return Ok(replacements);
+    }
+    // Post-processed and uniquified strings are output without highlighting.
+    let mut highlighted = replace_items_highlight(&replacer, items, &contexts);
+    for i in changed {
+        highlighted[i].1 = mem::take(&mut replacements[i].1);
+    }
+    Ok(highlighted)
+}
+
+/// Resolve replacements of items one at a time as they are iterated, without holding them, for
+/// inputs too large to fit in memory.
+///
+/// Counters number the matched items across all items. Replaced strings are post-processed, but
+/// neither highlighted nor made unique.
+///
+/// # Arguments
+///
+/// * `items` - Items.
+/// * `replacer_str` - Replacer string.
+/// * `opts` - Options. `highlight` is ignored.
+/// * `f` - Function called with each matched item and its replaced string.
+///
+/// # Returns
+///
+/// A `Result` which fails if the replacer is invalid, options require all items at once
+/// (`group_by` or `uniquify`), or `f` fails.
+pub fn resolve_each<'a, I, F>(
+    items: I,
+    replacer_str: &str,
+    opts: &ResolveOpts,
+    mut f: F,
+) -> Result<(), Box<dyn Error>>
+where
+    I: IntoIterator<Item = &'a str>,
+    F: FnMut(&str, &str) -> Result<(), Box<dyn Error>>,
+{
+    if opts.group_by.is_some() || opts.uniquify.is_some() {
+        return Err("--group-by and --uniquify require reading all items at once".into());
+    }
+    let replacer = build_replacer(replacer_str, opts)?;
+    let (uses_counter, uses_metadata) = (replacer.uses_counter(), replacer.uses_metadata());
+    let mut counter = 0;
+    for (index, item) in items.into_iter().enumerate() {
+        let mut ctx = ReplaceContext::new().with_index(index, 0);
+        if uses_counter {
+            if replacer.is_match(item) {
+                counter += 1;
+            }
+            ctx = ctx.with_counter(counter);
+        }
+        if uses_metadata {
+            let (path, root) = item_path(item, opts);
+            ctx = ctx.with_path(Some(path)).with_root(root);
+        }
+        if let Ok((right, _)) = replacer.replace_with(&ctx, item) {
+            f(item, &opts.post_process.apply(&right))?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolve replacements with highlight spans instead of ANSI colors.
+///
+/// # Arguments
+///
+/// * `items` - Items.
+/// * `replacer_str` - Replacer string.
+/// * `opts` - Options. `highlight` is ignored.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of highlighted replacements.
+pub fn resolve_highlights<'a, T>(
+    items: &'a [T],
+    replacer_str: &str,
+    opts: ResolveOpts,
+) -> Result<Vec<Highlight<'a>>, Box<dyn Error>>
+where
+    T: AsRef<str>,
+{
+    let replacer = build_replacer(replacer_str, &opts)?;
+    Ok(highlights_with(&replacer, items, &opts))
+}
+
+/// Resolve replacements with highlight spans like `resolve_highlights`, with a replacer already
+/// built by `build_replacer`.
+///
+/// # Arguments
+///
+/// * `replacer` - Replacer.
+/// * `items` - Items.
+/// * `opts` - Options the replacer was built with.
+///
+/// # Returns
+///
+/// A `Vec` of highlighted replacements.
+pub fn highlights_with<'a, T>(
+    replacer: &Replacer,
+    items: &'a [T],
+    opts: &ResolveOpts,
+) -> Vec<Highlight<'a>>
+where
+    T: AsRef<str>,
+{
+    let contexts = contexts(replacer, items, opts);
+    highlights(replacer, items, &contexts)
+}
+
+/// Find the items the replacer fails on, along with the reason.
+///
+/// # Arguments
+///
+/// * `items` - Items.
+/// * `replacer_str` - Replacer string.
+/// * `opts` - Options. `highlight` is ignored.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of failed items and their error messages, in the order of
+/// `items`.
+pub fn failures<'a, T>(
+    items: &'a [T],
+    replacer_str: &str,
+    opts: ResolveOpts,
+) -> Result<Vec<(&'a str, String)>, Box<dyn Error>>
+where
+    T: AsRef<str>,
+{
+    let replacer = build_replacer(replacer_str, &opts)?;
+    let contexts = contexts(&replacer, items, &opts);
+    Ok(items
+        .iter()
+        .zip(&contexts)
+        .filter_map(|(item, ctx)| {
+            let item = item.as_ref();
+            replacer
+                .replace_with(ctx, item)
+                .err()
+                .map(|e| (item, e.to_string()))
+        })
+        .collect())
+}
+
+/// Parse replacer string into a `Replacer` configured with options.
+///
+/// If `opts.pattern` is set, items are matched with the pattern, and the replacer string is the
+/// output template.
+pub fn build_replacer(replacer_str: &str, opts: &ResolveOpts) -> Result<Replacer, Box<dyn Error>> {
+    let elems = if opts.raw {
+        parse_raw(replacer_str, opts.syntax)?
     } else {
-        replace_items(&replacer, items)
-    })
+        parse_with_syntax(replacer_str, opts.syntax)?
+    };
+    for elem in &elems {
+        if let Elem::Meta(field) = elem {
+            field.check()?;
+        }
+    }
+    let replacer = match &opts.pattern {
+        Some(pattern) => Replacer::with_pattern(&parse_with_syntax(pattern, opts.syntax)?, elems)?,
+        None => Replacer::from(elems),
+    };
+    Ok(replacer
+        .with_token_mode(opts.token_mode)
+        .with_all_occurrences(opts.all_occurrences)
+        .with_keep_rest(opts.keep_rest)
+        .with_format_opts(opts.format_opts.clone()))
+}
+
+/// Compute the replace context of each item.
+///
+/// Counters number the matched items starting from 1, restarting for each distinct value of the
+/// match at index `opts.group_by` if specified. Unmatched items are given 0.
+///
+/// If the replacer uses metadata fields, each item is given its path, joined to `opts.base_dir`,
+/// and the longest of `opts.roots` containing it.
+fn contexts<T>(replacer: &Replacer, items: &[T], opts: &ResolveOpts) -> Vec<ReplaceContext>
+where
+    T: AsRef<str>,
+{
+    let total = items.len();
+    let group_by = opts.group_by;
+    let uses_metadata = replacer.uses_metadata();
+    let context = |index: usize, counter: usize| {
+        let ctx = ReplaceContext::new()
+            .with_index(index, total)
+            .with_counter(counter);
+        if !uses_metadata {
+            return ctx;
+        }
+        let (path, root) = item_path(items[index].as_ref(), opts);
+        ctx.with_path(Some(path)).with_root(root)
+    };
+    if !replacer.uses_counter() {
+        return (0..total).map(|i| context(i, 1)).collect();
+    }
+    let mut counts: HashMap<Option<&str>, usize> = HashMap::new();
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| match replacer.matches(item.as_ref()) {
+            Ok(groups) => {
+                let key = group_by
+                    .and_then(|i| i.checked_sub(1))
+                    .and_then(|i| groups.get(i).copied());
+                let count = counts.entry(key).or_insert(0);
+                *count += 1;
+                context(i, *count)
+            }
+            Err(_) => context(i, 0),
+        })
+        .collect()
+}
+
+/// Get the path of an item joined to `opts.base_dir`, and the longest of `opts.roots` containing
+/// it.
+fn item_path(item: &str, opts: &ResolveOpts) -> (PathBuf, Option<PathBuf>) {
+    let path = match &opts.base_dir {
+        Some(base_dir) => base_dir.join(item),
+        None => PathBuf::from(item),
+    };
+    let root = opts
+        .roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .cloned();
+    (path, root)
 }
 
 /// Apply replacer to each item.
-fn replace_items<'a, T>(replacer: &Replacer, items: &'a [T]) -> Vec<Replacement<'a>>
+fn replace_items<'a, T>(
+    replacer: &Replacer,
+    items: &'a [T],
+    contexts: &[ReplaceContext],
+) -> Vec<Replacement<'a>>
 where
     T: AsRef<str> + cmp::PartialEq + std::clone::Clone,
 {
     items
         .iter()
-        .filter_map(|left| {
+        .zip(contexts)
+        .filter_map(|(left, ctx)| {
             replacer
-                .replace(left.as_ref())
+                .replace_with(ctx, left.as_ref())
                 .map(|(right, _)| (Cow::Borrowed(left.as_ref()), right))
                 .ok()
         })
@@ -76,66 +441,480 @@ where
 }
 
 /// Apply replacer to each item with match highlighting.
-fn replace_items_highlight<'a, T>(replacer: &Replacer, items: &'a [T]) -> Vec<Replacement<'a>>
+fn replace_items_highlight<'a, T>(
+    replacer: &Replacer,
+    items: &'a [T],
+    contexts: &[ReplaceContext],
+) -> Vec<Replacement<'a>>
 where
     T: AsRef<str> + cmp::PartialEq + std::clone::Clone,
+{
+    highlights(replacer, items, contexts)
+        .into_iter()
+        .map(|h| {
+            // Replacers without specifiers (e.g. raw ones) match nothing of the item.
+            let left = if h.left_spans.is_empty() {
+                Cow::Borrowed(h.left)
+            } else {
+                Cow::Owned(apply_color_map(h.left, &h.left_spans))
+            };
+            (left, apply_color_map(&h.right, &h.right_spans))
+        })
+        .collect()
+}
+
+/// Apply replacer to each item, computing highlight spans.
+fn highlights<'a, T>(
+    replacer: &Replacer,
+    items: &'a [T],
+    contexts: &[ReplaceContext],
+) -> Vec<Highlight<'a>>
+where
+    T: AsRef<str>,
 {
     items
         .iter()
-        .filter_map(|left| {
+        .zip(contexts)
+        .filter_map(|(left, ctx)| {
+            let left = left.as_ref();
             replacer
-                .replace(left.as_ref())
+                .replace_with(ctx, left)
                 .map(|(right, indices)| {
-                    (
-                        Cow::Owned(apply_color_map(left.as_ref(), &indices.matches)),
-                        apply_replaced_color_map(
-                            right.as_ref(),
-                            &indices.replaced,
-                            &indices.sources,
-                        ),
-                    )
+                    let match_sources: Vec<ReplaceSource> = (0..indices.matches.len())
+                        .map(ReplaceSource::Index)
+                        .collect();
+                    Highlight {
+                        left,
+                        left_spans: spans(left.len(), &indices.matches, &match_sources),
+                        right_spans: spans(right.len(), &indices.replaced, &indices.sources),
+                        right,
+                    }
                 })
                 .ok()
         })
         .collect()
 }
 
-/// Apply color map to string.
-fn apply_color_map(s: &str, indices: &[usize]) -> String {
-    s.split_at_indices(indices)
+/// Build spans from the start indices of each part of a string of length `len`.
+fn spans(len: usize, indices: &[usize], sources: &[ReplaceSource]) -> Vec<Span> {
+    indices
         .iter()
-        .enumerate()
-        .map(|(i, p)| p.color(COLOR_MAP[i % COLOR_MAP.len()]).to_string())
+        .zip(indices.iter().skip(1).chain(&[len]))
+        .zip(sources)
+        .map(|((&start, &end), &source)| (start..end, source))
+        .collect()
+}
+
+/// Apply color map to string according to the source of each span.
+fn apply_color_map(s: &str, spans: &[Span]) -> String {
+    spans
+        .iter()
+        .map(|(range, source)| {
+            let p = &s[range.clone()];
+            match source {
+                ReplaceSource::Index(i) => palette().paint(p, *i),
+                _ => p.normal().to_string(),
+            }
+        })
         .collect::<Vec<String>>()
         .join("")
 }
 
-/// Apply color map to replaced string.
-fn apply_replaced_color_map(s: &str, indices: &[usize], sources: &[ReplaceSource]) -> String {
-    s.split_at_indices(indices)
+/// Parse a suffix template for `uniquify`, which must contain a counter, and may otherwise only
+/// contain literals.
+pub fn build_suffix(suffix_str: &str, syntax: Syntax) -> Result<Replacer, Box<dyn Error>> {
+    let replacer = Replacer::from(parse_with_syntax(suffix_str, syntax)?);
+    if replacer.required_matches() > 0 || replacer.uses_entire_input() || !replacer.uses_counter() {
+        return Err(format!(
+            "invalid uniquify suffix: {} (must contain a counter, and may otherwise only contain literals)",
+            suffix_str
+        )
+        .into());
+    }
+    Ok(replacer)
+}
+
+/// Post-process replaced strings in place.
+///
+/// # Returns
+///
+/// The indices of the replacements changed.
+fn post_process(replacements: &mut [Replacement], post_process: &PostProcess) -> Vec<usize> {
+    let mut changed = vec![];
+    for (i, (_, right)) in replacements.iter_mut().enumerate() {
+        if let Cow::Owned(processed) = post_process.apply(right) {
+            *right = processed;
+            changed.push(i);
+        }
+    }
+    changed
+}
+
+/// Make colliding replaced strings unique by inserting a numbered suffix before the extension.
+///
+/// Every replaced string produced by more than one item is given the suffix, with the counter
+/// numbering its items from 1. Numbers producing strings which are already taken are skipped.
+///
+/// # Arguments
+///
+/// * `replacements` - Replacements, modified in place.
+/// * `suffix` - Suffix template, built with `build_suffix`.
+/// * `truncate_to_fit` - Shorten the stem of names which the suffix makes too long for the
+///   filesystem, as `PostProcess::truncate_to_fit` does.
+///
+/// # Returns
+///
+/// A `Result` containing the indices of the modified replacements.
+pub fn uniquify(
+    replacements: &mut [Replacement],
+    suffix: &Replacer,
+    truncate_to_fit: bool,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (_, right) in replacements.iter() {
+        *counts.entry(right.clone()).or_insert(0) += 1;
+    }
+    let mut taken: HashSet<String> = counts.keys().cloned().collect();
+    let mut counters: HashMap<String, usize> = HashMap::new();
+    let mut changed = vec![];
+    for (i, (_, right)) in replacements.iter_mut().enumerate() {
+        if counts[right.as_str()] < 2 {
+            continue;
+        }
+        let counter = counters.entry(right.clone()).or_insert(0);
+        let (stem, extension) = split_extension(right);
+        let unique = loop {
+            *counter += 1;
+            let ctx = ReplaceContext::new().with_counter(*counter);
+            let (suffix, _) = suffix.replace_with(&ctx, "")?;
+            let tail = format!("{}{}", suffix, extension);
+            let stem = if truncate_to_fit {
+                fit_stem(stem, &tail)
+            } else {
+                Cow::Borrowed(stem)
+            };
+            let candidate = format!("{}{}", stem, tail);
+            if !taken.contains(&candidate) {
+                break candidate;
+            }
+        };
+        taken.insert(unique.clone());
+        *right = unique;
+        changed.push(i);
+    }
+    Ok(changed)
+}
+
+/// Shorten the name of `stem` so that it fits the filesystem when followed by `tail`.
+///
+/// # Returns
+///
+/// The shortened stem, borrowing from `stem` if it fits already.
+fn fit_stem<'a>(stem: &'a str, tail: &str) -> Cow<'a, str> {
+    match check_name_len(&[stem, tail].concat()) {
+        Some((_, max_len)) => {
+            let (dir, name) = split_name(stem);
+            let max_len = max_len.saturating_sub(name_len(tail));
+            Cow::Owned([dir, &truncate_name(name, max_len, name_len)].concat())
+        }
+        None => Cow::Borrowed(stem),
+    }
+}
+
+/// Split a path into the part before its extension, and the extension including the dot.
+fn split_extension(s: &str) -> (&str, &str) {
+    match Path::new(s).extension().and_then(|e| e.to_str()) {
+        Some(extension) => s.split_at(s.len() - extension.len() - 1),
+        None => (s, ""),
+    }
+}
+
+/// Find replacements whose replaced strings collide.
+///
+/// Strings differing only in separators which are equivalent on the platform (e.g. `/` and `\` on
+/// Windows) collide.
+///
+/// # Arguments
+///
+/// * `replacements` - Replacements.
+///
+/// # Returns
+///
+/// A `Vec` of replaced strings produced by more than one item, each with the items producing it,
+/// in order of first appearance.
+pub fn collisions<'a>(replacements: &'a [Replacement]) -> Vec<(&'a str, Vec<&'a str>)> {
+    collisions_by(replacements, native_separators)
+}
+
+/// Find replacements whose replaced strings collide when compared case-insensitively, as on
+/// case-insensitive filesystems.
+///
+/// Same as `collisions`, except that strings differing only in case collide.
+pub fn case_insensitive_collisions<'a>(
+    replacements: &'a [Replacement],
+) -> Vec<(&'a str, Vec<&'a str>)> {
+    collisions_by(replacements, |right| {
+        Cow::Owned(native_separators(right).to_lowercase())
+    })
+}
+
+/// Find replacements whose replaced strings have the same key.
+fn collisions_by<'a, F>(replacements: &'a [Replacement], key: F) -> Vec<(&'a str, Vec<&'a str>)>
+where
+    F: Fn(&'a str) -> Cow<'a, str>,
+{
+    let mut groups: Vec<(&str, Vec<&str>)> = vec![];
+    let mut positions: HashMap<Cow<str>, usize> = HashMap::new();
+    for (left, right) in replacements {
+        let key = key(right);
+        match positions.get(&key) {
+            Some(&i) => groups[i].1.push(left.as_ref()),
+            None => {
+                positions.insert(key, groups.len());
+                groups.push((right, vec![left.as_ref()]));
+            }
+        }
+    }
+    groups.retain(|(_, lefts)| lefts.len() > 1);
+    groups
+}
+
+/// Format collisions for display.
+pub fn format_collisions(collisions: &[(&str, Vec<&str>)]) -> String {
+    collisions
         .iter()
-        .enumerate()
-        .map(|(i, p)| match sources[i] {
-            ReplaceSource::Index(matcher_i) => {
-                p.color(COLOR_MAP[matcher_i % COLOR_MAP.len()]).to_string()
+        .map(|(right, lefts)| {
+            format!(
+                "{} items map to {}:\n{}",
+                lefts.len(),
+                right,
+                lefts
+                    .iter()
+                    .map(|left| format!("    {}", left))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// A replacement which likely destroys information about an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hazard {
+    /// The file extension is removed.
+    DropsExtension,
+    /// The name (the last path component) is empty.
+    EmptyName,
+    /// The name consists solely of dots or whitespace.
+    BlankName,
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Hazard::DropsExtension => write!(f, "drop the file extension"),
+            Hazard::EmptyName => write!(f, "produce an empty name"),
+            Hazard::BlankName => write!(f, "produce a name of only dots or whitespace"),
+        }
+    }
+}
+
+/// Get the last component of a path, after any prefix such as a drive (`D:`) or a UNC share
+/// (`\\server\share`) on Windows. Empty if the path ends with a separator or is only a prefix.
+fn file_name(path: &str) -> &str {
+    let prefix = match Path::new(path).components().next() {
+        Some(Component::Prefix(prefix)) => prefix.as_os_str().len(),
+        _ => 0,
+    };
+    path[prefix..]
+        .rsplit(std::path::is_separator)
+        .next()
+        .unwrap_or("")
+}
+
+/// Get the hazard of replacing `left` with `right`, if any.
+fn hazard(left: &str, right: &str) -> Option<Hazard> {
+    let name = file_name(right);
+    if name.is_empty() {
+        return Some(Hazard::EmptyName);
+    }
+    if name.chars().all(|c| c == '.' || c.is_whitespace()) {
+        return Some(Hazard::BlankName);
+    }
+    if Path::new(left).extension().is_some() && Path::new(right).extension().is_none() {
+        return Some(Hazard::DropsExtension);
+    }
+    None
+}
+
+/// Find replacements which likely destroy information, grouped by hazard.
+pub fn hazards<'a>(replacements: &'a [Replacement]) -> Vec<(Hazard, Vec<(&'a str, &'a str)>)> {
+    let mut groups: Vec<(Hazard, Vec<(&str, &str)>)> = vec![];
+    for (left, right) in replacements {
+        if let Some(h) = hazard(left, right) {
+            match groups.iter_mut().find(|(g, _)| *g == h) {
+                Some((_, items)) => items.push((left, right)),
+                None => groups.push((h, vec![(left, right)])),
             }
-            _ => p.normal().to_string(),
+        }
+    }
+    groups
+}
+
+/// Find replacements whose name (the last path component) is too long for the filesystem of its
+/// directory.
+///
+/// # Returns
+///
+/// The replacements, with the length of the name and the maximum length.
+pub fn long_names<'a>(replacements: &'a [Replacement]) -> Vec<(&'a str, &'a str, usize, usize)> {
+    replacements
+        .iter()
+        .filter_map(|(left, right)| {
+            let (len, max_len) = check_name_len(right)?;
+            Some((left.as_ref(), right.as_str(), len, max_len))
+        })
+        .collect()
+}
+
+/// Format replacements with names too long for display.
+pub fn format_long_names(long_names: &[(&str, &str, usize, usize)]) -> String {
+    long_names
+        .iter()
+        .map(|(left, right, len, max_len)| {
+            format!(
+                "{} ({} > {})",
+                Render::default().line(left, right, 0),
+                len,
+                max_len
+            )
         })
         .collect::<Vec<String>>()
-        .join("")
+        .join("\n")
+}
+
+/// Get the first reserved pattern matching the name (the last component) of a path, if any.
+///
+/// In patterns, `*` matches any characters and `?` any single character, e.g. `-*` matches names
+/// looking like flags.
+pub fn reserved_pattern<'a>(path: &str, patterns: &'a [String]) -> Option<&'a str> {
+    let name = file_name(path);
+    patterns
+        .iter()
+        .find(|pattern| glob_match(pattern, name))
+        .map(String::as_str)
+}
+
+/// Check if a string matches a glob pattern of `*` and `?` wildcards.
+fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+    let (mut p, mut i) = (0, 0);
+    // Position of the last `*` in the pattern, and of the character it was last matched up to.
+    let mut star = None;
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, i));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match star {
+                // Let the last `*` match one more character.
+                Some((star_p, star_i)) => {
+                    star = Some((star_p, star_i + 1));
+                    p = star_p + 1;
+                    i = star_i + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Find replacements producing reserved names.
+///
+/// # Returns
+///
+/// The replacements, with the pattern each matches.
+pub fn reserved_names<'a>(
+    replacements: &'a [Replacement],
+    patterns: &'a [String],
+) -> Vec<(&'a str, &'a str, &'a str)> {
+    replacements
+        .iter()
+        .filter_map(|(left, right)| {
+            Some((
+                left.as_ref(),
+                right.as_str(),
+                reserved_pattern(right, patterns)?,
+            ))
+        })
+        .collect()
+}
+
+/// Format replacements producing reserved names for display.
+pub fn format_reserved_names(reserved_names: &[(&str, &str, &str)]) -> String {
+    reserved_names
+        .iter()
+        .map(|(left, right, pattern)| {
+            format!(
+                "{} (matches \"{}\")",
+                Render::default().line(left, right, 0),
+                pattern
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Format hazards for display.
+pub fn format_hazards(hazards: &[(Hazard, Vec<(&str, &str)>)]) -> String {
+    hazards
+        .iter()
+        .map(|(hazard, items)| {
+            format!(
+                "{} replacements {}:\n{}",
+                items.len(),
+                hazard,
+                items
+                    .iter()
+                    .map(|(left, right)| Render::default().line(left, right, 0))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 #[derive(Default)]
 pub struct PreviewOpts {
     pub max_previews: usize,
-    pub highlight: bool,
+    pub resolve: ResolveOpts,
 }
 
 impl PreviewOpts {
     pub fn new() -> Self {
         Self {
             max_previews: DEFAULT_MAX_PREVIEWS,
-            highlight: true,
+            resolve: ResolveOpts::with_highlight(),
+        }
+    }
+
+    /// Create `PreviewOpts` resolving replacements with `resolve` options, with highlighting.
+    pub fn with_resolve_opts(resolve: ResolveOpts) -> Self {
+        Self {
+            max_previews: DEFAULT_MAX_PREVIEWS,
+            resolve: ResolveOpts {
+                highlight: true,
+                ..resolve
+            },
         }
     }
 }
@@ -157,32 +936,196 @@ pub fn previews<T>(
     opts: PreviewOpts,
 ) -> Result<String, Box<dyn Error>>
 where
-    T: AsRef<str> + cmp::PartialEq + fmt::Display,
+    T: AsRef<str> + cmp::PartialEq + std::clone::Clone + fmt::Display,
 {
-    let (head, tail) = if items.len() > opts.max_previews {
-        (opts.max_previews / 2, ((opts.max_previews - 1) / 2))
+    // Resolve all items rather than only the previewed ones, so that counters are numbered
+    // consistently with the actual replacements.
+    let replacements = resolve(items, replacer_str, opts.resolve)?;
+    Ok(format_previews(&replacements, opts.max_previews))
+}
+
+/// Return a formatted preview of replacements resolved one at a time as items are iterated,
+/// keeping only the previewed ones, for inputs too large to fit in memory.
+///
+/// Replacements are resolved like `resolve_each`, so they are not highlighted.
+///
+/// # Arguments
+///
+/// * `items` - Items.
+/// * `replacer_str` - Replacer string.
+/// * `opts` - Options.
+/// * `max_previews` - Maximum number of replacements to show.
+///
+/// # Returns
+///
+/// A `Result` containing the number of replacements and the preview string.
+pub fn stream_previews<'a, I>(
+    items: I,
+    replacer_str: &str,
+    opts: &ResolveOpts,
+    max_previews: usize,
+) -> Result<(usize, String), Box<dyn Error>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let (head_len, tail_len) = (max_previews / 2, max_previews.saturating_sub(1) / 2);
+    let mut head: Vec<Replacement> = vec![];
+    let mut tail: VecDeque<Replacement> = VecDeque::new();
+    let mut count = 0;
+    resolve_each(items, replacer_str, opts, |left, right| {
+        let replacement = (Cow::Owned(left.to_owned()), right.to_owned());
+        if head.len() < max_previews {
+            head.push(replacement);
+        } else {
+            tail.push_back(replacement);
+            if tail.len() > tail_len {
+                tail.pop_front();
+            }
+        }
+        count += 1;
+        Ok(())
+    })?;
+    if count <= max_previews {
+        return Ok((count, format_previews(&head, max_previews)));
+    }
+    let rest = head.split_off(head_len);
+    let tail: Vec<&Replacement> = rest.iter().chain(&tail).collect();
+    let head: Vec<&Replacement> = head.iter().collect();
+    Ok((count, format_ends(&head, &tail[tail.len() - tail_len..])))
+}
+
+/// Return a formatted preview of resolved replacements.
+///
+/// # Arguments
+///
+/// * `replacements` - Replacements.
+/// * `max_previews` - Maximum number of replacements to show. If there are more, replacements are
+///   taken from both ends, separated by an ellipsis.
+///
+/// # Returns
+///
+/// The preview string.
+pub fn format_previews(replacements: &[Replacement], max_previews: usize) -> String {
+    let (head, tail) = if replacements.len() > max_previews {
+        (max_previews / 2, ((max_previews - 1) / 2))
     } else {
-        (items.len(), 0)
+        (replacements.len(), 0)
     };
-    let preview_items = take_ends(items, head, tail);
-    let replacements = resolve(
-        &preview_items,
-        replacer_str,
-        ResolveOpts {
-            highlight: opts.highlight,
-        },
-    )?;
-    let mut lines = vec![];
-    for (left, right) in replacements.iter().take(head) {
-        lines.push(format!("    {} -> {}", left, right));
-    }
-    if tail != 0 {
+    let shown: Vec<&Replacement> = take_ends(replacements, head, tail);
+    format_ends(&shown[..head], &shown[head..])
+}
+
+/// Format replacements taken from each end, separated by an ellipsis if any are taken from the
+/// end.
+fn format_ends(head: &[&Replacement], tail: &[&Replacement]) -> String {
+    // Align arrows into a column, measuring width without ANSI escapes and accounting for wide
+    // characters. Very long items are not taken into account so that they don't push the column
+    // too far.
+    let width = head
+        .iter()
+        .chain(tail)
+        .map(|(left, _)| measure_text_width(left))
+        .filter(|&w| w <= MAX_ALIGN_WIDTH)
+        .max()
+        .unwrap_or(0);
+    let render = Render::terminal();
+    let format_line = |(left, right): &Replacement| render.line(left, right, width);
+    let mut lines: Vec<String> = head.iter().map(|r| format_line(r)).collect();
+    if !tail.is_empty() {
         lines.push("    ...".to_owned());
-        for (left, right) in replacements.iter().rev().take(tail).rev() {
-            lines.push(format!("    {} -> {}", left, right));
+        lines.extend(tail.iter().map(|r| format_line(r)));
+    }
+    lines.join("\n")
+}
+
+/// Rendering of `left -> right` preview lines.
+///
+/// Sides containing right-to-left characters or bidi controls are wrapped in bidi isolation
+/// marks, so that they neither reorder the arrow nor each other. Lines wider than `max_width`
+/// are truncated, sharing the width between both sides.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Render {
+    /// Maximum width of lines, or `None` to never truncate.
+    pub max_width: Option<usize>,
+}
+
+impl Render {
+    /// Create a `Render` fitting lines in the width of the terminal, if stdout is one.
+    pub fn terminal() -> Self {
+        Self {
+            max_width: Term::stdout()
+                .size_checked()
+                .map(|(_, width)| width as usize),
         }
     }
-    Ok(lines.join("\n"))
+
+    /// Render a preview line, indented, with the arrow aligned at column `column` of the item
+    /// side.
+    pub fn line(&self, left: &str, right: &str, column: usize) -> String {
+        let (mut left, mut right) = (Cow::Borrowed(left), Cow::Borrowed(right));
+        let mut column = column;
+        if let Some(max_width) = self.max_width {
+            let width = max_width.saturating_sub(PREVIEW_INDENT.len() + PREVIEW_ARROW.len());
+            let right_width = measure_text_width(&right);
+            if cmp::max(measure_text_width(&left), column) + right_width > width {
+                // Give each side half of the width, or more if the other side needs less.
+                column = cmp::min(column, width / 2);
+                left = truncate(left, cmp::max(width / 2, width.saturating_sub(right_width)));
+                let left_width = cmp::max(measure_text_width(&left), column);
+                right = truncate(right, width.saturating_sub(left_width));
+            }
+        }
+        let pad = column.saturating_sub(measure_text_width(&left));
+        format!(
+            "{}{}{}{}{}",
+            PREVIEW_INDENT,
+            isolate(&left),
+            " ".repeat(pad),
+            PREVIEW_ARROW,
+            isolate(&right)
+        )
+    }
+}
+
+/// Truncate a string, which may contain ANSI escapes, to a width, ending it with an ellipsis.
+fn truncate<'a>(s: Cow<'a, str>, width: usize) -> Cow<'a, str> {
+    if measure_text_width(&s) <= width {
+        return s;
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+    Cow::Owned(truncate_str(&s, width, "…").into_owned())
+}
+
+/// Wrap a string in first strong isolate (U+2068) and pop directional isolate (U+2069) marks if
+/// it contains right-to-left characters or bidi controls.
+fn isolate(s: &str) -> Cow<'_, str> {
+    if s.chars().any(|c| is_rtl(c) || is_bidi_control(c)) {
+        Cow::Owned(format!("\u{2068}{}\u{2069}", s))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// Check if a character is of a right-to-left script, e.g. Hebrew or Arabic.
+fn is_rtl(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0590}'..='\u{08ff}'
+            | '\u{fb1d}'..='\u{fdff}'
+            | '\u{fe70}'..='\u{fefc}'
+            | '\u{10800}'..='\u{10fff}'
+            | '\u{1e800}'..='\u{1efff}'
+    )
+}
+
+/// Check if a character is a bidi control, e.g. a right-to-left override.
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+    )
 }
 
 /// Take some items from each end.
@@ -196,10 +1139,319 @@ where
 /// # Returns
 ///
 /// Items.
-fn take_ends<'a, T>(items: &'a [T], head: usize, tail: usize) -> Vec<&'a T> {
+fn take_ends<T>(items: &[T], head: usize, tail: usize) -> Vec<&T> {
     items
         .iter()
         .take(head)
         .chain(items.iter().rev().take(tail).rev())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! collisions_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (replacements, expected): (&[(&str, &str)], &[(&str, &[&str])]) = $value;
+                    let replacements: Vec<Replacement> = replacements
+                        .iter()
+                        .map(|(l, r)| (Cow::Borrowed(*l), r.to_string()))
+                        .collect();
+                    let expected: Vec<(&str, Vec<&str>)> =
+                        expected.iter().map(|(r, l)| (*r, l.to_vec())).collect();
+                    assert_eq!(collisions(&replacements), expected);
+                }
+            )*
+        }
+    }
+
+    collisions_tests!(
+        collisions_none: (&[("a", "x"), ("b", "y")], &[]),
+        collisions_pair: (&[("a", "x"), ("b", "y"), ("c", "x")], &[("x", &["a", "c"])]),
+        collisions_order: (
+            &[("a", "y"), ("b", "x"), ("c", "x"), ("d", "y")],
+            &[("y", &["a", "d"]), ("x", &["b", "c"])],
+        ),
+    );
+
+    macro_rules! hazard_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (left, right, expected) = $value;
+                    assert_eq!(hazard(left, right), expected);
+                }
+            )*
+        }
+    }
+
+    hazard_tests!(
+        hazard_none: ("a.jpg", "b.jpg", None),
+        hazard_no_extension: ("a", "b", None),
+        hazard_drops_extension: ("a.jpg", "a", Some(Hazard::DropsExtension)),
+        hazard_empty: ("a.jpg", "", Some(Hazard::EmptyName)),
+        hazard_empty_name: ("dir/a.jpg", "dir/", Some(Hazard::EmptyName)),
+        hazard_dots: ("a.jpg", "..", Some(Hazard::BlankName)),
+        hazard_whitespace: ("dir/a", "dir/ ", Some(Hazard::BlankName)),
+    );
+
+    #[test]
+    #[cfg(windows)]
+    fn hazard_windows_prefix() {
+        assert_eq!(hazard("a.jpg", "D:"), Some(Hazard::EmptyName));
+        assert_eq!(hazard("a.jpg", r"\\server\share\"), Some(Hazard::EmptyName));
+        assert_eq!(hazard("a.jpg", r"\\server\share\b.jpg"), None);
+        assert_eq!(hazard("a.jpg", "D:b.jpg"), None);
+    }
+
+    #[test]
+    fn resolve_highlights_spans() {
+        let items = ["a-1"];
+        assert_eq!(
+            resolve_highlights(&items, "{}{=_}x{}", ResolveOpts::new()).unwrap(),
+            vec![Highlight {
+                left: "a-1",
+                right: "a_x1".to_owned(),
+                left_spans: vec![
+                    (0..1, ReplaceSource::Index(0)),
+                    (1..2, ReplaceSource::Index(1)),
+                    (2..3, ReplaceSource::Index(2)),
+                ],
+                right_spans: vec![
+                    (0..1, ReplaceSource::Index(0)),
+                    (1..2, ReplaceSource::Index(1)),
+                    (2..3, ReplaceSource::Literal),
+                    (3..4, ReplaceSource::Index(2)),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn resolve_highlight_without_specifiers() {
+        let opts = ResolveOpts {
+            raw: true,
+            ..ResolveOpts::with_highlight()
+        };
+        let replacements = resolve(&["a.dat"], "file-{#}", opts).unwrap();
+        assert_eq!(replacements[0].0, "a.dat");
+    }
+
+    #[test]
+    fn highlight_to_json() {
+        let highlights = resolve_highlights(&["a-1"], "{}{=_}x{}", ResolveOpts::new()).unwrap();
+        assert_eq!(
+            highlights[0].to_json(),
+            json!({
+                "left": "a-1",
+                "right": "a_x1",
+                "left_spans": [
+                    {"start": 0, "end": 1, "source": "group", "group": 1},
+                    {"start": 1, "end": 2, "source": "group", "group": 2},
+                    {"start": 2, "end": 3, "source": "group", "group": 3},
+                ],
+                "right_spans": [
+                    {"start": 0, "end": 1, "source": "group", "group": 1},
+                    {"start": 1, "end": 2, "source": "group", "group": 2},
+                    {"start": 2, "end": 3, "source": "literal"},
+                    {"start": 3, "end": 4, "source": "group", "group": 3},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn format_previews_aligned() {
+        let replacements: Vec<Replacement> = vec![
+            (Cow::Borrowed("a"), "x".to_owned()),
+            (Cow::Borrowed("日本"), "y".to_owned()),
+            (Cow::Owned("abc".cyan().to_string()), "z".to_owned()),
+        ];
+        assert_eq!(
+            format_previews(&replacements, 5),
+            "    a    -> x\n    日本 -> y\n    ".to_owned() + &"abc".cyan().to_string() + "  -> z"
+        );
+    }
+
+    #[test]
+    fn format_previews_ellipsis() {
+        let replacements: Vec<Replacement> = ["a", "bb", "c", "d"]
+            .iter()
+            .map(|l| (Cow::Borrowed(*l), "x".to_owned()))
+            .collect();
+        assert_eq!(
+            format_previews(&replacements, 3),
+            "    a -> x\n    ...\n    d -> x"
+        );
+    }
+
+    #[test]
+    fn reserved_patterns() {
+        let patterns: Vec<String> = ["-*", ".", "..", "*.tmp", "a?c"]
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        let matched = |path| reserved_pattern(path, &patterns);
+        assert_eq!(matched("-rf"), Some("-*"));
+        assert_eq!(matched("a/-rf"), Some("-*"));
+        assert_eq!(matched("-/a"), None);
+        assert_eq!(matched("."), Some("."));
+        assert_eq!(matched("a/.."), Some(".."));
+        assert_eq!(matched("..a"), None);
+        assert_eq!(matched("a/b.tmp"), Some("*.tmp"));
+        assert_eq!(matched("b.tmp.jpg"), None);
+        assert_eq!(matched("abc"), Some("a?c"));
+        assert_eq!(matched("abbc"), None);
+    }
+
+    macro_rules! glob_match_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (pattern, s, expected) = $value;
+                    assert_eq!(glob_match(pattern, s), expected);
+                }
+            )*
+        }
+    }
+
+    glob_match_tests!(
+        glob_match_literal: ("abc", "abc", true),
+        glob_match_literal_mismatch: ("abc", "abd", false),
+        glob_match_star_empty: ("a*", "a", true),
+        glob_match_star_backtrack: ("*a*b", "xaxab", true),
+        glob_match_star_mismatch: ("*a*b", "xaxa", false),
+        glob_match_only_star: ("*", "", true),
+        glob_match_question: ("?.?", "a.b", true),
+        glob_match_question_empty: ("a?", "a", false),
+    );
+
+    #[test]
+    fn render_isolates_rtl() {
+        let render = Render::default();
+        assert_eq!(render.line("a", "b", 2), "    a  -> b");
+        assert_eq!(
+            render.line("שלום.txt", "x.txt", 0),
+            "    \u{2068}שלום.txt\u{2069} -> x.txt"
+        );
+        assert_eq!(
+            render.line("a\u{202e}txt.exe", "b", 0),
+            "    \u{2068}a\u{202e}txt.exe\u{2069} -> b"
+        );
+    }
+
+    #[test]
+    fn render_truncates() {
+        let render = Render {
+            max_width: Some(20),
+        };
+        assert_eq!(render.line("abc", "def", 5), "    abc   -> def");
+        assert_eq!(render.line("abcdefghij", "xy", 0), "    abcdefghij -> xy");
+        assert_eq!(render.line("abcdefghijk", "xyz", 4), "    abcdefgh… -> xyz");
+        assert_eq!(
+            render.line("abcdefghij", "uvwxyz", 0),
+            "    abcde… -> uvwxyz"
+        );
+        assert_eq!(
+            render.line("日本語のファイル", "abcdefgh", 0),
+            "    日本… -> abcdef…"
+        );
+        let left = "abcdefghij".cyan().to_string();
+        assert_eq!(measure_text_width(&render.line(&left, "klmnopqrst", 0)), 20);
+    }
+
+    #[test]
+    fn stream_previews_like_format_previews() {
+        let items = ["a-1", "b", "c-2", "d-3", "e-4", "f-5"];
+        let replacements = resolve(&items, "{}{=_}{}", ResolveOpts::new()).unwrap();
+        for max_previews in 1..8 {
+            assert_eq!(
+                stream_previews(
+                    items.iter().copied(),
+                    "{}{=_}{}",
+                    &ResolveOpts::new(),
+                    max_previews
+                )
+                .unwrap(),
+                (5, format_previews(&replacements, max_previews))
+            );
+        }
+    }
+
+    #[test]
+    fn failures_of_items() {
+        let items = ["a-1", "b", "c-2"];
+        let failures = failures(&items, "{}{=_}{}", ResolveOpts::new()).unwrap();
+        assert_eq!(
+            failures,
+            vec![("b", "unable to match specifiers with input".to_owned())]
+        );
+    }
+
+    macro_rules! uniquify_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (rights, expected): (&[&str], &[&str]) = $value;
+                    let mut replacements: Vec<Replacement> = rights
+                        .iter()
+                        .map(|r| (Cow::Borrowed("x"), r.to_string()))
+                        .collect();
+                    let suffix = build_suffix("-{#}", Syntax::Braces).unwrap();
+                    uniquify(&mut replacements, &suffix, false).unwrap();
+                    let rights: Vec<&str> = replacements.iter().map(|(_, r)| r.as_str()).collect();
+                    assert_eq!(rights, expected);
+                }
+            )*
+        }
+    }
+
+    uniquify_tests!(
+        uniquify_none: (&["a", "b"], &["a", "b"]),
+        uniquify_simple: (&["a", "a", "b"], &["a-1", "a-2", "b"]),
+        uniquify_extension: (&["d/a.jpg", "d/a.jpg"], &["d/a-1.jpg", "d/a-2.jpg"]),
+        uniquify_skip_taken: (&["a", "a", "a-1"], &["a-2", "a-3", "a-1"]),
+    );
+
+    #[test]
+    fn uniquify_truncate_to_fit() {
+        let max = crate::fsops::name_max(Path::new(""));
+        let name = ["a".repeat(max - 4), ".txt".to_owned()].concat();
+        let mut replacements: Vec<Replacement> = vec![
+            (Cow::Borrowed("x"), name.clone()),
+            (Cow::Borrowed("y"), name.clone()),
+        ];
+        let suffix = build_suffix("-{#}", Syntax::Braces).unwrap();
+        uniquify(&mut replacements, &suffix, true).unwrap();
+        let stem = "a".repeat(max - 6);
+        assert_eq!(replacements[0].1, format!("{}-1.txt", stem));
+        assert_eq!(replacements[1].1, format!("{}-2.txt", stem));
+    }
+
+    #[test]
+    fn build_suffix_invalid() {
+        assert!(build_suffix("-{}", Syntax::Braces).is_err());
+        assert!(build_suffix("-x", Syntax::Braces).is_err());
+        assert!(build_suffix("_{#:02}", Syntax::Braces).is_ok());
+    }
+
+    #[test]
+    fn case_insensitive_collisions_of_replacements() {
+        let replacements: Vec<Replacement> = vec![
+            (Cow::Borrowed("a"), "x.txt".to_owned()),
+            (Cow::Borrowed("b"), "X.TXT".to_owned()),
+        ];
+        assert!(collisions(&replacements).is_empty());
+        assert_eq!(
+            case_insensitive_collisions(&replacements),
+            vec![("x.txt", vec!["a", "b"])]
+        );
+    }
+}
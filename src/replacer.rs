@@ -1,16 +1,29 @@
+use std::borrow::Cow;
 use std::error::Error;
+use std::fmt;
+use std::fs::Metadata;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use crate::{
     elem::Elem,
-    formatter::InputType,
+    formatter::{FormatOpts, Formatter, InputType},
     indices::SplitAtIndices,
-    matcher::{match_all, Matcher},
+    matcher::{self, match_all_with_budget, Matcher},
+    meta::MetaCache,
     spec::Spec,
+    tokens::{to_ascii_digits, TokenMode},
 };
 
 pub struct Replacer {
     elems: Vec<Elem>,
     matchers: Vec<Matcher>,
+    token_mode: TokenMode,
+    all_occurrences: bool,
+    format_opts: FormatOpts,
+    /// Steps left for all matches, if limited.
+    match_budget: Option<Arc<AtomicUsize>>,
 }
 
 impl Replacer {
@@ -24,14 +37,153 @@ impl Replacer {
     ///
     /// A `Replacer`.
     pub fn new(elems: &[Elem]) -> Self {
-        Self {
-            elems: elems.to_vec(),
-            matchers: matchers_from_elems(elems),
+        Self::from(elems.to_vec())
+    }
+
+    /// Create a `Replacer` matching with `pattern`, and outputting `template`.
+    ///
+    /// The pattern may only contain specifiers, whose matchers are used for matching. The
+    /// template refers to the matched groups by index, and its matchers are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - Elements to match with.
+    /// * `template` - Elements to output.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `Replacer`.
+    pub fn with_pattern(pattern: &[Elem], template: Vec<Elem>) -> Result<Self, Box<dyn Error>> {
+        if !pattern.iter().all(|e| matches!(e, Elem::Spec(_))) {
+            return Err("pattern may only contain specifiers".into());
+        }
+        Ok(Self {
+            matchers: matchers_from_elems(pattern),
+            ..Self::from(template)
+        })
+    }
+
+    /// Apply the replacer repeatedly along the string.
+    ///
+    /// If the last element replaces with the last match as-is, the replacer is applied again to
+    /// that match, and so on until it no longer matches. For example, `{}{=_}{}` replaces every
+    /// hyphen in `a-b-c` instead of only the first.
+    pub fn with_all_occurrences(mut self, all_occurrences: bool) -> Self {
+        self.all_occurrences = all_occurrences;
+        self
+    }
+
+    /// Append the rest of the string left unmatched by the elements to the output.
+    ///
+    /// A trailing group matching the remaining tokens (possibly none) is added and output as-is,
+    /// so that `{}{=_}` replaces `a-b.jpg` with `a_b.jpg` instead of `a_`.
+    pub fn with_keep_rest(mut self, keep_rest: bool) -> Self {
+        if keep_rest {
+            self.matchers
+                .push(Matcher::Repeat(Box::new(Matcher::Any), 0, Some(1)));
+            self.elems.push(Elem::Spec(Spec {
+                matcher: Matcher::Any,
+                index: Some(self.matchers.len()),
+                replace: None,
+                formatter: None,
+            }));
         }
+        self
+    }
+
+    /// Set the mode used to tokenize strings before matching.
+    pub fn with_token_mode(mut self, token_mode: TokenMode) -> Self {
+        self.token_mode = token_mode;
+        self
+    }
+
+    /// Set the options applying to all format specifiers, e.g. the separators of numbers.
+    pub fn with_format_opts(mut self, format_opts: FormatOpts) -> Self {
+        self.format_opts = format_opts;
+        self
+    }
+
+    /// Limit the work of all matches made by this replacer to `steps` steps (roughly tokens and
+    /// bytes examined), after which strings fail to match with `matcher::Error::TooComplex`.
+    pub fn with_match_budget(mut self, steps: usize) -> Self {
+        self.match_budget = Some(Arc::new(AtomicUsize::new(steps)));
+        self
+    }
+
+    /// Check if the budget set by `with_match_budget` has run out.
+    pub fn match_budget_exceeded(&self) -> bool {
+        self.match_budget
+            .as_ref()
+            .is_some_and(|budget| budget.load(Ordering::Relaxed) == 0)
+    }
+
+    /// Check if string matches the matchers of the elements.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - String slice to match.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the string matches.
+    pub fn is_match(&self, s: &str) -> bool {
+        self.match_all(s).is_ok()
+    }
+
+    /// Get the substrings matched by the matchers of the elements.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - String slice to match.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `Vec` of matched substrings.
+    pub fn matches<'a>(&self, s: &'a str) -> Result<Vec<&'a str>, Box<dyn Error>> {
+        Ok(s.split_at_indices(&self.match_all(s)?))
+    }
+
+    /// Match string with the matchers, with the token mode and the number locale.
+    fn match_all(&self, s: &str) -> Result<Vec<usize>, matcher::Error> {
+        match_all_with_budget(
+            s,
+            &self.matchers,
+            self.token_mode,
+            self.format_opts.number_locale,
+            self.match_budget.as_deref(),
+        )
+    }
+
+    /// Get the number of groups a string must be split into to match.
+    ///
+    /// For example, `{}{=_}{n}` requires 3 matches, and `{3}` requires 3 matches, even though only
+    /// the last one is output.
+    pub fn required_matches(&self) -> usize {
+        self.matchers.len()
+    }
+
+    /// Check if elements output the entire input string (`{0}`).
+    pub fn uses_entire_input(&self) -> bool {
+        self.elems
+            .iter()
+            .any(|e| matches!(e, Elem::Spec(spec) if spec.index == Some(0)))
+    }
+
+    /// Check if elements contain a counter.
+    pub fn uses_counter(&self) -> bool {
+        self.elems.iter().any(|e| matches!(e, Elem::Counter(_)))
+    }
+
+    /// Check if elements contain a metadata field.
+    pub fn uses_metadata(&self) -> bool {
+        self.elems.iter().any(|e| matches!(e, Elem::Meta(_)))
     }
 
     /// Replace string according to elements.
     ///
+    /// Counters are replaced with 1. Use `replace_with` to specify the counter value and other
+    /// per-item state.
+    ///
     /// # Arguments
     ///
     /// * `s` - String slice to replace.
@@ -40,44 +192,294 @@ impl Replacer {
     ///
     /// A `Result` containing the replaced string and the indices.
     pub fn replace(&self, s: &str) -> Result<(String, ReplaceIndices), Box<dyn Error>> {
-        let indices = match_all(s, &self.matchers)?;
-        let parts: Vec<&str> = [vec![s], s.split_at_indices(&indices)].concat();
+        self.replace_with_counter(s, 1)
+    }
+
+    /// Replace string according to elements, replacing counters with `counter`.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - String slice to replace.
+    /// * `counter` - Counter value.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the replaced string and the indices.
+    pub fn replace_with_counter(
+        &self,
+        s: &str,
+        counter: usize,
+    ) -> Result<(String, ReplaceIndices), Box<dyn Error>> {
+        self.replace_with(&ReplaceContext::new().with_counter(counter), s)
+    }
+
+    /// Replace string according to elements, with per-item state from `ctx`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - Context of the item.
+    /// * `s` - String slice to replace.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the replaced string and the indices.
+    pub fn replace_with(
+        &self,
+        ctx: &ReplaceContext,
+        s: &str,
+    ) -> Result<(String, ReplaceIndices), Box<dyn Error>> {
+        let mut out = String::new();
+        let mut indices = ReplaceIndices::default();
+        self.replace_into_with(ctx, s, &mut out, &mut indices)?;
+        Ok((out, indices))
+    }
+
+    /// Replace string according to elements into existing buffers.
+    ///
+    /// Same as `replace`, but clears and reuses `out` and `indices` instead of allocating new
+    /// ones, which avoids allocations when replacing many strings in a loop.
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - String slice to replace.
+    /// * `out` - Buffer receiving the replaced string.
+    /// * `indices` - Buffer receiving the indices.
+    pub fn replace_into(
+        &self,
+        s: &str,
+        out: &mut String,
+        indices: &mut ReplaceIndices,
+    ) -> Result<(), Box<dyn Error>> {
+        self.replace_into_with(&ReplaceContext::new(), s, out, indices)
+    }
+
+    /// Replace string according to elements into existing buffers, with per-item state from
+    /// `ctx`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - Context of the item.
+    /// * `s` - String slice to replace.
+    /// * `out` - Buffer receiving the replaced string.
+    /// * `indices` - Buffer receiving the indices.
+    pub fn replace_into_with(
+        &self,
+        ctx: &ReplaceContext,
+        s: &str,
+        out: &mut String,
+        indices: &mut ReplaceIndices,
+    ) -> Result<(), Box<dyn Error>> {
+        out.clear();
+        indices.clear();
+        self.replace_once_into(ctx, s, 0, 0, out, indices)?;
+        if !self.all_occurrences {
+            return Ok(());
+        }
+        // Apply the replacer again to the last match, as long as the last element is the last
+        // match, unmodified.
+        let mut offset = 0;
+        let mut index_offset = 0;
+        loop {
+            let n = indices.matches.len() - index_offset;
+            let (start, i) = match (indices.matches.last(), indices.sources.last()) {
+                (Some(&start), Some(&ReplaceSource::Index(i)))
+                    if i + 1 == index_offset + n && start > offset =>
+                {
+                    (start, i)
+                }
+                _ => return Ok(()),
+            };
+            let last = &s[start..];
+            if !out.ends_with(last) {
+                return Ok(());
+            }
+            let head_len = out.len() - last.len();
+            out.truncate(head_len);
+            indices.matches.pop();
+            indices.replaced.pop();
+            indices.sources.pop();
+            let lens = indices.lens();
+            if self
+                .replace_once_into(ctx, last, start, i, out, indices)
+                .is_err()
+            {
+                // Keep the last match as-is.
+                out.truncate(head_len);
+                indices.truncate(lens);
+                out.push_str(last);
+                indices.matches.push(start);
+                indices.replaced.push(head_len);
+                indices.sources.push(ReplaceSource::Index(i));
+                return Ok(());
+            }
+            offset = start;
+            index_offset = i;
+        }
+    }
+
+    /// Replace string according to elements once, appending to the buffers.
+    ///
+    /// `offset` is the position of `s` in the original string, and `index_offset` is the number of
+    /// matches preceding `s`.
+    fn replace_once_into(
+        &self,
+        ctx: &ReplaceContext,
+        s: &str,
+        offset: usize,
+        index_offset: usize,
+        out: &mut String,
+        indices: &mut ReplaceIndices,
+    ) -> Result<(), Box<dyn Error>> {
+        let matches = self.match_all(s)?;
+        let parts = Parts {
+            s,
+            matches: &matches,
+        };
+        indices.matches.extend(matches.iter().map(|m| m + offset));
         let mut cursor = 1;
-        let mut pos = 0;
-        let mut replaced_parts = vec![];
-        let mut replaced_indices = vec![];
-        let mut sources = vec![];
         for e in &self.elems {
-            let (r, src) = match e {
+            indices.replaced.push(out.len());
+            let src = match e {
                 Elem::Spec(spec) => {
-                    let (idx, r) = replace_spec(&spec, cursor, &parts);
+                    let (idx, r) = replace_spec(spec, cursor, &parts, &self.format_opts)?;
+                    out.push_str(&r);
                     cursor = idx + 1;
-                    let src = if idx == 0 {
+                    if idx == 0 {
                         ReplaceSource::Entire
                     } else {
-                        ReplaceSource::Index(idx - 1)
-                    };
-                    (r, src)
+                        ReplaceSource::Index(idx - 1 + index_offset)
+                    }
+                }
+                Elem::Lit(lit) => {
+                    out.push_str(lit);
+                    ReplaceSource::Literal
+                }
+                Elem::Counter(formatter) => {
+                    out.push_str(&format_counter(
+                        ctx.counter,
+                        formatter.as_ref(),
+                        &self.format_opts,
+                    ));
+                    ReplaceSource::Counter
+                }
+                Elem::Meta(field) => {
+                    out.push_str(&field.resolve(ctx)?);
+                    ReplaceSource::Metadata
                 }
-                Elem::Lit(lit) => (lit.to_owned(), ReplaceSource::Literal),
             };
-            replaced_indices.push(pos);
-            sources.push(src);
-            pos += r.len();
-            replaced_parts.push(r);
+            indices.sources.push(src);
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<Elem>> for Replacer {
+    /// Create a `Replacer` taking ownership of the elements.
+    fn from(elems: Vec<Elem>) -> Self {
+        Self {
+            matchers: matchers_from_elems(&elems),
+            elems,
+            token_mode: TokenMode::Default,
+            all_occurrences: false,
+            format_opts: FormatOpts::new(),
+            match_budget: None,
         }
-        Ok((
-            replaced_parts.join(""),
-            ReplaceIndices {
-                matches: indices,
-                replaced: replaced_indices,
-                sources,
-            },
-        ))
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Per-item state available to elements when replacing.
+#[derive(Debug, Clone)]
+pub struct ReplaceContext {
+    /// Index of the item among all items (0-based).
+    pub index: usize,
+    /// Total number of items.
+    pub total: usize,
+    /// Counter value, numbering matched items within their group (`{#}`).
+    pub counter: usize,
+    /// File metadata of the item, for subcommands operating on files.
+    pub metadata: Option<Metadata>,
+    /// Path of the item, for metadata fields (`{%mtime}`).
+    pub path: Option<PathBuf>,
+    /// Directory the item was found under when walking directories, for path components
+    /// (`{%path.0}`).
+    pub root: Option<PathBuf>,
+    /// Metadata of the item read by metadata fields so far.
+    pub cache: MetaCache,
+}
+
+impl ReplaceContext {
+    /// Create a `ReplaceContext` for a single item, with counter 1.
+    pub fn new() -> Self {
+        Self {
+            index: 0,
+            total: 1,
+            counter: 1,
+            metadata: None,
+            path: None,
+            root: None,
+            cache: MetaCache::new(),
+        }
+    }
+
+    /// Set the index of the item and the total number of items.
+    pub fn with_index(mut self, index: usize, total: usize) -> Self {
+        self.index = index;
+        self.total = total;
+        self
+    }
+
+    /// Set the counter value.
+    pub fn with_counter(mut self, counter: usize) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    /// Set the path of the item.
+    pub fn with_path(mut self, path: Option<PathBuf>) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Set the directory the item was found under.
+    pub fn with_root(mut self, root: Option<PathBuf>) -> Self {
+        self.root = root;
+        self
+    }
+
+    /// Set the file metadata of the item.
+    pub fn with_metadata(mut self, metadata: Option<Metadata>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+impl Default for ReplaceContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parts of a matched string, borrowed from the string.
+///
+/// Part 0 is the entire string, and part `i` is the `i`th match.
+struct Parts<'a> {
+    s: &'a str,
+    matches: &'a [usize],
+}
+
+impl<'a> Parts<'a> {
+    /// Get part at index, or `None` if out of range.
+    fn get(&self, idx: usize) -> Option<&'a str> {
+        if idx == 0 {
+            return Some(self.s);
+        }
+        let start = *self.matches.get(idx - 1)?;
+        let end = self.matches.get(idx).copied().unwrap_or(self.s.len());
+        self.s.get(start..end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct ReplaceIndices {
     /// Match indices in source string.
     pub matches: Vec<usize>,
@@ -87,6 +489,25 @@ pub struct ReplaceIndices {
     pub sources: Vec<ReplaceSource>,
 }
 
+impl ReplaceIndices {
+    /// Clear all indices.
+    fn clear(&mut self) {
+        self.truncate((0, 0, 0));
+    }
+
+    /// Get the lengths of the match, part and source indices.
+    fn lens(&self) -> (usize, usize, usize) {
+        (self.matches.len(), self.replaced.len(), self.sources.len())
+    }
+
+    /// Truncate the match, part and source indices to the given lengths.
+    fn truncate(&mut self, (matches, replaced, sources): (usize, usize, usize)) {
+        self.matches.truncate(matches);
+        self.replaced.truncate(replaced);
+        self.sources.truncate(sources);
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ReplaceSource {
     /// Replaces with match at index.
@@ -95,22 +516,77 @@ pub enum ReplaceSource {
     Entire,
     /// Replaces with literal.
     Literal,
+    /// Replaces with counter.
+    Counter,
+    /// Replaces with metadata field.
+    Metadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplaceError {
+    /// A specifier refers to a group beyond the groups matched.
+    IndexOutOfRange { index: usize, groups: usize },
 }
 
+impl fmt::Display for ReplaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplaceError::IndexOutOfRange { index, groups } => write!(
+                f,
+                "index {} out of range (pattern expects at least {} groups, matched {})",
+                index, index, groups
+            ),
+        }
+    }
+}
+
+impl Error for ReplaceError {}
+
 /// Replace specifier given current cursor and parts.
-fn replace_spec(spec: &Spec, cursor: usize, parts: &[&str]) -> (usize, String) {
-    let idx = spec.index.unwrap_or_else(|| cursor);
+///
+/// Returns the index of the part replaced, and the replacement, which borrows from the part or the
+/// spec unless formatted.
+fn replace_spec<'a>(
+    spec: &'a Spec,
+    cursor: usize,
+    parts: &Parts<'a>,
+    format_opts: &FormatOpts,
+) -> Result<(usize, Cow<'a, str>), Box<dyn Error>> {
+    let idx = spec.index.unwrap_or(cursor);
     let r: &str = if let Some(replace) = &spec.replace {
-        &replace
+        replace
     } else {
-        parts[idx]
+        parts.get(idx).ok_or(ReplaceError::IndexOutOfRange {
+            index: idx,
+            groups: parts.matches.len(),
+        })?
     };
-    let r = if let Some(formatter) = &spec.formatter {
-        formatter.format(spec_input_type(&spec), r)
+    let r = if format_opts.ascii_digits {
+        to_ascii_digits(r)
     } else {
-        r.to_owned()
+        Cow::Borrowed(r)
+    };
+    let r = match &spec.formatter {
+        Some(formatter) => {
+            Cow::Owned(formatter.format_with_opts(spec_input_type(spec), &r, format_opts))
+        }
+        None => r,
     };
-    (idx, r)
+    Ok((idx, r))
+}
+
+/// Format counter value.
+fn format_counter(
+    counter: usize,
+    formatter: Option<&Formatter>,
+    format_opts: &FormatOpts,
+) -> String {
+    match formatter {
+        Some(formatter) => {
+            formatter.format_with_opts(InputType::Number, &counter.to_string(), format_opts)
+        }
+        None => counter.to_string(),
+    }
 }
 
 /// Extract matchers from elements.
@@ -150,7 +626,7 @@ fn spec_input_type(spec: &Spec) -> InputType {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{formatter::Formatter, spec::Spec};
+    use crate::spec::Spec;
 
     macro_rules! replace_tests {
         ($($name:ident: $value:expr,)*) => {
@@ -425,6 +901,231 @@ mod tests {
         })),
     );
 
+    macro_rules! is_match_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, elems, expected) = $value;
+                    assert_eq!(Replacer::new(elems).is_match(s), expected);
+                }
+            )*
+        }
+    }
+
+    is_match_tests!(
+        is_match_any: ("a", &[Elem::Spec(Spec::new(Matcher::Any))], true),
+        is_match_number: ("a1", &[
+            Elem::Spec(Spec::new(Matcher::Any)),
+            Elem::Spec(Spec::new(Matcher::Number)),
+        ], true),
+        is_match_no_number: ("ab", &[
+            Elem::Spec(Spec::new(Matcher::Any)),
+            Elem::Spec(Spec::new(Matcher::Number)),
+        ], false),
+    );
+
+    #[test]
+    fn replace_with_counter() {
+        let replacer = Replacer::new(&[
+            Elem::Spec(Spec::new(Matcher::Any)),
+            Elem::Lit("-".to_owned()),
+            Elem::Counter(Some(Formatter::with_width(3, '0'))),
+        ]);
+        assert_eq!(
+            replacer.replace_with_counter("a", 7).unwrap(),
+            (
+                "a-007".to_owned(),
+                ReplaceIndices {
+                    matches: vec![0],
+                    replaced: vec![0, 1, 2],
+                    sources: vec![
+                        ReplaceSource::Index(0),
+                        ReplaceSource::Literal,
+                        ReplaceSource::Counter,
+                    ],
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn replace_with_context() {
+        let replacer = Replacer::new(&[Elem::Spec(Spec::new(Matcher::Any)), Elem::Counter(None)]);
+        let ctx = ReplaceContext::new().with_index(4, 10).with_counter(5);
+        assert_eq!(replacer.replace_with(&ctx, "a").unwrap().0, "a5");
+    }
+
+    #[test]
+    fn replace_all_occurrences() {
+        let replacer = Replacer::new(&[
+            Elem::Spec(Spec::new(Matcher::Any)),
+            Elem::Spec(Spec {
+                matcher: Matcher::Any,
+                index: None,
+                replace: Some("_".to_owned()),
+                formatter: None,
+            }),
+            Elem::Spec(Spec::new(Matcher::Any)),
+        ])
+        .with_all_occurrences(true);
+        assert_eq!(
+            replacer.replace("a-b-c").unwrap(),
+            (
+                "a_b_c".to_owned(),
+                ReplaceIndices {
+                    matches: vec![0, 1, 2, 3, 4],
+                    replaced: vec![0, 1, 2, 3, 4],
+                    sources: vec![
+                        ReplaceSource::Index(0),
+                        ReplaceSource::Index(1),
+                        ReplaceSource::Index(2),
+                        ReplaceSource::Index(3),
+                        ReplaceSource::Index(4),
+                    ],
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn replace_all_occurrences_no_repeat() {
+        let replacer = Replacer::new(&[
+            Elem::Spec(Spec::new(Matcher::Any)),
+            Elem::Lit("-x".to_owned()),
+        ])
+        .with_all_occurrences(true);
+        assert_eq!(replacer.replace("a-b").unwrap().0, "a-b-x");
+    }
+
+    #[test]
+    fn replace_into_reuses_buffers() {
+        let replacer = Replacer::new(&[
+            Elem::Spec(Spec::new(Matcher::Any)),
+            Elem::Lit("-".to_owned()),
+            Elem::Spec(Spec::new(Matcher::Number)),
+        ])
+        .with_all_occurrences(true);
+        let mut out = String::new();
+        let mut indices = ReplaceIndices::default();
+        replacer
+            .replace_into("a1b2", &mut out, &mut indices)
+            .unwrap();
+        replacer.replace_into("c3", &mut out, &mut indices).unwrap();
+        assert_eq!((out, indices), replacer.replace("c3").unwrap(),);
+    }
+
+    macro_rules! required_matches_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected) = $value;
+                    let replacer = Replacer::from(crate::parser::parse(s).unwrap());
+                    assert_eq!(
+                        (replacer.required_matches(), replacer.uses_entire_input()),
+                        expected
+                    );
+                }
+            )*
+        }
+    }
+
+    required_matches_tests!(
+        required_matches_lit: ("a", (0, false)),
+        required_matches_implicit: ("{}{=_}{n}", (3, false)),
+        required_matches_indexed: ("{3}", (3, false)),
+        required_matches_entire: ("{0}-{n}", (1, true)),
+    );
+
+    macro_rules! keep_rest_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (r, s, expected) = $value;
+                    let replacer = Replacer::from(crate::parser::parse(r).unwrap())
+                        .with_keep_rest(true);
+                    assert_eq!(replacer.replace(s).unwrap().0, expected);
+                }
+            )*
+        }
+    }
+
+    keep_rest_tests!(
+        keep_rest_suffix: ("{}{=_}", "a-b.jpg", "a_b.jpg"),
+        keep_rest_trailing_any: ("{}{=_}{}", "a-b.jpg", "a_b.jpg"),
+        keep_rest_nothing_left: ("{}{n:03}", "a1", "a001"),
+        keep_rest_literal: ("x-", "a", "x-a"),
+    );
+
+    #[test]
+    fn replace_with_pattern() {
+        let parse = crate::parser::parse;
+        let replacer =
+            Replacer::with_pattern(&parse("{}{n}").unwrap(), parse("{2}-{1}").unwrap()).unwrap();
+        assert_eq!(replacer.replace("a12").unwrap().0, "12-a");
+        assert!(replacer.replace("ab").is_err());
+        assert!(Replacer::with_pattern(&parse("a{n}").unwrap(), vec![]).is_err());
+    }
+
+    #[test]
+    fn replace_index_out_of_range() {
+        let parts = Parts {
+            s: "a1",
+            matches: &[0, 1],
+        };
+        let spec = Spec {
+            matcher: Matcher::Any,
+            index: Some(3),
+            replace: None,
+            formatter: None,
+        };
+        let err = replace_spec(&spec, 1, &parts, &FormatOpts::new()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ReplaceError>(),
+            Some(&ReplaceError::IndexOutOfRange {
+                index: 3,
+                groups: 2
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "index 3 out of range (pattern expects at least 3 groups, matched 2)"
+        );
+    }
+
+    /// Parse and replace every short replacer built from special characters, which must never
+    /// panic.
+    #[test]
+    fn parse_replace_total() {
+        let alphabet: Vec<char> = "{}n1=:0#\\*?,a".chars().collect();
+        let mut replacers = vec![String::new()];
+        for _ in 0..4 {
+            replacers = replacers
+                .iter()
+                .flat_map(|r| alphabet.iter().map(move |&c| format!("{}{}", r, c)))
+                .collect();
+            for r in &replacers {
+                if let Ok(elems) = crate::parser::parse(r) {
+                    let replacer = Replacer::from(elems).with_all_occurrences(true);
+                    for s in &["", "a", "a1", "a-1b22"] {
+                        let _ = replacer.replace(s);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_number() {
+        let replacer = Replacer::new(&[
+            Elem::Spec(Spec::new(Matcher::Any)),
+            Elem::Spec(Spec::new(Matcher::Number)),
+        ]);
+        assert_eq!(replacer.matches("a-1").unwrap(), vec!["a-", "1"]);
+    }
+
     macro_rules! matchers_from_elems_tests {
         ($($name:ident: $value:expr,)*) => {
             $(
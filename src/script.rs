@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use crate::replacement::Replacement;
+use crate::sink::{write_all, Script};
+
+/// Shell to emit scripts for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// POSIX shell.
+    Sh,
+    /// PowerShell.
+    Ps1,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sh" => Ok(Shell::Sh),
+            "ps1" => Ok(Shell::Ps1),
+            _ => Err(format!("invalid shell: {}", s)),
+        }
+    }
+}
+
+/// Generate a script moving each file according to the replacements.
+///
+/// # Arguments
+///
+/// * `replacements` - Replacements.
+/// * `shell` - Shell to generate the script for.
+///
+/// # Returns
+///
+/// The script. The script stops at the first failure.
+pub fn move_script(replacements: &[Replacement], shell: Shell) -> String {
+    let mut script = vec![];
+    // Writing to a vector cannot fail.
+    write_all(&mut Script::new(&mut script, shell), replacements).unwrap();
+    String::from_utf8(script).unwrap()
+}
+
+/// Quote string for POSIX shell.
+pub fn quote_sh(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Quote string for PowerShell.
+///
+/// PowerShell also ends single-quoted strings at the typographic single quotes U+2018 to U+201B,
+/// so these are doubled too.
+pub fn quote_ps1(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('\'');
+    for c in s.chars() {
+        if matches!(c, '\'' | '\u{2018}'..='\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn move_script_sh() {
+        assert_eq!(
+            move_script(&[(Cow::Borrowed("a b"), "it's".to_owned())], Shell::Sh),
+            "#!/bin/sh\nset -e\nmv -- 'a b' 'it'\\''s'\n"
+        );
+    }
+
+    #[test]
+    fn move_script_ps1() {
+        assert_eq!(
+            move_script(&[(Cow::Borrowed("a b"), "it's".to_owned())], Shell::Ps1),
+            "$ErrorActionPreference = 'Stop'\nMove-Item -LiteralPath 'a b' -Destination 'it''s'\n"
+        );
+    }
+
+    #[test]
+    fn quote_ps1_typographic_quotes() {
+        assert_eq!(quote_ps1("it’s ‘a’ ‚b‛"), "'it’’s ‘‘a’’ ‚‚b‛‛'");
+        assert_eq!(quote_ps1("“a”"), "'“a”'");
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+
+use crate::replacement::{build_replacer, resolve_highlights, ResolveOpts};
+use crate::replacer::ReplaceSource;
+use crate::tokens::tokenize;
+
+/// Tokens items are generated from, covering each token type.
+pub const TOKENS: &[&str] = &[
+    "a", "abc", "Dark", "été", "日本", "0", "7", "001", "1234567", "3.14", "-", "_", ".", "[", "]",
+    "(", " ", "  ", "\t",
+];
+
+/// Matchers of generated specifiers.
+pub const MATCHERS: &[&str] = &["", "n", "t", "p", "x", "d"];
+
+/// Literals of generated replacers, free of characters special to the replacer syntax.
+pub const LITERALS: &[&str] = &["_", "-", ".", " ", "x", "new-", "é"];
+
+/// Format specifiers checked for idempotence.
+pub const FORMATS: &[&str] = &[
+    "03",
+    "5",
+    "03,",
+    ",",
+    ".2",
+    "8.3",
+    "titlecase",
+    "ordinal",
+    "si",
+    "bytes",
+];
+
+/// Pseudo-random number generator (xorshift64*), so that a seed always generates the same cases
+/// in `mrf selftest`. Tests check the same invariants with proptest instead, which also shrinks
+/// failing cases (see tests/invariants.rs).
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The state must not be zero.
+        Self(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Generate a number below `n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Pick an element of a slice.
+    fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+        choices[self.below(choices.len())]
+    }
+}
+
+/// Generate an item of 1 to 8 random tokens.
+pub fn item(rng: &mut Rng) -> String {
+    (0..1 + rng.below(8)).map(|_| rng.pick(TOKENS)).collect()
+}
+
+/// Generate a replacer of 1 to 4 specifiers without replacements or formatting, e.g. `{t}{}`.
+pub fn plain_replacer(rng: &mut Rng) -> String {
+    (0..1 + rng.below(4))
+        .map(|_| format!("{{{}}}", rng.pick(MATCHERS)))
+        .collect()
+}
+
+/// Generate a replacer of 1 to 4 specifiers without replacements or formatting, each optionally
+/// preceded by a literal.
+///
+/// # Returns
+///
+/// The replacer and its literals, in order.
+pub fn replacer(rng: &mut Rng) -> (String, Vec<&'static str>) {
+    let mut replacer = String::new();
+    let mut literals = vec![];
+    for _ in 0..1 + rng.below(4) {
+        if rng.below(2) == 0 {
+            let literal = rng.pick(LITERALS);
+            replacer.push_str(literal);
+            literals.push(literal);
+        }
+        replacer.push_str(&format!("{{{}}}", rng.pick(MATCHERS)));
+    }
+    (replacer, literals)
+}
+
+/// Check that the tokens of a string start at its start, are in order and fall on character
+/// boundaries, so that they reconstruct the string.
+pub fn check_tokens(s: &str) -> Result<(), String> {
+    let (indices, types) = tokenize(s);
+    if indices.len() != types.len() {
+        return Err(format!(
+            "{} token indices but {} token types",
+            indices.len(),
+            types.len()
+        ));
+    }
+    if !s.is_empty() && indices.first() != Some(&0) {
+        return Err(format!("tokens start at {:?}", indices.first()));
+    }
+    if indices.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(format!("token indices are not increasing: {:?}", indices));
+    }
+    match indices.iter().find(|&&i| !s.is_char_boundary(i)) {
+        Some(i) => Err(format!("token index {} is not a character boundary", i)),
+        None => Ok(()),
+    }
+}
+
+/// Check that the spans of a replacement cover the item and the replaced string, and that the
+/// replaced string is reconstructed by replacing each span with the match or literal it comes
+/// from. Items the replacer does not match are skipped.
+///
+/// # Arguments
+///
+/// * `item` - Item.
+/// * `replacer` - Replacer of specifiers without replacements or formatting, and literals.
+/// * `literals` - Literals of the replacer, in order.
+pub fn check_spans(item: &str, replacer: &str, literals: &[&str]) -> Result<(), String> {
+    let items = [item];
+    let highlights =
+        resolve_highlights(&items, replacer, ResolveOpts::new()).map_err(|e| e.to_string())?;
+    let highlight = match highlights.first() {
+        Some(highlight) => highlight,
+        None => return Ok(()),
+    };
+    check_cover(
+        "item",
+        item.len(),
+        highlight.left_spans.iter().map(|s| &s.0),
+    )?;
+    check_cover(
+        "replaced string",
+        highlight.right.len(),
+        highlight.right_spans.iter().map(|s| &s.0),
+    )?;
+    let mut literals = literals.iter();
+    let mut reconstructed = String::new();
+    for (_, source) in &highlight.right_spans {
+        match source {
+            ReplaceSource::Index(i) => {
+                let span = highlight
+                    .left_spans
+                    .get(*i)
+                    .ok_or_else(|| format!("span of match {} is missing", i))?;
+                reconstructed.push_str(&item[span.0.clone()]);
+            }
+            ReplaceSource::Literal => {
+                reconstructed.push_str(literals.next().ok_or("unexpected literal span")?)
+            }
+            source => return Err(format!("unexpected {:?} span", source)),
+        }
+    }
+    if reconstructed != highlight.right {
+        return Err(format!(
+            "spans reconstruct \"{}\" instead of \"{}\"",
+            reconstructed, highlight.right
+        ));
+    }
+    Ok(())
+}
+
+/// Check that ranges are contiguous and cover a string of length `len`.
+fn check_cover<'a>(
+    name: &str,
+    len: usize,
+    ranges: impl Iterator<Item = &'a std::ops::Range<usize>>,
+) -> Result<(), String> {
+    let mut end = 0;
+    for range in ranges {
+        if range.start != end {
+            return Err(format!(
+                "spans of the {} skip from {} to {}",
+                name, end, range.start
+            ));
+        }
+        end = range.end;
+    }
+    if end != len {
+        return Err(format!("spans of the {} end at {} of {}", name, end, len));
+    }
+    Ok(())
+}
+
+/// Check that a replacer of specifiers without replacements or formatting reproduces the item.
+/// Items the replacer does not match are skipped.
+pub fn check_identity(item: &str, replacer: &str) -> Result<(), String> {
+    let replacer = build_replacer(replacer, &ResolveOpts::new()).map_err(|e| e.to_string())?;
+    match replacer.replace(item) {
+        Ok((replaced, _)) if replaced != item => Err(format!("replaced with \"{}\"", replaced)),
+        _ => Ok(()),
+    }
+}
+
+/// Check that formatting the item with a format specifier, e.g. `03`, and then formatting the
+/// result again does not change it further.
+pub fn check_idempotent(item: &str, format: &str) -> Result<(), String> {
+    let replacer = build_replacer(&format!("{{:{}}}", format), &ResolveOpts::new())
+        .map_err(|e| e.to_string())?;
+    let once = replacer.replace(item).map_err(|e| e.to_string())?.0;
+    let twice = replacer.replace(&once).map_err(|e| e.to_string())?.0;
+    if once != twice {
+        return Err(format!("formatted as \"{}\", then as \"{}\"", once, twice));
+    }
+    Ok(())
+}
+
+/// Case on which an invariant does not hold.
+#[derive(Debug)]
+pub struct Failure {
+    /// Index of the case.
+    pub case: usize,
+    /// Name of the invariant.
+    pub check: &'static str,
+    pub item: String,
+    /// Replacer, or format specifier.
+    pub replacer: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "case {} failed {} with item {:?} and replacer {:?}: {}",
+            self.case, self.check, self.item, self.replacer, self.reason
+        )
+    }
+}
+
+/// Check the invariants on random cases.
+///
+/// # Arguments
+///
+/// * `seed` - Seed of the cases. The same seed generates the same cases.
+/// * `cases` - Number of cases.
+///
+/// # Returns
+///
+/// A `Result` containing the first case on which an invariant does not hold, if any.
+pub fn run(seed: u64, cases: usize) -> Result<(), Failure> {
+    let mut rng = Rng::new(seed);
+    for case in 0..cases {
+        let item = item(&mut rng);
+        let plain = plain_replacer(&mut rng);
+        let (replacer, literals) = replacer(&mut rng);
+        let format = rng.pick(FORMATS);
+        let checks: [(&'static str, &str, Result<(), String>); 4] = [
+            ("tokens", "", check_tokens(&item)),
+            ("identity", &plain, check_identity(&item, &plain)),
+            ("spans", &replacer, check_spans(&item, &replacer, &literals)),
+            ("idempotent", format, check_idempotent(&item, format)),
+        ];
+        for (check, replacer, result) in checks {
+            if let Err(reason) = result {
+                return Err(Failure {
+                    case,
+                    check,
+                    item,
+                    replacer: replacer.to_owned(),
+                    reason,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_cases() {
+        for seed in 0..8 {
+            if let Err(failure) = run(seed, 500) {
+                panic!("seed {}: {}", seed, failure);
+            }
+        }
+    }
+
+    #[test]
+    fn checks_detect_violations() {
+        assert!(check_identity("a-1", "{}{=_}{}").is_err());
+        assert!(check_spans("a-1", "{}_{}", &["-"]).is_err());
+        assert!(check_spans("a-1", "{}_{}", &["_"]).is_ok());
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::json;
+
+use crate::plan::quote_csv;
+use crate::replacement::Replacement;
+use crate::script::{quote_ps1, quote_sh, Shell};
+
+/// Destination of replacements, writing each as it is produced.
+pub trait OutputSink {
+    /// Write what precedes the replacements, e.g. a header.
+    fn begin(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Write a replacement.
+    fn write(&mut self, left: &str, right: &str) -> io::Result<()>;
+
+    /// Write what follows the replacements, and flush the output.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Write all replacements to a sink.
+pub fn write_all(sink: &mut dyn OutputSink, replacements: &[Replacement]) -> io::Result<()> {
+    sink.begin()?;
+    for (left, right) in replacements {
+        sink.write(left, right)?;
+    }
+    sink.finish()
+}
+
+/// Side of the replacements to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Both the item and the replaced string.
+    Both,
+    /// Only the item.
+    Left,
+    /// Only the replaced string.
+    Right,
+}
+
+/// Format of machine-readable output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A JSON object with `left` and `right` fields per line.
+    Json,
+    /// CSV with a `left,right` header, as read by `mrf apply`.
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!("invalid output format: {}", s)),
+        }
+    }
+}
+
+/// Human-readable lines, `left -> right` or one side only.
+pub struct Pretty<W: Write> {
+    out: W,
+    side: Side,
+}
+
+impl<W: Write> Pretty<W> {
+    pub fn new(out: W, side: Side) -> Self {
+        Self { out, side }
+    }
+}
+
+impl<W: Write> OutputSink for Pretty<W> {
+    fn write(&mut self, left: &str, right: &str) -> io::Result<()> {
+        match self.side {
+            Side::Both => writeln!(self.out, "{} -> {}", left, right),
+            Side::Left => writeln!(self.out, "{}", left),
+            Side::Right => writeln!(self.out, "{}", right),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Strings terminated by NUL characters, the item followed by the replaced string or one side
+/// only, e.g. for `xargs -0`.
+pub struct NulPairs<W: Write> {
+    out: W,
+    side: Side,
+}
+
+impl<W: Write> NulPairs<W> {
+    pub fn new(out: W, side: Side) -> Self {
+        Self { out, side }
+    }
+}
+
+impl<W: Write> OutputSink for NulPairs<W> {
+    fn write(&mut self, left: &str, right: &str) -> io::Result<()> {
+        match self.side {
+            Side::Both => write!(self.out, "{}\0{}\0", left, right),
+            Side::Left => write!(self.out, "{}\0", left),
+            Side::Right => write!(self.out, "{}\0", right),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// A JSON object with `left` and `right` fields per line.
+pub struct JsonLines<W: Write> {
+    out: W,
+}
+
+impl<W: Write> JsonLines<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: Write> OutputSink for JsonLines<W> {
+    fn write(&mut self, left: &str, right: &str) -> io::Result<()> {
+        writeln!(self.out, "{}", json!({ "left": left, "right": right }))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// CSV with a `left,right` header, in the format of CSV plans.
+pub struct Csv<W: Write> {
+    out: W,
+}
+
+impl<W: Write> Csv<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: Write> OutputSink for Csv<W> {
+    fn begin(&mut self) -> io::Result<()> {
+        writeln!(self.out, "left,right")
+    }
+
+    fn write(&mut self, left: &str, right: &str) -> io::Result<()> {
+        writeln!(self.out, "{},{}", quote_csv(left), quote_csv(right))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Part of a template.
+#[derive(Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Left,
+    Right,
+}
+
+/// A line per replacement, formatted with a template in which `{left}` and `{right}` are
+/// replaced with the item and the replaced string, e.g. `mv {left} {right}`.
+pub struct Template<W: Write> {
+    out: W,
+    parts: Vec<Part>,
+}
+
+impl<W: Write> Template<W> {
+    pub fn new(out: W, template: &str) -> Self {
+        let mut parts = vec![];
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let (part, after) = if let Some(after) = rest[start..].strip_prefix("{left}") {
+                (Part::Left, after)
+            } else if let Some(after) = rest[start..].strip_prefix("{right}") {
+                (Part::Right, after)
+            } else {
+                parts.push(Part::Text(rest[..=start].to_owned()));
+                rest = &rest[start + 1..];
+                continue;
+            };
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_owned()));
+            }
+            parts.push(part);
+            rest = after;
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+        Self { out, parts }
+    }
+}
+
+impl<W: Write> OutputSink for Template<W> {
+    fn write(&mut self, left: &str, right: &str) -> io::Result<()> {
+        for part in &self.parts {
+            self.out.write_all(match part {
+                Part::Text(text) => text.as_bytes(),
+                Part::Left => left.as_bytes(),
+                Part::Right => right.as_bytes(),
+            })?;
+        }
+        self.out.write_all(b"\n")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// A script moving each item to the replaced string, stopping at the first failure.
+pub struct Script<W: Write> {
+    out: W,
+    shell: Shell,
+    /// Parent directories to create before moving, if creating them. Each is created once.
+    parents: Option<HashSet<String>>,
+}
+
+impl<W: Write> Script<W> {
+    pub fn new(out: W, shell: Shell) -> Self {
+        Self {
+            out,
+            shell,
+            parents: None,
+        }
+    }
+
+    /// Create the parent directories of each replaced string before moving to it.
+    pub fn with_create_parents(mut self, create_parents: bool) -> Self {
+        self.parents = if create_parents {
+            Some(HashSet::new())
+        } else {
+            None
+        };
+        self
+    }
+}
+
+impl<W: Write> OutputSink for Script<W> {
+    fn begin(&mut self) -> io::Result<()> {
+        match self.shell {
+            Shell::Sh => write!(self.out, "#!/bin/sh\nset -e\n"),
+            Shell::Ps1 => writeln!(self.out, "$ErrorActionPreference = 'Stop'"),
+        }
+    }
+
+    fn write(&mut self, left: &str, right: &str) -> io::Result<()> {
+        if let Some(parents) = &mut self.parents {
+            let parent = Path::new(right).parent().unwrap_or_else(|| Path::new(""));
+            let parent = parent.to_string_lossy();
+            if !parent.is_empty() && parents.insert(parent.to_string()) {
+                match self.shell {
+                    Shell::Sh => writeln!(self.out, "mkdir -p -- {}", quote_sh(&parent))?,
+                    Shell::Ps1 => writeln!(
+                        self.out,
+                        "New-Item -ItemType Directory -Force -Path {} | Out-Null",
+                        quote_ps1(&parent)
+                    )?,
+                }
+            }
+        }
+        match self.shell {
+            Shell::Sh => writeln!(self.out, "mv -- {} {}", quote_sh(left), quote_sh(right)),
+            Shell::Ps1 => writeln!(
+                self.out,
+                "Move-Item -LiteralPath {} -Destination {}",
+                quote_ps1(left),
+                quote_ps1(right)
+            ),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    /// Write replacements to a sink created over a buffer, returning the output.
+    fn output(new: fn(&mut Vec<u8>) -> Box<dyn OutputSink + '_>) -> String {
+        let mut buf = vec![];
+        let replacements = [
+            (Cow::Borrowed("a-1"), "a_1".to_owned()),
+            (Cow::Borrowed("b,\"c\""), "{right}".to_owned()),
+        ];
+        write_all(new(&mut buf).as_mut(), &replacements).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn pretty_and_nul() {
+        assert_eq!(
+            output(|out| Box::new(Pretty::new(out, Side::Both))),
+            "a-1 -> a_1\nb,\"c\" -> {right}\n"
+        );
+        assert_eq!(
+            output(|out| Box::new(Pretty::new(out, Side::Right))),
+            "a_1\n{right}\n"
+        );
+        assert_eq!(
+            output(|out| Box::new(NulPairs::new(out, Side::Both))),
+            "a-1\0a_1\0b,\"c\"\0{right}\0"
+        );
+        assert_eq!(
+            output(|out| Box::new(NulPairs::new(out, Side::Left))),
+            "a-1\0b,\"c\"\0"
+        );
+    }
+
+    #[test]
+    fn json_and_csv() {
+        assert_eq!(
+            output(|out| Box::new(JsonLines::new(out))),
+            "{\"left\":\"a-1\",\"right\":\"a_1\"}\n{\"left\":\"b,\\\"c\\\"\",\"right\":\"{right}\"}\n"
+        );
+        assert_eq!(
+            output(|out| Box::new(Csv::new(out))),
+            "left,right\na-1,a_1\n\"b,\"\"c\"\"\",{right}\n"
+        );
+    }
+
+    #[test]
+    fn script_create_parents() {
+        let replacements = [
+            (Cow::Borrowed("a-1"), "2020/01/a_1".to_owned()),
+            (Cow::Borrowed("b-2"), "2020/01/b_2".to_owned()),
+            (Cow::Borrowed("c-3"), "c_3".to_owned()),
+        ];
+        let script = |shell| {
+            let mut buf = vec![];
+            let mut sink = Script::new(&mut buf, shell).with_create_parents(true);
+            write_all(&mut sink, &replacements).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(
+            script(Shell::Sh),
+            "#!/bin/sh\nset -e\nmkdir -p -- '2020/01'\nmv -- 'a-1' '2020/01/a_1'\n\
+             mv -- 'b-2' '2020/01/b_2'\nmv -- 'c-3' 'c_3'\n"
+        );
+        assert_eq!(
+            script(Shell::Ps1),
+            "$ErrorActionPreference = 'Stop'\n\
+             New-Item -ItemType Directory -Force -Path '2020/01' | Out-Null\n\
+             Move-Item -LiteralPath 'a-1' -Destination '2020/01/a_1'\n\
+             Move-Item -LiteralPath 'b-2' -Destination '2020/01/b_2'\n\
+             Move-Item -LiteralPath 'c-3' -Destination 'c_3'\n"
+        );
+    }
+
+    #[test]
+    fn template() {
+        assert_eq!(
+            output(|out| Box::new(Template::new(out, "{} {left} {{right}} {lef"))),
+            "{} a-1 {a_1} {lef\n{} b,\"c\" {{right}} {lef\n"
+        );
+    }
+}
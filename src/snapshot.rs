@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::replacement::Replacement;
+
+/// Listing of the directories affected by replacements, for diffing before and after moving.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Directories listed, i.e. the parents of the sources and destinations.
+    dirs: Vec<PathBuf>,
+    /// Paths of the entries in `dirs`, sorted.
+    entries: BTreeSet<String>,
+    /// Absolute paths of files not to list, e.g. the snapshots themselves.
+    skip: Vec<PathBuf>,
+}
+
+impl Snapshot {
+    /// List the directories containing the sources and destinations of the replacements.
+    ///
+    /// Directories are not listed recursively. Missing directories are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `replacements` - Replacements.
+    /// * `skip` - Files not to list, e.g. the snapshots themselves.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the snapshot. Fails if a directory cannot be read.
+    pub fn take(replacements: &[Replacement], skip: &[&Path]) -> io::Result<Self> {
+        let dirs: BTreeSet<PathBuf> = replacements
+            .iter()
+            .flat_map(|(left, right)| vec![Path::new(left.as_ref()), Path::new(right)])
+            .map(|path| path.parent().unwrap_or_else(|| Path::new("")).to_owned())
+            .collect();
+        let mut snapshot = Self {
+            dirs: dirs.into_iter().collect(),
+            entries: BTreeSet::new(),
+            skip: skip.iter().filter_map(|path| absolute(path)).collect(),
+        };
+        snapshot.entries = snapshot.list()?;
+        Ok(snapshot)
+    }
+
+    /// List the same directories again.
+    pub fn retake(&self) -> io::Result<Self> {
+        Ok(Self {
+            entries: self.list()?,
+            ..self.clone()
+        })
+    }
+
+    /// Get the snapshot expected after moving each source to its destination.
+    pub fn planned(&self, replacements: &[Replacement]) -> Self {
+        let mut entries = self.entries.clone();
+        for (left, _) in replacements {
+            entries.remove(left.as_ref());
+        }
+        for (_, right) in replacements {
+            entries.insert(right.clone());
+        }
+        Self {
+            entries,
+            ..self.clone()
+        }
+    }
+
+    /// Write the snapshot to a file, one path per line.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// List the entries of the directories.
+    fn list(&self) -> io::Result<BTreeSet<String>> {
+        let mut entries = BTreeSet::new();
+        for dir in &self.dirs {
+            let read_dir = match fs::read_dir(non_empty(dir)) {
+                Ok(read_dir) => read_dir,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let canonical = non_empty(dir).canonicalize()?;
+            for entry in read_dir {
+                let name = entry?.file_name();
+                if !self.skip.contains(&canonical.join(&name)) {
+                    entries.insert(dir.join(name).to_string_lossy().into_owned());
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Get the path of the current directory in place of an empty path.
+fn non_empty(dir: &Path) -> &Path {
+    if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    }
+}
+
+/// Get the absolute path of a file which may not exist yet, given that its parent exists.
+fn absolute(path: &Path) -> Option<PathBuf> {
+    let parent = non_empty(path.parent().unwrap_or_else(|| Path::new("")));
+    Some(parent.canonicalize().ok()?.join(path.file_name()?))
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn snapshot_planned_and_retaken() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a").touch().unwrap();
+        temp.child("b").touch().unwrap();
+        let path = |name: &str| temp.child(name).path().to_string_lossy().into_owned();
+        let (a, c) = (path("a"), path("c"));
+        let replacements = vec![(Cow::Borrowed(a.as_str()), c.clone())];
+        let skip = temp.child("snapshot");
+        skip.touch().unwrap();
+        let before = Snapshot::take(&replacements, &[skip.path()]).unwrap();
+        assert_eq!(before.to_string(), format!("{}\n{}\n", a, path("b")));
+        let planned = before.planned(&replacements);
+        assert_eq!(planned.to_string(), format!("{}\n{}\n", path("b"), c));
+        fs::rename(&a, &c).unwrap();
+        assert_eq!(before.retake().unwrap(), planned);
+    }
+}
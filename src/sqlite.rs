@@ -0,0 +1,140 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Text column of a table in an SQLite database, read and updated with the `sqlite3` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    /// Path of the database.
+    pub db: PathBuf,
+    pub table: String,
+    pub column: String,
+}
+
+impl Column {
+    /// Read the values of the column, skipping `NULL` values.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the rowid and value of each row, in the order of the rowids. Fails if
+    /// `sqlite3` fails, e.g. if the table or column does not exist.
+    pub fn read(&self) -> Result<Vec<(i64, String)>, String> {
+        // Values are read as hexadecimal, so that they may contain separators and newlines.
+        let query = format!(
+            "SELECT rowid, hex(CAST({} AS BLOB)) FROM {} WHERE {} IS NOT NULL ORDER BY rowid;",
+            quote(&self.column),
+            quote(&self.table),
+            quote(&self.column)
+        );
+        let output = self.sqlite3(&["-readonly", "-list", "-separator", " "], &query)?;
+        output
+            .lines()
+            .map(|line| {
+                let (rowid, value) = line
+                    .split_once(' ')
+                    .ok_or_else(|| format!("unexpected sqlite3 output: {}", line))?;
+                let rowid = rowid
+                    .parse()
+                    .map_err(|_| format!("unexpected sqlite3 output: {}", line))?;
+                let value = decode_hex(value)
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| format!("value of row {} is not valid UTF-8", rowid))?;
+                Ok((rowid, value))
+            })
+            .collect()
+    }
+
+    /// Set the values of rows in a single transaction, so that either all or none are updated.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - Rowid and new value of each row.
+    pub fn update(&self, rows: &[(i64, &str)]) -> Result<(), String> {
+        let mut script = String::from("BEGIN;\n");
+        for (rowid, value) in rows {
+            script.push_str(&format!(
+                "UPDATE {} SET {} = CAST(X'{}' AS TEXT) WHERE rowid = {};\n",
+                quote(&self.table),
+                quote(&self.column),
+                encode_hex(value.as_bytes()),
+                rowid
+            ));
+        }
+        script.push_str("COMMIT;\n");
+        // With -bail, sqlite3 exits on the first error before committing, rolling back.
+        self.sqlite3(&["-bail"], &script).map(|_| ())
+    }
+
+    /// Run `sqlite3` on the database with SQL given on stdin.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the output. Fails if `sqlite3` cannot be run or fails.
+    fn sqlite3(&self, args: &[&str], sql: &str) -> Result<String, String> {
+        let mut child = Command::new("sqlite3")
+            .arg("-batch")
+            .args(args)
+            .arg(&self.db)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to run sqlite3: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(sql.as_bytes())
+                .map_err(|e| format!("failed to run sqlite3: {}", e))?;
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("failed to run sqlite3: {}", e))?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !stderr.trim().is_empty() {
+            return Err(format!(
+                "sqlite3 failed on {}: {}",
+                self.db.display(),
+                stderr.trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Quote an identifier, e.g. a table name.
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_identifiers() {
+        assert_eq!(quote("files"), "\"files\"");
+        assert_eq!(quote("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(encode_hex("a/é".as_bytes()), "612FC3A9");
+        assert_eq!(decode_hex("612FC3A9").unwrap(), "a/é".as_bytes());
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("6"), None);
+        assert_eq!(decode_hex("ZZ"), None);
+    }
+}
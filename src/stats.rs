@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::replacement::Highlight;
+
+/// Summary statistics of replacements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    /// Number of items.
+    pub items: usize,
+    /// Number of matched items.
+    pub matched: usize,
+    /// Number of distinct replaced strings.
+    pub distinct: usize,
+    /// Distribution of the lengths (in characters) of each match group.
+    pub lengths: Vec<Distribution>,
+}
+
+/// Distribution of lengths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub median: usize,
+}
+
+impl Stats {
+    /// Compute statistics of replacements.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - Number of items, including unmatched items.
+    /// * `highlights` - Highlighted replacements of the matched items.
+    ///
+    /// # Returns
+    ///
+    /// The statistics.
+    pub fn new(items: usize, highlights: &[Highlight]) -> Self {
+        let distinct = highlights
+            .iter()
+            .map(|h| h.right.as_str())
+            .collect::<HashSet<&str>>()
+            .len();
+        let groups = highlights.iter().map(|h| h.left_spans.len()).max();
+        let lengths = (0..groups.unwrap_or(0))
+            .filter_map(|g| {
+                let lengths: Vec<usize> = highlights
+                    .iter()
+                    .filter_map(|h| {
+                        let (range, _) = h.left_spans.get(g)?;
+                        Some(h.left[range.clone()].chars().count())
+                    })
+                    .collect();
+                Distribution::new(lengths)
+            })
+            .collect();
+        Self {
+            items,
+            matched: highlights.len(),
+            distinct,
+            lengths,
+        }
+    }
+}
+
+impl Distribution {
+    /// Compute the distribution of lengths, or `None` if there are none.
+    fn new(mut lengths: Vec<usize>) -> Option<Self> {
+        if lengths.is_empty() {
+            return None;
+        }
+        lengths.sort_unstable();
+        Some(Self {
+            min: lengths[0],
+            max: lengths[lengths.len() - 1],
+            mean: lengths.iter().sum::<usize>() as f64 / lengths.len() as f64,
+            median: lengths[lengths.len() / 2],
+        })
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Items: {}", self.items)?;
+        writeln!(f, "Matched: {}", self.matched)?;
+        writeln!(f, "Unmatched: {}", self.items - self.matched)?;
+        write!(f, "Distinct outputs: {}", self.distinct)?;
+        if !self.lengths.is_empty() {
+            write!(f, "\nMatch lengths:")?;
+        }
+        for (i, d) in self.lengths.iter().enumerate() {
+            write!(
+                f,
+                "\n    Group {}: min {}, max {}, mean {:.1}, median {}",
+                i + 1,
+                d.min,
+                d.max,
+                d.mean,
+                d.median
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replacement::{resolve_highlights, ResolveOpts};
+
+    #[test]
+    fn stats() {
+        let items = ["a-1", "bb-22", "ccc-1", "x"];
+        let highlights = resolve_highlights(&items, "{=y}{}{n}", ResolveOpts::new()).unwrap();
+        let stats = Stats::new(items.len(), &highlights);
+        assert_eq!(stats.items, 4);
+        assert_eq!(stats.matched, 3);
+        assert_eq!(stats.distinct, 2);
+        assert_eq!(
+            stats.lengths[0],
+            Distribution {
+                min: 1,
+                max: 3,
+                mean: 2.0,
+                median: 2,
+            }
+        );
+        assert_eq!(
+            stats.to_string(),
+            "Items: 4\nMatched: 3\nUnmatched: 1\nDistinct outputs: 2\nMatch lengths:
+    Group 1: min 1, max 3, mean 2.0, median 2
+    Group 2: min 1, max 1, mean 1.0, median 1
+    Group 3: min 1, max 2, mean 1.3, median 1"
+        );
+    }
+}
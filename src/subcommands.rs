@@ -1,4 +1,23 @@
+pub mod apply;
+pub mod check;
+pub mod chmod;
+pub mod confirm;
+pub mod cp;
+pub mod diff;
 pub mod exec;
+pub mod history;
 pub mod map;
 pub mod mv;
+pub mod plan;
+pub mod playground;
+pub mod progress;
+pub mod refactor;
+pub mod replace;
+pub mod select;
+pub mod selftest;
+pub mod serve;
+pub mod snapshot;
+pub mod source;
+pub mod tag;
+pub mod touch;
 pub mod utils;
@@ -0,0 +1,119 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::{AppSettings, Clap};
+
+use super::confirm::ConfirmOpts;
+use super::mv::{move_all, Hooks};
+use super::progress::ProgressOpts;
+use super::snapshot::SnapshotOpts;
+use super::utils::{check_collisions, check_reserved, setup_rayon};
+
+use crate::fsops::MoveOpts;
+use crate::plan::{self, Format};
+use crate::replacement::{format_long_names, format_previews, long_names};
+use crate::script::Shell;
+use crate::sink::{write_all, Script};
+
+/// Move each file according to a plan file written by `mrf plan`.
+///
+/// The plan may be edited by hand before applying it.
+///
+/// Examples:
+///
+/// 1. Apply a plan:
+///
+///     $ mrf apply plan.json
+///     Moving 1 out of 1 items:
+///         image-001.jpg -> image_001.jpg
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+pub struct Opts {
+    /// When copying between filesystems, verify the copied contents before removing the source.
+    #[clap(long)]
+    verify: bool,
+    /// Print an equivalent script for the given shell instead of moving files.
+    #[clap(long, possible_values = &["sh", "ps1"])]
+    emit_script: Option<Shell>,
+    /// Number of threads to use.
+    #[clap(short, long)]
+    concurrency: Option<usize>,
+    /// Plan format. Inferred from the plan file extension if not specified, defaulting to json.
+    #[clap(short, long, possible_values = &["json", "csv"])]
+    format: Option<Format>,
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    #[clap(flatten)]
+    progress: ProgressOpts,
+    #[clap(flatten)]
+    snapshot: SnapshotOpts,
+    /// Plan file.
+    plan: PathBuf,
+}
+
+/// Run apply subcommand.
+pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+    let concurrency = opts.concurrency.unwrap_or(0);
+    setup_rayon(concurrency)?;
+    let format = opts.format.unwrap_or_else(|| Format::from_path(&opts.plan));
+    let replacements = plan::from_str(&fs::read_to_string(&opts.plan)?, format)?;
+    // The plan may have been edited by hand, so it is checked as if it was resolved here.
+    check_collisions(&replacements, false)?;
+    let snapshot = opts.snapshot.before(&replacements)?;
+    if let Some(shell) = opts.emit_script {
+        let mut sink = Script::new(io::BufWriter::new(io::stdout().lock()), shell);
+        write_all(&mut sink, &replacements)?;
+        opts.snapshot.after(snapshot, &replacements, false)?;
+        return Ok(());
+    }
+    // Items finished before an interruption were moved already, and are skipped.
+    let finished = opts.progress.finished()?;
+    let missing: Vec<&str> = replacements
+        .iter()
+        .filter(|(left, right)| !finished.contains(&(left.to_string(), right.clone())))
+        .map(|(left, _)| left.as_ref())
+        .filter(|left| !Path::new(left).exists())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "{} items in the plan do not exist:\n    {}",
+            missing.len(),
+            missing.join("\n    ")
+        )
+        .into());
+    }
+    let long_names = long_names(&replacements);
+    if !long_names.is_empty() {
+        return Err(format!(
+            "names are too long for the filesystem:\n{}",
+            format_long_names(&long_names)
+        )
+        .into());
+    }
+    check_reserved(&replacements)?;
+    if !opts.confirm.confirm(|max_previews| {
+        Ok(format!(
+            "Moving {} out of {} items:\n{}",
+            replacements.len(),
+            replacements.len(),
+            format_previews(&replacements, max_previews)
+        ))
+    })? {
+        opts.snapshot.after(snapshot, &replacements, false)?;
+        return Ok(());
+    }
+    move_all(
+        &replacements,
+        &MoveOpts {
+            verify: opts.verify,
+            ..MoveOpts::new()
+        },
+        &Hooks::new(),
+        &opts.progress,
+    )?;
+    opts.snapshot.after(snapshot, &replacements, true)?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::error::Error;
+
+use clap::{AppSettings, Clap};
+
+use crate::elem::Elem;
+use crate::lint::lint;
+use crate::parser::{parse_with_syntax, Syntax};
+use crate::replacer::Replacer;
+
+/// Check a replacer, printing how it is interpreted and warning about common mistakes.
+///
+/// Examples:
+///
+/// 1. Check a replacer:
+///
+///     $ mrf check '{2}{1}{}'
+///     Elem  Kind  Group  Matcher  Replace  Format
+///     1     Spec  2      Any
+///     2     Spec  1      Any
+///     3     Spec  2      Any
+///     Warning: element 3: outputs group 2 but its matcher applies to group 3, specify the index explicitly
+///     Warning: group 3 is matched but never output, use {=} to remove it explicitly
+///
+/// 2. Check a replacer against sample items:
+///
+///     $ mrf check '{}{=_}{n}' a-1 b
+///     Elem  Kind  Group  Matcher  Replace  Format
+///     1     Spec  1      Any
+///     2     Spec  2      Any      "_"
+///     3     Spec  3      Number
+///     a-1 -> a_1
+///     Error: b: unable to match specifiers with input
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+pub struct Opts {
+    /// Fail if there are any warnings, or if the replacer fails on any sample item.
+    #[clap(long)]
+    strict: bool,
+    /// Replacer syntax.
+    #[clap(long, default_value = "braces", possible_values = &["braces", "angle", "percent"])]
+    syntax: Syntax,
+    /// Replacer string.
+    replacer: String,
+    /// Sample items to apply the replacer to.
+    item: Vec<String>,
+}
+
+/// Run check subcommand.
+pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+    let elems = parse_with_syntax(&opts.replacer, opts.syntax)?;
+    let (infos, warnings) = lint(&elems);
+    let mut rows = vec![[
+        "Elem".to_owned(),
+        "Kind".to_owned(),
+        "Group".to_owned(),
+        "Matcher".to_owned(),
+        "Replace".to_owned(),
+        "Format".to_owned(),
+    ]];
+    for (i, (e, info)) in elems.iter().zip(&infos).enumerate() {
+        let (kind, replace, format) = match e {
+            Elem::Lit(lit) => ("Lit", format!("{:?}", lit), String::new()),
+            Elem::Spec(spec) => (
+                "Spec",
+                spec.replace
+                    .as_ref()
+                    .map(|r| format!("{:?}", r))
+                    .unwrap_or_default(),
+                spec.formatter
+                    .as_ref()
+                    .map(|f| f.to_string())
+                    .unwrap_or_default(),
+            ),
+            Elem::Counter(formatter) => (
+                "Count",
+                String::new(),
+                formatter
+                    .as_ref()
+                    .map(|f| f.to_string())
+                    .unwrap_or_default(),
+            ),
+            Elem::Meta(field) => ("Meta", field.to_string(), String::new()),
+        };
+        rows.push([
+            (i + 1).to_string(),
+            kind.to_owned(),
+            info.group.map(|g| g.to_string()).unwrap_or_default(),
+            info.matcher
+                .as_ref()
+                .map(|m| m.to_string())
+                .unwrap_or_default(),
+            replace,
+            format,
+        ]);
+    }
+    println!("{}", format_table(&rows));
+    for warning in &warnings {
+        println!("Warning: {}", warning);
+    }
+    let replacer = Replacer::from(elems);
+    let mut errors = 0;
+    for item in &opts.item {
+        match replacer.replace(item) {
+            Ok((replaced, _)) => println!("{} -> {}", item, replaced),
+            Err(e) => {
+                println!("Error: {}: {}", item, e);
+                errors += 1;
+            }
+        }
+    }
+    if opts.strict && !warnings.is_empty() {
+        return Err(format!("found {} warnings", warnings.len()).into());
+    }
+    if opts.strict && errors > 0 {
+        return Err(format!("replacer failed on {} items", errors).into());
+    }
+    Ok(())
+}
+
+/// Format rows into left-aligned columns separated by two spaces.
+fn format_table(rows: &[[String; 6]]) -> String {
+    let mut widths = [0; 6];
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, w)| format!("{:w$}", cell, w = w))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
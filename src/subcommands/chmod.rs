@@ -0,0 +1,177 @@
+use std::error::Error;
+use std::io;
+use std::mem;
+use std::path::Path;
+
+use clap::{AppSettings, Clap};
+use rayon::prelude::*;
+
+use super::confirm::ConfirmOpts;
+use super::progress::ProgressOpts;
+use super::replace::ReplaceOpts;
+use super::select::{select, SelectOpts};
+use super::source::SourceOpts;
+use super::utils::setup_rayon;
+
+use crate::fsops::{set_mode, Mode};
+use crate::replacement::{previews, resolve, PreviewOpts, Replacement};
+
+/// Set the permissions of each file to the mode given by the replacer.
+///
+/// The replaced string of each file must be an octal mode, e.g. "755". On Windows, only the
+/// owner's write permission applies, making files without it read-only.
+///
+/// Examples:
+///
+/// 1. Make scripts executable:
+///
+///     $ mrf chmod *.sh '{=755}'
+///     Changing mode of 1 out of 1 items:
+///         build.sh -> 755
+///
+/// 2. Use the mode suffixed to each file:
+///
+///     $ mrf chmod * '{=}{n}'
+///     Changing mode of 1 out of 1 items:
+///         key-600 -> 600
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf chmod [FLAGS] [OPTIONS] <item>... <replacer>",
+    verbatim_doc_comment
+)]
+pub struct Opts {
+    /// Number of threads to use.
+    #[clap(short, long)]
+    concurrency: Option<usize>,
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    #[clap(flatten)]
+    progress: ProgressOpts,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Files to change, followed by the replacer string unless "--to" is given. Pass "-" to read
+    /// from stdin.
+    #[clap(required = true)]
+    item: Vec<String>,
+    /// Replacer string.
+    #[clap(skip)]
+    replacer: String,
+}
+
+/// Run chmod subcommand.
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let concurrency = opts.concurrency.unwrap_or(0);
+    setup_rayon(concurrency)?;
+    let (replacer, items) = opts
+        .source
+        .take_items(&mut opts.replace, mem::take(&mut opts.item))?;
+    opts.replacer = replacer;
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let replacements = resolve(&items, &opts.replacer, opts.replace.resolve_opts())?;
+    let modes = parse_all(&replacements, |s| s.parse::<Mode>())?;
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    if !opts.confirm.confirm(|max_previews| {
+        Ok(format!(
+            "Changing mode of {} out of {} items:\n{}",
+            replacements.len(),
+            items.len(),
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })? {
+        return Ok(());
+    }
+    set_all(&replacements, &modes, &opts.progress, |path, mode| {
+        set_mode(path, *mode)
+    })?;
+    Ok(())
+}
+
+/// Parse the replaced string of each replacement, before changing any file.
+///
+/// # Returns
+///
+/// A `Result` containing the parsed values. Fails listing each replacement which cannot be
+/// parsed.
+pub fn parse_all<T, F>(replacements: &[Replacement], parse: F) -> Result<Vec<T>, String>
+where
+    F: Fn(&str) -> Result<T, String>,
+{
+    let mut values = vec![];
+    let mut invalid = vec![];
+    for (left, right) in replacements {
+        match parse(right) {
+            Ok(value) => values.push(value),
+            Err(e) => invalid.push(format!("    {} -> {}", left, e)),
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(format!("invalid replacements:\n{}", invalid.join("\n")));
+    }
+    Ok(values)
+}
+
+/// Set an attribute of the file of each replacement to its parsed value, reporting progress and
+/// printing the items which failed.
+pub fn set_all<T, F>(
+    replacements: &[Replacement],
+    values: &[T],
+    progress: &ProgressOpts,
+    set: F,
+) -> io::Result<()>
+where
+    T: Sync,
+    F: Fn(&Path, &T) -> io::Result<()> + Sync,
+{
+    let progress = progress.start(replacements.len())?;
+    let statuses: Vec<io::Result<()>> = replacements
+        .par_iter()
+        .zip(values)
+        .enumerate()
+        .map(|(index, ((left, right), value))| {
+            if progress.skip(index, left, right) {
+                return Ok(());
+            }
+            progress.started(index, left, right);
+            let status = set(Path::new(left.as_ref()), value);
+            let error = status.as_ref().err().map(|e| e.to_string());
+            progress.finished(index, left, right, None, error);
+            status
+        })
+        .collect();
+    let failures: Vec<String> = replacements
+        .iter()
+        .zip(&statuses)
+        .filter_map(|((left, right), status)| {
+            let e = status.as_ref().err()?;
+            Some(format!("    {} -> {}: {}", left, right, e))
+        })
+        .collect();
+    progress.done(replacements.len(), failures.len());
+    if !failures.is_empty() {
+        eprintln!(
+            "Failed {} out of {} items:",
+            failures.len(),
+            replacements.len()
+        );
+        eprintln!("{}", failures.join("\n"));
+    }
+    Ok(())
+}
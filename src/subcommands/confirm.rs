@@ -0,0 +1,183 @@
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::process::Stdio;
+use std::str::FromStr;
+
+use clap::Clap;
+use console::Term;
+use dialoguer::Confirm;
+
+use crate::command::{self, ParseOpts};
+use crate::replacement::DEFAULT_MAX_PREVIEWS;
+
+const PROMPT: &str = "Do you want to continue?";
+
+/// Default pager, used when `PAGER` is not set.
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Options controlling the preview and confirmation prompt.
+#[derive(Clap)]
+pub struct ConfirmOpts {
+    /// Assume yes as answer to all prompts and run non-interactively.
+    #[clap(short = "y", long, conflicts_with = "no")]
+    assume_yes: bool,
+    /// Show the preview and answer no to all prompts, without making any changes.
+    #[clap(long, conflicts_with = "assume-yes")]
+    no: bool,
+    /// Default answer of prompts, chosen by pressing Enter.
+    #[clap(long, default_value = "no", possible_values = &["yes", "no"])]
+    prompt_default: Answer,
+    /// Preview all items instead of only a few. Long previews are shown in $PAGER (or less).
+    #[clap(long)]
+    preview_all: bool,
+}
+
+/// Answer to a yes/no prompt.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    Yes,
+    No,
+}
+
+impl FromStr for Answer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yes" => Ok(Answer::Yes),
+            "no" => Ok(Answer::No),
+            _ => Err(format!("invalid answer: {}", s)),
+        }
+    }
+}
+
+impl ConfirmOpts {
+    /// Show a preview and ask the user whether to continue.
+    ///
+    /// # Arguments
+    ///
+    /// * `preview` - Function building the preview given the maximum number of items to show.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing `true` if the user chose to continue (or `assume_yes` is set), and
+    /// `false` if the user declined (or `no` is set).
+    pub fn confirm<F>(&self, preview: F) -> Result<bool, Box<dyn Error>>
+    where
+        F: FnOnce(usize) -> Result<String, Box<dyn Error>>,
+    {
+        if self.assume_yes {
+            return Ok(true);
+        }
+        let max_previews = if self.preview_all {
+            usize::MAX
+        } else {
+            DEFAULT_MAX_PREVIEWS
+        };
+        show(&preview(max_previews)?);
+        if self.no {
+            return Ok(false);
+        }
+        let default = self.prompt_default == Answer::Yes;
+        if !atty::is(atty::Stream::Stdin) {
+            return confirm_line(PROMPT, default);
+        }
+        Ok(Confirm::new()
+            .with_prompt(PROMPT)
+            .default(default)
+            .interact()?)
+    }
+}
+
+/// Ask for confirmation by reading a plain line from stdin, for when stdin is not a terminal.
+///
+/// Fails if stdin is closed, since there is nobody to answer.
+fn confirm_line(prompt: &str, default: bool) -> Result<bool, Box<dyn Error>> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    loop {
+        print!("{} {} ", prompt, hint);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            println!();
+            return Err(
+                "stdin is not interactive, pass -y (--assume-yes) to continue without confirmation"
+                    .into(),
+            );
+        }
+        match parse_answer(&line, default) {
+            Some(answer) => return Ok(answer),
+            None => println!("Please answer y or n."),
+        }
+    }
+}
+
+/// Parse an answer to a yes/no prompt. An empty answer gives the default.
+fn parse_answer(s: &str, default: bool) -> Option<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "" => Some(default),
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Print preview, through the pager if it does not fit in the terminal.
+///
+/// The first line (the summary) is printed again after paging, so that it stays visible above the
+/// prompt.
+fn show(preview: &str) {
+    let term = Term::stdout();
+    let fits = match term.size_checked() {
+        Some((height, _)) => preview.lines().count() < height as usize,
+        None => true,
+    };
+    if fits || page(preview).is_err() {
+        println!("{}", preview);
+    } else if let Some(summary) = preview.lines().next() {
+        println!("{}", summary);
+    }
+}
+
+/// Show text in the pager given by `PAGER`, defaulting to `less -R`.
+fn page(text: &str) -> Result<(), Box<dyn Error>> {
+    let pager = env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_owned());
+    let args = command::parse(&pager, &ParseOpts::default())?;
+    if args.is_empty() {
+        return Err("empty pager".into());
+    }
+    let mut child = command::build(&args, &[])?.stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may exit before reading everything, which is fine.
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! parse_answer_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, default, expected) = $value;
+                    assert_eq!(parse_answer(s, default), expected);
+                }
+            )*
+        }
+    }
+
+    parse_answer_tests!(
+        parse_answer_yes: ("y\n", false, Some(true)),
+        parse_answer_yes_long: (" YES ", false, Some(true)),
+        parse_answer_no: ("no\n", true, Some(false)),
+        parse_answer_default_no: ("\n", false, Some(false)),
+        parse_answer_default_yes: ("", true, Some(true)),
+        parse_answer_invalid: ("maybe", false, None),
+    );
+}
@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+
+use clap::{AppSettings, Clap};
+
+use super::confirm::ConfirmOpts;
+use super::mv::{dry_run, move_all, Hooks};
+use super::progress::ProgressOpts;
+use super::replace::ReplaceOpts;
+use super::select::{select, SelectOpts};
+use super::source::SourceOpts;
+use super::utils::{check_reserved, setup_rayon};
+
+use crate::fsops::{MoveOpts, Reflink};
+use crate::replacement::{
+    collisions, format_collisions, format_hazards, format_long_names, hazards, long_names,
+    previews, resolve, PreviewOpts,
+};
+use crate::throttle::{Bandwidth, Throttle};
+
+/// Copy each file according to the replacer.
+///
+/// Files are cloned where the filesystem supports it (btrfs and XFS on Linux, APFS on macOS),
+/// sharing their data until modified, which is instant regardless of their size.
+///
+/// Examples:
+///
+/// 1. Copy with underscores instead of hyphens:
+///
+///     $ mrf cp * '{}{=_}{}'
+///     Copying 1 out of 1 items:
+///         image-001.jpg -> image_001.jpg
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf cp [FLAGS] [OPTIONS] <item>... <replacer>",
+    verbatim_doc_comment
+)]
+pub struct Opts {
+    /// Whether to clone files instead of copying their contents. With "auto", files are copied
+    /// where cloning is not supported. With "always", such files fail to copy.
+    #[clap(long, default_value = "auto", possible_values = &["auto", "always", "never"])]
+    reflink: Reflink,
+    /// Verify the copied contents.
+    #[clap(long)]
+    verify: bool,
+    /// Limit the bandwidth of copies across all threads, in bytes per second. Suffixes K, M and
+    /// G are binary multiples, e.g. "50M". Cloned files are not limited.
+    #[clap(long, value_name = "rate")]
+    bwlimit: Option<Bandwidth>,
+    /// Fail instead of warning when replacements drop extensions or produce empty or blank names.
+    #[clap(long)]
+    safe: bool,
+    /// Simulate the copies on an in-memory copy of the items and their destinations, reporting
+    /// the items which would fail to copy, without changing any file.
+    #[clap(long, conflicts_with = "checkpoint")]
+    dry_run: bool,
+    /// Number of threads to use.
+    #[clap(short, long)]
+    concurrency: Option<usize>,
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    #[clap(flatten)]
+    progress: ProgressOpts,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Files to copy, followed by the replacer string unless "--to" is given. Pass "-" to read
+    /// from stdin.
+    #[clap(required = true)]
+    item: Vec<String>,
+    /// Replacer string.
+    #[clap(skip)]
+    replacer: String,
+}
+
+/// Run copy subcommand.
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let concurrency = opts.concurrency.unwrap_or(0);
+    setup_rayon(concurrency)?;
+    let (replacer, items) = opts
+        .source
+        .take_items(&mut opts.replace, mem::take(&mut opts.item))?;
+    opts.replacer = replacer;
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let mut replacements = resolve(&items, &opts.replacer, opts.replace.resolve_opts())?;
+    // Copying an item onto itself would truncate it.
+    replacements.retain(|(left, right)| left != right);
+    let existing: Vec<String> = replacements
+        .iter()
+        .filter(|(_, right)| Path::new(right).symlink_metadata().is_ok())
+        .map(|(left, right)| format!("    {} -> {}", left, right))
+        .collect();
+    if !existing.is_empty() {
+        return Err(format!("destinations already exist:\n{}", existing.join("\n")).into());
+    }
+    let collisions = collisions(&replacements);
+    if !collisions.is_empty() {
+        return Err(format!(
+            "destinations are not unique:\n{}",
+            format_collisions(&collisions)
+        )
+        .into());
+    }
+    let long_names = long_names(&replacements);
+    if !long_names.is_empty() {
+        return Err(format!(
+            "names are too long for the filesystem, pass --truncate-to-fit to shorten them:\n{}",
+            format_long_names(&long_names)
+        )
+        .into());
+    }
+    check_reserved(&replacements)?;
+    let hazards = hazards(&replacements);
+    if !hazards.is_empty() {
+        if opts.safe {
+            return Err(format!("unsafe replacements:\n{}", format_hazards(&hazards)).into());
+        }
+        eprintln!("Warning: {}", format_hazards(&hazards));
+    }
+    let move_opts = MoveOpts {
+        verify: opts.verify,
+        keep_source: true,
+        reflink: opts.reflink,
+        throttle: opts.bwlimit.map(|b| Arc::new(Throttle::new(b))),
+        ..MoveOpts::new()
+    };
+    if opts.dry_run {
+        dry_run(&replacements, move_opts, false, &opts.progress)?;
+        return Ok(());
+    }
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    if !opts.confirm.confirm(|max_previews| {
+        Ok(format!(
+            "Copying {} out of {} items:\n{}",
+            replacements.len(),
+            items.len(),
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })? {
+        return Ok(());
+    }
+    move_all(&replacements, &move_opts, &Hooks::new(), &opts.progress)?;
+    opts.replace.record_history();
+    Ok(())
+}
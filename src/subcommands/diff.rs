@@ -0,0 +1,114 @@
+use std::error::Error;
+use std::fs;
+
+use clap::{AppSettings, Clap};
+
+use super::utils::read_items_from_stdin;
+
+use crate::diff::{diff, infer_replacer, WordDiff};
+use crate::parser::parse;
+use crate::replacer::Replacer;
+
+/// Compare two lists of items, such as directory listings before and after a rename.
+///
+/// Items are paired by position. The token-level differences of each changed pair are shown, and
+/// a replacer reproducing all changes is inferred if possible.
+///
+/// Examples:
+///
+/// 1. Audit what a rename did:
+///
+///     $ ls > before.txt
+///     $ some-rename-tool
+///     $ ls > after.txt
+///     $ mrf diff before.txt after.txt
+///     Changed 1 out of 1 items:
+///         IMG_001.jpg -> [-IMG-]{+photo+}_001.jpg
+///     Replacer: {=photo}{}
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+pub struct Opts {
+    /// File listing the items before, one per line. Pass "-" to read from stdin.
+    before: String,
+    /// File listing the items after, one per line. Pass "-" to read from stdin.
+    after: String,
+}
+
+/// Run diff subcommand.
+pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+    if opts.before == "-" && opts.after == "-" {
+        return Err("only one of the lists may be read from stdin".into());
+    }
+    let before = read_list(&opts.before)?;
+    let after = read_list(&opts.after)?;
+    if before.len() != after.len() {
+        return Err(format!(
+            "lists have different lengths ({} and {})",
+            before.len(),
+            after.len()
+        )
+        .into());
+    }
+    let pairs: Vec<(&str, &str)> = before
+        .iter()
+        .zip(&after)
+        .map(|(l, r)| (l.as_str(), r.as_str()))
+        .collect();
+    let changed: Vec<(&str, &str)> = pairs.iter().copied().filter(|(l, r)| l != r).collect();
+    println!("Changed {} out of {} items:", changed.len(), pairs.len());
+    let mut candidates: Vec<String> = vec![];
+    for (left, right) in &changed {
+        let changes = diff(left, right);
+        println!("    {} -> {}", left, WordDiff(&changes));
+        let replacer = infer_replacer(&changes);
+        if !candidates.contains(&replacer) {
+            candidates.push(replacer);
+        }
+    }
+    if changed.is_empty() {
+        return Ok(());
+    }
+    match candidates.iter().find(|r| reproduces(r, &pairs)) {
+        Some(replacer) => println!("Replacer: {}", replacer),
+        None => println!("No single replacer reproduces all changes"),
+    }
+    Ok(())
+}
+
+/// Read list of items from file, one item per line, or from stdin if `path` is "-".
+fn read_list(path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    if path == "-" {
+        return Ok(read_items_from_stdin()?);
+    }
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(|l| l.to_owned())
+        .collect())
+}
+
+/// Check if replacer maps every item to its pair, leaving unmatched items unchanged.
+fn reproduces(replacer_str: &str, pairs: &[(&str, &str)]) -> bool {
+    let replacer = match parse(replacer_str) {
+        Ok(elems) => Replacer::from(elems),
+        Err(_) => return false,
+    };
+    pairs
+        .iter()
+        .all(|(left, right)| match replacer.replace(left) {
+            Ok((replaced, _)) => replaced == *right,
+            Err(_) => left == right,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproduces_pairs() {
+        let pairs = [("a-1", "a_1"), ("b", "b")];
+        assert!(reproduces("{}{=_}{}", &pairs));
+        assert!(!reproduces("{}{=-}{}", &pairs));
+    }
+}
@@ -1,15 +1,27 @@
+use std::env;
 use std::error::Error;
-use std::process::Command;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::mem;
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
 
 use clap::{AppSettings, Clap};
-use dialoguer::Confirm;
-use indicatif::{ParallelProgressIterator, ProgressBar};
 use rayon::prelude::*;
 
-use super::utils::{items_from_opt, setup_rayon};
+use super::confirm::ConfirmOpts;
+use super::progress::{Progress, ProgressOpts};
+use super::replace::ReplaceOpts;
+use super::select::{select, SelectOpts};
+use super::source::SourceOpts;
+use super::utils::{parse_duration, setup_rayon};
 
-use crate::command;
-use crate::replacement::{previews, resolve, PreviewOpts, ResolveOpts};
+use crate::command::{self, ParseOpts};
+use crate::replacement::{previews, resolve, resolve_each, stream_previews, PreviewOpts};
 
 /// Execute the given command with each replaced item.
 ///
@@ -27,26 +39,75 @@ use crate::replacement::{previews, resolve, PreviewOpts, ResolveOpts};
 ///     Matched 1 out of 1 items:
 ///         image-001.jpg -> image_001.jpg
 #[derive(Clap)]
-#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf exec [FLAGS] [OPTIONS] <command> <item>... <replacer>",
+    verbatim_doc_comment
+)]
 pub struct Opts {
-    /// Assume yes as answer to all prompts and run non-interactively.
-    #[clap(short = "y", long)]
-    assume_yes: bool,
     /// Only pass the input string (left-hand side of mapping) to the command.
     #[clap(short = "l", long, conflicts_with = "right-only")]
     left_only: bool,
     /// Only pass the replaced string (right-hand side of mapping) to the command.
     #[clap(short = "r", long, conflicts_with = "left-only")]
     right_only: bool,
+    /// Write the input string, the replaced string, or both (NUL-separated) to the command's stdin
+    /// instead of passing them as arguments.
+    #[clap(
+        long,
+        possible_values = &["left", "right", "both"],
+        conflicts_with_all = &["left-only", "right-only"],
+    )]
+    stdin: Option<StdinMode>,
+    /// Run the command in the given directory.
+    #[clap(long, conflicts_with = "cwd-from-item")]
+    cwd: Option<PathBuf>,
+    /// Run the command in the parent directory of each item. Items are passed relative to that
+    /// directory, with ".." components for replaced strings in other directories.
+    #[clap(long, conflicts_with = "cwd")]
+    cwd_from_item: bool,
+    /// Write the stdout and stderr of each command to "<item>.stdout.log" and "<item>.stderr.log"
+    /// in the given directory instead of the console. Separators and "%" in items are
+    /// percent-encoded, e.g. "a/b" is logged to "a%2Fb.stdout.log".
+    #[clap(long, value_name = "dir")]
+    capture_logs: Option<PathBuf>,
+    /// Expand environment variables ($VAR, ${VAR}) and leading tildes (~) in the command.
+    #[clap(short = "E", long)]
+    expand_env: bool,
     /// Number of threads to use.
     #[clap(short, long)]
     concurrency: Option<usize>,
+    /// Number of times to retry a failed command before reporting it.
+    #[clap(long, default_value = "0")]
+    retries: usize,
+    /// Delay between retries (e.g. "500ms", "2s", "1m").
+    #[clap(long, default_value = "1s", parse(try_from_str = parse_duration))]
+    retry_delay: Duration,
+    /// Resolve and execute this many items at a time, holding the replacements of only one chunk
+    /// in memory, for very large inputs. Previews are then not highlighted, and "--group-by" and
+    /// "--uniquify" are not supported.
+    #[clap(long, value_name = "n")]
+    chunk_size: Option<NonZeroUsize>,
     /// Command to run. To pass arguments to the command, quote the command (e.g. "mkdir -p").
+    /// Arguments are split and unquoted following POSIX shell rules.
     command: String,
-    /// Items to replace. Pass "-" to read from stdin.
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    #[clap(flatten)]
+    progress: ProgressOpts,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Items to replace, followed by the replacer string unless "--to" is given.
+    /// Pass "-" to read from stdin.
     #[clap(required = true)]
     item: Vec<String>,
     /// Replacer string.
+    #[clap(skip)]
     replacer: String,
 }
 
@@ -54,61 +115,460 @@ pub struct Opts {
 struct OutputOpts {
     left_only: bool,
     right_only: bool,
+    stdin: Option<StdinMode>,
+    cwd: Option<PathBuf>,
+    cwd_from_item: bool,
+    capture_logs: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy)]
+enum StdinMode {
+    /// Write the input string.
+    Left,
+    /// Write the replaced string.
+    Right,
+    /// Write both strings, each terminated by a NUL character.
+    Both,
+}
+
+impl FromStr for StdinMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(StdinMode::Left),
+            "right" => Ok(StdinMode::Right),
+            "both" => Ok(StdinMode::Both),
+            _ => Err(format!("invalid stdin mode: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct RetryOpts {
+    retries: usize,
+    delay: Duration,
 }
 
 /// Run exec subcommand.
-pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let (replacer, items) = opts
+        .source
+        .take_items(&mut opts.replace, mem::take(&mut opts.item))?;
+    opts.replacer = replacer;
     let concurrency = opts.concurrency.unwrap_or(0);
     setup_rayon(concurrency)?;
-    let items = items_from_opt(opts.item)?;
-    let replacements = resolve(&items, &opts.replacer, ResolveOpts::new())?;
-    if !opts.assume_yes {
-        println!(
-            "Matched {} out of {} items:",
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    if let Some(chunk_size) = opts.chunk_size {
+        return run_chunked(&opts, &items, chunk_size.get());
+    }
+    let replacements = resolve(&items, &opts.replacer, opts.replace.resolve_opts())?;
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    if !opts.confirm.confirm(|max_previews| {
+        Ok(format!(
+            "Matched {} out of {} items:\n{}",
             replacements.len(),
-            items.len()
-        );
-        println!("{}", previews(&items, &opts.replacer, PreviewOpts::new())?);
-        if !Confirm::new()
-            .with_prompt("Do you want to continue?")
-            .default(false)
-            .interact()?
-        {
-            return Ok(());
-        }
+            items.len(),
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })? {
+        return Ok(());
+    }
+    let runner = Runner::new(&opts)?;
+    let progress = opts.progress.start(replacements.len())?;
+    let statuses = runner.exec_all(&replacements, 0, &progress);
+    let failures = format_failures(&replacements, &statuses);
+    progress.done(replacements.len(), failures.len());
+    print_failures(&failures, replacements.len());
+    if !failures.is_empty() {
+        return Err(format!("{} items failed", failures.len()).into());
     }
-    let args = command::parse(&opts.command)?;
-    let output_opts = OutputOpts {
-        left_only: opts.left_only,
-        right_only: opts.right_only,
+    opts.replace.record_history();
+    Ok(())
+}
+
+/// Run exec subcommand, resolving and executing `chunk_size` items at a time.
+fn run_chunked(opts: &Opts, items: &[String], chunk_size: usize) -> Result<(), Box<dyn Error>> {
+    let resolve_opts = opts.replace.resolve_opts();
+    let items = || items.iter().map(String::as_str);
+    let mut total = None;
+    if !opts.confirm.confirm(|max_previews| {
+        let (count, previews) =
+            stream_previews(items(), &opts.replacer, &resolve_opts, max_previews)?;
+        total = Some(count);
+        Ok(format!(
+            "Matched {} out of {} items:\n{}",
+            count,
+            items().len(),
+            previews
+        ))
+    })? {
+        return Ok(());
+    }
+    let total = match total {
+        Some(total) => total,
+        None => stream_previews(items(), &opts.replacer, &resolve_opts, 0)?.0,
     };
-    replacements
-        .par_iter()
-        .progress_with(ProgressBar::new(replacements.len() as u64))
-        .for_each(|(left, right)| {
-            do_exec(&output_opts, &args, left, right).unwrap_or_else(|e| {
-                eprintln!("{}", e);
-            })
-        });
+    let runner = Runner::new(opts)?;
+    let progress = opts.progress.start(total)?;
+    let mut failures = vec![];
+    let mut offset = 0;
+    let mut exec_chunk = |chunk: &mut Vec<(String, String)>| {
+        let statuses = runner.exec_all(chunk, offset, &progress);
+        failures.extend(format_failures(chunk, &statuses));
+        offset += chunk.len();
+        chunk.clear();
+    };
+    let mut chunk = Vec::with_capacity(chunk_size);
+    resolve_each(items(), &opts.replacer, &resolve_opts, |left, right| {
+        chunk.push((left.to_owned(), right.to_owned()));
+        if chunk.len() == chunk_size {
+            exec_chunk(&mut chunk);
+        }
+        Ok(())
+    })?;
+    exec_chunk(&mut chunk);
+    progress.done(total, failures.len());
+    print_failures(&failures, total);
+    if !failures.is_empty() {
+        return Err(format!("{} items failed", failures.len()).into());
+    }
+    opts.replace.record_history();
     Ok(())
 }
 
+/// Command to execute with each replacement, and how.
+struct Runner {
+    args: Vec<String>,
+    output: OutputOpts,
+    retry: RetryOpts,
+}
+
+impl Runner {
+    /// Parse the command, and create the directory of logs if capturing them.
+    fn new(opts: &Opts) -> Result<Self, Box<dyn Error>> {
+        let args = command::parse(
+            &opts.command,
+            &ParseOpts {
+                expand_env: opts.expand_env,
+            },
+        )?;
+        if args.is_empty() {
+            return Err("command is empty".into());
+        }
+        if let Some(dir) = &opts.capture_logs {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(Self {
+            args,
+            output: OutputOpts {
+                left_only: opts.left_only,
+                right_only: opts.right_only,
+                stdin: opts.stdin,
+                cwd: opts.cwd.clone(),
+                cwd_from_item: opts.cwd_from_item,
+                capture_logs: opts.capture_logs.clone(),
+            },
+            retry: RetryOpts {
+                retries: opts.retries,
+                delay: opts.retry_delay,
+            },
+        })
+    }
+
+    /// Execute the command with each replacement in parallel, reporting progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `replacements` - Replacements.
+    /// * `offset` - Index of the first replacement among all replacements, for progress.
+    /// * `progress` - Progress reporter.
+    ///
+    /// # Returns
+    ///
+    /// The status of the command of each replacement.
+    fn exec_all<L, R>(
+        &self,
+        replacements: &[(L, R)],
+        offset: usize,
+        progress: &Progress,
+    ) -> Vec<Result<(), String>>
+    where
+        L: AsRef<str> + Sync,
+        R: AsRef<str> + Sync,
+    {
+        replacements
+            .par_iter()
+            .enumerate()
+            .map(|(index, (left, right))| {
+                let (index, left, right) = (offset + index, left.as_ref(), right.as_ref());
+                if progress.skip(index, left, right) {
+                    return Ok(());
+                }
+                progress.started(index, left, right);
+                let status = do_exec_with_retries(
+                    &self.output,
+                    &self.retry,
+                    progress,
+                    index,
+                    &self.args,
+                    left,
+                    right,
+                )
+                // Failures are reported once all commands exit, by `print_failures`.
+                .map_err(|e| e.to_string());
+                progress.finished(index, left, right, None, status.clone().err());
+                status
+            })
+            .collect()
+    }
+}
+
+/// Format the items whose command failed after all retries, along with the error.
+fn format_failures<L, R>(replacements: &[(L, R)], statuses: &[Result<(), String>]) -> Vec<String>
+where
+    L: AsRef<str>,
+    R: AsRef<str>,
+{
+    replacements
+        .iter()
+        .zip(statuses)
+        .filter_map(|((left, right), status)| {
+            status
+                .as_ref()
+                .err()
+                .map(|e| format!("    {} -> {}: {}", left.as_ref(), right.as_ref(), e))
+        })
+        .collect()
+}
+
+/// Print a summary of the items whose command failed after all retries.
+fn print_failures(failures: &[String], total: usize) {
+    if failures.is_empty() {
+        return;
+    }
+    eprintln!("Failed {} out of {} items:", failures.len(), total);
+    eprintln!("{}", failures.join("\n"));
+}
+
+/// Execute command, retrying up to `retry_opts.retries` times on failure.
+fn do_exec_with_retries(
+    opts: &OutputOpts,
+    retry_opts: &RetryOpts,
+    progress: &Progress,
+    index: usize,
+    args: &[String],
+    left: &str,
+    right: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut attempt = 0;
+    loop {
+        match do_exec(opts, progress, index, args, left, right) {
+            Err(_) if attempt < retry_opts.retries => {
+                attempt += 1;
+                thread::sleep(retry_opts.delay);
+            }
+            result => return result,
+        }
+    }
+}
+
 /// Execute command with args and replacement.
+///
+/// The output of the command is captured and printed once it exits, so that the output of
+/// commands running in parallel is not interleaved, unless it is written to log files.
 fn do_exec(
     opts: &OutputOpts,
+    progress: &Progress,
+    index: usize,
     args: &[String],
     left: &str,
     right: &str,
 ) -> Result<(), Box<dyn Error>> {
-    let mut cmd = Command::new(&args[0]);
-    cmd.args(&args[1..]);
-    if opts.left_only {
-        cmd.arg(left);
-    } else if opts.right_only {
-        cmd.arg(right);
+    let logs = opts.capture_logs.as_ref().map(|dir| {
+        let name = log_name(left);
+        (
+            dir.join(format!("{}.stdout.log", name)),
+            dir.join(format!("{}.stderr.log", name)),
+        )
+    });
+    let (left, right, cwd) = if opts.cwd_from_item {
+        match Path::new(left).parent() {
+            Some(parent) if parent != Path::new("") => (
+                relative_to(left, parent)?,
+                relative_to(right, parent)?,
+                Some(parent),
+            ),
+            _ => (left.to_owned(), right.to_owned(), None),
+        }
     } else {
-        cmd.args(&[left, right]);
+        (left.to_owned(), right.to_owned(), opts.cwd.as_deref())
+    };
+    let (left, right) = (left.as_str(), right.as_str());
+    let (item_args, input): (Vec<&str>, _) = match opts.stdin {
+        Some(StdinMode::Left) => (vec![], Some(format!("{}\n", left))),
+        Some(StdinMode::Right) => (vec![], Some(format!("{}\n", right))),
+        Some(StdinMode::Both) => (vec![], Some(format!("{}\0{}\0", left, right))),
+        None => {
+            if opts.left_only {
+                (vec![left], None)
+            } else if opts.right_only {
+                (vec![right], None)
+            } else {
+                (vec![left, right], None)
+            }
+        }
+    };
+    let mut cmd = command::build(args, &item_args)?;
+    if let Some(cwd) = cwd {
+        cmd.current_dir(cwd);
+    }
+    if input.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+    match logs {
+        Some((stdout, stderr)) => {
+            cmd.stdout(File::create(stdout)?);
+            cmd.stderr(File::create(stderr)?);
+        }
+        None => {
+            cmd.stdout(Stdio::piped());
+            cmd.stderr(Stdio::piped());
+        }
+    }
+    let mut child = cmd.spawn()?;
+    // Take stdin so that it is closed once written, signalling EOF to the command.
+    let written = match (input, child.stdin.take()) {
+        (Some(input), Some(mut stdin)) => match stdin.write_all(input.as_bytes()) {
+            // The command exited or closed stdin without reading all of it, which is up to it.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            written => written,
+        },
+        _ => Ok(()),
+    };
+    // Wait for the command even if writing failed, so that it is not left running.
+    let output = child.wait_with_output()?;
+    written?;
+    progress.output(index, &output.stdout, &output.stderr);
+    let status = output.status;
+    if !status.success() {
+        return Err(format!("{}: {}", args[0], status).into());
     }
-    cmd.spawn()?.wait()?;
     Ok(())
 }
+
+/// Get a file name for the logs of an item, with separators and `%` percent-encoded (`a/b` becomes
+/// `a%2Fb`), so that distinct items get distinct names.
+fn log_name(item: &str) -> String {
+    let mut name = String::with_capacity(item.len());
+    for c in item.chars() {
+        match c {
+            '%' | '/' | '\\' | ':' => name.push_str(&format!("%{:02X}", c as u32)),
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+/// Make a relative path relative to `parent` instead, going up with `..` if it is not inside
+/// `parent`, e.g. `b/c` relative to `a` is `../b/c`.
+///
+/// # Returns
+///
+/// An `io::Result` containing the path. Absolute paths are returned as-is, and paths which cannot
+/// be expressed relative to `parent` (if `parent` is absolute or goes up itself) are made
+/// absolute.
+fn relative_to(path: &str, parent: &Path) -> io::Result<String> {
+    if Path::new(path).is_absolute() {
+        return Ok(path.to_owned());
+    }
+    fn components(p: &Path) -> Vec<Component<'_>> {
+        p.components().filter(|c| *c != Component::CurDir).collect()
+    }
+    let (path_components, parent_components) = (components(Path::new(path)), components(parent));
+    let common = path_components
+        .iter()
+        .zip(&parent_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if parent.is_absolute() || parent_components[common..].contains(&Component::ParentDir) {
+        return Ok(env::current_dir()?
+            .join(path)
+            .to_string_lossy()
+            .into_owned());
+    }
+    let mut relative = PathBuf::new();
+    for _ in common..parent_components.len() {
+        relative.push(Component::ParentDir);
+    }
+    relative.extend(&path_components[common..]);
+    if relative.as_os_str().is_empty() {
+        return Ok(".".to_owned());
+    }
+    Ok(relative.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! relative_to_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (path, parent, expected) = $value;
+                    let expected = Path::new(expected).to_string_lossy().into_owned();
+                    assert_eq!(relative_to(path, Path::new(parent)).unwrap(), expected);
+                }
+            )*
+        }
+    }
+
+    macro_rules! log_name_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (item, expected) = $value;
+                    assert_eq!(log_name(item), expected);
+                }
+            )*
+        }
+    }
+
+    log_name_tests!(
+        log_name_plain: ("a_b", "a_b"),
+        log_name_separators: (r"a/b\c:d", "a%2Fb%5Cc%3Ad"),
+        log_name_percent: ("a%2Fb", "a%252Fb"),
+    );
+
+    relative_to_tests!(
+        relative_to_inside: ("a/b/c", "a/b", "c"),
+        relative_to_sibling: ("a/x/c", "a/b", "../x/c"),
+        relative_to_other: ("x/c", "a/b", "../../x/c"),
+        relative_to_current: ("./a/c", "a", "c"),
+        relative_to_parent: ("a", "a", "."),
+    );
+
+    #[test]
+    fn relative_to_up() {
+        let expected = env::current_dir().unwrap().join("c");
+        assert_eq!(
+            relative_to("c", Path::new("../a")).unwrap(),
+            expected.to_string_lossy()
+        );
+    }
+}
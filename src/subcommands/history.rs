@@ -0,0 +1,46 @@
+use std::env;
+use std::error::Error;
+
+use clap::{AppSettings, Clap};
+
+use crate::history;
+
+/// List the replacers used in the current directory, most recent last.
+///
+/// Replacers are recorded by "mv", "cp" and "exec" once all items were processed successfully.
+/// Reuse the most recent one with "--last". The oldest entries are dropped beyond 1000. The history is stored in "mrf/history.jsonl" in the user's data directory, or the
+/// file given by $MRF_HISTORY. Set $MRF_HISTORY to an empty string to disable it.
+///
+/// Examples:
+///
+/// 1. Reuse the replacer used last in the current directory:
+///
+///     $ mrf history
+///     {}{=_}{}
+///     $ mrf mv --last *
+///     Moving 1 out of 1 items:
+///         image-001.jpg -> image_001.jpg
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+pub struct Opts {
+    /// List the replacers used in all directories, each preceded by the directory and a tab.
+    #[clap(long)]
+    all: bool,
+}
+
+/// Run history subcommand.
+pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+    let path = history::path().ok_or("history is disabled")?;
+    let entries = history::load(&path)?;
+    if opts.all {
+        for entry in &entries {
+            println!("{}\t{}", entry.dir.display(), entry.replacer);
+        }
+        return Ok(());
+    }
+    for replacer in history::replacers(&entries, &env::current_dir()?) {
+        println!("{}", replacer);
+    }
+    Ok(())
+}
@@ -1,11 +1,28 @@
-use std::borrow::Cow;
+use std::collections::HashMap;
 use std::error::Error;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
 
 use clap::{AppSettings, Clap};
+use serde_json::json;
 
-use super::utils::items_from_opt;
+use super::confirm::ConfirmOpts;
+use super::replace::ReplaceOpts;
+use super::select::{select, select_indices, select_iter, SelectOpts};
+use super::source::SourceOpts;
 
-use crate::replacement::{resolve, ResolveOpts};
+use crate::aggregate::{format_aggregates, Aggregate};
+use crate::mmap::{records, Mmap};
+use crate::replacement::{
+    collisions, failures, format_collisions, previews, resolve, resolve_each, resolve_highlights,
+    PreviewOpts, ResolveOpts,
+};
+use crate::sink::{
+    write_all, Csv, JsonLines, NulPairs, OutputFormat, OutputSink, Pretty, Side, Template,
+};
+use crate::sqlite::Column;
+use crate::stats::Stats;
 
 /// Map each item according to the replacer.
 ///
@@ -19,8 +36,25 @@ use crate::replacement::{resolve, ResolveOpts};
 /// 2. Pipe to cp (consider using the "exec" subcommand instead):
 ///
 ///     $ mrf map * '{}{=-}{}' | xargs -0 -n2 cp
+///
+/// 3. Replace each line of a document:
+///
+///     $ printf 'a-1\nb-2\n' | mrf map --multiline - '{}{=_}{}'
+///     a_1
+///     b_2
+///
+/// 4. Update the paths stored in a database after moving files:
+///
+///     $ mrf map --sqlite app.db --table files --column path '{p}{}{p}{=new}{}'
+///     Updating 1 out of 1 rows of files.path:
+///         /mnt/old/a.jpg -> /mnt/new/a.jpg
 #[derive(Clap)]
-#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf map [FLAGS] [OPTIONS] <item>... <replacer>",
+    verbatim_doc_comment
+)]
 pub struct Opts {
     /// Only output the input string (left-hand side of mapping).
     #[clap(short = "l", long, conflicts_with = "right-only")]
@@ -28,39 +62,359 @@ pub struct Opts {
     /// Only output the replaced string (right-hand side of mapping).
     #[clap(short = "r", long, conflicts_with = "left-only")]
     right_only: bool,
-    /// Items to replace. Pass "-" to read from stdin.
+    /// Fail instead of warning when multiple items map to the same string.
+    #[clap(long)]
+    unique: bool,
+    /// Read stdin as a single document and replace each of its lines, printing the document.
+    /// Lines which are not matched or selected are printed unchanged.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "left-only", "right-only", "unique", "glob", "walk", "csv", "from-plan"
+        ]
+    )]
+    multiline: bool,
+    /// Print statistics of the matched items, the distinct outputs and the match lengths to
+    /// stderr.
+    #[clap(long, conflicts_with = "multiline")]
+    stats: bool,
+    /// Print a JSON line with the item and the reason to stderr for each item the replacer fails
+    /// on, and fail after mapping the other items if there were any.
+    #[clap(long, conflicts_with = "multiline")]
+    report_errors: bool,
+    /// Print each replacement as a JSON line, with the spans of each match in the item and the
+    /// source of each part of the replaced string, as returned by "mrf serve".
+    #[clap(long, conflicts_with_all = &["left-only", "right-only", "multiline"])]
+    json: bool,
+    /// Print the replacements in this format instead of "left -> right" lines on a terminal or
+    /// NUL-terminated strings otherwise: "json" prints a JSON object with "left" and "right" per
+    /// line, "csv" a CSV table with a "left,right" header, which "mrf apply" reads as a plan.
+    #[clap(
+        long,
+        possible_values = &["json", "csv"],
+        conflicts_with_all = &["left-only", "right-only", "multiline", "json"]
+    )]
+    output_format: Option<OutputFormat>,
+    /// Print a line per replacement formatted with this template, in which "{left}" and "{right}"
+    /// are replaced with the item and the replaced string, e.g. "{left}\t{right}".
+    #[clap(
+        long,
+        conflicts_with_all = &["left-only", "right-only", "multiline", "json", "output-format"]
+    )]
+    template: Option<String>,
+    /// Print an aggregate of the numeric values of a match group to stderr, given as
+    /// "function:index" with function one of sum, min, max or count (e.g. "sum:1"). May be
+    /// specified multiple times.
+    #[clap(long, number_of_values = 1, conflicts_with = "multiline")]
+    aggregate: Vec<Aggregate>,
+    /// Replace the values of a text column of this SQLite database instead of items, updating
+    /// them in a single transaction after a preview. Requires the "sqlite3" command. Only the
+    /// replacer is then given as positional argument.
+    #[clap(
+        long,
+        value_name = "db",
+        requires_all = &["table", "column"],
+        conflicts_with_all = &[
+            "left-only", "right-only", "multiline", "glob", "walk", "csv", "from-plan"
+        ]
+    )]
+    sqlite: Option<PathBuf>,
+    /// With "--sqlite", the table to update.
+    #[clap(long, requires = "sqlite")]
+    table: Option<String>,
+    /// With "--sqlite", the column to update. NULL values are skipped.
+    #[clap(long, requires = "sqlite")]
+    column: Option<String>,
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    /// Read the items from this file, one per line, mapping it into memory and streaming the
+    /// replacements out instead of reading all items at once, for very large inputs. Only the
+    /// replacer is then given as positional argument. Replaced strings are not checked for
+    /// uniqueness, and "--sort", "--group-by" and "--uniquify" are not supported. The file must
+    /// not be modified while mrf runs.
+    #[clap(
+        long,
+        value_name = "file",
+        conflicts_with_all = &[
+            "multiline", "sqlite", "json", "unique", "stats", "report-errors", "aggregate",
+            "glob", "walk", "csv", "from-plan"
+        ]
+    )]
+    mmap: Option<PathBuf>,
+    /// With "--mmap", items are separated by NUL characters instead of newlines.
+    #[clap(long, requires = "mmap")]
+    null: bool,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Items to replace, followed by the replacer string unless "--to" is given.
+    /// Pass "-" to read from stdin.
     #[clap(required = true)]
     item: Vec<String>,
     /// Replacer string.
+    #[clap(skip)]
     replacer: String,
 }
 
 /// Run map (`map`) subcommand.
-pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
-    let items = items_from_opt(opts.item)?;
-    let print: fn(&(Cow<'_, str>, String)) = if atty::is(atty::Stream::Stdout) {
-        if opts.left_only {
-            |(left, _)| println!("{}", left)
-        } else if opts.right_only {
-            |(_, right)| println!("{}", right)
-        } else {
-            |(left, right)| println!("{} -> {}", left, right)
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    if opts.sqlite.is_some() {
+        return run_sqlite(opts);
+    }
+    if let Some(path) = opts.mmap.take() {
+        return run_mmap(opts, &path);
+    }
+    if opts.multiline {
+        opts.replacer = opts.replace.take_replacer(&mut opts.item)?;
+        return run_multiline(opts);
+    }
+    let (replacer, items) = opts
+        .source
+        .take_items(&mut opts.replace, mem::take(&mut opts.item))?;
+    opts.replacer = replacer;
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    if opts.json {
+        let highlights = resolve_highlights(&items, &opts.replacer, opts.replace.resolve_opts())?;
+        for highlight in &highlights {
+            println!("{}", highlight.to_json());
         }
-    } else if opts.left_only {
-        |(left, _)| print!("{}\0", left)
-    } else if opts.right_only {
-        |(_, right)| print!("{}\0", right)
     } else {
-        |(left, right)| print!("{}\0{}\0", left, right)
-    };
-    resolve(
-        &items,
+        print_replacements(&opts, &items)?;
+    }
+    if opts.stats || !opts.aggregate.is_empty() {
+        let highlights = resolve_highlights(&items, &opts.replacer, opts.replace.resolve_opts())?;
+        if opts.stats {
+            eprintln!("{}", Stats::new(items.len(), &highlights));
+        }
+        if !opts.aggregate.is_empty() {
+            eprintln!("{}", format_aggregates(&opts.aggregate, &highlights));
+        }
+    }
+    if opts.report_errors {
+        let failures = failures(&items, &opts.replacer, opts.replace.resolve_opts())?;
+        for (item, reason) in &failures {
+            eprintln!("{}", json!({ "item": item, "error": reason }));
+        }
+        if !failures.is_empty() {
+            return Err(format!("replacer failed on {} items", failures.len()).into());
+        }
+    }
+    Ok(())
+}
+
+/// Print the replacement of each item, warning about (or with `--unique`, failing on) items
+/// replaced with the same string.
+fn print_replacements(opts: &Opts, items: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut sink = sink(opts);
+    let highlight =
+        atty::is(atty::Stream::Stdout) && opts.output_format.is_none() && opts.template.is_none();
+    let replacements = resolve(
+        items,
         &opts.replacer,
         ResolveOpts {
-            highlight: atty::is(atty::Stream::Stdout),
+            highlight,
+            ..opts.replace.resolve_opts()
         },
+    )?;
+    // Highlighted strings may differ in color only, so check collisions without highlighting.
+    let plain = if highlight {
+        resolve(items, &opts.replacer, opts.replace.resolve_opts())?
+    } else {
+        vec![]
+    };
+    let collisions = collisions(if highlight { &plain } else { &replacements });
+    if !collisions.is_empty() {
+        if opts.unique {
+            return Err(format!(
+                "replaced strings are not unique:\n{}",
+                format_collisions(&collisions)
+            )
+            .into());
+        }
+        eprintln!("Warning: {}", format_collisions(&collisions));
+    }
+    write_all(sink.as_mut(), &replacements)?;
+    Ok(())
+}
+
+/// Create the sink to print the replacements to, writing to stdout through a buffer.
+fn sink(opts: &Opts) -> Box<dyn OutputSink> {
+    let out = io::BufWriter::new(io::stdout().lock());
+    let side = if opts.left_only {
+        Side::Left
+    } else if opts.right_only {
+        Side::Right
+    } else {
+        Side::Both
+    };
+    match (&opts.template, opts.output_format) {
+        (Some(template), _) => Box::new(Template::new(out, template)),
+        (None, Some(OutputFormat::Json)) => Box::new(JsonLines::new(out)),
+        (None, Some(OutputFormat::Csv)) => Box::new(Csv::new(out)),
+        (None, None) if atty::is(atty::Stream::Stdout) => Box::new(Pretty::new(out, side)),
+        (None, None) => Box::new(NulPairs::new(out, side)),
+    }
+}
+
+/// Run map subcommand on the items of a memory-mapped file, streaming the replacements.
+fn run_mmap(mut opts: Opts, path: &Path) -> Result<(), Box<dyn Error>> {
+    // SAFETY: "--mmap" is documented to require that the file is not modified while mrf runs.
+    let data = unsafe { Mmap::open(path) }.map_err(|e| format!("{}: {}", path.display(), e))?;
+    // The replacer is the only positional argument, so there is always an item to take it from.
+    let mut args = mem::take(&mut opts.item);
+    args.insert(0, String::new());
+    opts.replacer = opts.replace.take_replacer(&mut args)?;
+    if args.len() != 1 {
+        return Err("--mmap takes the items from the file, only give the replacer".into());
+    }
+    let separator = if opts.null { b'\0' } else { b'\n' };
+    let mut error = None;
+    // Stop at the first invalid record, reporting it once the preceding ones are written.
+    let items =
+        records(&data, separator).map_while(|record| record.map_err(|e| error = Some(e)).ok());
+    let resolve_opts = opts.replace.resolve_opts();
+    let items = select_iter(items, &opts.replacer, &opts.select, &resolve_opts)?;
+    let mut sink = sink(&opts);
+    sink.begin()?;
+    resolve_each(items, &opts.replacer, &resolve_opts, |left, right| {
+        sink.write(left, right).map_err(Into::into)
+    })?;
+    sink.finish()?;
+    match error {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+/// Run map subcommand on a column of an SQLite database, updating its values.
+fn run_sqlite(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let column = Column {
+        db: opts.sqlite.take().unwrap_or_default(),
+        table: opts.table.take().unwrap_or_default(),
+        column: opts.column.take().unwrap_or_default(),
+    };
+    let rows = column.read()?;
+    let mut values: Vec<String> = rows.iter().map(|(_, value)| value.clone()).collect();
+    values.append(&mut opts.item);
+    opts.replacer = opts.replace.take_replacer(&mut values)?;
+    if values.len() != rows.len() {
+        return Err("--sqlite takes the items from the column, only give the replacer".into());
+    }
+    let indices = select_indices(
+        &values,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let items: Vec<&str> = indices.iter().map(|&i| values[i].as_str()).collect();
+    let replaced = resolve_selected(
+        &values,
+        &indices,
+        &opts.replacer,
+        opts.replace.resolve_opts(),
+    )?;
+    let updates: Vec<(i64, &str)> = replaced
+        .iter()
+        .filter(|(index, right)| *right != values[*index])
+        .map(|(index, right)| (rows[*index].0, right.as_str()))
+        .collect();
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    if !opts.confirm.confirm(|max_previews| {
+        Ok(format!(
+            "Updating {} out of {} rows of {}.{}:\n{}",
+            updates.len(),
+            rows.len(),
+            column.table,
+            column.column,
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })? {
+        return Ok(());
+    }
+    column.update(&updates)?;
+    Ok(())
+}
+
+/// Run map subcommand on stdin as a single document, replacing each line.
+fn run_multiline(opts: Opts) -> Result<(), Box<dyn Error>> {
+    if opts.item != ["-"] {
+        return Err("--multiline requires reading from stdin (\"-\")".into());
+    }
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    // Split each line into its content and line ending, so that line endings are preserved.
+    let lines: Vec<(&str, &str)> = text
+        .split_inclusive('\n')
+        .map(|line| {
+            let content = line.trim_end_matches(&['\r', '\n'][..]);
+            (content, &line[content.len()..])
+        })
+        .collect();
+    let contents: Vec<String> = lines.iter().map(|(c, _)| c.to_string()).collect();
+    let indices = select_indices(
+        &contents,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let replaced: HashMap<usize, String> = resolve_selected(
+        &contents,
+        &indices,
+        &opts.replacer,
+        opts.replace.resolve_opts(),
     )?
-    .iter()
-    .for_each(print);
+    .into_iter()
+    .collect();
+    let mut out = String::with_capacity(text.len());
+    for (index, (content, ending)) in lines.into_iter().enumerate() {
+        out.push_str(replaced.get(&index).map_or(content, String::as_str));
+        out.push_str(ending);
+    }
+    io::stdout().write_all(out.as_bytes())?;
     Ok(())
 }
+
+/// Resolve the selected items, as given by `select_indices`.
+///
+/// Only the selected items are resolved, so that counters number them alone.
+///
+/// # Returns
+///
+/// A `Result` containing the index and replaced string of each matched item.
+fn resolve_selected(
+    items: &[String],
+    indices: &[usize],
+    replacer_str: &str,
+    opts: ResolveOpts,
+) -> Result<Vec<(usize, String)>, Box<dyn Error>> {
+    let selected: Vec<&str> = indices.iter().map(|&i| items[i].as_str()).collect();
+    let replacements = resolve(&selected, replacer_str, opts)?;
+    // Replacements are in the same order as the selected items, skipping unmatched items. Items
+    // with the same content are either all matched or all unmatched.
+    let mut replacements = replacements.into_iter().peekable();
+    let mut replaced = vec![];
+    for (&index, item) in indices.iter().zip(&selected) {
+        if let Some((left, _)) = replacements.peek() {
+            if left == item {
+                let (_, right) = replacements.next().unwrap();
+                replaced.push((index, right));
+            }
+        }
+    }
+    Ok(replaced)
+}
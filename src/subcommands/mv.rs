@@ -1,18 +1,39 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::error::Error;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use clap::{AppSettings, Clap};
-use dialoguer::Confirm;
-use indicatif::{ParallelProgressIterator, ProgressBar};
 use rayon::prelude::*;
 
-use super::utils::{items_from_opt, setup_rayon};
+use super::confirm::ConfirmOpts;
+use super::progress::{Progress, ProgressOpts};
+use super::replace::ReplaceOpts;
+use super::select::{select, SelectOpts};
+use super::snapshot::SnapshotOpts;
+use super::source::SourceOpts;
+use super::utils::{check_collisions, check_reserved, read_items_from_stdin, setup_rayon};
 
-use crate::replacement::{previews, resolve, PreviewOpts, ResolveOpts};
+use crate::bucket::Bucket;
+use crate::command::{self, ParseOpts};
+use crate::dryfs::DryFs;
+use crate::fsops::{check_move, hard_link_id, move_path, relink_path, Failure, MoveOpts};
+use crate::replacement::{
+    format_hazards, format_long_names, format_previews, hazards, long_names, previews, resolve,
+    PreviewOpts, Replacement,
+};
+use crate::script::Shell;
+use crate::sink::{write_all, Script};
+use crate::throttle::{Bandwidth, Throttle};
+use crate::walk::{expand_dirs, walk_relative, WalkOpts};
 
 /// Move each file according to the replacer.
 ///
-/// Note: Currently this subcommand only supports moving within the same filesystem. To move
-/// between filesystems, use `mrf exec mv` instead.
+/// When moving between filesystems, files are copied and then removed. Use `--verify` to compare
+/// the copied contents before removing the source.
 ///
 /// Examples:
 ///
@@ -33,50 +54,635 @@ use crate::replacement::{previews, resolve, PreviewOpts, ResolveOpts};
 ///     $ mrf mv * '{}{n:03}{}'
 ///     Moving 1 out of 1 items:
 ///         image-1.jpg -> image-001.jpg
+///
+/// 4. Recreate a directory tree with renamed files:
+///
+///     $ mrf mv --mirror photos sorted --copy '{}{}{}{=_}{}'
+///     Mirroring 1 out of 1 items from photos to sorted:
+///         2020/image-1.jpg -> 2020/image_1.jpg
 #[derive(Clap)]
-#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf mv [FLAGS] [OPTIONS] <item>... <replacer>",
+    verbatim_doc_comment
+)]
 pub struct Opts {
-    /// Assume yes as answer to all prompts and run non-interactively.
-    #[clap(short = "y", long)]
-    assume_yes: bool,
+    /// When copying between filesystems, verify the copied contents before removing the source.
+    #[clap(long)]
+    verify: bool,
+    /// Print an equivalent script for the given shell instead of moving files.
+    #[clap(long, possible_values = &["sh", "ps1"], conflicts_with = "mirror")]
+    emit_script: Option<Shell>,
+    /// Fail instead of warning when replacements drop extensions or produce empty or blank names.
+    #[clap(long)]
+    safe: bool,
+    /// Move items given as explicit pairs instead of by replacer, each source followed by its
+    /// destination. With "-", pairs are read from stdin, one tab-separated pair per line.
+    #[clap(long, conflicts_with_all = &["glob", "walk", "csv", "from-plan"])]
+    pairs: bool,
+    /// Expand directory items into the files under them, recursively.
+    #[clap(short = "R", long)]
+    recursive: bool,
+    /// Take the items from the files under <src>, recursively, and move each to <dst> joined with
+    /// its replaced path relative to <src>, creating directories as needed. Only the replacer is
+    /// then given as positional argument.
+    #[clap(
+        long,
+        number_of_values = 2,
+        value_names = &["src", "dst"],
+        conflicts_with_all = &["pairs", "recursive", "glob", "walk", "csv", "from-plan"]
+    )]
+    mirror: Vec<PathBuf>,
+    /// When walking directories, include hidden files and directories. ".git" directories are
+    /// always skipped. On Windows, files are hidden by their hidden attribute rather than a
+    /// leading dot.
+    #[clap(long)]
+    hidden: bool,
+    /// When walking directories, include files with the system attribute. Only applies to
+    /// Windows.
+    #[clap(long)]
+    include_system: bool,
+    /// When walking directories, do not respect ignore files (.gitignore, .ignore).
+    #[clap(long)]
+    no_ignore: bool,
+    /// When walking directories, descend at most this many levels.
+    #[clap(long)]
+    max_depth: Option<usize>,
+    /// With "--mirror", copy files instead of moving them.
+    #[clap(long, requires = "mirror")]
+    copy: bool,
+    /// Prefix each destination with a directory derived from the file's times, creating it as
+    /// needed. "{%mtime:%Y/%m}" is replaced with the modification time formatted as year and
+    /// month. Fields are mtime, ctime (creation) and atime (access).
+    #[clap(long, conflicts_with_all = &["pairs", "mirror"])]
+    bucket_by: Option<Bucket>,
+    /// When permission is denied on a read-only item, make it writable and retry, restoring its
+    /// permissions afterwards. On Unix, where moving a file depends only on the permissions of
+    /// its directory, this only applies to read-only directories.
+    #[clap(long)]
+    force: bool,
+    /// Treat destinations differing only in case as colliding, as on case-insensitive
+    /// filesystems. By default, this is detected from the destination directories.
+    #[clap(long)]
+    case_insensitive_fs: bool,
+    /// When items are hard links to the same file, move only the first, and re-link the others
+    /// to its destination, so that the file is not copied more than once between filesystems.
+    /// Only supported on Unix.
+    #[clap(long)]
+    dedupe_hardlinks: bool,
+    /// Limit the bandwidth of copies across all threads, in bytes per second. Suffixes K, M and
+    /// G are binary multiples, e.g. "50M". Renames within a filesystem are not limited.
+    #[clap(long, value_name = "rate")]
+    bwlimit: Option<Bandwidth>,
+    /// Command to run before moving each item, with "{left}" and "{right}" replaced with the item
+    /// and its destination. If it fails, the item is not moved.
+    #[clap(long, value_name = "command")]
+    pre_cmd: Option<String>,
+    /// Command to run after moving each item, with "{left}" and "{right}" replaced with the item
+    /// and its destination, e.g. "touch -r {right} {right}.done".
+    #[clap(long, value_name = "command")]
+    post_cmd: Option<String>,
+    /// Simulate the moves on an in-memory copy of the items and their destinations, reporting
+    /// the items which would fail to move, without changing any file. Hooks are not run.
+    #[clap(long, conflicts_with_all = &["emit-script", "checkpoint"])]
+    dry_run: bool,
     /// Number of threads to use.
     #[clap(short, long)]
     concurrency: Option<usize>,
-    /// Files to move. Pass "-" to read from stdin.
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    #[clap(flatten)]
+    progress: ProgressOpts,
+    #[clap(flatten)]
+    snapshot: SnapshotOpts,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Files to move, followed by the replacer string unless "--to" or "--pairs" is given.
+    /// Pass "-" to read from stdin.
     #[clap(required = true)]
     item: Vec<String>,
     /// Replacer string.
+    #[clap(skip)]
     replacer: String,
 }
 
+impl Opts {
+    /// Get options for walking directories.
+    fn walk_opts(&self) -> WalkOpts {
+        WalkOpts {
+            hidden: self.hidden,
+            system: self.include_system,
+            ignore: !self.no_ignore,
+            max_depth: self.max_depth,
+        }
+    }
+}
+
 /// Run move subcommand.
-pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
     let concurrency = opts.concurrency.unwrap_or(0);
     setup_rayon(concurrency)?;
-    let items = items_from_opt(opts.item)?;
-    let replacements = resolve(&items, &opts.replacer, ResolveOpts::new())?;
-    if !opts.assume_yes {
-        println!(
-            "Moving {} out of {} items:",
+    if opts.pairs {
+        return run_pairs(opts);
+    }
+    if !opts.mirror.is_empty() {
+        return run_mirror(opts);
+    }
+    let (replacer, mut items) = opts
+        .source
+        .take_items(&mut opts.replace, mem::take(&mut opts.item))?;
+    opts.replacer = replacer;
+    if opts.recursive {
+        let roots = items
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.is_dir())
+            .collect();
+        opts.replace.set_roots(roots, None);
+        items = expand_dirs(items, &opts.walk_opts())?;
+    }
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let mut replacements = resolve(&items, &opts.replacer, opts.replace.resolve_opts())?;
+    if let Some(bucket) = &opts.bucket_by {
+        for (left, right) in replacements.iter_mut() {
+            let dir = bucket.resolve(Path::new(left.as_ref()))?;
+            *right = Path::new(&dir).join(&right).to_string_lossy().into_owned();
+        }
+        return move_replacements(&replacements, &opts, |max_previews| {
+            Ok(format!(
+                "Moving {} out of {} items:\n{}",
+                replacements.len(),
+                items.len(),
+                format_previews(&replacements, max_previews)
+            ))
+        });
+    }
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    move_replacements(&replacements, &opts, |max_previews| {
+        Ok(format!(
+            "Moving {} out of {} items:\n{}",
             replacements.len(),
-            items.len()
+            items.len(),
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })
+}
+
+/// Run move subcommand with items given as explicit pairs.
+fn run_pairs(opts: Opts) -> Result<(), Box<dyn Error>> {
+    let items = if opts.item == ["-"] {
+        read_pairs_from_stdin()?
+    } else {
+        opts.item.clone()
+    };
+    let replacements = pairs(&items)?;
+    move_replacements(&replacements, &opts, |max_previews| {
+        Ok(format!(
+            "Moving {} items:\n{}",
+            replacements.len(),
+            format_previews(&replacements, max_previews)
+        ))
+    })
+}
+
+/// Run move subcommand mirroring the files under a directory into another.
+fn run_mirror(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let (src, dst) = (opts.mirror[0].clone(), opts.mirror[1].clone());
+    let mut items = walk_relative(&src, &opts.walk_opts())?;
+    let count = items.len();
+    items.append(&mut opts.item);
+    opts.replacer = opts.replace.take_replacer(&mut items)?;
+    if items.len() != count {
+        return Err("--mirror takes the items from <src>, only give the replacer".into());
+    }
+    opts.replace.set_roots(vec![src.clone()], Some(src.clone()));
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let replacements: Vec<Replacement> =
+        resolve(&items, &opts.replacer, opts.replace.resolve_opts())?
+            .into_iter()
+            .map(|(left, right)| {
+                (
+                    Cow::Owned(src.join(left.as_ref()).to_string_lossy().into_owned()),
+                    dst.join(right).to_string_lossy().into_owned(),
+                )
+            })
+            .collect();
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    move_replacements(&replacements, &opts, |max_previews| {
+        Ok(format!(
+            "Mirroring {} out of {} items from {} to {}:\n{}",
+            replacements.len(),
+            items.len(),
+            src.display(),
+            dst.display(),
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })
+}
+
+/// Read tab-separated pairs from stdin, one pair per line, flattened into a list of items.
+fn read_pairs_from_stdin() -> Result<Vec<String>, Box<dyn Error>> {
+    let mut items = vec![];
+    for line in read_items_from_stdin()? {
+        let mut columns = line.splitn(2, '\t');
+        match (columns.next(), columns.next()) {
+            (Some(left), Some(right)) => {
+                items.push(left.to_owned());
+                items.push(right.to_owned());
+            }
+            _ => return Err(format!("expected two tab-separated columns: {}", line).into()),
+        }
+    }
+    Ok(items)
+}
+
+/// Consume items two at a time as source and destination.
+fn pairs(items: &[String]) -> Result<Vec<Replacement<'_>>, Box<dyn Error>> {
+    if !items.len().is_multiple_of(2) {
+        return Err(format!("expected pairs of items, got {} items", items.len()).into());
+    }
+    Ok(items
+        .chunks(2)
+        .filter(|pair| pair[0] != pair[1])
+        .map(|pair| (Cow::Borrowed(pair[0].as_str()), pair[1].clone()))
+        .collect())
+}
+
+/// Confirm and move each file according to the replacements.
+fn move_replacements<F>(
+    replacements: &[Replacement],
+    opts: &Opts,
+    preview: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(usize) -> Result<String, Box<dyn Error>>,
+{
+    check_collisions(replacements, opts.case_insensitive_fs)?;
+    let long_names = long_names(replacements);
+    if !long_names.is_empty() {
+        return Err(format!(
+            "names are too long for the filesystem, pass --truncate-to-fit to shorten them:\n{}",
+            format_long_names(&long_names)
+        )
+        .into());
+    }
+    check_reserved(replacements)?;
+    let hazards = hazards(replacements);
+    if !hazards.is_empty() {
+        if opts.safe {
+            return Err(format!("unsafe replacements:\n{}", format_hazards(&hazards)).into());
+        }
+        eprintln!("Warning: {}", format_hazards(&hazards));
+    }
+    let hooks = Hooks {
+        pre: opts.pre_cmd.as_deref().map(parse_hook).transpose()?,
+        post: opts.post_cmd.as_deref().map(parse_hook).transpose()?,
+    };
+    let snapshot = opts.snapshot.before(replacements)?;
+    if let Some(shell) = opts.emit_script {
+        let mut sink = Script::new(io::BufWriter::new(io::stdout().lock()), shell)
+            .with_create_parents(opts.bucket_by.is_some());
+        write_all(&mut sink, replacements)?;
+        opts.snapshot.after(snapshot, replacements, false)?;
+        return Ok(());
+    }
+    let move_opts = MoveOpts {
+        verify: opts.verify,
+        create_parents: !opts.mirror.is_empty() || opts.bucket_by.is_some(),
+        keep_source: opts.copy,
+        force: opts.force,
+        throttle: opts.bwlimit.map(|b| Arc::new(Throttle::new(b))),
+        ..MoveOpts::new()
+    };
+    let denied: Vec<String> = replacements
+        .iter()
+        .filter_map(|(left, right)| {
+            let problem = check_move(Path::new(left.as_ref()), Path::new(right), &move_opts)?;
+            Some(format!("    {} -> {}: {}", left, right, problem))
+        })
+        .collect();
+    if !denied.is_empty() {
+        eprintln!(
+            "Warning: {} items are likely to fail to move:\n{}",
+            denied.len(),
+            denied.join("\n")
         );
-        println!("{}", previews(&items, &opts.replacer, PreviewOpts::new())?);
-        if !Confirm::new()
-            .with_prompt("Do you want to continue?")
-            .default(false)
-            .interact()?
-        {
-            return Ok(());
+    }
+    if opts.dry_run {
+        dry_run(
+            replacements,
+            move_opts,
+            opts.dedupe_hardlinks,
+            &opts.progress,
+        )?;
+        opts.snapshot.after(snapshot, replacements, false)?;
+        return Ok(());
+    }
+    if !opts.confirm.confirm(preview)? {
+        opts.snapshot.after(snapshot, replacements, false)?;
+        return Ok(());
+    }
+    let result = if opts.dedupe_hardlinks {
+        move_deduped(replacements, &move_opts, &hooks, &opts.progress)
+    } else {
+        move_all(replacements, &move_opts, &hooks, &opts.progress)
+    };
+    // The items which were moved are recorded even if others failed.
+    opts.snapshot.after(snapshot, replacements, true)?;
+    result?;
+    opts.replace.record_history();
+    Ok(())
+}
+
+/// Simulate moving each file according to the replacements, on an in-memory copy of the files and
+/// their destinations, reporting the items which would fail.
+pub fn dry_run(
+    replacements: &[Replacement],
+    opts: MoveOpts,
+    dedupe_hardlinks: bool,
+    progress: &ProgressOpts,
+) -> io::Result<()> {
+    let paths = replacements
+        .iter()
+        .flat_map(|(left, right)| [Path::new(left.as_ref()), Path::new(right)]);
+    let opts = MoveOpts {
+        fs: Arc::new(DryFs::load(paths)?),
+        throttle: None,
+        ..opts
+    };
+    let result = if dedupe_hardlinks {
+        move_deduped(replacements, &opts, &Hooks::new(), progress)
+    } else {
+        move_all(replacements, &opts, &Hooks::new(), progress)
+    };
+    eprintln!("Dry run, no files were changed.");
+    result
+}
+
+/// Move only the first hard link of each file, and re-link the others to its destination once
+/// the first links are moved.
+///
+/// Re-linked items are reported, checkpointed and hooked like moved ones.
+fn move_deduped(
+    replacements: &[Replacement],
+    opts: &MoveOpts,
+    hooks: &Hooks,
+    progress: &ProgressOpts,
+) -> io::Result<()> {
+    let (primaries, links) = split_hard_links(replacements);
+    let (targets, links): (Vec<String>, Vec<Replacement>) = links.into_iter().unzip();
+    let progress = progress.start(replacements.len())?;
+    let mut statuses = move_each(&primaries, 0, hooks, &progress, |_, from, to| {
+        move_path(from, to, opts)
+    });
+    statuses.extend(move_each(
+        &links,
+        primaries.len(),
+        hooks,
+        &progress,
+        |index, from, to| relink_path(Path::new(&targets[index]), from, to, opts),
+    ));
+    progress.done(
+        replacements.len(),
+        statuses.iter().filter(|s| s.is_err()).count(),
+    );
+    let replacements: Vec<Replacement> = primaries.into_iter().chain(links).collect();
+    print_failures(&replacements, &statuses)
+}
+
+/// Split replacements into those to move, and the other hard links of files moved by them.
+///
+/// # Returns
+///
+/// The replacements to move, and each other hard link with the destination of the first link of
+/// its file.
+fn split_hard_links<'a>(
+    replacements: &[Replacement<'a>],
+) -> (Vec<Replacement<'a>>, Vec<(String, Replacement<'a>)>) {
+    let mut primaries = vec![];
+    let mut links = vec![];
+    let mut targets: HashMap<(u64, u64), String> = HashMap::new();
+    for replacement in replacements {
+        match hard_link_id(Path::new(replacement.0.as_ref())) {
+            Some(id) => match targets.get(&id) {
+                Some(target) => links.push((target.clone(), replacement.clone())),
+                None => {
+                    targets.insert(id, replacement.1.clone());
+                    primaries.push(replacement.clone());
+                }
+            },
+            None => primaries.push(replacement.clone()),
         }
     }
+    (primaries, links)
+}
+
+/// Commands run before and after moving each item.
+#[derive(Default)]
+pub struct Hooks {
+    /// Parsed command run before moving.
+    pub pre: Option<Vec<String>>,
+    /// Parsed command run after moving.
+    pub post: Option<Vec<String>>,
+}
+
+impl Hooks {
+    pub fn new() -> Self {
+        Self {
+            pre: None,
+            post: None,
+        }
+    }
+}
+
+/// Parse a hook command.
+fn parse_hook(s: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let args = command::parse(s, &ParseOpts::default())?;
+    if args.is_empty() {
+        return Err("hook command is empty".into());
+    }
+    Ok(args)
+}
+
+/// Run a hook command, if any, with `{left}` and `{right}` substituted.
+///
+/// # Returns
+///
+/// An `io::Result`. Fails if the command cannot be run or exits unsuccessfully.
+fn run_hook(args: Option<&Vec<String>>, name: &str, left: &str, right: &str) -> io::Result<()> {
+    let args = match args {
+        Some(args) => command::substitute(args, left, right),
+        None => return Ok(()),
+    };
+    let status = command::build(&args, &[])?.status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed: {}: {}",
+            name, args[0], status
+        )));
+    }
+    Ok(())
+}
+
+/// Move each file according to the replacements, in parallel, reporting progress.
+///
+/// Each item is moved only if its pre-move hook succeeds. Failures are reported in a summary
+/// after all items are moved.
+///
+/// # Returns
+///
+/// An `io::Result`. Fails only if progress cannot be reported.
+pub fn move_all(
+    replacements: &[Replacement],
+    opts: &MoveOpts,
+    hooks: &Hooks,
+    progress: &ProgressOpts,
+) -> io::Result<()> {
+    let progress = progress.start(replacements.len())?;
+    let statuses = move_each(replacements, 0, hooks, &progress, |_, from, to| {
+        move_path(from, to, opts)
+    });
+    progress.done(
+        replacements.len(),
+        statuses.iter().filter(|s| s.is_err()).count(),
+    );
+    print_failures(replacements, &statuses)
+}
+
+/// Move each item with `move_item`, in parallel, running the hooks around it and reporting its
+/// progress.
+///
+/// # Arguments
+///
+/// * `replacements` - Replacements.
+/// * `offset` - Index of the first replacement among all replacements, for progress.
+/// * `hooks` - Hooks.
+/// * `progress` - Progress reporter.
+/// * `move_item` - Function moving an item, given its index in `replacements`, its path and its
+///   destination.
+///
+/// # Returns
+///
+/// The status of each item.
+fn move_each<F>(
+    replacements: &[Replacement],
+    offset: usize,
+    hooks: &Hooks,
+    progress: &Progress,
+    move_item: F,
+) -> Vec<io::Result<()>>
+where
+    F: Fn(usize, &Path, &Path) -> io::Result<()> + Sync,
+{
     replacements
         .par_iter()
-        .progress_with(ProgressBar::new(replacements.len() as u64))
-        .for_each(|(left, right)| {
-            std::fs::rename(left.as_ref(), right).unwrap_or_else(|e| {
-                eprintln!("{}", e);
-            })
-        });
-    Ok(())
+        .enumerate()
+        .map(|(i, (left, right))| {
+            let index = offset + i;
+            if progress.skip(index, left, right) {
+                return Ok(());
+            }
+            progress.started(index, left, right);
+            let to = Path::new(right);
+            let status = run_hook(hooks.pre.as_ref(), "pre-cmd", left, right)
+                .and_then(|_| move_item(i, Path::new(left.as_ref()), to))
+                .and_then(|_| run_hook(hooks.post.as_ref(), "post-cmd", left, right));
+            let bytes = status
+                .as_ref()
+                .ok()
+                .and_then(|_| to.symlink_metadata().ok())
+                .map(|m| m.len());
+            let error = status.as_ref().err().map(|e| e.to_string());
+            progress.finished(index, left, right, bytes, error);
+            status
+        })
+        .collect()
+}
+
+/// Print a summary of the items which failed to move, counting each cause of failure.
+///
+/// # Returns
+///
+/// An `io::Result` which fails if any item failed.
+fn print_failures(replacements: &[Replacement], statuses: &[io::Result<()>]) -> io::Result<()> {
+    let failures: Vec<(&Replacement, &io::Error)> = replacements
+        .iter()
+        .zip(statuses)
+        .filter_map(|(replacement, status)| Some((replacement, status.as_ref().err()?)))
+        .collect();
+    if failures.is_empty() {
+        return Ok(());
+    }
+    let mut counts: Vec<(Failure, usize)> = vec![];
+    for (_, e) in &failures {
+        let failure = Failure::of(e);
+        match counts.iter_mut().find(|(f, _)| *f == failure) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((failure, 1)),
+        }
+    }
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(failure, count)| format!("{} {}", count, failure))
+        .collect();
+    eprintln!(
+        "Failed {} out of {} items ({}):",
+        failures.len(),
+        replacements.len(),
+        counts.join(", ")
+    );
+    for ((left, right), e) in &failures {
+        eprintln!("    {} -> {}: {}", left, right, e);
+    }
+    if statuses
+        .iter()
+        .any(|s| matches!(s, Err(e) if Failure::of(e) == Failure::Locked))
+    {
+        eprintln!("Locked files are in use by another process, close it and retry.");
+    }
+    Err(io::Error::other(format!("{} items failed", failures.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_of_items() {
+        let items: Vec<String> = ["a", "b", "c", "c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            pairs(&items).unwrap(),
+            vec![(Cow::Borrowed("a"), "b".to_owned())]
+        );
+        assert!(pairs(&items[..3]).is_err());
+    }
 }
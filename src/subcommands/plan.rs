@@ -0,0 +1,78 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{AppSettings, Clap};
+
+use super::replace::ReplaceOpts;
+use super::select::{select, SelectOpts};
+use super::source::SourceOpts;
+use super::utils::{check_collisions, check_reserved};
+
+use crate::plan::{self, Format};
+use crate::replacement::resolve;
+
+/// Write the replacements to a plan file, to be reviewed and executed later with `mrf apply`.
+///
+/// Examples:
+///
+/// 1. Write a plan, then apply it:
+///
+///     $ mrf plan * '{}{=_}{}' -o plan.json
+///     $ mrf apply plan.json
+///     Moving 1 out of 1 items:
+///         image-001.jpg -> image_001.jpg
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf plan [FLAGS] [OPTIONS] <item>... <replacer>",
+    verbatim_doc_comment
+)]
+pub struct Opts {
+    /// Write the plan to this file instead of stdout.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// Plan format. Inferred from the output file extension if not specified, defaulting to json.
+    #[clap(short, long, possible_values = &["json", "csv"])]
+    format: Option<Format>,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Items to replace, followed by the replacer string unless "--to" is given.
+    /// Pass "-" to read from stdin.
+    #[clap(required = true)]
+    item: Vec<String>,
+    /// Replacer string.
+    #[clap(skip)]
+    replacer: String,
+}
+
+/// Run plan subcommand.
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let (replacer, items) = opts.source.take_items(&mut opts.replace, opts.item)?;
+    opts.replacer = replacer;
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let replacements = resolve(&items, &opts.replacer, opts.replace.resolve_opts())?;
+    check_collisions(&replacements, false)?;
+    check_reserved(&replacements)?;
+    let format = match (opts.format, &opts.output) {
+        (Some(format), _) => format,
+        (None, Some(path)) => Format::from_path(path),
+        (None, None) => Format::Json,
+    };
+    let s = plan::to_string(&replacements, format);
+    match &opts.output {
+        Some(path) => fs::write(path, s)?,
+        None => print!("{}", s),
+    }
+    Ok(())
+}
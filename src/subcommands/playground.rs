@@ -0,0 +1,129 @@
+use std::error::Error;
+
+use clap::{AppSettings, Clap};
+use console::{truncate_str, Key, Term};
+
+use super::replace::ReplaceOpts;
+use super::source::SourceOpts;
+
+use crate::replacement::{format_previews, resolve, ResolveOpts};
+
+/// Interactively try out a replacer against sample items, previewing the replacements as you type.
+///
+/// Press Enter to print the replacer and exit, or Escape to exit without printing.
+///
+/// Examples:
+///
+/// 1. Try replacers on files in the current directory, then use the result:
+///
+///     $ mrf mv * "$(mrf try *)"
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+pub struct Opts {
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Sample items to preview replacements with.
+    #[clap(required_unless_one = &["glob", "walk", "csv", "from-plan"])]
+    item: Vec<String>,
+}
+
+/// Run try subcommand.
+pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+    let items = opts.source.items(opts.item)?;
+    let resolve_opts = opts.replace.resolve_opts();
+    // Draw on stderr so that stdout only receives the chosen replacer.
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err("try requires an interactive terminal".into());
+    }
+    let mut input = opts.replace.replacer().unwrap_or_default().to_owned();
+    loop {
+        let (width, height) = term.size();
+        let lines = render(
+            &items,
+            &input,
+            &resolve_opts,
+            (height as usize).saturating_sub(3).max(1),
+        );
+        for line in &lines {
+            term.write_line(&truncate_str(line, width as usize, "…"))?;
+        }
+        term.write_str(&format!("Replacer: {}", input))?;
+        let key = term.read_key()?;
+        term.clear_line()?;
+        term.clear_last_lines(lines.len())?;
+        match key {
+            Key::Enter => {
+                println!("{}", input);
+                return Ok(());
+            }
+            Key::Escape | Key::Char('\u{3}') => return Ok(()),
+            Key::Backspace => {
+                input.pop();
+            }
+            Key::Char(c) if !c.is_control() => input.push(c),
+            _ => {}
+        }
+    }
+}
+
+/// Render the preview of replacements.
+///
+/// # Arguments
+///
+/// * `items` - Sample items.
+/// * `replacer_str` - Replacer string.
+/// * `opts` - Options for resolving replacements.
+/// * `max_previews` - Maximum number of replacements to show.
+///
+/// # Returns
+///
+/// The lines of the preview, or of the error if the replacer is invalid.
+fn render(
+    items: &[String],
+    replacer_str: &str,
+    opts: &ResolveOpts,
+    max_previews: usize,
+) -> Vec<String> {
+    let opts = ResolveOpts {
+        highlight: true,
+        ..opts.clone()
+    };
+    match resolve(items, replacer_str, opts) {
+        Ok(replacements) => {
+            let mut lines = vec![format!(
+                "Matched {} out of {} items:",
+                replacements.len(),
+                items.len()
+            )];
+            let preview = format_previews(&replacements, max_previews);
+            lines.extend(preview.lines().map(|l| l.to_owned()));
+            lines
+        }
+        Err(e) => e.to_string().lines().map(|l| l.to_owned()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_matches() {
+        let items = vec!["a-1".to_owned(), "b".to_owned()];
+        let lines = render(&items, "{}{=_}{}", &ResolveOpts::new(), 5);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "Matched 1 out of 2 items:");
+    }
+
+    #[test]
+    fn render_error() {
+        let items = vec!["a-1".to_owned()];
+        let lines = render(&items, "{", &ResolveOpts::new(), 5);
+        assert!(!lines.is_empty());
+        assert!(!lines[0].starts_with("Matched"));
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use clap::Clap;
+use indicatif::ProgressBar;
+use notify_rust::Notification;
+use serde_json::{json, Value};
+
+use crate::checkpoint::{self, Checkpoint};
+
+/// Options controlling how progress is reported.
+#[derive(Clap)]
+pub struct ProgressOpts {
+    /// Progress format. With "json", events are written as JSON Lines instead of showing a
+    /// progress bar: "start" and "finish" of each item (with "bytes" and "error" if any), and
+    /// "done" with the number of failed items.
+    #[clap(long, default_value = "bar", possible_values = &["bar", "json"])]
+    progress: ProgressFormat,
+    /// With "--progress json", write events to this file or FIFO instead of stderr.
+    #[clap(long, value_name = "path")]
+    progress_file: Option<PathBuf>,
+    /// Show a desktop notification when all items are finished, with the number of failed items.
+    #[clap(long)]
+    notify: bool,
+    /// Report the output and "finish" events of items as soon as they finish instead of in input
+    /// order. By default, those of an item are held back until all items before it are finished.
+    #[clap(long)]
+    unordered: bool,
+    /// Append each item to this file as JSON Lines once it is finished successfully, and skip the
+    /// items already in it, so that an interrupted run can be resumed by running it again.
+    #[clap(long, value_name = "file")]
+    checkpoint: Option<PathBuf>,
+}
+
+/// Format of progress reports.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ProgressFormat {
+    Bar,
+    Json,
+}
+
+impl FromStr for ProgressFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bar" => Ok(ProgressFormat::Bar),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(format!("invalid progress format: {}", s)),
+        }
+    }
+}
+
+/// Function showing a notification with the given body.
+type Notifier = Box<dyn Fn(&str) + Send + Sync>;
+
+/// Reporter of the progress of items processed in parallel.
+pub struct Progress {
+    sink: Sink,
+    /// Notifier to show the summary with once all items are finished, if requested.
+    notifier: Option<Notifier>,
+    /// Reports held back until the items before them are finished, unless unordered.
+    order: Option<Mutex<Order>>,
+    /// Checkpoint to record finished items in, and the items finished in previous runs.
+    checkpoint: Option<(Checkpoint, HashSet<(String, String)>)>,
+}
+
+/// Reports of items finished out of order.
+#[derive(Default)]
+struct Order {
+    /// Index of the first item not reported yet.
+    next: usize,
+    pending: BTreeMap<usize, Pending>,
+}
+
+/// Reports of an item, held back until the items before it are finished.
+#[derive(Default)]
+struct Pending {
+    /// Captured stdout and stderr.
+    output: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Whether the item is finished.
+    finished: bool,
+    /// "finish" event, if reporting events.
+    event: Option<Value>,
+}
+
+/// Destination of progress reports.
+enum Sink {
+    Bar(ProgressBar),
+    Json(Mutex<Box<dyn Write + Send>>),
+}
+
+impl ProgressOpts {
+    /// Get the items finished in previous runs according to the checkpoint, if any.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the items and their replaced strings. Fails if the checkpoint
+    /// cannot be read.
+    pub fn finished(&self) -> io::Result<HashSet<(String, String)>> {
+        match &self.checkpoint {
+            Some(path) => checkpoint::load(path),
+            None => Ok(HashSet::new()),
+        }
+    }
+
+    /// Start reporting progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - Number of items.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the reporter. Fails if the progress file or checkpoint cannot be
+    /// opened.
+    pub fn start(&self, len: usize) -> io::Result<Progress> {
+        let checkpoint = match &self.checkpoint {
+            Some(path) => Some((Checkpoint::open(path)?, self.finished()?)),
+            None => None,
+        };
+        let sink = match self.progress {
+            ProgressFormat::Bar => Sink::Bar(ProgressBar::new(len as u64)),
+            ProgressFormat::Json => {
+                let out: Box<dyn Write + Send> = match &self.progress_file {
+                    Some(path) => {
+                        Box::new(OpenOptions::new().create(true).append(true).open(path)?)
+                    }
+                    None => Box::new(io::stderr()),
+                };
+                Sink::Json(Mutex::new(out))
+            }
+        };
+        Ok(Progress {
+            sink,
+            notifier: if self.notify {
+                Some(Box::new(show_notification))
+            } else {
+                None
+            },
+            order: if self.unordered {
+                None
+            } else {
+                Some(Mutex::new(Order::default()))
+            },
+            checkpoint,
+        })
+    }
+}
+
+impl Progress {
+    /// Report that an item was started.
+    pub fn started(&self, index: usize, left: &str, right: &str) {
+        self.event(json!({"event": "start", "index": index, "from": left, "to": right}));
+    }
+
+    /// Check whether an item was finished in a previous run according to the checkpoint, and
+    /// report it as skipped if so.
+    pub fn skip(&self, index: usize, left: &str, right: &str) -> bool {
+        let skip = match &self.checkpoint {
+            Some((_, finished)) => finished.contains(&(left.to_owned(), right.to_owned())),
+            None => false,
+        };
+        if skip {
+            let event = match &self.sink {
+                Sink::Bar(bar) => {
+                    bar.inc(1);
+                    None
+                }
+                Sink::Json(_) => Some(json!({
+                    "event": "skip",
+                    "index": index,
+                    "from": left,
+                    "to": right,
+                })),
+            };
+            self.report_finished(index, event);
+        }
+        skip
+    }
+
+    /// Report that an item was finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the item.
+    /// * `left` - Item.
+    /// * `right` - Replaced item.
+    /// * `bytes` - Number of bytes processed, if known.
+    /// * `error` - Error, if the item failed.
+    pub fn finished(
+        &self,
+        index: usize,
+        left: &str,
+        right: &str,
+        bytes: Option<u64>,
+        error: Option<String>,
+    ) {
+        let event = match &self.sink {
+            Sink::Bar(bar) => {
+                bar.inc(1);
+                None
+            }
+            Sink::Json(_) => Some(json!({
+                "event": "finish",
+                "index": index,
+                "from": left,
+                "to": right,
+                "bytes": bytes,
+                "error": error,
+            })),
+        };
+        if let Some((checkpoint, _)) = &self.checkpoint {
+            if error.is_none() {
+                if let Err(e) = checkpoint.record(left, right) {
+                    eprintln!("Warning: failed to write checkpoint: {}", e);
+                }
+            }
+        }
+        self.report_finished(index, event);
+    }
+
+    /// Report the output and "finish" event of an item, unless held back until the items before
+    /// it are finished.
+    fn report_finished(&self, index: usize, event: Option<Value>) {
+        let order = match &self.order {
+            Some(order) => order,
+            None => {
+                if let Some(event) = event {
+                    self.event(event);
+                }
+                return;
+            }
+        };
+        // Report the item and the ones after it which are finished already, if all items before
+        // it are reported. The lock is held while reporting so that reports stay in order.
+        let mut order = order.lock().unwrap();
+        let pending = order.pending.entry(index).or_default();
+        pending.finished = true;
+        pending.event = event;
+        while order.pending.get(&order.next).is_some_and(|p| p.finished) {
+            let next = order.next;
+            if let Some(pending) = order.pending.remove(&next) {
+                self.report(pending);
+            }
+            order.next += 1;
+        }
+    }
+
+    /// Report that all items were finished, and show a notification if requested.
+    pub fn done(&self, total: usize, failed: usize) {
+        if let Some((checkpoint, _)) = &self.checkpoint {
+            if let Err(e) = checkpoint.sync() {
+                eprintln!("Warning: failed to write checkpoint: {}", e);
+            }
+        }
+        if let Some(order) = &self.order {
+            // Report items which did not finish, e.g. if processing was interrupted by an error.
+            let pending = std::mem::take(&mut order.lock().unwrap().pending);
+            pending.into_values().for_each(|p| self.report(p));
+        }
+        match &self.sink {
+            Sink::Bar(bar) => bar.finish(),
+            Sink::Json(_) => self.event(json!({"event": "done", "total": total, "failed": failed})),
+        }
+        if let Some(notifier) = &self.notifier {
+            notifier(&summary(total, failed));
+        }
+    }
+
+    /// Show notifications with `notifier` instead of desktop notifications, if requested.
+    pub fn with_notifier(mut self, notifier: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        if self.notifier.is_some() {
+            self.notifier = Some(Box::new(notifier));
+        }
+        self
+    }
+
+    /// Print the captured output of an item without disrupting the progress bar.
+    ///
+    /// Unless unordered, the output is held back until the item and all items before it are
+    /// finished.
+    pub fn output(&self, index: usize, stdout: &[u8], stderr: &[u8]) {
+        match &self.order {
+            Some(order) => order
+                .lock()
+                .unwrap()
+                .pending
+                .entry(index)
+                .or_default()
+                .output
+                .push((stdout.to_vec(), stderr.to_vec())),
+            None => self.write_output(stdout, stderr),
+        }
+    }
+
+    /// Write the held back reports of an item.
+    fn report(&self, pending: Pending) {
+        for (stdout, stderr) in &pending.output {
+            self.write_output(stdout, stderr);
+        }
+        if let Some(event) = pending.event {
+            self.event(event);
+        }
+    }
+
+    /// Write output of an item.
+    ///
+    /// While the progress bar is shown, output to the terminal is printed above it. Other output
+    /// is written as-is, holding the lock of the stream so that the output of items is not
+    /// interleaved.
+    fn write_output(&self, stdout: &[u8], stderr: &[u8]) {
+        let bar = match &self.sink {
+            Sink::Bar(bar) if !bar.is_hidden() => Some(bar),
+            _ => None,
+        };
+        if !stdout.is_empty() {
+            match bar {
+                Some(bar) if atty::is(atty::Stream::Stdout) => {
+                    bar.println(String::from_utf8_lossy(stdout))
+                }
+                _ => {
+                    let _ = io::stdout().lock().write_all(stdout);
+                }
+            }
+        }
+        if !stderr.is_empty() {
+            match bar {
+                Some(bar) => bar.println(String::from_utf8_lossy(stderr)),
+                None => {
+                    let _ = io::stderr().lock().write_all(stderr);
+                }
+            }
+        }
+    }
+
+    /// Write an event as a JSON line, ignoring write errors so as not to abort the operation.
+    fn event(&self, event: Value) {
+        if let Sink::Json(out) = &self.sink {
+            let mut out = out.lock().unwrap();
+            let _ = writeln!(out, "{}", event).and_then(|_| out.flush());
+        }
+    }
+}
+
+/// Summarize the finished items.
+fn summary(total: usize, failed: usize) -> String {
+    if failed == 0 {
+        format!("Finished {} items", total)
+    } else {
+        format!("Failed {} out of {} items", failed, total)
+    }
+}
+
+/// Show a desktop notification.
+///
+/// Failures to show the notification, e.g. without a notification daemon, are warned about.
+fn show_notification(body: &str) {
+    if let Err(e) = Notification::new().summary("mrf").body(body).show() {
+        eprintln!("Warning: failed to show notification: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn notify_summary() {
+        for (notify, total, failed, expected) in [
+            (true, 2, 0, vec!["Finished 2 items"]),
+            (true, 2, 1, vec!["Failed 1 out of 2 items"]),
+            (false, 2, 1, vec![]),
+        ] {
+            let mut args = vec!["progress"];
+            if notify {
+                args.push("--notify");
+            }
+            let bodies = Arc::new(Mutex::new(vec![]));
+            let notified = Arc::clone(&bodies);
+            let progress = ProgressOpts::parse_from(args)
+                .start(total)
+                .unwrap()
+                .with_notifier(move |body| notified.lock().unwrap().push(body.to_owned()));
+            progress.done(total, failed);
+            assert_eq!(*bodies.lock().unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{AppSettings, Clap};
+
+use super::confirm::ConfirmOpts;
+use super::replace::ReplaceOpts;
+use super::select::{select, SelectOpts};
+use super::source::SourceOpts;
+use super::utils::{check_collisions, check_reserved};
+
+use crate::fsops::{move_path, MoveOpts};
+use crate::replacement::{format_long_names, long_names, previews, resolve, PreviewOpts};
+
+/// Move each file according to the replacer, and rewrite references to it in other files.
+///
+/// Every occurrence of a moved file's stem (its name without extension) in the files given by
+/// `--in` is replaced with the new stem. Only whole identifiers are replaced, so renaming `foo`
+/// does not affect `foobar`.
+///
+/// Examples:
+///
+/// 1. Rename a module and update its imports:
+///
+///     $ mrf refactor src/foo_bar.rs '{}{=_baz}{=}{}' --in src/main.rs --in src/lib.rs
+///     Moving 1 out of 1 items:
+///         src/foo_bar.rs -> src/foo_baz.rs
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf refactor [FLAGS] [OPTIONS] <item>... <replacer>",
+    verbatim_doc_comment
+)]
+pub struct Opts {
+    /// File to rewrite references in. May be specified multiple times.
+    #[clap(long = "in", number_of_values = 1)]
+    files: Vec<PathBuf>,
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Files to move, followed by the replacer string unless "--to" is given.
+    /// Pass "-" to read from stdin.
+    #[clap(required = true)]
+    item: Vec<String>,
+    /// Replacer string.
+    #[clap(skip)]
+    replacer: String,
+}
+
+/// Run refactor subcommand.
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let (replacer, items) = opts.source.take_items(&mut opts.replace, opts.item)?;
+    opts.replacer = replacer;
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let replacements = resolve(&items, &opts.replacer, opts.replace.resolve_opts())?;
+    check_collisions(&replacements, false)?;
+    let long_names = long_names(&replacements);
+    if !long_names.is_empty() {
+        return Err(format!(
+            "names are too long for the filesystem, pass --truncate-to-fit to shorten them:\n{}",
+            format_long_names(&long_names)
+        )
+        .into());
+    }
+    check_reserved(&replacements)?;
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    if !opts.confirm.confirm(|max_previews| {
+        Ok(format!(
+            "Moving {} out of {} items:\n{}",
+            replacements.len(),
+            items.len(),
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })? {
+        return Ok(());
+    }
+    // Read the files first, so that an unreadable file fails before anything is moved.
+    let contents = opts
+        .files
+        .iter()
+        .map(|file| {
+            fs::read_to_string(file).map_err(|e| format!("{}: {}", file.display(), e).into())
+        })
+        .collect::<Result<Vec<String>, Box<dyn Error>>>()?;
+    let mut failures = vec![];
+    let mut moved = vec![];
+    for (left, right) in &replacements {
+        match move_path(Path::new(left.as_ref()), Path::new(right), &MoveOpts::new()) {
+            Ok(()) => moved.push((left.as_ref(), right.as_str())),
+            Err(e) => failures.push(format!("    {} -> {}: {}", left, right, e)),
+        }
+    }
+    // References are only rewritten to files which were moved.
+    let stems: Vec<(&str, &str)> = moved
+        .iter()
+        .filter_map(|(left, right)| Some((file_stem(left)?, file_stem(right)?)))
+        .filter(|(left, right)| left != right)
+        .collect();
+    for (file, content) in opts.files.iter().zip(contents) {
+        let rewritten = replace_identifiers(&content, &stems);
+        if rewritten == content {
+            continue;
+        }
+        // A file to rewrite may have been moved itself.
+        let file = moved
+            .iter()
+            .find(|(left, _)| Path::new(left) == file)
+            .map_or(file.as_path(), |(_, right)| Path::new(right));
+        if let Err(e) = fs::write(file, rewritten) {
+            failures.push(format!("    {}: {}", file.display(), e));
+        }
+    }
+    if !failures.is_empty() {
+        return Err(format!(
+            "failed to refactor {} items:\n{}",
+            failures.len(),
+            failures.join("\n")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Get file stem of path.
+fn file_stem(path: &str) -> Option<&str> {
+    Path::new(path).file_stem().and_then(|s| s.to_str())
+}
+
+/// Replace whole identifiers in a single pass, so that replaced text is never replaced again.
+///
+/// When multiple identifiers match at the same position, the longest one is used.
+fn replace_identifiers(s: &str, replacements: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut pos = 0;
+    while pos < s.len() {
+        let rest = &s[pos..];
+        let at_boundary = !s[..pos].chars().next_back().is_some_and(is_ident_char);
+        let found = if at_boundary {
+            replacements
+                .iter()
+                .filter(|(from, _)| {
+                    !from.is_empty()
+                        && rest.starts_with(from)
+                        && !rest[from.len()..].chars().next().is_some_and(is_ident_char)
+                })
+                .max_by_key(|(from, _)| from.len())
+        } else {
+            None
+        };
+        match found {
+            Some((from, to)) => {
+                out.push_str(to);
+                pos += from.len();
+            }
+            None => {
+                let c = rest.chars().next().unwrap();
+                out.push(c);
+                pos += c.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// Check if character may be part of an identifier.
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! replace_identifiers_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, replacements, expected): (&str, &[(&str, &str)], &str) = $value;
+                    assert_eq!(replace_identifiers(s, replacements), expected);
+                }
+            )*
+        }
+    }
+
+    replace_identifiers_tests!(
+        replace_identifiers_simple: ("mod foo;", &[("foo", "bar")], "mod bar;"),
+        replace_identifiers_whole_only: ("foobar foo", &[("foo", "bar")], "foobar bar"),
+        replace_identifiers_no_cascade: ("a b", &[("a", "b"), ("b", "c")], "b c"),
+        replace_identifiers_longest: ("foo_bar", &[("foo", "x"), ("foo_bar", "y")], "y"),
+        replace_identifiers_unicode: ("é foo", &[("foo", "bar")], "é bar"),
+    );
+}
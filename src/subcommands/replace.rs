@@ -0,0 +1,259 @@
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use clap::Clap;
+
+use crate::config::{Config, CONFIG_FILE};
+use crate::define::{expand, Define};
+use crate::elem::Elem;
+use crate::formatter::{FormatOpts, NumberLocale};
+use crate::history::{self, Entry};
+use crate::normalize::{Normalization, PostProcess};
+use crate::parser::{parse_with_syntax, Syntax};
+use crate::replacement::ResolveOpts;
+use crate::tokens::TokenMode;
+
+/// Options controlling how items are replaced.
+#[derive(Clap)]
+pub struct ReplaceOpts {
+    /// Restart counters ("{#}") for each distinct value of the match at this index.
+    #[clap(long)]
+    group_by: Option<usize>,
+    /// Tokenization mode. "code" additionally splits camelCase names at word boundaries.
+    #[clap(long, default_value = "default", possible_values = &["default", "code"])]
+    token_mode: TokenMode,
+    /// Apply the replacer repeatedly along each item, e.g. "{}{=_}{}" replaces every separator
+    /// instead of only the first.
+    #[clap(long)]
+    all_occurrences: bool,
+    /// Append the rest of each item left unmatched by the replacer, so that e.g. "{}{=_}" keeps
+    /// the extension without a trailing "{}".
+    #[clap(long)]
+    keep_rest: bool,
+    /// Replacer syntax. "angle" delimits specifiers with angle brackets ("<n>"), and "percent"
+    /// with a percent sign and curly braces ("%{n}"), so that curly braces are literals.
+    #[clap(long, default_value = "braces", possible_values = &["braces", "angle", "percent"])]
+    syntax: Syntax,
+    /// Match items with this pattern, using the replacer only as the output template, which
+    /// refers to the matched groups by index (e.g. --from "{}{n}" --to "{2}-{1}").
+    #[clap(long)]
+    from: Option<String>,
+    /// Take the replacer as a literal output, in which only counters ("{#}") and metadata fields
+    /// ("{%mtime}") are specifiers, e.g. "file-{#:04}.bin". Other curly braces and backslashes
+    /// are kept as-is.
+    #[clap(long, conflicts_with_all = &["from", "define"])]
+    raw: bool,
+    /// Output template for "--from". All positional arguments are then items.
+    #[clap(long, requires = "from")]
+    to: Option<String>,
+    /// Replacer string, given by name instead of as the last positional argument. All
+    /// positional arguments are then items.
+    #[clap(short = "e", long, conflicts_with = "to")]
+    replacer: Option<String>,
+    /// Reuse the replacer used last in the current directory. All positional arguments are then
+    /// items. See "mrf history".
+    #[clap(long, conflicts_with_all = &["to", "replacer"])]
+    last: bool,
+    /// Use the replacer of this name, defined in the "[replacers]" table of ".mrf.toml" in the
+    /// current directory or its nearest parent with one. All positional arguments are then items.
+    #[clap(long, value_name = "name", conflicts_with_all = &["to", "replacer", "last"])]
+    named: Option<String>,
+    /// Define a reusable fragment of replacers, e.g. "date={n}-{n}-{n}", referred to as "{@date}"
+    /// (in the replacer syntax). May be given multiple times, and fragments may refer to others.
+    #[clap(long, value_name = "name=fragment", number_of_values = 1)]
+    define: Vec<Define>,
+    /// Separators of numbers formatted with thousands separators ("{n:,}") or decimal places
+    /// ("{d:.2}"): "en" (1,234.5), "de" (1.234,5), "fr" (1 234,5), "ch" (1'234.5), or the
+    /// thousands separator followed by the decimal separator (e.g. "_.").
+    /// Decimal numbers ("{d}") are also matched with these separators.
+    #[clap(long, value_name = "locale", default_value = "en")]
+    number_locale: NumberLocale,
+    /// Transliterate digits of other scripts in matches, e.g. the Arabic-Indic "١٢" or the
+    /// Devanagari "१२", to ASCII digits.
+    #[clap(long)]
+    ascii_digits: bool,
+    /// Normalize replaced strings to this Unicode normalization form, e.g. "nfc" to fix
+    /// decomposed names created on macOS.
+    #[clap(long, value_name = "form", possible_values = &["nfc", "nfd"])]
+    normalize: Option<Normalization>,
+    /// Collapse runs of whitespace in replaced strings into a single space, and trim leading and
+    /// trailing whitespace.
+    #[clap(long)]
+    collapse_spaces: bool,
+    /// Shorten replaced names too long for the filesystem (usually 255 bytes), cutting the end of
+    /// the name before the extension.
+    #[clap(long)]
+    truncate_to_fit: bool,
+    /// Make replaced strings which would collide unique, by inserting a numbered suffix before
+    /// the extension (e.g. "a.jpg" becomes "a-1.jpg" and "a-2.jpg").
+    #[clap(long)]
+    uniquify: bool,
+    /// Suffix template for "--uniquify", where the counter is the number [default: "-{#}", in
+    /// the replacer syntax].
+    #[clap(long)]
+    uniquify_suffix: Option<String>,
+    /// Stop words of "{:titlecase}", from ".mrf.toml".
+    #[clap(skip)]
+    stop_words: Option<Vec<String>>,
+    /// Directory relative items are in.
+    #[clap(skip)]
+    base_dir: Option<PathBuf>,
+    /// Directories walked to find the items.
+    #[clap(skip)]
+    roots: Vec<PathBuf>,
+    /// Replacer to record in the history once the items are processed.
+    #[clap(skip)]
+    history: Option<String>,
+}
+
+impl ReplaceOpts {
+    /// Get options for resolving replacements.
+    pub fn resolve_opts(&self) -> ResolveOpts {
+        ResolveOpts {
+            group_by: self.group_by,
+            token_mode: self.token_mode,
+            all_occurrences: self.all_occurrences,
+            syntax: self.syntax,
+            pattern: self.from.clone(),
+            raw: self.raw,
+            keep_rest: self.keep_rest,
+            format_opts: FormatOpts {
+                number_locale: self.number_locale,
+                stop_words: self
+                    .stop_words
+                    .clone()
+                    .unwrap_or_else(|| FormatOpts::new().stop_words),
+                ascii_digits: self.ascii_digits,
+            },
+            post_process: PostProcess {
+                normalize: self.normalize,
+                collapse_spaces: self.collapse_spaces,
+                truncate_to_fit: self.truncate_to_fit,
+            },
+            uniquify: if self.uniquify {
+                Some(self.uniquify_suffix.clone().unwrap_or_else(|| {
+                    match self.syntax {
+                        Syntax::Braces => "-{#}",
+                        Syntax::Angle => "-<#>",
+                        Syntax::Percent => "-%{#}",
+                    }
+                    .to_owned()
+                }))
+            } else {
+                None
+            },
+            base_dir: self.base_dir.clone(),
+            roots: self.roots.clone(),
+            ..ResolveOpts::new()
+        }
+    }
+
+    /// Set the directories walked to find the items, and the directory relative items are in, for
+    /// metadata fields.
+    pub fn set_roots(&mut self, roots: Vec<PathBuf>, base_dir: Option<PathBuf>) {
+        self.roots = roots;
+        self.base_dir = base_dir;
+    }
+
+    /// Get the replacer string given by "--replacer", if any.
+    pub fn replacer(&self) -> Option<&str> {
+        self.replacer.as_deref()
+    }
+
+    /// Take the replacer string from the positional items.
+    ///
+    /// The replacer is the last item, unless "--to", "--replacer", "--last" or "--named" is
+    /// given. A last item without specifiers naming an existing file is rejected, since it is most
+    /// likely an item misparsed as the replacer (e.g. when the replacer was forgotten).
+    ///
+    /// References to fragments defined with "--define" are expanded in the replacer and in the
+    /// pattern of "--from". The expanded replacer is kept to be recorded with `record_history`,
+    /// unless "--from" is given. With "--raw", the replacer is taken as-is, without any of these.
+    ///
+    /// The stop words of "{:titlecase}" are read from the configuration of the current directory.
+    pub fn take_replacer(&mut self, item: &mut Vec<String>) -> Result<String, Box<dyn Error>> {
+        let replacer = match (&self.to, &self.replacer, &self.named) {
+            (Some(to), _, _) => to.clone(),
+            (None, Some(replacer), _) => replacer.clone(),
+            (None, None, Some(name)) => named_replacer(name)?,
+            (None, None, None) if self.last => last_replacer()?,
+            (None, None, None) => {
+                let replacer = item.pop().ok_or("no replacer given")?;
+                if !self.raw && looks_like_filename(&replacer, self.syntax) {
+                    return Err(format!(
+                        "replacer \"{}\" looks like a filename, pass the replacer with \"--replacer\" (-e)",
+                        replacer
+                    )
+                    .into());
+                }
+                replacer
+            }
+        };
+        if item.is_empty() {
+            return Err("no items given".into());
+        }
+        if let Some((_, config)) = Config::find(&env::current_dir()?)? {
+            self.stop_words = config
+                .stop_words
+                .map(|words| words.iter().map(|w| w.to_lowercase()).collect());
+        }
+        if self.raw {
+            return Ok(replacer);
+        }
+        let replacer = expand(&replacer, &self.define, self.syntax)?;
+        if let Some(from) = &self.from {
+            self.from = Some(expand(from, &self.define, self.syntax)?);
+        } else {
+            self.history = Some(replacer.clone());
+        }
+        Ok(replacer)
+    }
+
+    /// Record the replacer taken by `take_replacer` in the history of the current directory,
+    /// ignoring failures. Called once the items were processed successfully, so that failed and
+    /// cancelled runs are not recorded.
+    pub fn record_history(&self) {
+        let replacer = match &self.history {
+            Some(replacer) => replacer,
+            None => return,
+        };
+        if let (Some(path), Ok(dir)) = (history::path(), env::current_dir()) {
+            let entry = Entry {
+                dir,
+                replacer: replacer.clone(),
+            };
+            let _ = history::record(&path, &entry);
+        }
+    }
+}
+
+/// Get the replacer used last in the current directory.
+fn last_replacer() -> Result<String, Box<dyn Error>> {
+    let path = history::path().ok_or("history is disabled")?;
+    let dir = env::current_dir()?;
+    match history::last(&history::load(&path)?, &dir) {
+        Some(replacer) => Ok(replacer.clone()),
+        None => Err(format!("no replacer was used in {} yet", dir.display()).into()),
+    }
+}
+
+/// Get a replacer defined in the configuration of the current directory.
+fn named_replacer(name: &str) -> Result<String, Box<dyn Error>> {
+    let (path, config) = Config::find(&env::current_dir()?)?
+        .ok_or_else(|| format!("no {} found for replacer \"{}\"", CONFIG_FILE, name))?;
+    match config.replacers.get(name) {
+        Some(replacer) => Ok(replacer.clone()),
+        None => Err(format!("no replacer \"{}\" in {}", name, path.display()).into()),
+    }
+}
+
+/// Check if a replacer string looks like a filename, i.e. has no specifiers or counters and
+/// names an existing path.
+fn looks_like_filename(replacer: &str, syntax: Syntax) -> bool {
+    let literal = match parse_with_syntax(replacer, syntax) {
+        Ok(elems) => elems.iter().all(|elem| matches!(elem, Elem::Lit(_))),
+        Err(_) => false,
+    };
+    literal && Path::new(replacer).exists()
+}
@@ -0,0 +1,159 @@
+use std::error::Error;
+
+use clap::Clap;
+
+use crate::{
+    parser::parse_with_syntax,
+    predicate,
+    replacement::{build_replacer, ResolveOpts},
+    replacer::Replacer,
+};
+
+/// Options selecting which items to process.
+#[derive(Clap)]
+pub struct SelectOpts {
+    /// Only process items matching the pattern (e.g. "{}{n}").
+    #[clap(long)]
+    only: Option<String>,
+    /// Do not process items matching the pattern.
+    #[clap(long)]
+    exclude: Option<String>,
+    /// Only process items whose matches satisfy the predicate (e.g. "n1 >= 100 && n1 < 200",
+    /// where "n1" is the numeric value of the first match).
+    #[clap(long = "where")]
+    where_: Option<String>,
+    /// Sort items before selecting them.
+    #[clap(long)]
+    sort: bool,
+    /// Skip the first N items.
+    #[clap(long, default_value = "0")]
+    skip: usize,
+    /// Process at most N items.
+    #[clap(long)]
+    limit: Option<usize>,
+}
+
+/// Select items according to options.
+///
+/// Items are filtered by the `only` and `exclude` patterns and the `where` predicate (evaluated
+/// against the matches of `replacer_str`), sorted (if requested), then the first `skip` items are
+/// skipped, and at most `limit` items are kept. Patterns are parsed and matched with the syntax
+/// and tokenization mode of `resolve_opts`.
+pub fn select(
+    items: Vec<String>,
+    replacer_str: &str,
+    opts: &SelectOpts,
+    resolve_opts: &ResolveOpts,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let indices = select_indices(&items, replacer_str, opts, resolve_opts)?;
+    let mut items: Vec<Option<String>> = items.into_iter().map(Some).collect();
+    Ok(indices
+        .into_iter()
+        .filter_map(|i| items[i].take())
+        .collect())
+}
+
+/// Select items according to options, like `select`.
+///
+/// # Returns
+///
+/// A `Result` containing the indices of the selected items, in the order they are selected.
+pub fn select_indices(
+    items: &[String],
+    replacer_str: &str,
+    opts: &SelectOpts,
+    resolve_opts: &ResolveOpts,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let filter = Filter::new(replacer_str, opts, resolve_opts)?;
+    let mut indices: Vec<usize> = (0..items.len())
+        .filter(|&i| filter.selects(&items[i]))
+        .collect();
+    if opts.sort {
+        indices.sort_by(|&a, &b| items[a].cmp(&items[b]));
+    }
+    Ok(indices
+        .into_iter()
+        .skip(opts.skip)
+        .take(opts.limit.unwrap_or(usize::MAX))
+        .collect())
+}
+
+/// Select items according to options as they are iterated, like `select`, without holding them.
+///
+/// # Returns
+///
+/// A `Result` containing an iterator over the selected items. Fails if sorting is requested,
+/// which requires all items.
+pub fn select_iter<'a, I>(
+    items: I,
+    replacer_str: &str,
+    opts: &SelectOpts,
+    resolve_opts: &ResolveOpts,
+) -> Result<impl Iterator<Item = &'a str>, Box<dyn Error>>
+where
+    I: Iterator<Item = &'a str>,
+{
+    if opts.sort {
+        return Err("--sort requires reading all items at once".into());
+    }
+    let filter = Filter::new(replacer_str, opts, resolve_opts)?;
+    Ok(items
+        .filter(move |item| filter.selects(item))
+        .skip(opts.skip)
+        .take(opts.limit.unwrap_or(usize::MAX)))
+}
+
+/// Patterns and predicate filtering items.
+struct Filter {
+    only: Option<Replacer>,
+    exclude: Option<Replacer>,
+    where_: Option<(predicate::Predicate, Replacer)>,
+}
+
+impl Filter {
+    fn new(
+        replacer_str: &str,
+        opts: &SelectOpts,
+        resolve_opts: &ResolveOpts,
+    ) -> Result<Self, Box<dyn Error>> {
+        let only = match &opts.only {
+            Some(p) => Some(pattern_replacer(p, resolve_opts)?),
+            None => None,
+        };
+        let exclude = match &opts.exclude {
+            Some(p) => Some(pattern_replacer(p, resolve_opts)?),
+            None => None,
+        };
+        let where_ = match &opts.where_ {
+            Some(w) => Some((
+                predicate::parse(w)?,
+                build_replacer(replacer_str, resolve_opts)?,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            only,
+            exclude,
+            where_,
+        })
+    }
+
+    /// Check whether an item passes the patterns and the predicate.
+    fn selects(&self, item: &str) -> bool {
+        self.only.as_ref().is_none_or(|r| r.is_match(item))
+            && !self.exclude.as_ref().is_some_and(|r| r.is_match(item))
+            && self.where_.as_ref().is_none_or(|(p, r)| {
+                r.matches(item)
+                    .map(|groups| p.eval(&groups))
+                    .unwrap_or(false)
+            })
+    }
+}
+
+/// Parse pattern into a `Replacer`.
+fn pattern_replacer(pattern: &str, resolve_opts: &ResolveOpts) -> Result<Replacer, Box<dyn Error>> {
+    Ok(
+        Replacer::from(parse_with_syntax(pattern, resolve_opts.syntax)?)
+            .with_token_mode(resolve_opts.token_mode),
+    )
+}
@@ -0,0 +1,32 @@
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{AppSettings, Clap};
+
+use crate::selftest;
+
+/// Check invariants of tokenizing, matching, replacing and formatting on random cases, e.g. after
+/// packaging mrf for a platform.
+///
+/// Failures print the seed, with which the same cases are generated again.
+#[derive(Clap)]
+#[clap(setting = AppSettings::ColoredHelp)]
+pub struct Opts {
+    /// Seed of the random cases. Defaults to the current time.
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Number of cases to check.
+    #[clap(long, default_value = "10000")]
+    cases: usize,
+}
+
+/// Run selftest subcommand.
+pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+    let seed = match opts.seed {
+        Some(seed) => seed,
+        None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    };
+    selftest::run(seed, opts.cases).map_err(|failure| format!("seed {}: {}", seed, failure))?;
+    println!("Checked {} cases with seed {}", opts.cases, seed);
+    Ok(())
+}
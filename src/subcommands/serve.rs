@@ -0,0 +1,324 @@
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use clap::{AppSettings, Clap};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::replace::ReplaceOpts;
+
+use crate::replacement::{build_replacer, highlights_with, ResolveOpts};
+
+/// Maximum size of request bodies, in bytes. Bodies are buffered, so up to `MAX_CONNECTIONS`
+/// times this much memory may be held at once.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Maximum number of steps spent matching the items of a request (see
+/// `matcher::match_all_with_budget`). Requests needing more are answered with status 413.
+const MAX_MATCH_STEPS: usize = 10_000_000;
+
+/// Maximum number of request headers.
+const MAX_HEADERS: usize = 100;
+
+/// Maximum length of the request line and of each header line, in bytes.
+const MAX_LINE_LENGTH: usize = 8 * 1024;
+
+/// Maximum number of connections served at once. Further connections are answered with status
+/// 503.
+const MAX_CONNECTIONS: usize = 64;
+
+/// Time after which a connection which does not send or receive anything is closed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serve the replacement engine over HTTP, so that other tools can use it without running mrf for
+/// each request.
+///
+/// "POST /replace" with a body such as {"items": ["a-1", "b"], "replacer": "{}{=_}{}"} responds
+/// with {"replacements": [...]}, the replacement of each matched item in the format of
+/// "mrf map --json". Invalid requests and replacers are answered with status 400 and
+/// {"error": "..."}. Items are only strings here, so metadata fields ("{%mtime}"), which would
+/// read local files, are rejected. Requests whose items take too long to match, e.g. with many
+/// specifiers, are answered with status 413.
+///
+/// Examples:
+///
+/// 1. Serve on a local port, and replace items:
+///
+///     $ mrf serve --http 127.0.0.1:8080
+///     Listening on http://127.0.0.1:8080
+///
+///     $ curl -d '{"items": ["a-1"], "replacer": "{}{=_}{}"}' http://127.0.0.1:8080/replace
+///     {"replacements":[{"left":"a-1","left_spans":[...],"right":"a_1","right_spans":[...]}]}
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(setting = AppSettings::ColoredHelp, verbatim_doc_comment)]
+pub struct Opts {
+    /// Address to listen on. Use port 0 to pick a free port.
+    #[clap(long, value_name = "address", default_value = "127.0.0.1:8080")]
+    http: String,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+}
+
+/// Body of requests to `/replace`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReplaceRequest {
+    items: Vec<String>,
+    replacer: String,
+}
+
+/// HTTP request.
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// Run serve subcommand.
+pub fn run(opts: Opts) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(&opts.http)?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    io::stdout().flush()?;
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            let body = json!({ "error": "too many connections" });
+            let _ = stream
+                .set_write_timeout(Some(TIMEOUT))
+                .and_then(|_| respond(&stream, 503, &body));
+            continue;
+        }
+        let resolve_opts = opts.replace.resolve_opts();
+        let connections = Arc::clone(&connections);
+        thread::spawn(move || {
+            if let Err(e) = serve(stream, resolve_opts) {
+                eprintln!("Failed to serve request: {}", e);
+            }
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+/// Answer a single request on a connection, then close it.
+fn serve(stream: TcpStream, opts: ResolveOpts) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let (status, body) = match read_request(&mut BufReader::new(&stream)) {
+        Ok(request) => handle(&request, opts),
+        Err(e) => (400, json!({ "error": e.to_string() })),
+    };
+    respond(&stream, status, &body)
+}
+
+/// Write a response with a JSON body.
+fn respond(mut stream: &TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Handle a request.
+///
+/// # Returns
+///
+/// The status code and the JSON body of the response.
+fn handle(request: &Request, opts: ResolveOpts) -> (u16, Value) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/replace") => {}
+        (_, "/replace") => return (405, json!({ "error": "method not allowed, use POST" })),
+        _ => {
+            return (
+                404,
+                json!({ "error": format!("not found: {}", request.path) }),
+            )
+        }
+    }
+    let body: ReplaceRequest = match serde_json::from_slice(&request.body) {
+        Ok(body) => body,
+        Err(e) => return (400, json!({ "error": format!("invalid request: {}", e) })),
+    };
+    let replacer = match build_replacer(&body.replacer, &opts) {
+        Ok(replacer) if replacer.uses_metadata() => {
+            return (
+                400,
+                json!({ "error": "metadata fields are not supported by mrf serve" }),
+            )
+        }
+        Ok(replacer) => replacer.with_match_budget(MAX_MATCH_STEPS),
+        Err(e) => return (400, json!({ "error": e.to_string() })),
+    };
+    let highlights = highlights_with(&replacer, &body.items, &opts);
+    if replacer.match_budget_exceeded() {
+        return (
+            413,
+            json!({ "error": "items are too complex to match with this replacer" }),
+        );
+    }
+    let replacements: Vec<Value> = highlights.iter().map(|h| h.to_json()).collect();
+    (200, json!({ "replacements": replacements }))
+}
+
+/// Read an HTTP/1.x request, with its body if it has a `Content-Length`.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+    let mut line = String::new();
+    read_line(reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(path), Some(version)) if version.starts_with("HTTP/1.") => {
+            (method.to_owned(), path.to_owned())
+        }
+        _ => return Err(invalid("invalid request line")),
+    };
+    let mut content_length = 0;
+    for _ in 0..=MAX_HEADERS {
+        line.clear();
+        if read_line(reader, &mut line)? == 0 {
+            return Err(invalid("unexpected end of request"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            // Grow the body as it arrives, rather than trusting `Content-Length` up front.
+            let mut body = vec![];
+            reader.take(content_length as u64).read_to_end(&mut body)?;
+            if body.len() < content_length {
+                return Err(invalid("unexpected end of request"));
+            }
+            return Ok(Request { method, path, body });
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| invalid("invalid header"))?;
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .trim()
+                .parse()
+                .map_err(|_| invalid("invalid Content-Length"))?;
+            if content_length > MAX_BODY_SIZE {
+                return Err(invalid("request body too large"));
+            }
+        }
+    }
+    Err(invalid("too many headers"))
+}
+
+/// Read a line of at most `MAX_LINE_LENGTH` bytes.
+///
+/// # Returns
+///
+/// An `io::Result` containing the number of bytes read. Fails if the line is longer.
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let n = reader.take(MAX_LINE_LENGTH as u64 + 1).read_line(line)?;
+    if n > MAX_LINE_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "request line or header too long",
+        ));
+    }
+    Ok(n)
+}
+
+/// Get the reason phrase of a status code.
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_request_with_body() {
+        let raw = "POST /replace HTTP/1.1\r\nHost: x\r\ncontent-length: 4\r\n\r\nabcdef";
+        assert_eq!(
+            read_request(&mut raw.as_bytes()).unwrap(),
+            Request {
+                method: "POST".to_owned(),
+                path: "/replace".to_owned(),
+                body: b"abcd".to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn read_request_invalid() {
+        assert!(read_request(&mut "GARBAGE\r\n\r\n".as_bytes()).is_err());
+        assert!(read_request(&mut "GET / HTTP/1.1\r\nHost: x\r\n".as_bytes()).is_err());
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n";
+        assert!(read_request(&mut raw.as_bytes()).is_err());
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc";
+        assert!(read_request(&mut raw.as_bytes()).is_err());
+        let raw = format!(
+            "GET / HTTP/1.1\r\nX: {}\r\n\r\n",
+            "x".repeat(MAX_LINE_LENGTH)
+        );
+        assert!(read_request(&mut raw.as_bytes()).is_err());
+    }
+
+    macro_rules! handle_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (method, path, body, expected_status): (&str, &str, &str, u16) = $value;
+                    let request = Request {
+                        method: method.to_owned(),
+                        path: path.to_owned(),
+                        body: body.as_bytes().to_vec(),
+                    };
+                    assert_eq!(handle(&request, ResolveOpts::new()).0, expected_status);
+                }
+            )*
+        }
+    }
+
+    handle_tests!(
+        handle_replace: ("POST", "/replace", r#"{"items": ["a-1"], "replacer": "{}{=_}{}"}"#, 200),
+        handle_invalid_replacer: ("POST", "/replace", r#"{"items": [], "replacer": "{"}"#, 400),
+        handle_invalid_body: ("POST", "/replace", r#"{"items": ["a"]}"#, 400),
+        handle_metadata: ("POST", "/replace", r#"{"items": ["a"], "replacer": "{%mtime}"}"#, 400),
+        handle_too_complex: (
+            "POST",
+            "/replace",
+            &format!(
+                r#"{{"items": ["{}"], "replacer": "{}{{n}}"}}"#,
+                vec!["a"; 21].join("-"),
+                "{}".repeat(14)
+            ),
+            413,
+        ),
+        handle_method: ("GET", "/replace", "", 405),
+        handle_not_found: ("POST", "/other", "", 404),
+    );
+}
@@ -0,0 +1,70 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Clap;
+
+use crate::replacement::Replacement;
+use crate::snapshot::Snapshot;
+
+/// Options controlling snapshots of the affected directories.
+#[derive(Clap)]
+pub struct SnapshotOpts {
+    /// Write the listing of the directories containing the items to this file before moving, and
+    /// to "<path>.after" (or "--snapshot-after") after moving. Without moving, e.g. with "--no",
+    /// the planned listing is written instead, so that the two can be diffed.
+    #[clap(long, value_name = "path")]
+    snapshot: Option<PathBuf>,
+    /// With "--snapshot", write the listing after moving to this file.
+    #[clap(long, value_name = "path", requires = "snapshot")]
+    snapshot_after: Option<PathBuf>,
+}
+
+impl SnapshotOpts {
+    /// Take and write the snapshot before moving, if requested.
+    pub fn before(&self, replacements: &[Replacement]) -> io::Result<Option<Snapshot>> {
+        match &self.snapshot {
+            Some(path) => {
+                let after = self.after_path(path);
+                let snapshot = Snapshot::take(replacements, &[path, &after])?;
+                snapshot.write(path)?;
+                Ok(Some(snapshot))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Write the snapshot after moving, or the planned snapshot if nothing was moved.
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - Snapshot taken before moving.
+    /// * `replacements` - Replacements.
+    /// * `moved` - Whether the items were moved.
+    pub fn after(
+        &self,
+        before: Option<Snapshot>,
+        replacements: &[Replacement],
+        moved: bool,
+    ) -> io::Result<()> {
+        let (before, path) = match (before, &self.snapshot) {
+            (Some(before), Some(path)) => (before, path),
+            _ => return Ok(()),
+        };
+        let path = self.after_path(path);
+        let after = if moved {
+            before.retake()?
+        } else {
+            before.planned(replacements)
+        };
+        after.write(&path)
+    }
+
+    /// Get the path of the snapshot after moving.
+    fn after_path(&self, before: &Path) -> PathBuf {
+        self.snapshot_after.clone().unwrap_or_else(|| {
+            let mut path = before.as_os_str().to_owned();
+            path.push(".after");
+            path.into()
+        })
+    }
+}
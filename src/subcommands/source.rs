@@ -0,0 +1,254 @@
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use clap::Clap;
+use ignore::overrides::OverrideBuilder;
+
+use super::replace::ReplaceOpts;
+use super::utils::read_items_from_stdin;
+
+use crate::plan::{self, Format};
+use crate::walk::{expand_dirs, walk_relative, WalkOpts};
+
+/// Source of the items of a subcommand.
+pub trait ItemSource {
+    /// Read the items.
+    fn items(&self) -> Result<Vec<String>, Box<dyn Error>>;
+}
+
+/// Items given as arguments.
+pub struct Args(pub Vec<String>);
+
+impl ItemSource for Args {
+    fn items(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Items read from stdin, one item per line, or separated by NUL characters.
+pub struct Stdin {
+    pub null: bool,
+}
+
+impl ItemSource for Stdin {
+    fn items(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        if !self.null {
+            return Ok(read_items_from_stdin()?);
+        }
+        let mut s = String::new();
+        std::io::stdin().lock().read_to_string(&mut s)?;
+        Ok(split_null(&s))
+    }
+}
+
+/// Files under the current directory matching a glob pattern, e.g. `**/*.jpg`.
+///
+/// Patterns are matched against paths relative to the current directory, with the syntax of
+/// gitignore patterns. Hidden and ignored files are skipped, as with `--walk`.
+pub struct Glob(pub String);
+
+impl ItemSource for Glob {
+    fn items(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        // Anchor the pattern, so that e.g. "*.jpg" only matches in the current directory.
+        let pattern = format!("/{}", self.0.trim_start_matches('/'));
+        let glob = OverrideBuilder::new(".").add(&pattern)?.build()?;
+        Ok(walk_relative(Path::new("."), &WalkOpts::new())?
+            .into_iter()
+            .filter(|path| glob.matched(path, false).is_whitelist())
+            .collect())
+    }
+}
+
+/// Files under a directory, recursively.
+pub struct Walk(pub String);
+
+impl ItemSource for Walk {
+    fn items(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        if !Path::new(&self.0).is_dir() {
+            return Err(format!("not a directory: {}", self.0).into());
+        }
+        expand_dirs(vec![self.0.clone()], &WalkOpts::new())
+    }
+}
+
+/// Values of a column of a CSV file with a header.
+pub struct CsvColumn {
+    pub path: PathBuf,
+    pub column: String,
+}
+
+impl ItemSource for CsvColumn {
+    fn items(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let invalid = |msg: String| format!("invalid CSV file {}: {}", self.path.display(), msg);
+        let records = plan::parse_csv(&fs::read_to_string(&self.path)?).map_err(|e| match e {
+            plan::Error::Csv(msg) => invalid(msg),
+            e => e.to_string(),
+        })?;
+        let mut records = records.into_iter();
+        let header = records.next().unwrap_or_default();
+        let index = header
+            .iter()
+            .position(|name| *name == self.column)
+            .ok_or_else(|| invalid(format!("no column named \"{}\"", self.column)))?;
+        records
+            .enumerate()
+            .map(|(i, mut record)| {
+                if index < record.len() {
+                    Ok(record.swap_remove(index))
+                } else {
+                    Err(invalid(format!("missing column on row {}", i + 2)).into())
+                }
+            })
+            .collect()
+    }
+}
+
+/// Items on the left side of the replacements of a plan file.
+pub struct PlanFile(pub PathBuf);
+
+impl ItemSource for PlanFile {
+    fn items(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let s = fs::read_to_string(&self.0)?;
+        Ok(plan::from_str(&s, Format::from_path(&self.0))?
+            .into_iter()
+            .map(|(left, _)| left.into_owned())
+            .collect())
+    }
+}
+
+/// Options reading the items from another source than the item arguments.
+#[derive(Clap, Default)]
+pub struct SourceOpts {
+    /// Read items from stdin separated by NUL characters instead of newlines, when "-" is given,
+    /// e.g. from "find -print0".
+    #[clap(short = "z", long)]
+    null_input: bool,
+    /// Take the items from the files matching this glob pattern, relative to the current
+    /// directory, e.g. "**/*.jpg". Hidden and ignored files are skipped.
+    #[clap(long, value_name = "pattern", conflicts_with_all = &["walk", "csv", "from-plan"])]
+    glob: Option<String>,
+    /// Take the items from the files under this directory, recursively. Hidden and ignored files
+    /// are skipped.
+    #[clap(long, value_name = "dir", conflicts_with_all = &["csv", "from-plan"])]
+    walk: Option<String>,
+    /// Take the items from a column of this CSV file, named with "--csv-column".
+    #[clap(
+        long,
+        value_name = "file",
+        requires = "csv-column",
+        conflicts_with = "from-plan"
+    )]
+    csv: Option<PathBuf>,
+    /// Name of the column of the CSV file given with "--csv" to take the items from.
+    #[clap(long, value_name = "name", requires = "csv")]
+    csv_column: Option<String>,
+    /// Take the items from the left side of the replacements of this plan file, e.g. to replace
+    /// them differently.
+    #[clap(long, value_name = "file")]
+    from_plan: Option<PathBuf>,
+}
+
+impl SourceOpts {
+    /// Get the source of the items given with a flag, if any.
+    fn source(&self) -> Option<Box<dyn ItemSource>> {
+        if let Some(pattern) = &self.glob {
+            Some(Box::new(Glob(pattern.clone())))
+        } else if let Some(dir) = &self.walk {
+            Some(Box::new(Walk(dir.clone())))
+        } else if let (Some(path), Some(column)) = (&self.csv, &self.csv_column) {
+            Some(Box::new(CsvColumn {
+                path: path.clone(),
+                column: column.clone(),
+            }))
+        } else if let Some(path) = &self.from_plan {
+            Some(Box::new(PlanFile(path.clone())))
+        } else {
+            None
+        }
+    }
+
+    /// Read the items, from the source given with a flag, from stdin if the arguments are a
+    /// single "-", or else from the arguments.
+    pub fn items(&self, args: Vec<String>) -> Result<Vec<String>, Box<dyn Error>> {
+        let source: Box<dyn ItemSource> = match self.source() {
+            Some(source) if args.is_empty() => source,
+            Some(_) => return Err("items are taken from another source, do not give any".into()),
+            None if args == ["-"] => Box::new(Stdin {
+                null: self.null_input,
+            }),
+            None => Box::new(Args(args)),
+        };
+        source.items()
+    }
+
+    /// Take the replacer from the arguments, then read the items as with `items`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the replacer and the items.
+    pub fn take_items(
+        &self,
+        replace: &mut ReplaceOpts,
+        mut args: Vec<String>,
+    ) -> Result<(String, Vec<String>), Box<dyn Error>> {
+        match self.source() {
+            Some(source) => {
+                let mut items = source.items()?;
+                let count = items.len();
+                items.append(&mut args);
+                let replacer = replace.take_replacer(&mut items)?;
+                if items.len() != count {
+                    return Err(
+                        "items are taken from another source, only give the replacer".into(),
+                    );
+                }
+                Ok((replacer, items))
+            }
+            None => {
+                let replacer = replace.take_replacer(&mut args)?;
+                Ok((replacer, self.items(args)?))
+            }
+        }
+    }
+}
+
+/// Split a string into items separated by NUL characters, ignoring a trailing separator.
+fn split_null(s: &str) -> Vec<String> {
+    let s = s.strip_suffix('\0').unwrap_or(s);
+    if s.is_empty() {
+        return vec![];
+    }
+    s.split('\0').map(str::to_owned).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn split_null_items() {
+        assert_eq!(split_null("a\0b c\0"), ["a", "b c"]);
+        assert_eq!(split_null("a\nb"), ["a\nb"]);
+        assert!(split_null("").is_empty());
+    }
+
+    #[test]
+    fn csv_column_items() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let path = temp.child("items.csv");
+        path.write_str("id,path\n1,a.jpg\n2,\"b,c.jpg\"\n").unwrap();
+        let source = CsvColumn {
+            path: path.path().to_path_buf(),
+            column: "path".to_owned(),
+        };
+        assert_eq!(source.items().unwrap(), ["a.jpg", "b,c.jpg"]);
+        let source = CsvColumn {
+            path: path.path().to_path_buf(),
+            column: "name".to_owned(),
+        };
+        assert!(source.items().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::error::Error;
+use std::mem;
+
+use clap::{AppSettings, Clap};
+
+use super::chmod::{parse_all, set_all};
+use super::confirm::ConfirmOpts;
+use super::progress::ProgressOpts;
+use super::replace::ReplaceOpts;
+use super::select::{select, SelectOpts};
+use super::source::SourceOpts;
+use super::utils::setup_rayon;
+
+use crate::replacement::{previews, resolve, PreviewOpts};
+use crate::xattr::{self, finder_tags_plist, FINDER_TAGS_ATTR};
+
+/// Write the replaced string of each file into an extended attribute, without renaming it.
+///
+/// Files replaced with an empty string have the attribute removed. Only supported on Linux and
+/// macOS.
+///
+/// Examples:
+///
+/// 1. Label files with their prefix:
+///
+///     $ mrf tag * '{t}{=}'
+///     Tagging 1 out of 1 items:
+///         draft-001.txt -> draft
+///
+/// 2. Set the Finder tag of files on macOS:
+///
+///     $ mrf tag --finder * '{=Red}'
+///     Tagging 1 out of 1 items:
+///         image-001.jpg -> Red
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf tag [FLAGS] [OPTIONS] <item>... <replacer>",
+    verbatim_doc_comment
+)]
+pub struct Opts {
+    /// Name of the extended attribute to write.
+    #[clap(long, default_value = "user.mrf.label", conflicts_with = "finder")]
+    attr: String,
+    /// Write the Finder tag of each file instead (macOS), replacing its other tags.
+    #[clap(long)]
+    finder: bool,
+    /// Number of threads to use.
+    #[clap(short, long)]
+    concurrency: Option<usize>,
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    #[clap(flatten)]
+    progress: ProgressOpts,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Files to tag, followed by the replacer string unless "--to" is given. Pass "-" to read
+    /// from stdin.
+    #[clap(required = true)]
+    item: Vec<String>,
+    /// Replacer string.
+    #[clap(skip)]
+    replacer: String,
+}
+
+/// Run tag subcommand.
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let concurrency = opts.concurrency.unwrap_or(0);
+    setup_rayon(concurrency)?;
+    let (replacer, items) = opts
+        .source
+        .take_items(&mut opts.replace, mem::take(&mut opts.item))?;
+    opts.replacer = replacer;
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let replacements = resolve(&items, &opts.replacer, opts.replace.resolve_opts())?;
+    let finder = opts.finder;
+    let values = parse_all(&replacements, |s| {
+        Ok(match s {
+            "" => None,
+            s if finder => Some(finder_tags_plist(&[s])),
+            s => Some(s.as_bytes().to_vec()),
+        })
+    })?;
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    if !opts.confirm.confirm(|max_previews| {
+        Ok(format!(
+            "Tagging {} out of {} items:\n{}",
+            replacements.len(),
+            items.len(),
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })? {
+        return Ok(());
+    }
+    let attr = if finder { FINDER_TAGS_ATTR } else { &opts.attr };
+    set_all(
+        &replacements,
+        &values,
+        &opts.progress,
+        |path, value| match value {
+            Some(value) => xattr::set(path, attr, value),
+            None => xattr::remove(path, attr),
+        },
+    )?;
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use std::error::Error;
+use std::mem;
+use std::time::SystemTime;
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use clap::{AppSettings, Clap};
+
+use super::chmod::{parse_all, set_all};
+use super::confirm::ConfirmOpts;
+use super::progress::ProgressOpts;
+use super::replace::ReplaceOpts;
+use super::select::{select, SelectOpts};
+use super::source::SourceOpts;
+use super::utils::setup_rayon;
+
+use crate::fsops::set_times;
+use crate::replacement::{previews, resolve, PreviewOpts};
+
+/// Formats of timestamps tried in order when no format is given. Formats without a time set the
+/// time to midnight.
+const TIMESTAMP_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d_%H-%M-%S",
+    "%Y%m%d_%H%M%S",
+    "%Y%m%d%H%M%S",
+    "%Y-%m-%d",
+    "%Y%m%d",
+];
+
+/// Set the modification time of each file to the timestamp given by the replacer.
+///
+/// The replaced string of each file is parsed as a timestamp in the local time zone, e.g.
+/// "2020-01-31 12:00:00", "20200131_120000" or "2020-01-31" (midnight). This is the inverse of
+/// the "{%mtime}" metadata field.
+///
+/// Examples:
+///
+/// 1. Recover the times of photos from their names:
+///
+///     $ mrf touch * '{=}{n}{p}{n}{=}'
+///     Setting time of 1 out of 1 items:
+///         IMG_20200131_120000.jpg -> 20200131_120000
+///
+/// 2. Use dates with a custom format:
+///
+///     $ mrf touch --format '%d.%m.%Y' * '{n}{p}{n}{p}{n}{=}'
+///     Setting time of 1 out of 1 items:
+///         31.01.2020 party.jpg -> 31.01.2020
+#[derive(Clap)]
+#[allow(clippy::doc_overindented_list_items)]
+#[clap(
+    setting = AppSettings::ColoredHelp,
+    override_usage = "mrf touch [FLAGS] [OPTIONS] <item>... <replacer>",
+    verbatim_doc_comment
+)]
+pub struct Opts {
+    /// Format of the timestamps, in strftime-style (e.g. "%d.%m.%Y %H:%M"). Formats without a
+    /// time set the time to midnight. By default, common formats such as "%Y-%m-%d %H:%M:%S" and
+    /// "%Y%m%d_%H%M%S" are tried.
+    #[clap(long, value_name = "format")]
+    format: Option<String>,
+    /// Set the access time too.
+    #[clap(long)]
+    atime: bool,
+    /// Number of threads to use.
+    #[clap(short, long)]
+    concurrency: Option<usize>,
+    #[clap(flatten)]
+    confirm: ConfirmOpts,
+    #[clap(flatten)]
+    progress: ProgressOpts,
+    #[clap(flatten)]
+    select: SelectOpts,
+    #[clap(flatten)]
+    source: SourceOpts,
+    #[clap(flatten)]
+    replace: ReplaceOpts,
+    /// Files to change, followed by the replacer string unless "--to" is given. Pass "-" to read
+    /// from stdin.
+    #[clap(required = true)]
+    item: Vec<String>,
+    /// Replacer string.
+    #[clap(skip)]
+    replacer: String,
+}
+
+/// Run touch subcommand.
+pub fn run(mut opts: Opts) -> Result<(), Box<dyn Error>> {
+    let concurrency = opts.concurrency.unwrap_or(0);
+    setup_rayon(concurrency)?;
+    let (replacer, items) = opts
+        .source
+        .take_items(&mut opts.replace, mem::take(&mut opts.item))?;
+    opts.replacer = replacer;
+    let items = select(
+        items,
+        &opts.replacer,
+        &opts.select,
+        &opts.replace.resolve_opts(),
+    )?;
+    let replacements = resolve(&items, &opts.replacer, opts.replace.resolve_opts())?;
+    let format = opts.format.as_deref();
+    let times = parse_all(&replacements, |s| parse_timestamp(s, format))?;
+    let (replacer, resolve_opts) = (&opts.replacer, opts.replace.resolve_opts());
+    if !opts.confirm.confirm(|max_previews| {
+        Ok(format!(
+            "Setting time of {} out of {} items:\n{}",
+            replacements.len(),
+            items.len(),
+            previews(
+                &items,
+                replacer,
+                PreviewOpts {
+                    max_previews,
+                    ..PreviewOpts::with_resolve_opts(resolve_opts)
+                }
+            )?
+        ))
+    })? {
+        return Ok(());
+    }
+    let atime = opts.atime;
+    set_all(&replacements, &times, &opts.progress, |path, time| {
+        set_times(path, *time, atime.then_some(*time))
+    })?;
+    Ok(())
+}
+
+/// Parse a timestamp in the local time zone.
+///
+/// # Arguments
+///
+/// * `s` - Timestamp.
+/// * `format` - strftime-style format, or `None` to try `TIMESTAMP_FORMATS`.
+///
+/// # Returns
+///
+/// A `Result` containing the time. Fails if the timestamp matches none of the formats, or does
+/// not exist in the local time zone.
+fn parse_timestamp(s: &str, format: Option<&str>) -> Result<SystemTime, String> {
+    let formats = match format {
+        Some(format) => vec![format],
+        None => TIMESTAMP_FORMATS.to_vec(),
+    };
+    let datetime = formats
+        .iter()
+        .find_map(|format| {
+            NaiveDateTime::parse_from_str(s, format).ok().or_else(|| {
+                NaiveDate::parse_from_str(s, format)
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+        })
+        .ok_or_else(|| format!("invalid timestamp: {}", s))?;
+    Local
+        .from_local_datetime(&datetime)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("timestamp does not exist in the local time zone: {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! parse_timestamp_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, format, expected): (&str, Option<&str>, Option<&str>) = $value;
+                    let expected = expected.map(|e| {
+                        let datetime = NaiveDateTime::parse_from_str(e, "%Y-%m-%d %H:%M:%S").unwrap();
+                        SystemTime::from(Local.from_local_datetime(&datetime).unwrap())
+                    });
+                    assert_eq!(parse_timestamp(s, format).ok(), expected);
+                }
+            )*
+        }
+    }
+
+    parse_timestamp_tests!(
+        parse_timestamp_iso: ("2020-01-31 12:34:56", None, Some("2020-01-31 12:34:56")),
+        parse_timestamp_compact: ("20200131_123456", None, Some("2020-01-31 12:34:56")),
+        parse_timestamp_date: ("2020-01-31", None, Some("2020-01-31 00:00:00")),
+        parse_timestamp_format: ("31.01.2020", Some("%d.%m.%Y"), Some("2020-01-31 00:00:00")),
+        parse_timestamp_format_mismatch: ("2020-01-31", Some("%d.%m.%Y"), None),
+        parse_timestamp_invalid: ("2020-13-01", None, None),
+    );
+}
@@ -1,5 +1,16 @@
+use std::collections::HashSet;
+use std::env;
 use std::error::Error;
 use std::io::BufRead;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::fsops::is_case_insensitive;
+use crate::replacement::{
+    case_insensitive_collisions, collisions, format_collisions, format_reserved_names,
+    reserved_names, Replacement,
+};
 
 /// Setup rayon (initialize threadpools according to concurrency).
 pub fn setup_rayon(concurrency: usize) -> Result<(), Box<dyn Error>> {
@@ -9,15 +20,6 @@ pub fn setup_rayon(concurrency: usize) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-/// If items contain a single string "-", read items from stdin, otherwise return as-is.
-pub fn items_from_opt(items: Vec<String>) -> Result<Vec<String>, std::io::Error> {
-    Ok(if items.len() == 1 && items[0] == "-" {
-        read_items_from_stdin()?
-    } else {
-        items
-    })
-}
-
 /// Read items from stdin, one item per line.
 pub fn read_items_from_stdin() -> Result<Vec<String>, std::io::Error> {
     let mut items: Vec<String> = vec![];
@@ -26,3 +28,108 @@ pub fn read_items_from_stdin() -> Result<Vec<String>, std::io::Error> {
     }
     Ok(items)
 }
+
+/// Fail if replaced strings match the reserved patterns of the configuration of the current
+/// directory.
+pub fn check_reserved(replacements: &[Replacement]) -> Result<(), Box<dyn Error>> {
+    let (path, config) = match Config::find(&env::current_dir()?)? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    let reserved_names = reserved_names(replacements, &config.reserved);
+    if reserved_names.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "{} replaced strings are reserved by {}:\n{}",
+        reserved_names.len(),
+        path.display(),
+        format_reserved_names(&reserved_names)
+    )
+    .into())
+}
+
+/// Fail if multiple replacements have the same destination.
+///
+/// Destinations differing only in case collide if `case_insensitive` is set, or if any of the
+/// destination directories is on a case-insensitive filesystem.
+pub fn check_collisions(
+    replacements: &[Replacement],
+    case_insensitive: bool,
+) -> Result<(), Box<dyn Error>> {
+    let case_insensitive = case_insensitive || {
+        let dirs: HashSet<&Path> = replacements
+            .iter()
+            .map(|(_, right)| Path::new(right).parent().unwrap_or_else(|| Path::new("")))
+            .collect();
+        dirs.into_iter().any(is_case_insensitive)
+    };
+    let collisions = if case_insensitive {
+        case_insensitive_collisions(replacements)
+    } else {
+        collisions(replacements)
+    };
+    if collisions.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "destinations are not unique:\n{}",
+        format_collisions(&collisions)
+    )
+    .into())
+}
+
+/// Parse a duration such as "500ms", "2s" or "1m". A bare number is treated as seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", s))?;
+    let secs = |factor: u64| {
+        value
+            .checked_mul(factor)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("duration is too long: {}", s))
+    };
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => secs(1),
+        "m" => secs(60),
+        "h" => secs(60 * 60),
+        _ => Err(format!("invalid duration unit: {}", unit)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! parse_duration_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected) = $value;
+                    assert_eq!(parse_duration(s), expected);
+                }
+            )*
+        }
+    }
+
+    parse_duration_tests!(
+        parse_duration_bare: ("2", Ok(Duration::from_secs(2))),
+        parse_duration_ms: ("500ms", Ok(Duration::from_millis(500))),
+        parse_duration_s: ("2s", Ok(Duration::from_secs(2))),
+        parse_duration_m: ("1m", Ok(Duration::from_secs(60))),
+        parse_duration_h: ("1h", Ok(Duration::from_secs(3600))),
+        parse_duration_invalid_unit: ("1d", Err("invalid duration unit: d".to_owned())),
+        parse_duration_empty: ("", Err("invalid duration: ".to_owned())),
+        parse_duration_overflow: (
+            "99999999999999999h",
+            Err("duration is too long: 99999999999999999h".to_owned())
+        ),
+        parse_duration_max: ("18446744073709551615s", Ok(Duration::from_secs(u64::MAX))),
+    );
+}
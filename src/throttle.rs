@@ -0,0 +1,132 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Size of the chunks copied between pacing.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Bandwidth in bytes per second, e.g. `50M`.
+///
+/// Suffixes `K`, `M` and `G` are binary multiples (1024, 1024^2 and 1024^3). Numbers without a
+/// suffix are bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bandwidth(pub u64);
+
+impl FromStr for Bandwidth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, multiplier) = match s.char_indices().last() {
+            Some((i, 'K')) | Some((i, 'k')) => (&s[..i], 1 << 10),
+            Some((i, 'M')) | Some((i, 'm')) => (&s[..i], 1 << 20),
+            Some((i, 'G')) | Some((i, 'g')) => (&s[..i], 1 << 30),
+            _ => (s, 1),
+        };
+        match u64::from_str(number)
+            .ok()
+            .and_then(|n| n.checked_mul(multiplier))
+        {
+            Some(bytes) if bytes > 0 => Ok(Self(bytes)),
+            _ => Err(format!("invalid bandwidth: {} (expected e.g. 50M)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Bandwidth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Limit on the bandwidth of copies, shared across threads.
+#[derive(Debug)]
+pub struct Throttle {
+    bandwidth: Bandwidth,
+    /// Time of the first copy, and bytes copied since.
+    state: Mutex<Option<(Instant, u64)>>,
+}
+
+impl Throttle {
+    pub fn new(bandwidth: Bandwidth) -> Self {
+        Self {
+            bandwidth,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Account for bytes copied, sleeping until the average bandwidth is within the limit.
+    pub fn pace(&self, bytes: u64) {
+        let (start, total) = {
+            let mut state = self.state.lock().unwrap();
+            let (start, total) = state.get_or_insert_with(|| (Instant::now(), 0));
+            *total += bytes;
+            (*start, *total)
+        };
+        let due = Duration::from_secs_f64(total as f64 / self.bandwidth.0 as f64);
+        if let Some(wait) = due.checked_sub(start.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Copy all of `reader` into `writer` in chunks, pacing after each.
+    ///
+    /// # Returns
+    ///
+    /// An `io::Result` containing the number of bytes copied.
+    pub fn copy<R: Read, W: Write>(&self, reader: &mut R, writer: &mut W) -> io::Result<u64> {
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut copied = 0;
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => return Ok(copied),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            writer.write_all(&buf[..n])?;
+            copied += n as u64;
+            self.pace(n as u64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! parse_bandwidth_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected): (&str, Option<u64>) = $value;
+                    assert_eq!(s.parse::<Bandwidth>().ok(), expected.map(Bandwidth));
+                }
+            )*
+        }
+    }
+
+    parse_bandwidth_tests!(
+        parse_bandwidth_bytes: ("100", Some(100)),
+        parse_bandwidth_kilo: ("4K", Some(4096)),
+        parse_bandwidth_mega: ("50M", Some(50 << 20)),
+        parse_bandwidth_giga: ("1g", Some(1 << 30)),
+        parse_bandwidth_zero: ("0M", None),
+        parse_bandwidth_invalid: ("fast", None),
+        parse_bandwidth_overflow: ("99999999999999999G", None),
+    );
+
+    #[test]
+    fn throttle_copy_paces() {
+        let throttle = Throttle::new(Bandwidth(1 << 20));
+        let data = vec![1u8; 256 << 10];
+        let mut out = vec![];
+        let start = Instant::now();
+        assert_eq!(throttle.copy(&mut &data[..], &mut out).unwrap(), 256 << 10);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(out, data);
+    }
+}
@@ -1,8 +1,15 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Zeros of the scripts whose digits are numbers, each followed by the other nine digits: ASCII,
+/// Arabic-Indic, Extended Arabic-Indic (Persian and Urdu), Devanagari, Bengali and fullwidth.
+const ZEROS: [char; 6] = ['0', '\u{660}', '\u{6f0}', '\u{966}', '\u{9e6}', '\u{ff10}'];
+
 /// Tokenize string.
 ///
 /// Each contiguous section of a type of character is treated as a token:
 ///
-/// * Number - Characters representing numbers.
+/// * Number - Decimal digits, of ASCII or of another script (see `digit_value`).
 /// * Whitespace - Characters representing whitespaces.
 /// * Punctuation - Characters representing ASCII punctuations.
 /// * Text - Everything else.
@@ -15,35 +22,198 @@
 ///
 /// # Returns
 ///
-/// A `Vec` of indices pointing to the start of each token, and a corresponding `Vec` of the types
-/// of each token.
+/// A `Vec` of byte indices pointing to the start of each token, and a corresponding `Vec` of the
+/// types of each token.
 pub fn tokenize(s: &str) -> (Vec<usize>, Vec<TokenType>) {
-    let mut current_token_type = TokenType::Init;
+    tokenize_with_mode(s, TokenMode::Default)
+}
+
+/// Tokenize string with the given mode.
+///
+/// In `TokenMode::Code`, text is additionally split at camel case word boundaries, e.g.
+/// "parseHTTPRequest" is tokenized as "[parse][HTTP][Request]". Snake case names are already split
+/// at underscores, which are punctuations.
+///
+/// # Arguments
+///
+/// * s - String slice to tokenize.
+/// * mode - Tokenization mode.
+///
+/// # Returns
+///
+/// A `Vec` of byte indices pointing to the start of each token, and a corresponding `Vec` of the
+/// types of each token.
+pub fn tokenize_with_mode(s: &str, mode: TokenMode) -> (Vec<usize>, Vec<TokenType>) {
     let mut indices = vec![];
     let mut token_types = vec![];
-    for (i, c) in s.chars().enumerate() {
-        let tt = token_type(c);
+    tokenize_into(s, mode, &mut indices, &mut token_types);
+    (indices, token_types)
+}
+
+/// Tokenize string into existing buffers.
+///
+/// Same as `tokenize_with_mode`, but clears and reuses `indices` and `token_types` instead of
+/// allocating new `Vec`s, which avoids allocations when tokenizing many strings in a loop.
+///
+/// # Arguments
+///
+/// * s - String slice to tokenize.
+/// * mode - Tokenization mode.
+/// * indices - Buffer receiving the byte indices pointing to the start of each token.
+/// * token_types - Buffer receiving the types of each token.
+pub fn tokenize_into(
+    s: &str,
+    mode: TokenMode,
+    indices: &mut Vec<usize>,
+    token_types: &mut Vec<TokenType>,
+) {
+    indices.clear();
+    token_types.clear();
+    match mode {
+        TokenMode::Default if s.is_ascii() => tokenize_bytes(s, indices, token_types),
+        TokenMode::Default => tokenize_chars(s, false, indices, token_types),
+        TokenMode::Code => tokenize_chars(s, true, indices, token_types),
+    }
+}
+
+/// Tokenize an ASCII string one byte at a time.
+fn tokenize_bytes(s: &str, indices: &mut Vec<usize>, token_types: &mut Vec<TokenType>) {
+    let mut current_token_type = TokenType::Init;
+    for (i, &b) in s.as_bytes().iter().enumerate() {
+        let tt = BYTE_TOKEN_TYPES[b as usize];
         if current_token_type != tt {
             indices.push(i);
             token_types.push(tt);
             current_token_type = tt;
         }
     }
-    (indices, token_types)
+}
+
+/// Tokenize string one character at a time, optionally splitting text at camel case word
+/// boundaries.
+fn tokenize_chars(
+    s: &str,
+    split_camel_case: bool,
+    indices: &mut Vec<usize>,
+    token_types: &mut Vec<TokenType>,
+) {
+    let mut current_token_type = TokenType::Init;
+    let mut prev = None;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let tt = token_type(c);
+        let is_word_boundary = split_camel_case
+            && tt == TokenType::Text
+            && current_token_type == TokenType::Text
+            && prev.is_some_and(|p| is_camel_case_boundary(p, c, chars.peek().map(|&(_, c)| c)));
+        if current_token_type != tt || is_word_boundary {
+            indices.push(i);
+            token_types.push(tt);
+            current_token_type = tt;
+        }
+        prev = Some(c);
+    }
+}
+
+/// Token types of each byte, indexed by byte value.
+static BYTE_TOKEN_TYPES: [TokenType; 256] = byte_token_types();
+
+/// Build the table of token types of each byte.
+const fn byte_token_types() -> [TokenType; 256] {
+    let mut table = [TokenType::Text; 256];
+    let mut b = 0;
+    while b < 128 {
+        table[b] = ascii_token_type(b as u8);
+        b += 1;
+    }
+    table
+}
+
+/// Get token type of ASCII byte.
+const fn ascii_token_type(b: u8) -> TokenType {
+    if b.is_ascii_digit() {
+        TokenType::Number
+    } else if b.is_ascii_whitespace() {
+        TokenType::Whitespace
+    } else if b.is_ascii_punctuation() {
+        TokenType::Punctuation
+    } else {
+        TokenType::Text
+    }
+}
+
+/// Check if a camel case word starts at `c`, given the previous and next characters.
+///
+/// A word starts at an uppercase character following a lowercase character ("camel|Case"), or at
+/// the last uppercase character of an acronym followed by a lowercase character ("HTTP|Request").
+fn is_camel_case_boundary(prev: char, c: char, next: Option<char>) -> bool {
+    c.is_uppercase()
+        && (prev.is_lowercase() || (prev.is_uppercase() && next.is_some_and(|n| n.is_lowercase())))
 }
 
 /// Get token type of character.
 fn token_type(c: char) -> TokenType {
-    if c.is_ascii_digit() {
-        return TokenType::Number;
+    if c.is_ascii() {
+        BYTE_TOKEN_TYPES[c as usize]
+    } else if digit_value(c).is_some() {
+        TokenType::Number
+    } else {
+        TokenType::Text
     }
-    if c.is_ascii_whitespace() {
-        return TokenType::Whitespace;
+}
+
+/// Get the value of a decimal digit of ASCII or of another script, e.g. 3 for the Arabic-Indic
+/// "٣" or the Devanagari "३".
+pub fn digit_value(c: char) -> Option<u32> {
+    ZEROS.iter().find_map(|&zero| {
+        let value = (c as u32).checked_sub(zero as u32)?;
+        if value < 10 {
+            Some(value)
+        } else {
+            None
+        }
+    })
+}
+
+/// Transliterate the decimal digits of other scripts to ASCII digits.
+///
+/// # Returns
+///
+/// The transliterated string, borrowing from `s` if it has no such digits.
+pub fn to_ascii_digits(s: &str) -> Cow<'_, str> {
+    if s.is_ascii() || !s.chars().any(|c| !c.is_ascii() && digit_value(c).is_some()) {
+        return Cow::Borrowed(s);
     }
-    if c.is_ascii_punctuation() {
-        return TokenType::Punctuation;
+    Cow::Owned(
+        s.chars()
+            .map(|c| match digit_value(c) {
+                Some(value) if !c.is_ascii() => char::from_digit(value, 10).unwrap(),
+                _ => c,
+            })
+            .collect(),
+    )
+}
+
+/// Tokenization mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenMode {
+    /// Split at changes of token type.
+    #[default]
+    Default,
+    /// Additionally split text at camel case word boundaries.
+    Code,
+}
+
+impl FromStr for TokenMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(TokenMode::Default),
+            "code" => Ok(TokenMode::Code),
+            _ => Err(format!("invalid token mode: {}", s)),
+        }
     }
-    TokenType::Text
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -107,6 +277,20 @@ mod tests {
             TokenType::Whitespace,
             TokenType::Text,
         ])),
+        tokenize_unicode: ("é1", (vec![0, 2], vec![
+            TokenType::Text,
+            TokenType::Number,
+        ])),
+        tokenize_arabic_indic: ("ص١٢.", (vec![0, 2, 6], vec![
+            TokenType::Text,
+            TokenType::Number,
+            TokenType::Punctuation,
+        ])),
+        tokenize_devanagari: ("अ ३", (vec![0, 3, 4], vec![
+            TokenType::Text,
+            TokenType::Whitespace,
+            TokenType::Number,
+        ])),
         tokenize_punctuation: ("12b.c", (vec![0, 2, 3, 4], vec![
             TokenType::Number,
             TokenType::Text,
@@ -114,4 +298,72 @@ mod tests {
             TokenType::Text,
         ])),
     );
+
+    macro_rules! tokenize_code_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (s, expected) = $value;
+                    assert_eq!(tokenize_with_mode(s, TokenMode::Code).0, expected);
+                }
+            )*
+        }
+    }
+
+    tokenize_code_tests!(
+        tokenize_code_camel: ("camelCaseName", vec![0, 5, 9]),
+        tokenize_code_pascal: ("PascalCase", vec![0, 6]),
+        tokenize_code_acronym: ("parseHTTPRequest", vec![0, 5, 9]),
+        tokenize_code_acronym_end: ("useHTTP", vec![0, 3]),
+        tokenize_code_snake: ("snake_case_name", vec![0, 5, 6, 10, 11]),
+        tokenize_code_lowercase: ("lowercase", vec![0]),
+        tokenize_code_unicode: ("éAb1", vec![0, 2, 4]),
+    );
+
+    #[test]
+    fn tokenize_default_matches_chars() {
+        for s in &[
+            "",
+            "a1 b.c",
+            "é1 ü-ß\t42",
+            "日本語12 テスト.txt",
+            "\u{a0}x\u{2003}9",
+            "٣٤ ३x12",
+        ] {
+            let (mut indices, mut types) = (vec![], vec![]);
+            tokenize_into(s, TokenMode::Default, &mut indices, &mut types);
+            let mut expected = vec![];
+            let mut expected_types = vec![];
+            for (i, c) in s.char_indices() {
+                let tt = token_type(c);
+                if expected_types.last() != Some(&tt) {
+                    expected.push(i);
+                    expected_types.push(tt);
+                }
+            }
+            assert_eq!((indices, types), (expected, expected_types), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn digit_values() {
+        assert_eq!(digit_value('7'), Some(7));
+        assert_eq!(digit_value('\u{663}'), Some(3));
+        assert_eq!(digit_value('\u{6f9}'), Some(9));
+        assert_eq!(digit_value('\u{969}'), Some(3));
+        assert_eq!(digit_value('a'), None);
+        assert_eq!(digit_value('\u{66a}'), None);
+        assert_eq!(to_ascii_digits("ص١٢-३"), "ص12-3");
+        assert!(matches!(to_ascii_digits("é12"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn tokenize_into_reuses_buffers() {
+        let (mut indices, mut token_types) = (vec![], vec![]);
+        tokenize_into("a1 b", TokenMode::Default, &mut indices, &mut token_types);
+        tokenize_into("12", TokenMode::Default, &mut indices, &mut token_types);
+        assert_eq!(indices, vec![0]);
+        assert_eq!(token_types, vec![TokenType::Number]);
+    }
 }
@@ -0,0 +1,210 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+
+/// Attribute of hidden files on Windows.
+#[cfg(windows)]
+const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+/// Attribute of files used by the operating system on Windows.
+#[cfg(windows)]
+const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+
+/// Options controlling directory traversal.
+#[derive(Clone, Default)]
+pub struct WalkOpts {
+    /// Include hidden files and directories.
+    pub hidden: bool,
+    /// Include system files and directories. Only Windows has system files.
+    pub system: bool,
+    /// Respect ignore files (`.gitignore`, `.ignore` and git excludes).
+    pub ignore: bool,
+    /// Maximum depth of directories to descend into, the root being depth 0.
+    pub max_depth: Option<usize>,
+}
+
+impl WalkOpts {
+    pub fn new() -> Self {
+        Self {
+            hidden: false,
+            system: false,
+            ignore: true,
+            max_depth: None,
+        }
+    }
+}
+
+/// List the files under a directory recursively, sorted by path.
+///
+/// Directories themselves are not listed. If `root` is a file, it is the only item. `.git`
+/// directories are always skipped. Hidden and system files are skipped unless included, as
+/// determined by `is_hidden` and `is_system`.
+///
+/// # Arguments
+///
+/// * `root` - Directory to walk.
+/// * `opts` - Options.
+///
+/// # Returns
+///
+/// A `Result` containing the paths of the files, each starting with `root`.
+pub fn walk(root: &Path, opts: &WalkOpts) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = vec![];
+    let (hidden, system) = (opts.hidden, opts.system);
+    let walker = WalkBuilder::new(root)
+        .standard_filters(opts.ignore)
+        .hidden(false)
+        .max_depth(opts.max_depth)
+        .filter_entry(move |entry| {
+            if entry.file_name() == ".git" {
+                return false;
+            }
+            entry.depth() == 0
+                || ((hidden || !is_hidden(entry.path())) && (system || !is_system(entry.path())))
+        })
+        .build();
+    for entry in walker {
+        let entry = entry?;
+        if entry.file_type().is_some_and(|t| !t.is_dir()) {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Check if a file is hidden, i.e. has the hidden attribute on Windows, or a name starting with a
+/// dot elsewhere.
+#[cfg(windows)]
+pub fn is_hidden(path: &Path) -> bool {
+    has_attribute(path, FILE_ATTRIBUTE_HIDDEN)
+}
+
+/// Check if a file is hidden, i.e. has the hidden attribute on Windows, or a name starting with a
+/// dot elsewhere.
+#[cfg(not(windows))]
+pub fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Check if a file has the system attribute. Always `false` outside of Windows.
+#[cfg(windows)]
+pub fn is_system(path: &Path) -> bool {
+    has_attribute(path, FILE_ATTRIBUTE_SYSTEM)
+}
+
+/// Check if a file has the system attribute. Always `false` outside of Windows.
+#[cfg(not(windows))]
+pub fn is_system(_path: &Path) -> bool {
+    false
+}
+
+/// Check if a file has an attribute, without following symbolic links.
+#[cfg(windows)]
+fn has_attribute(path: &Path, attribute: u32) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    path.symlink_metadata()
+        .is_ok_and(|metadata| metadata.file_attributes() & attribute != 0)
+}
+
+/// List the files under a directory recursively, relative to it.
+///
+/// # Returns
+///
+/// A `Result` containing the relative paths of the files, sorted. Fails if a path is not valid
+/// UTF-8.
+pub fn walk_relative(root: &Path, opts: &WalkOpts) -> Result<Vec<String>, Box<dyn Error>> {
+    walk(root, opts)?
+        .iter()
+        .map(|path| {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            relative
+                .to_str()
+                .map(str::to_owned)
+                .ok_or_else(|| format!("path is not valid UTF-8: {}", path.display()).into())
+        })
+        .collect()
+}
+
+/// Expand directories into the files under them, recursively, keeping other items as-is.
+pub fn expand_dirs(items: Vec<String>, opts: &WalkOpts) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut expanded = vec![];
+    for item in items {
+        let path = Path::new(&item);
+        if !path.is_dir() {
+            expanded.push(item);
+            continue;
+        }
+        for file in walk(path, opts)? {
+            match file.to_str() {
+                Some(file) => expanded.push(file.to_owned()),
+                None => return Err(format!("path is not valid UTF-8: {}", file.display()).into()),
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn walk_relative_nested() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("b/c.txt").touch().unwrap();
+        temp.child("a.txt").touch().unwrap();
+        temp.child("d").create_dir_all().unwrap();
+        assert_eq!(
+            walk_relative(temp.path(), &WalkOpts::new()).unwrap(),
+            vec![
+                "a.txt".to_owned(),
+                format!("b{}c.txt", std::path::MAIN_SEPARATOR)
+            ]
+        );
+    }
+
+    macro_rules! walk_opts_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (opts, expected): (WalkOpts, &[&str]) = $value;
+                    let temp = assert_fs::TempDir::new().unwrap();
+                    temp.child("a").touch().unwrap();
+                    temp.child(".b").touch().unwrap();
+                    temp.child("c").touch().unwrap();
+                    temp.child("d/e/f").touch().unwrap();
+                    temp.child(".git/config").touch().unwrap();
+                    temp.child(".ignore").write_str("c\n").unwrap();
+                    let files: Vec<String> = walk_relative(temp.path(), &opts)
+                        .unwrap()
+                        .into_iter()
+                        .map(|f| f.replace(std::path::MAIN_SEPARATOR, "/"))
+                        .collect();
+                    assert_eq!(files, expected);
+                }
+            )*
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn hidden_by_name() {
+        assert!(is_hidden(Path::new("a/.b")));
+        assert!(!is_hidden(Path::new(".a/b")));
+        assert!(!is_system(Path::new(".a")));
+    }
+
+    walk_opts_tests!(
+        walk_default: (WalkOpts::new(), &["a", "d/e/f"]),
+        walk_hidden: (
+            WalkOpts { hidden: true, ..WalkOpts::new() },
+            &[".b", ".ignore", "a", "d/e/f"]
+        ),
+        walk_no_ignore: (WalkOpts { ignore: false, ..WalkOpts::new() }, &["a", "c", "d/e/f"]),
+        walk_max_depth: (WalkOpts { max_depth: Some(1), ..WalkOpts::new() }, &["a"]),
+    );
+}
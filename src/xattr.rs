@@ -0,0 +1,279 @@
+use std::io;
+use std::path::Path;
+
+/// Extended attribute holding the Finder tags of a file on macOS, as a binary property list.
+pub const FINDER_TAGS_ATTR: &str = "com.apple.metadata:_kMDItemUserTags";
+
+/// Whether extended attributes are supported on this platform.
+const SUPPORTED: bool = cfg!(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos"
+));
+
+/// Set an extended attribute of a file, replacing its value if it exists. Only supported on
+/// Linux, Android and macOS.
+pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    check_supported()?;
+    let (path, name) = (c_path(path)?, c_name(name)?);
+    // SAFETY: `path` and `name` are valid NUL-terminated strings, and `value` is valid for its
+    // length.
+    let ret = unsafe { sys::set(&path, &name, value) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Get an extended attribute of a file.
+///
+/// # Returns
+///
+/// An `io::Result` containing the value, or `None` if the file has no such attribute.
+pub fn get(path: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+    check_supported()?;
+    let (path, name) = (c_path(path)?, c_name(name)?);
+    loop {
+        // SAFETY: as in `set`, with an empty buffer to get the size of the value.
+        let size = unsafe { sys::get(&path, &name, &mut []) };
+        if size < 0 {
+            return match io::Error::last_os_error() {
+                e if e.raw_os_error() == Some(sys::ENOATTR) => Ok(None),
+                e => Err(e),
+            };
+        }
+        let mut value = vec![0; size as usize];
+        // SAFETY: as in `set`, with `value` writable for its length.
+        let read = unsafe { sys::get(&path, &name, &mut value) };
+        match read {
+            // The value changed size in between, try again.
+            _ if read < 0 && io::Error::last_os_error().raw_os_error() == Some(sys::ERANGE) => {}
+            _ if read < 0 => return Err(io::Error::last_os_error()),
+            _ => {
+                value.truncate(read as usize);
+                return Ok(Some(value));
+            }
+        }
+    }
+}
+
+/// Remove an extended attribute of a file. Files without the attribute are left as-is.
+pub fn remove(path: &Path, name: &str) -> io::Result<()> {
+    check_supported()?;
+    let (path, name) = (c_path(path)?, c_name(name)?);
+    // SAFETY: `path` and `name` are valid NUL-terminated strings.
+    if unsafe { sys::remove(&path, &name) } < 0 {
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(sys::ENOATTR) {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Encode Finder tags as the binary property list stored in `FINDER_TAGS_ATTR`, i.e. an array
+/// of strings.
+pub fn finder_tags_plist(tags: &[&str]) -> Vec<u8> {
+    let mut plist = b"bplist00".to_vec();
+    let mut offsets = vec![plist.len()];
+    // The array is object 0, referring to the tags as objects 1 to n.
+    plist.extend(length_marker(0xA0, tags.len()));
+    plist.extend((1..=tags.len()).map(|i| i as u8));
+    for tag in tags {
+        offsets.push(plist.len());
+        if tag.is_ascii() {
+            plist.extend(length_marker(0x50, tag.len()));
+            plist.extend(tag.as_bytes());
+        } else {
+            let units: Vec<u16> = tag.encode_utf16().collect();
+            plist.extend(length_marker(0x60, units.len()));
+            plist.extend(units.iter().flat_map(|u| u.to_be_bytes()));
+        }
+    }
+    let offset_table = plist.len() as u64;
+    for offset in &offsets {
+        plist.extend((*offset as u64).to_be_bytes());
+    }
+    // Trailer: sizes of offsets (8 bytes) and object references (1 byte), number of objects,
+    // index of the top object and position of the offset table.
+    plist.extend([0; 6]);
+    plist.extend([8, 1]);
+    plist.extend((offsets.len() as u64).to_be_bytes());
+    plist.extend(0u64.to_be_bytes());
+    plist.extend(offset_table.to_be_bytes());
+    plist
+}
+
+/// Encode the marker of an object with a length, e.g. an array or a string, followed by the
+/// length as an integer object if it does not fit in the marker.
+fn length_marker(marker: u8, len: usize) -> Vec<u8> {
+    if len < 0xF {
+        return vec![marker | len as u8];
+    }
+    let mut bytes = vec![marker | 0xF];
+    if len <= u8::MAX as usize {
+        bytes.extend([0x10, len as u8]);
+    } else if len <= u16::MAX as usize {
+        bytes.push(0x11);
+        bytes.extend((len as u16).to_be_bytes());
+    } else {
+        bytes.push(0x12);
+        bytes.extend((len as u32).to_be_bytes());
+    }
+    bytes
+}
+
+fn check_supported() -> io::Result<()> {
+    if !SUPPORTED {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "extended attributes are not supported on this platform",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn c_path(path: &Path) -> io::Result<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+#[cfg(not(unix))]
+fn c_path(path: &Path) -> io::Result<std::ffi::CString> {
+    c_name(&path.to_string_lossy())
+}
+
+fn c_name(name: &str) -> io::Result<std::ffi::CString> {
+    std::ffi::CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::ffi::CStr;
+
+    pub const ENOATTR: i32 = libc::ENODATA;
+    pub const ERANGE: i32 = libc::ERANGE;
+
+    pub unsafe fn set(path: &CStr, name: &CStr, value: &[u8]) -> isize {
+        libc::setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        ) as isize
+    }
+
+    pub unsafe fn get(path: &CStr, name: &CStr, value: &mut [u8]) -> isize {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_mut_ptr().cast(),
+            value.len(),
+        )
+    }
+
+    pub unsafe fn remove(path: &CStr, name: &CStr) -> isize {
+        libc::removexattr(path.as_ptr(), name.as_ptr()) as isize
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod sys {
+    use std::ffi::CStr;
+
+    pub const ENOATTR: i32 = libc::ENOATTR;
+    pub const ERANGE: i32 = libc::ERANGE;
+
+    pub unsafe fn set(path: &CStr, name: &CStr, value: &[u8]) -> isize {
+        libc::setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+            0,
+        ) as isize
+    }
+
+    pub unsafe fn get(path: &CStr, name: &CStr, value: &mut [u8]) -> isize {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_mut_ptr().cast(),
+            value.len(),
+            0,
+            0,
+        )
+    }
+
+    pub unsafe fn remove(path: &CStr, name: &CStr) -> isize {
+        libc::removexattr(path.as_ptr(), name.as_ptr(), 0) as isize
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+mod sys {
+    //! Never called, as `SUPPORTED` is false.
+    use std::ffi::CStr;
+
+    pub const ENOATTR: i32 = -1;
+    pub const ERANGE: i32 = -1;
+
+    pub unsafe fn set(_path: &CStr, _name: &CStr, _value: &[u8]) -> isize {
+        -1
+    }
+
+    pub unsafe fn get(_path: &CStr, _name: &CStr, _value: &mut [u8]) -> isize {
+        -1
+    }
+
+    pub unsafe fn remove(_path: &CStr, _name: &CStr) -> isize {
+        -1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finder_tags_plist_ascii() {
+        let mut expected = b"bplist00".to_vec();
+        expected.extend([0xA1, 1, 0x53]);
+        expected.extend(b"Red");
+        for offset in [8u64, 10] {
+            expected.extend(offset.to_be_bytes());
+        }
+        expected.extend([0, 0, 0, 0, 0, 0, 8, 1]);
+        expected.extend(2u64.to_be_bytes());
+        expected.extend(0u64.to_be_bytes());
+        expected.extend(14u64.to_be_bytes());
+        assert_eq!(finder_tags_plist(&["Red"]), expected);
+    }
+
+    #[test]
+    fn finder_tags_plist_long_and_unicode() {
+        let plist = finder_tags_plist(&["a long finder tag", "é"]);
+        assert_eq!(&plist[8..14], &[0xA2, 1, 2, 0x5F, 0x10, 17]);
+        assert_eq!(&plist[31..34], &[0x61, 0x00, 0xE9]);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+    #[test]
+    fn set_get_remove() {
+        let temp = assert_fs::NamedTempFile::new("a").unwrap();
+        std::fs::write(temp.path(), "").unwrap();
+        let name = "user.mrf.test";
+        // Some filesystems, e.g. tmpfs on older kernels, do not support user attributes.
+        if set(temp.path(), name, b"x").is_err() {
+            return;
+        }
+        assert_eq!(get(temp.path(), name).unwrap(), Some(b"x".to_vec()));
+        remove(temp.path(), name).unwrap();
+        remove(temp.path(), name).unwrap();
+        assert_eq!(get(temp.path(), name).unwrap(), None);
+    }
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn check_simple() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("check").arg("{}{=_}{n:03}");
+    cmd.assert().success().stdout(predicate::eq(
+        "Elem  Kind  Group  Matcher  Replace  Format
+1     Spec  1      Any
+2     Spec  2      Any      \"_\"
+3     Spec  3      Number            03
+",
+    ));
+    Ok(())
+}
+
+#[test]
+fn check_strict() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("check").arg("{2}").arg("--strict");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Warning: group 1 is matched"));
+    Ok(())
+}
+
+#[test]
+fn check_items() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("check").arg("{}{=_}{n}").arg("a-1").arg("b");
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        "a-1 -> a_1
+Error: b: unable to match specifiers with input
+",
+    ));
+    Ok(())
+}
+
+#[test]
+fn check_items_strict() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("check").arg("{}{=_}{n}").arg("b").arg("--strict");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("replacer failed on 1 items"));
+    Ok(())
+}
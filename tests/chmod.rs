@@ -0,0 +1,68 @@
+use std::error::Error;
+
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[cfg(unix)]
+#[test]
+fn chmod_simple() -> Result<(), Box<dyn Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("build.sh").touch()?;
+    temp.child("key-600").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("chmod")
+        .arg("-y")
+        .arg("key-600")
+        .arg("{=}{n}");
+    cmd.assert().success();
+
+    let mode = |name: &str| {
+        temp.child(name)
+            .path()
+            .metadata()
+            .unwrap()
+            .permissions()
+            .mode()
+    };
+    assert_eq!(mode("key-600") & 0o777, 0o600);
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("chmod")
+        .arg("-y")
+        .arg("build.sh")
+        .arg("{=755}");
+    cmd.assert().success();
+
+    assert_eq!(mode("build.sh") & 0o777, 0o755);
+
+    Ok(())
+}
+
+#[test]
+fn chmod_invalid_mode() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("chmod")
+        .arg("-y")
+        .arg("a-1")
+        .arg("{=rwx}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("a-1 -> invalid mode: rwx"));
+
+    Ok(())
+}
@@ -0,0 +1,9 @@
+use assert_cmd::Command;
+
+/// Get a command running mrf, with the history disabled so that tests do not record replacers in
+/// the user's history.
+pub fn mrf() -> Result<Command, assert_cmd::cargo::CargoError> {
+    let mut cmd = Command::cargo_bin("mrf")?;
+    cmd.env("MRF_HISTORY", "");
+    Ok(cmd)
+}
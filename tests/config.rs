@@ -0,0 +1,124 @@
+use std::error::Error;
+
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn config_named_replacer() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child(".mrf.toml")
+        .write_str("[replacers]\nunderscore = \"{}{=_}{}\"\n")?;
+    temp.child("dir").create_dir_all()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.child("dir").path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("--named")
+        .arg("underscore")
+        .arg("test-001");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("test-001\0test_001\0"));
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("--named")
+        .arg("other")
+        .arg("test-001");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no replacer \"other\""));
+
+    Ok(())
+}
+
+#[test]
+fn config_defaults() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child(".mrf.toml")
+        .write_str("[defaults]\nmap = [\"--syntax=angle\"]\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("test-001")
+        .arg("<><=_><>");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("test-001\0test_001\0"));
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("--syntax")
+        .arg("braces")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("test-001\0test_001\0"));
+
+    Ok(())
+}
+
+#[test]
+fn config_defaults_not_allowed() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child(".mrf.toml")
+        .write_str("[defaults]\nmv = [\"--assume-yes\"]\n")?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("mv")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().stderr(predicate::str::contains(
+        "--assume-yes of mv is not allowed",
+    ));
+
+    temp.child("test-001").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn config_invalid_help() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child(".mrf.toml").write_str("[unknown]\n")?;
+    temp.child("a").create_dir_all()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.child("a").path()).arg("--help");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("invalid"));
+
+    Ok(())
+}
+
+#[test]
+fn config_stop_words() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child(".mrf.toml")
+        .write_str("stop_words = [\"OF\"]\n")?;
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("the wall of sound")
+        .arg("{:titlecase}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("the wall of sound\0The Wall of Sound\0"));
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use std::error::Error;
+
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn cp_simple() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").write_str("a")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("cp")
+        .arg("-y")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("test-001").assert("a");
+    temp.child("test_001").assert("a");
+
+    Ok(())
+}
+
+#[test]
+fn cp_reflink_never() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").write_str("a")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("cp")
+        .arg("-y")
+        .arg("--reflink=never")
+        .arg("--verify")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("test_001").assert("a");
+
+    Ok(())
+}
+
+#[test]
+fn cp_reflink_invalid() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("cp").arg("--reflink=sometimes").arg("a").arg("{}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("sometimes"));
+
+    Ok(())
+}
+
+#[test]
+fn cp_bwlimit() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").write_str("a")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("cp")
+        .arg("-y")
+        .arg("--reflink=never")
+        .arg("--bwlimit=1M")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("test_001").assert("a");
+
+    Ok(())
+}
+
+#[test]
+fn cp_existing() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").write_str("a")?;
+    temp.child("test_001").write_str("b")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("cp")
+        .arg("-y")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("already exist"));
+
+    temp.child("test_001").assert("b");
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::error::Error;
+
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn diff_infer() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("before.txt")
+        .write_str("IMG_001.jpg\nIMG_002.jpg\n")?;
+    temp.child("after.txt")
+        .write_str("photo_001.jpg\nphoto_002.jpg\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("diff")
+        .arg("before.txt")
+        .arg("after.txt");
+    cmd.assert().success().stdout(predicate::eq(
+        "Changed 2 out of 2 items:
+    IMG_001.jpg -> [-IMG-]{+photo+}_001.jpg
+    IMG_002.jpg -> [-IMG-]{+photo+}_002.jpg
+Replacer: {=photo}{}
+",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn diff_no_replacer() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("before.txt").write_str("a-1\nb-2\n")?;
+    temp.child("after.txt").write_str("a_1\nb.2\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("diff")
+        .arg("before.txt")
+        .arg("after.txt");
+    cmd.assert().success().stdout(predicate::str::ends_with(
+        "No single replacer reproduces all changes\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn diff_length_mismatch() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("before.txt").write_str("a\nb\n")?;
+    temp.child("after.txt").write_str("a\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("diff")
+        .arg("before.txt")
+        .arg("after.txt");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "lists have different lengths (2 and 1)",
+    ));
+
+    Ok(())
+}
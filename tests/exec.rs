@@ -0,0 +1,348 @@
+use std::error::Error;
+
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+#[cfg(unix)]
+fn exec_retries() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("-l")
+        .arg(r#"sh -c "echo $0 >> log; exit 1""#)
+        .arg("test-001")
+        .arg("{}")
+        .arg("--retries")
+        .arg("2")
+        .arg("--retry-delay")
+        .arg("0s");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed 1 out of 1 items:"));
+
+    temp.child("log")
+        .assert(predicate::eq("test-001\ntest-001\ntest-001\n"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_stdin_both() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg(r#"sh -c "cat > out""#)
+        .arg("test-001")
+        .arg("{}{=_}{}")
+        .arg("--stdin")
+        .arg("both");
+    cmd.assert().success();
+
+    temp.child("out")
+        .assert(predicate::eq("test-001\0test_001\0"));
+
+    Ok(())
+}
+
+#[test]
+fn exec_empty_command() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("exec").arg("-y").arg(" ").arg("a").arg("{}");
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("command is empty"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_stdin_unread() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    // Larger than a pipe buffer, so that writing it fails once `true` exits.
+    let item = "x".repeat(100_000);
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("true")
+        .arg(&item)
+        .arg("{}")
+        .arg("--stdin")
+        .arg("left");
+    cmd.assert().success();
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_cwd_from_item() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("dir/test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("cp")
+        .arg("dir/test-001")
+        .arg("{}{n=002}")
+        .arg("--cwd-from-item");
+    cmd.assert().success();
+
+    temp.child("dir/test-002").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_cwd_from_item_other_dir() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("dir/test-001").touch()?;
+    temp.child("other").create_dir_all()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("cp")
+        .arg("dir/test-001")
+        .arg("{=other}{}")
+        .arg("--cwd-from-item");
+    cmd.assert().success();
+
+    temp.child("other/test-001")
+        .assert(predicate::path::exists());
+    temp.child("dir/other").assert(predicate::path::missing());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_capture_logs() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("--capture-logs=logs")
+        .arg(r#"sh -c "echo out $0; echo err $1 >&2""#)
+        .arg("dir/test-001")
+        .arg("{}{}{}{=_}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("out").not());
+
+    temp.child("logs/dir%2Ftest-001.stdout.log")
+        .assert("out dir/test-001\n");
+    temp.child("logs/dir%2Ftest-001.stderr.log")
+        .assert("err dir/test_001\n");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_capture_logs_distinct() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("--capture-logs=logs")
+        .arg("echo")
+        .arg("a/b")
+        .arg("a_b")
+        .arg("{}");
+    cmd.assert().success();
+
+    temp.child("logs/a%2Fb.stdout.log").assert("a/b a/b\n");
+    temp.child("logs/a_b.stdout.log").assert("a_b a_b\n");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_output_not_interleaved() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("-l")
+        .arg("-c")
+        .arg("2")
+        .arg(r#"sh -c "echo $0 1; sleep 0.2; echo $0 2""#)
+        .arg("a")
+        .arg("b")
+        .arg("{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a 1\na 2\nb 1\nb 2\n"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_output_ordered() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("-l")
+        .arg("-c")
+        .arg("3")
+        .arg("--progress=json")
+        .arg("--progress-file=progress.jsonl")
+        .arg(r#"sh -c "sleep $0; echo $0""#)
+        .arg("0.4")
+        .arg("0.2")
+        .arg("0")
+        .arg("{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("0.4\n0.2\n0\n"));
+    let progress = std::fs::read_to_string(temp.child("progress.jsonl").path())?;
+    let first = progress
+        .lines()
+        .find(|line| line.contains(r#""event":"finish""#));
+    assert!(first.unwrap_or_default().contains(r#""from":"0.4""#));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_output_unordered() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("exec")
+        .arg("-y")
+        .arg("-l")
+        .arg("-c")
+        .arg("2")
+        .arg("--unordered")
+        .arg(r#"sh -c "sleep $0; echo $0""#)
+        .arg("1")
+        .arg("0")
+        .arg("{}");
+    cmd.assert().success().stdout(predicate::eq("0\n1\n"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_chunk_size() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("exec")
+        .arg("-y")
+        .arg("-r")
+        .arg("--chunk-size")
+        .arg("2")
+        .arg(r#"sh -c "echo $0""#)
+        .arg("a-1")
+        .arg("b")
+        .arg("c-2")
+        .arg("d-3")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a_1\nc_2\nd_3\n"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_failure() -> Result<(), Box<dyn Error>> {
+    for chunk_size in &[None, Some("1")] {
+        let mut cmd = mrf()?;
+        cmd.env("MRF_HISTORY", "")
+            .arg("exec")
+            .arg("-y")
+            .arg("false")
+            .arg("a-1")
+            .arg("{}{=_}{}");
+        if let Some(chunk_size) = chunk_size {
+            cmd.arg("--chunk-size").arg(chunk_size);
+        }
+        let output = cmd.assert().failure().get_output().stderr.clone();
+        let stderr = String::from_utf8(output)?;
+        assert!(stderr.contains("1 items failed"));
+        // The error is only reported in the summary.
+        assert_eq!(stderr.matches("false: exit status: 1").count(), 1);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn exec_checkpoint() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("checkpoint.jsonl")
+        .write_str("{\"left\":\"a-1\",\"right\":\"a_1\"}\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("exec")
+        .arg("-y")
+        .arg("-r")
+        .arg("--checkpoint")
+        .arg("checkpoint.jsonl")
+        .arg("echo")
+        .arg("a-1")
+        .arg("b-2")
+        .arg("{}{=_}{}");
+    cmd.assert().success().stdout(predicate::eq("b_2\n"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(windows)]
+fn exec_builtin_percent() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.env("MRF_HISTORY", "")
+        .arg("exec")
+        .arg("-y")
+        .arg("-l")
+        .arg("echo")
+        .arg("100%USERNAME%!PATH!.txt")
+        .arg("{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("\"100%USERNAME%!PATH!.txt\"\r\n"));
+
+    Ok(())
+}
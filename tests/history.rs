@@ -0,0 +1,63 @@
+use std::error::Error;
+
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn history_last() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("dir").create_dir_all()?;
+    let history = temp.child("history.jsonl");
+
+    temp.child("dir/test-001").touch()?;
+    temp.child("dir/test-002").touch()?;
+
+    // Only successful moves are recorded.
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.child("dir").path())
+        .env("MRF_HISTORY", history.path())
+        .arg("map")
+        .arg("test-001")
+        .arg("{}{=.}{}");
+    cmd.assert().success();
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.child("dir").path())
+        .env("MRF_HISTORY", history.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.child("dir").path())
+        .env("MRF_HISTORY", history.path())
+        .arg("history");
+    cmd.assert().success().stdout(predicate::eq("{}{=_}{}\n"));
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.child("dir").path())
+        .env("MRF_HISTORY", history.path())
+        .arg("map")
+        .arg("--last")
+        .arg("test-002");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("test-002\0test_002\0"));
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", history.path())
+        .arg("map")
+        .arg("--last")
+        .arg("test-002");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no replacer was used"));
+
+    Ok(())
+}
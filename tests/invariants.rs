@@ -0,0 +1,70 @@
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use mrf::selftest::{
+    check_idempotent, check_identity, check_spans, check_tokens, FORMATS, LITERALS, MATCHERS,
+    TOKENS,
+};
+
+/// Items of 1 to 8 tokens, covering each token type.
+fn item() -> impl Strategy<Value = String> {
+    prop::collection::vec(select(TOKENS), 1..=8).prop_map(|tokens| tokens.concat())
+}
+
+/// Replacers of 1 to 4 specifiers without replacements or formatting, e.g. `{t}{}`.
+fn plain_replacer() -> impl Strategy<Value = String> {
+    prop::collection::vec(select(MATCHERS), 1..=4).prop_map(|matchers| {
+        matchers
+            .iter()
+            .map(|matcher| format!("{{{}}}", matcher))
+            .collect()
+    })
+}
+
+/// Replacers of 1 to 4 specifiers without replacements or formatting, each optionally preceded
+/// by a literal, along with their literals in order.
+fn replacer() -> impl Strategy<Value = (String, Vec<&'static str>)> {
+    prop::collection::vec(
+        (prop::option::of(select(LITERALS)), select(MATCHERS)),
+        1..=4,
+    )
+    .prop_map(|parts| {
+        let mut replacer = String::new();
+        let mut literals = vec![];
+        for (literal, matcher) in parts {
+            if let Some(literal) = literal {
+                replacer.push_str(literal);
+                literals.push(literal);
+            }
+            replacer.push_str(&format!("{{{}}}", matcher));
+        }
+        (replacer, literals)
+    })
+}
+
+proptest! {
+    #[test]
+    fn tokens_reconstruct_any_string(s in any::<String>()) {
+        check_tokens(&s).map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn tokens_reconstruct_item(item in item()) {
+        check_tokens(&item).map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn plain_replacer_is_identity(item in item(), replacer in plain_replacer()) {
+        check_identity(&item, &replacer).map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn spans_reconstruct_replacement(item in item(), (replacer, literals) in replacer()) {
+        check_spans(&item, &replacer, &literals).map_err(TestCaseError::fail)?;
+    }
+
+    #[test]
+    fn format_is_idempotent(item in item(), format in select(FORMATS)) {
+        check_idempotent(&item, format).map_err(TestCaseError::fail)?;
+    }
+}
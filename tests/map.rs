@@ -1,14 +1,811 @@
 use std::error::Error;
 
-use assert_cmd::Command;
+use assert_fs::prelude::*;
 use predicates::prelude::*;
 
+mod common;
+use common::mrf;
+
 #[test]
 fn map_simple() -> Result<(), Box<dyn Error>> {
-    let mut cmd = Command::cargo_bin("mrf")?;
+    let mut cmd = mrf()?;
     cmd.arg("map").arg("test-001").arg("{}{=_}{}");
     cmd.assert()
         .success()
         .stdout(predicate::eq("test-001\0test_001\0"));
     Ok(())
 }
+
+#[test]
+fn map_window() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-r")
+        .arg("c")
+        .arg("a")
+        .arg("b")
+        .arg("d")
+        .arg("{}")
+        .arg("--sort")
+        .arg("--skip")
+        .arg("1")
+        .arg("--limit")
+        .arg("2");
+    cmd.assert().success().stdout(predicate::eq("b\0c\0"));
+    Ok(())
+}
+
+#[test]
+fn map_only() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-l")
+        .arg("a-1")
+        .arg("b")
+        .arg("2")
+        .arg("{}")
+        .arg("--only")
+        .arg("{}{n}");
+    cmd.assert().success().stdout(predicate::eq("a-1\0"));
+    Ok(())
+}
+
+#[test]
+fn map_exclude() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-l")
+        .arg("a-1")
+        .arg("b")
+        .arg("2")
+        .arg("{}")
+        .arg("--exclude")
+        .arg("{n}");
+    cmd.assert().success().stdout(predicate::eq("a-1\0b\0"));
+    Ok(())
+}
+
+#[test]
+fn map_where() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-l")
+        .arg("e099")
+        .arg("e100")
+        .arg("e199")
+        .arg("e200")
+        .arg("{}{n}")
+        .arg("--where")
+        .arg("n2 >= 100 && n2 < 200");
+    cmd.assert().success().stdout(predicate::eq("e100\0e199\0"));
+    Ok(())
+}
+
+#[test]
+fn map_counter_group_by() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-r")
+        .arg("a-x")
+        .arg("a-y")
+        .arg("b-z")
+        .arg("{}{=_}{=}{#:02}")
+        .arg("--group-by")
+        .arg("1");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a_01\0a_02\0b_01\0"));
+    Ok(())
+}
+
+#[test]
+fn map_collision_warning() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map").arg("a-1").arg("b-1").arg("{=x}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a-1\0x-1\0b-1\0x-1\0"))
+        .stderr(predicate::eq(
+            "Warning: 2 items map to x-1:\n    a-1\n    b-1\n",
+        ));
+    Ok(())
+}
+
+#[test]
+fn map_unique() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("a-1")
+        .arg("b-1")
+        .arg("{=x}{}")
+        .arg("--unique");
+    cmd.assert().failure().stdout(predicate::eq(""));
+    Ok(())
+}
+
+#[test]
+fn map_token_mode_code() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-r")
+        .arg("getUserName")
+        .arg("{}_{}_{}")
+        .arg("--token-mode")
+        .arg("code");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("get_User_Name\0"));
+    Ok(())
+}
+
+#[test]
+fn map_multiline() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-")
+        .arg("{}{=_}{}")
+        .arg("--multiline")
+        .arg("--exclude")
+        .arg("{}{n}")
+        .write_stdin("a-b\r\nc\nd-e\nf-1\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a_b\r\nc\nd_e\nf-1\n"));
+    Ok(())
+}
+
+#[test]
+fn map_multiline_counter_skip() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-")
+        .arg("{}{#}")
+        .arg("--multiline")
+        .arg("--skip")
+        .arg("1")
+        .write_stdin("a\nb\nc\n");
+    cmd.assert().success().stdout(predicate::eq("a\nb1\nc2\n"));
+    Ok(())
+}
+
+#[test]
+fn map_all_occurrences() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-r")
+        .arg("a-b.c d")
+        .arg("{}{=_}{}")
+        .arg("--all-occurrences");
+    cmd.assert().success().stdout(predicate::eq("a_b_c_d\0"));
+    Ok(())
+}
+
+#[test]
+fn map_syntax_angle() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-r")
+        .arg("a-1")
+        .arg("{<>}<=_><n:03>")
+        .arg("--syntax")
+        .arg("angle");
+    cmd.assert().success().stdout(predicate::eq("{a}_001\0"));
+    Ok(())
+}
+
+#[test]
+fn map_stats() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-r")
+        .arg("a-1")
+        .arg("b-1")
+        .arg("c")
+        .arg("{=x}{}")
+        .arg("--stats");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("x-1\0x-1\0"))
+        .stderr(predicate::str::contains(
+            "Items: 3\nMatched: 2\nUnmatched: 1\nDistinct outputs: 1\n",
+        ));
+    Ok(())
+}
+
+#[test]
+fn map_stats_all_occurrences() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--all-occurrences")
+        .arg("--stats")
+        .arg("a-b")
+        .arg("x-y-zzzzzz")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a-b\0a_b\0x-y-zzzzzz\0x_y_zzzzzz\0"))
+        .stderr(predicate::str::contains("Items: 2\nMatched: 2\n"))
+        .stderr(predicate::str::contains(
+            "Group 3: min 1, max 1, mean 1.0, median 1\n",
+        ))
+        .stderr(predicate::str::contains(
+            "Group 5: min 6, max 6, mean 6.0, median 6\n",
+        ));
+    Ok(())
+}
+
+#[test]
+fn map_bytes() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-r")
+        .arg("size-1.4G")
+        .arg("{}{=_}{:bytes}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("size_1400000000\0"));
+    Ok(())
+}
+
+#[test]
+fn map_from_to() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("a12")
+        .arg("b3")
+        .arg("--from")
+        .arg("{}{n}")
+        .arg("--to")
+        .arg("{2}-{1}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a12\u{0}12-a\u{0}b3\u{0}3-b\u{0}"));
+    Ok(())
+}
+
+#[test]
+fn map_keep_rest() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("a-b.jpg")
+        .arg("{}{=_}")
+        .arg("--keep-rest");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a-b.jpg\u{0}a_b.jpg\u{0}"));
+    Ok(())
+}
+
+#[test]
+fn map_replacer_option() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("-e")
+        .arg("{}{=_}{}")
+        .arg("--")
+        .arg("-a-1");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("-a-1\u{0}-_-1\u{0}"));
+    Ok(())
+}
+
+#[test]
+fn map_replacer_looks_like_filename() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a.jpg").touch()?;
+    temp.child("b.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("map")
+        .arg("a.jpg")
+        .arg("b.jpg");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("looks like a filename"));
+    Ok(())
+}
+
+#[test]
+fn map_report_errors() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--report-errors")
+        .arg("a-1")
+        .arg("b")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::eq("a-1\u{0}a_1\u{0}"))
+        .stderr(
+            predicate::str::contains(r#"{"error":"#)
+                .and(predicate::str::contains(r#""item":"b""#))
+                .and(predicate::str::contains("replacer failed on 1 items")),
+        );
+    Ok(())
+}
+
+#[test]
+fn map_aggregate() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--aggregate")
+        .arg("sum:3")
+        .arg("--aggregate")
+        .arg("max:3")
+        .arg("a-10")
+        .arg("b-32")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .success()
+        .stderr(predicate::eq("sum:3: 42\nmax:3: 32\n"));
+    Ok(())
+}
+
+#[test]
+fn map_uniquify() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--uniquify")
+        .arg("a/x.jpg")
+        .arg("b/x.jpg")
+        .arg("{=c}{}");
+    cmd.assert().success().stdout(predicate::eq(
+        "a/x.jpg\u{0}c/x-1.jpg\u{0}b/x.jpg\u{0}c/x-2.jpg\u{0}",
+    ));
+    Ok(())
+}
+
+#[test]
+fn map_define() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--define")
+        .arg("sep={=_}")
+        .arg("--define")
+        .arg("snake={}{@sep}{}")
+        .arg("test-001")
+        .arg("{@snake}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("test-001\0test_001\0"));
+    Ok(())
+}
+
+#[test]
+fn map_number_format() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("size-1234567")
+        .arg("len-3.14159")
+        .arg("{t}{=_}{d:,.2}");
+    cmd.assert().success().stdout(predicate::eq(
+        "size-1234567\0size_1,234,567.00\0len-3.14159\0len_3.14\0",
+    ));
+    Ok(())
+}
+
+#[test]
+fn map_number_locale() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--number-locale")
+        .arg("de")
+        .arg("size-1234.5")
+        .arg("{t}{=_}{d:,.2}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("size-1234.5\0size_1.234,50\0"));
+    Ok(())
+}
+
+#[test]
+fn map_number_locale_input() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--number-locale")
+        .arg("de")
+        .arg("size-1.234,6")
+        .arg("{t}{=_}{d:.0}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("size-1.234,6\0size_1235\0"));
+    Ok(())
+}
+
+#[test]
+fn map_ascii_digits() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("img-١٢")
+        .arg("भाग-३")
+        .arg("{t}{=_}{n:03}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("img-١٢\0img_٠١٢\0भाग-३\0भाग_००३\0"));
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--ascii-digits")
+        .arg("img-١٢")
+        .arg("{t}{=_}{n:03}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("img-١٢\0img_012\0"));
+    Ok(())
+}
+
+#[test]
+fn map_ordinal() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("chapter-01.mp3")
+        .arg("chapter-12.mp3")
+        .arg("chapter-22.mp3")
+        .arg("{}{=_}{n:ordinal}{}");
+    cmd.assert().success().stdout(predicate::eq(
+        "chapter-01.mp3\0chapter_1st.mp3\0chapter-12.mp3\0chapter_12th.mp3\0chapter-22.mp3\0chapter_22nd.mp3\0",
+    ));
+    Ok(())
+}
+
+#[test]
+fn map_si() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("dump-1400000000.bin")
+        .arg("{}{=_}{n:si}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("dump-1400000000.bin\0dump_1.4G.bin\0"));
+    Ok(())
+}
+
+#[test]
+fn map_checksum() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("Show-01[ABCD1234].mkv")
+        .arg("Show-01[1234].mkv")
+        .arg("{}{p=}{crc32=}{p=.}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("Show-01[ABCD1234].mkv\0Show-01.mkv\0"));
+    Ok(())
+}
+
+#[test]
+fn map_normalize() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--normalize")
+        .arg("nfc")
+        .arg("--collapse-spaces")
+        .arg("Cafe\u{301}  -  01.txt")
+        .arg("{}");
+    cmd.assert().success().stdout(predicate::eq(
+        "Cafe\u{301}  -  01.txt\0Caf\u{e9} - 01.txt\0",
+    ));
+    Ok(())
+}
+
+#[test]
+fn map_meta_time() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a.txt").touch()?;
+    let year = chrono::Local::now().format("%Y").to_string();
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("map")
+        .arg("a.txt")
+        .arg("{}-{%mtime:%Y}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(format!("a.txt\0a-{}.txt\0", year).as_bytes()));
+    Ok(())
+}
+
+#[cfg(not(feature = "media"))]
+#[test]
+fn map_meta_video_without_feature() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map").arg("a.mkv").arg("{}{=-}{%video.height}p{}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("\"media\" feature"));
+    Ok(())
+}
+
+#[cfg(feature = "doc")]
+#[test]
+fn map_meta_doc_title() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("scan001.pdf").write_str(
+        "%PDF-1.4\n3 0 obj\n<< /Title (Invoice 2020/04) >>\nendobj\ntrailer\n<< /Info 3 0 R >>\n%%EOF\n",
+    )?;
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("map")
+        .arg("scan001.pdf")
+        .arg("{%doc.title}{=}{p}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("scan001.pdf\0Invoice 2020_04.pdf\0"));
+    Ok(())
+}
+
+#[cfg(not(feature = "doc"))]
+#[test]
+fn map_meta_doc_without_feature() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map").arg("a.pdf").arg("{%doc.title}{p}{}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("\"doc\" feature"));
+    Ok(())
+}
+
+#[test]
+fn map_meta_mime_ext() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("download.jpg")
+        .write_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR")?;
+    temp.child("notes.txt").write_str("notes")?;
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("map")
+        .arg("download.jpg")
+        .arg("notes.txt")
+        .arg("{}{p}{=}{%mime.ext}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("download.jpg\0download.png\0"));
+    Ok(())
+}
+
+#[test]
+fn map_meta_parent() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("Show/Season 1/01.mkv").touch()?;
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.child("Show/Season 1").path())
+        .arg("map")
+        .arg("01.mkv")
+        .arg("{%parent2} - {%parent} - {}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("01.mkv\0Show - Season 1 - 01.mkv\0"));
+    Ok(())
+}
+
+#[test]
+fn map_sqlite() -> Result<(), Box<dyn Error>> {
+    let sqlite3 = |db: &std::path::Path, sql: &str| {
+        std::process::Command::new("sqlite3")
+            .arg(db)
+            .arg(sql)
+            .output()
+    };
+    let temp = assert_fs::TempDir::new()?;
+    let db = temp.child("app.db");
+    let create = "CREATE TABLE files (path TEXT); \
+                  INSERT INTO files VALUES ('/mnt/old/a|b.jpg'), (NULL), ('other');";
+    // Skip if sqlite3 is not installed.
+    match sqlite3(db.path(), create) {
+        Ok(output) if output.status.success() => {}
+        _ => return Ok(()),
+    }
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("-y")
+        .arg("--sqlite")
+        .arg("app.db")
+        .arg("--table")
+        .arg("files")
+        .arg("--column")
+        .arg("path")
+        .arg("{p}{}{p}{=new}{}");
+    cmd.assert().success();
+
+    let output = sqlite3(db.path(), "SELECT quote(path) FROM files ORDER BY rowid;")?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "'/mnt/new/a|b.jpg'\nNULL\n'other'\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn map_sqlite_sort() -> Result<(), Box<dyn Error>> {
+    let sqlite3 = |db: &std::path::Path, sql: &str| {
+        std::process::Command::new("sqlite3")
+            .arg(db)
+            .arg(sql)
+            .output()
+    };
+    let temp = assert_fs::TempDir::new()?;
+    let db = temp.child("app.db");
+    let create = "CREATE TABLE files (path TEXT); \
+                  INSERT INTO files VALUES ('b-2'), ('a-1'), ('c-3');";
+    // Skip if sqlite3 is not installed.
+    match sqlite3(db.path(), create) {
+        Ok(output) if output.status.success() => {}
+        _ => return Ok(()),
+    }
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("-y")
+        .arg("--sqlite")
+        .arg("app.db")
+        .arg("--table")
+        .arg("files")
+        .arg("--column")
+        .arg("path")
+        .arg("--sort")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    let output = sqlite3(db.path(), "SELECT path FROM files ORDER BY rowid;")?;
+    assert_eq!(String::from_utf8(output.stdout)?, "b_2\na_1\nc_3\n");
+
+    Ok(())
+}
+
+#[test]
+fn map_json() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--json")
+        .arg("a-1")
+        .arg("b")
+        .arg("{}{=_}{}");
+    cmd.assert().success().stdout(predicate::eq(concat!(
+        r#"{"left":"a-1","left_spans":[{"end":1,"group":1,"source":"group","start":0},"#,
+        r#"{"end":2,"group":2,"source":"group","start":1},"#,
+        r#"{"end":3,"group":3,"source":"group","start":2}],"right":"a_1","right_spans":["#,
+        r#"{"end":1,"group":1,"source":"group","start":0},"#,
+        r#"{"end":2,"group":2,"source":"group","start":1},"#,
+        r#"{"end":3,"group":3,"source":"group","start":2}]}"#,
+        "\n"
+    )));
+    Ok(())
+}
+
+#[test]
+fn map_mmap() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    std::fs::write(temp.child("items.txt").path(), b"a-1\r\nb\nc-2\n\xff\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("--mmap")
+        .arg("items.txt")
+        .arg("--only")
+        .arg("{t}{p}{n}")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::eq("a-1\0a_1\0c-2\0c_2\0"))
+        .stderr(predicate::str::contains("item 4 is not valid UTF-8"));
+
+    Ok(())
+}
+
+#[test]
+fn map_glob() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    for name in ["a-1.jpg", "b-2.png", "d/c-3.jpg", ".e-4.jpg"] {
+        temp.child(name).touch()?;
+    }
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("--glob")
+        .arg("**/*.jpg")
+        .arg("-r")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a_1.jpg\0d_c-3.jpg\0"));
+
+    Ok(())
+}
+
+#[test]
+fn map_csv_column() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("items.csv")
+        .write_str("id,path\n1,a-1\n2,\"b-2,c\"\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("--csv")
+        .arg("items.csv")
+        .arg("--csv-column")
+        .arg("path")
+        .arg("a-1")
+        .arg("{}{=_}{}");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "items are taken from another source, only give the replacer",
+    ));
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("--csv")
+        .arg("items.csv")
+        .arg("--csv-column")
+        .arg("path")
+        .arg("-r")
+        .arg("{}{=_}{}");
+    cmd.assert().success().stdout(predicate::eq("a_1\0b_2,c\0"));
+
+    Ok(())
+}
+
+#[test]
+fn map_null_input() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.env("MRF_HISTORY", "")
+        .arg("map")
+        .arg("-z")
+        .arg("-r")
+        .arg("-")
+        .arg("{}{=_}{}")
+        .write_stdin("a-1\0b\nc-2\0");
+    cmd.assert().success().stdout(predicate::eq("a_1\0b_c-2\0"));
+
+    Ok(())
+}
+
+#[test]
+fn map_output_format() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--output-format")
+        .arg("csv")
+        .arg("a-1")
+        .arg("b,c-2")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("left,right\na-1,a_1\n\"b,c-2\",b_c-2\n"));
+
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--template")
+        .arg("cp {left} {right}")
+        .arg("a-1")
+        .arg("{}{=_}{}");
+    cmd.assert().success().stdout(predicate::eq("cp a-1 a_1\n"));
+
+    Ok(())
+}
+
+#[test]
+fn map_raw() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("map")
+        .arg("--raw")
+        .arg("a-1")
+        .arg("b-2")
+        .arg("file-{}-{#:04}.bin");
+    cmd.assert().success().stdout(predicate::eq(
+        "a-1\0file-{}-0001.bin\0b-2\0file-{}-0002.bin\0",
+    ));
+    Ok(())
+}
@@ -1,15 +1,17 @@
 use std::error::Error;
 
-use assert_cmd::Command;
 use assert_fs::prelude::*;
 use predicates::prelude::*;
 
+mod common;
+use common::mrf;
+
 #[test]
 fn mv_simple() -> Result<(), Box<dyn Error>> {
     let temp = assert_fs::TempDir::new()?;
     temp.child("test-001").touch()?;
 
-    let mut cmd = Command::cargo_bin("mrf")?;
+    let mut cmd = mrf()?;
     cmd.current_dir(temp.path())
         .arg("mv")
         .arg("-y")
@@ -22,3 +24,782 @@ fn mv_simple() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn mv_dry_run() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a").touch()?;
+    temp.child("b").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("--dry-run")
+        .arg("--pairs")
+        .arg("a")
+        .arg("c")
+        .arg("b")
+        .arg("missing/b");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "    b -> missing/b: no such file or directory",
+        ))
+        .stderr(predicate::str::contains("Dry run, no files were changed."));
+
+    temp.child("a").assert(predicate::path::exists());
+    temp.child("c").assert(predicate::path::missing());
+
+    Ok(())
+}
+
+#[test]
+fn mv_emit_script() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("test-001")
+        .arg("{}{=_}{}")
+        .arg("--emit-script")
+        .arg("sh");
+    cmd.assert().success().stdout(predicate::eq(
+        "#!/bin/sh\nset -e\nmv -- 'test-001' 'test_001'\n",
+    ));
+
+    temp.child("test-001").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_non_interactive_answer() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("test-001")
+        .arg("{}{=_}{}")
+        .write_stdin("y\n");
+    cmd.assert().success();
+
+    temp.child("test_001").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_non_interactive_closed_stdin() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("pass -y"));
+
+    temp.child("test-001").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_no() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("test-001")
+        .arg("{}{=_}{}")
+        .arg("--no");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("test-001 -> test_001"));
+
+    temp.child("test-001").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_prompt_default_yes() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("test-001")
+        .arg("{}{=_}{}")
+        .arg("--prompt-default")
+        .arg("yes")
+        .write_stdin("\n");
+    cmd.assert().success();
+
+    temp.child("test_001").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_safe() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--safe")
+        .arg("test-001.jpg")
+        .arg("{}{=_}");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "1 replacements drop the file extension:\n    test-001.jpg -> test_",
+    ));
+    temp.child("test-001.jpg").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_safe_emit_script() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1.txt").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("--safe")
+        .arg("--emit-script")
+        .arg("sh")
+        .arg("a-1.txt")
+        .arg("{}{=}{}{=}{}");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains(
+            "1 replacements drop the file extension:\n    a-1.txt -> a1txt",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn mv_hazard_warning() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("test-001.jpg")
+        .arg("{}{=_}");
+    cmd.assert().success().stderr(predicate::str::starts_with(
+        "Warning: 1 replacements drop the file extension",
+    ));
+    temp.child("test_").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_pairs() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a").touch()?;
+    temp.child("b").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--pairs")
+        .arg("a")
+        .arg("x")
+        .arg("b")
+        .arg("y");
+    cmd.assert().success();
+
+    temp.child("x").assert(predicate::path::exists());
+    temp.child("y").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_pairs_stdin() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--pairs")
+        .arg("-")
+        .write_stdin("a\tx\n");
+    cmd.assert().success();
+
+    temp.child("x").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_pairs_collision() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a").touch()?;
+    temp.child("b").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--pairs")
+        .arg("a")
+        .arg("x")
+        .arg("b")
+        .arg("x");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("destinations are not unique"));
+    temp.child("a").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_uniquify() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-x.jpg").touch()?;
+    temp.child("b-x.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--uniquify")
+        .arg("a-x.jpg")
+        .arg("b-x.jpg")
+        .arg("{=}{=}{}");
+    cmd.assert().success();
+
+    temp.child("x-1.jpg").assert(predicate::path::exists());
+    temp.child("x-2.jpg").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_mirror_copy() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("src/2020/a-1.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--mirror")
+        .arg("src")
+        .arg("dst")
+        .arg("--copy")
+        .arg("{}{}{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("src/2020/a-1.jpg")
+        .assert(predicate::path::exists());
+    temp.child("dst/2020/a_1.jpg")
+        .assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_recursive() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("d/a-1.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("-R")
+        .arg("d")
+        .arg("{}{}{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("d/a_1.jpg").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_recursive_path_components() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("d/2020/trip/a.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("mv")
+        .arg("-y")
+        .arg("-R")
+        .arg("d")
+        .arg("{}{p}{}{p}{}{p}{=}{%path.0}-{%path.-1}-{%depth}{}");
+    cmd.assert().success();
+
+    temp.child("d/2020/trip/2020-trip-2.jpg")
+        .assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_bucket_by() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--bucket-by")
+        .arg("sorted/{%mtime:%Y}")
+        .arg("a-1.jpg")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    let year = chrono::Local::now().format("%Y").to_string();
+    temp.child(format!("sorted/{}/a_1.jpg", year))
+        .assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn mv_bucket_by_emit_script() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("--bucket-by")
+        .arg("sorted/{%mtime:%Y}")
+        .arg("--emit-script")
+        .arg("sh")
+        .arg("a-1.jpg")
+        .arg("{}{=_}{}");
+    let output = cmd.assert().success().get_output().stdout.clone();
+
+    let year = chrono::Local::now().format("%Y").to_string();
+    assert!(
+        String::from_utf8(output.clone())?.contains(&format!("mkdir -p -- 'sorted/{}'\n", year))
+    );
+    temp.child("script.sh").write_binary(&output)?;
+    let status = std::process::Command::new("sh")
+        .arg("script.sh")
+        .current_dir(temp.path())
+        .status()?;
+    assert!(status.success());
+    temp.child(format!("sorted/{}/a_1.jpg", year))
+        .assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_failure_summary() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1").touch()?;
+    temp.child("b-2").touch()?;
+    temp.child("b_2").create_dir_all()?;
+    temp.child("b_2/x").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("a-1")
+        .arg("b-2")
+        .arg("{}{=_}{}");
+    cmd.assert().failure().stderr(
+        predicate::str::contains("Failed 1 out of 2 items (1 other error):")
+            .and(predicate::str::contains("    b-2 -> b_2: ")),
+    );
+
+    temp.child("a_1").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn mv_access_warning() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("--no")
+        .arg("a-1")
+        .arg("b-2")
+        .arg("{}{=_}{}");
+    cmd.assert().success().stderr(
+        predicate::str::contains("1 items are likely to fail to move").and(
+            predicate::str::contains("b-2 -> b_2: source does not exist"),
+        ),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn mv_case_insensitive_collision() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1.txt").touch()?;
+    temp.child("b-1.TXT").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--case-insensitive-fs")
+        .arg("a-1.txt")
+        .arg("b-1.TXT")
+        .arg("{=x}{}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("destinations are not unique"));
+
+    temp.child("a-1.txt").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn mv_dedupe_hardlinks() -> Result<(), Box<dyn Error>> {
+    use std::os::unix::fs::MetadataExt;
+
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1").write_str("abc")?;
+    std::fs::hard_link(temp.child("a-1").path(), temp.child("b-1").path())?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--dedupe-hardlinks")
+        .arg("a-1")
+        .arg("b-1")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    let a = std::fs::metadata(temp.child("a_1").path())?;
+    let b = std::fs::metadata(temp.child("b_1").path())?;
+    assert_eq!(a.ino(), b.ino());
+    temp.child("b-1").assert(predicate::path::missing());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn mv_dedupe_hardlinks_reported() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1").write_str("abc")?;
+    std::fs::hard_link(temp.child("a-1").path(), temp.child("b-1").path())?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--dedupe-hardlinks")
+        .arg("--progress=json")
+        .arg("--progress-file=progress.jsonl")
+        .arg("--checkpoint=checkpoint.jsonl")
+        .arg("--post-cmd=touch {right}.post")
+        .arg("a-1")
+        .arg("b-1")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("a_1.post").assert(predicate::path::exists());
+    temp.child("b_1.post").assert(predicate::path::exists());
+    temp.child("progress.jsonl").assert(
+        predicate::str::contains(
+            r#"{"bytes":3,"error":null,"event":"finish","from":"b-1","index":1,"to":"b_1"}"#,
+        )
+        .and(predicate::str::contains(
+            r#"{"event":"done","failed":0,"total":2}"#,
+        )),
+    );
+    temp.child("checkpoint.jsonl")
+        .assert(predicate::str::contains(r#""left":"b-1""#));
+
+    Ok(())
+}
+
+#[test]
+fn mv_progress_json() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").write_str("abc")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--progress=json")
+        .arg("--progress-file=progress.jsonl")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("progress.jsonl").assert(
+        predicate::str::contains(
+            r#"{"event":"start","from":"test-001","index":0,"to":"test_001"}"#,
+        )
+        .and(predicate::str::contains(
+            r#"{"bytes":3,"error":null,"event":"finish","from":"test-001","index":0,"to":"test_001"}"#,
+        ))
+        .and(predicate::str::contains(
+            r#"{"event":"done","failed":0,"total":1}"#,
+        )),
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn mv_progress_json_ordered() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1").touch()?;
+    temp.child("b-1").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("-c")
+        .arg("2")
+        .arg("--progress=json")
+        .arg("--progress-file=progress.jsonl")
+        .arg(r#"--pre-cmd=sh -c "test {left} = b-1 || sleep 0.3""#)
+        .arg("a-1")
+        .arg("b-1")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    let progress = std::fs::read_to_string(temp.child("progress.jsonl").path())?;
+    let finished: Vec<&str> = progress
+        .lines()
+        .filter(|line| line.contains(r#""event":"finish""#))
+        .collect();
+    assert_eq!(finished.len(), 2);
+    assert!(finished[0].contains(r#""from":"a-1""#));
+
+    Ok(())
+}
+
+#[test]
+fn mv_snapshot() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+    temp.child("other").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--snapshot=before.txt")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("before.txt").assert("other\ntest-001\n");
+    temp.child("before.txt.after").assert("other\ntest_001\n");
+
+    Ok(())
+}
+
+#[test]
+fn mv_snapshot_planned() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("--no")
+        .arg("--snapshot=before.txt")
+        .arg("--snapshot-after=planned.txt")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("test-001").assert(predicate::path::exists());
+    temp.child("before.txt").assert("test-001\n");
+    temp.child("planned.txt").assert("test_001\n");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn mv_hooks() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--pre-cmd=touch {left}.pre")
+        .arg("--post-cmd=touch {right}.post")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+
+    temp.child("test-001.pre").assert(predicate::path::exists());
+    temp.child("test_001").assert(predicate::path::exists());
+    temp.child("test_001.post")
+        .assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn mv_pre_cmd_failure() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--pre-cmd=false")
+        .arg("test-001")
+        .arg("{}{=_}{}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("pre-cmd failed: false"));
+
+    temp.child("test-001").assert(predicate::path::exists());
+    temp.child("test_001").assert(predicate::path::missing());
+
+    Ok(())
+}
+
+#[test]
+fn mv_name_too_long() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001.txt").touch()?;
+    let replacer = format!("{}{{}}", "x".repeat(300));
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("test-001.txt")
+        .arg(&replacer);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("names are too long"));
+    temp.child("test-001.txt").assert(predicate::path::exists());
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--truncate-to-fit")
+        .arg("test-001.txt")
+        .arg(&replacer);
+    cmd.assert().success();
+    temp.child("test-001.txt")
+        .assert(predicate::path::missing());
+    let names: Vec<String> = std::fs::read_dir(temp.path())?
+        .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(names.len(), 1);
+    assert!(names[0].starts_with("xxx") && names[0].ends_with(".txt"));
+    assert!(names[0].len() <= 255);
+
+    Ok(())
+}
+
+#[test]
+fn mv_truncate_to_fit_uniquify() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    // Both names are truncated to the same name, which is then made unique.
+    let (a, b) = (
+        format!("{}-a", "x".repeat(250)),
+        format!("{}-b", "x".repeat(250)),
+    );
+    temp.child(&a).touch()?;
+    temp.child(&b).touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--truncate-to-fit")
+        .arg("--uniquify")
+        .arg(&a)
+        .arg(&b)
+        .arg("{}{=bbbbbbbbbb}{}");
+    cmd.assert().success();
+    let mut names: Vec<String> = std::fs::read_dir(temp.path())?
+        .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<_, _>>()?;
+    names.sort();
+    assert_eq!(names.len(), 2);
+    assert!(names[0].ends_with("-1") && names[1].ends_with("-2"));
+    assert!(names.iter().all(|name| name.len() <= 255));
+
+    Ok(())
+}
+
+#[test]
+fn mv_reserved() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child(".mrf.toml")
+        .write_str("reserved = [\"-*\", \"*.tmp\"]\n")?;
+    temp.child("rf.txt").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("--")
+        .arg("rf.txt")
+        .arg("-{}");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "1 replaced strings are reserved by",
+    ));
+    temp.child("rf.txt").assert(predicate::path::exists());
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("mv")
+        .arg("-y")
+        .arg("rf.txt")
+        .arg("{}{=_}{}");
+    cmd.assert().success();
+    temp.child("rf_txt").assert(predicate::path::exists());
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::error::Error;
+
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn plan_apply() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("test-001").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("plan")
+        .arg("test-001")
+        .arg("{}{=_}{}")
+        .arg("-o")
+        .arg("plan.csv");
+    cmd.assert().success();
+
+    temp.child("plan.csv")
+        .assert(predicate::eq("left,right\ntest-001,test_001\n"));
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("apply")
+        .arg("-y")
+        .arg("plan.csv");
+    cmd.assert().success();
+
+    temp.child("test-001").assert(predicate::path::missing());
+    temp.child("test_001").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn apply_missing() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("plan.json")
+        .write_str(r#"[{"left": "test-001", "right": "test_001"}]"#)?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("apply")
+        .arg("-y")
+        .arg("plan.json");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("1 items in the plan do not exist"));
+
+    Ok(())
+}
+
+#[test]
+fn apply_checkpoint() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    // The first item was moved before the run was interrupted.
+    temp.child("a_1").touch()?;
+    temp.child("b-2").touch()?;
+    temp.child("plan.csv")
+        .write_str("left,right\na-1,a_1\nb-2,b_2\n")?;
+    temp.child("checkpoint.jsonl")
+        .write_str("{\"left\":\"a-1\",\"right\":\"a_1\"}\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("apply")
+        .arg("-y")
+        .arg("--checkpoint")
+        .arg("checkpoint.jsonl")
+        .arg("plan.csv");
+    cmd.assert().success();
+
+    temp.child("a_1").assert(predicate::path::exists());
+    temp.child("b_2").assert(predicate::path::exists());
+    temp.child("checkpoint.jsonl").assert(concat!(
+        "{\"left\":\"a-1\",\"right\":\"a_1\"}\n",
+        "{\"left\":\"b-2\",\"right\":\"b_2\"}\n"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn plan_collision() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("plan")
+        .arg("a-1")
+        .arg("b-1")
+        .arg("{=c}{}")
+        .arg("-o")
+        .arg("plan.csv");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("destinations are not unique"));
+
+    temp.child("plan.csv").assert(predicate::path::missing());
+
+    Ok(())
+}
+
+#[test]
+fn apply_collision() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a").write_str("a")?;
+    temp.child("b").write_str("b")?;
+    temp.child("plan.csv").write_str("left,right\na,c\nb,c\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("apply")
+        .arg("-y")
+        .arg("plan.csv");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("destinations are not unique"));
+
+    temp.child("a").assert(predicate::eq("a"));
+    temp.child("b").assert(predicate::eq("b"));
+    temp.child("c").assert(predicate::path::missing());
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::error::Error;
+
+use assert_fs::prelude::*;
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn refactor_simple() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("foo_bar.rs").touch()?;
+    temp.child("lib.rs")
+        .write_str("mod foo_bar;\nmod foo_bar_test;\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("refactor")
+        .arg("-y")
+        .arg("foo_bar.rs")
+        .arg("{}{=_baz}{=}{}")
+        .arg("--in")
+        .arg("lib.rs");
+    cmd.assert().success();
+
+    temp.child("foo_bar.rs").assert(predicate::path::missing());
+    temp.child("foo_baz.rs").assert(predicate::path::exists());
+    temp.child("lib.rs")
+        .assert(predicate::eq("mod foo_baz;\nmod foo_bar_test;\n"));
+
+    Ok(())
+}
+
+#[test]
+fn refactor_failure() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("foo.rs").touch()?;
+    temp.child("lib.rs").write_str("mod foo;\n")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("refactor")
+        .arg("-y")
+        .arg("foo.rs")
+        .arg("{}{=/bar}{}")
+        .arg("--in")
+        .arg("lib.rs");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("foo.rs -> foo/barrs"));
+
+    temp.child("foo.rs").assert(predicate::path::exists());
+    temp.child("lib.rs").assert(predicate::eq("mod foo;\n"));
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("refactor")
+        .arg("-y")
+        .arg("foo.rs")
+        .arg("{}{=_baz}{}")
+        .arg("--in")
+        .arg("missing.rs");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("missing.rs"));
+
+    temp.child("foo.rs").assert(predicate::path::exists());
+
+    Ok(())
+}
+
+#[test]
+fn refactor_collision() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1").write_str("a")?;
+    temp.child("b-1").write_str("b")?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .arg("refactor")
+        .arg("-y")
+        .arg("a-1")
+        .arg("b-1")
+        .arg("{=c}{}{}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("destinations are not unique"));
+
+    temp.child("a-1").assert(predicate::eq("a"));
+    temp.child("b-1").assert(predicate::eq("b"));
+    temp.child("c-1").assert(predicate::path::missing());
+
+    Ok(())
+}
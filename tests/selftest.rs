@@ -0,0 +1,21 @@
+use std::error::Error;
+
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn selftest_seed() -> Result<(), Box<dyn Error>> {
+    let mut cmd = mrf()?;
+    cmd.arg("selftest")
+        .arg("--seed")
+        .arg("42")
+        .arg("--cases")
+        .arg("200");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("Checked 200 cases with seed 42\n"));
+
+    Ok(())
+}
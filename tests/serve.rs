@@ -0,0 +1,51 @@
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+
+use serde_json::{json, Value};
+
+#[test]
+fn serve_replace() -> Result<(), Box<dyn Error>> {
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin("mrf"))
+        .env("MRF_HISTORY", "")
+        .arg("serve")
+        .arg("--http")
+        .arg("127.0.0.1:0")
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line)?;
+    let address = line
+        .trim()
+        .trim_start_matches("Listening on http://")
+        .to_owned();
+
+    let body = json!({"items": ["a-1", "b"], "replacer": "{}{=_}{}"}).to_string();
+    let mut stream = TcpStream::connect(&address)?;
+    write!(
+        stream,
+        "POST /replace HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+        address,
+        body.len(),
+        body
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    child.kill()?;
+    child.wait()?;
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    let body: Value = serde_json::from_str(body)?;
+    let replacements = body["replacements"].as_array().unwrap();
+    assert_eq!(replacements.len(), 1);
+    assert_eq!(replacements[0]["left"], "a-1");
+    assert_eq!(replacements[0]["right"], "a_1");
+    assert_eq!(
+        replacements[0]["right_spans"][1],
+        json!({"start": 1, "end": 2, "source": "group", "group": 2})
+    );
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+#![cfg(any(target_os = "linux", target_os = "macos"))]
+
+use std::error::Error;
+
+use assert_fs::prelude::*;
+
+use mrf::xattr;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn tag_simple() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    let file = temp.child("draft-001.txt");
+    file.touch()?;
+    // Some filesystems do not support user attributes.
+    if xattr::set(file.path(), "user.mrf.probe", b"").is_err() {
+        return Ok(());
+    }
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("tag")
+        .arg("-y")
+        .arg("draft-001.txt")
+        .arg("{t}{=}");
+    cmd.assert().success();
+
+    assert_eq!(
+        xattr::get(file.path(), "user.mrf.label")?,
+        Some(b"draft".to_vec())
+    );
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("tag")
+        .arg("-y")
+        .arg("draft-001.txt")
+        .arg("{=}");
+    cmd.assert().success();
+
+    assert_eq!(xattr::get(file.path(), "user.mrf.label")?, None);
+
+    Ok(())
+}
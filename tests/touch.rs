@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::fs;
+
+use assert_fs::prelude::*;
+use chrono::{DateTime, Local};
+use predicates::prelude::*;
+
+mod common;
+use common::mrf;
+
+#[test]
+fn touch_simple() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("IMG_20200131_120000.jpg").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("touch")
+        .arg("-y")
+        .arg("IMG_20200131_120000.jpg")
+        .arg("{=}{n}{p}{n}{=}");
+    cmd.assert().success();
+
+    let modified: DateTime<Local> = fs::metadata(temp.child("IMG_20200131_120000.jpg").path())?
+        .modified()?
+        .into();
+    assert_eq!(
+        modified.format("%Y-%m-%d %H:%M:%S").to_string(),
+        "2020-01-31 12:00:00"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn touch_invalid_timestamp() -> Result<(), Box<dyn Error>> {
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("a-1").touch()?;
+
+    let mut cmd = mrf()?;
+    cmd.current_dir(temp.path())
+        .env("MRF_HISTORY", "")
+        .arg("touch")
+        .arg("-y")
+        .arg("a-1")
+        .arg("{}{=}{n}");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("a-1 -> invalid timestamp: a1"));
+
+    Ok(())
+}